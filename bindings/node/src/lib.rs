@@ -0,0 +1,234 @@
+//! Node.js bindings for fast-encode
+//!
+//! Builds a native addon with napi-rs (`npm run build` in `bindings/node`).
+//! Conversions take and return `Buffer`s; `convertFile` streams on the libuv
+//! thread pool and resolves with the number of bytes written.
+//!
+//! Encoding names accept the same labels as the CLI (`cp1252`, `IBM037`, ...).
+//! The `errors` option takes `strict` (the default), `replace`, `ignore`,
+//! `backslashreplace` or `xmlcharrefreplace`.
+
+use std::fs::File;
+use std::io::{BufWriter, ErrorKind, Read, Write};
+
+use napi::bindgen_prelude::*;
+use napi::{Env, Task};
+use napi_derive::napi;
+
+use fast_encode::detection::EncodingDetector;
+use fast_encode::{Encoding, ErrorPolicy, StreamingTranslator, Translator};
+
+const DEFAULT_BUFFER_SIZE: u32 = 65536;
+
+/// Look up an encoding by name, alias or code page number
+fn encoding(label: &str) -> Result<Encoding> {
+    Encoding::for_label(label)
+        .ok_or_else(|| Error::new(Status::InvalidArg, format!("unknown encoding: {}", label)))
+}
+
+fn conversion_error(error: fast_encode::Error) -> Error {
+    Error::new(Status::GenericFailure, error.to_string())
+}
+
+/// Options shared by `convert` and `convertFile`
+#[napi(object)]
+#[derive(Default)]
+pub struct ConvertOptions {
+    /// Error handler name, `strict` by default
+    pub errors: Option<String>,
+    /// Replacement used by the `replace` handler
+    pub replacement: Option<String>,
+    /// Read size for `convertFile`, 64 KiB by default
+    pub buffer_size: Option<u32>,
+}
+
+impl ConvertOptions {
+    fn policy(&self) -> Result<ErrorPolicy> {
+        Ok(match self.errors.as_deref().unwrap_or("strict") {
+            "strict" => ErrorPolicy::Strict,
+            "replace" => match self.replacement.as_deref() {
+                None => ErrorPolicy::Replace,
+                Some(text) => {
+                    let mut chars = text.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(ch), None) => ErrorPolicy::ReplaceWithChar(ch),
+                        _ => ErrorPolicy::ReplaceWithStr(text.into()),
+                    }
+                }
+            },
+            "ignore" => ErrorPolicy::Ignore,
+            "backslashreplace" => ErrorPolicy::BackslashReplace,
+            "xmlcharrefreplace" => ErrorPolicy::XmlCharRefReplace,
+            other => {
+                return Err(Error::new(
+                    Status::InvalidArg,
+                    format!("unknown error handler: {}", other),
+                ));
+            }
+        })
+    }
+}
+
+/// Convert a buffer from one encoding to another
+#[napi]
+pub fn convert(
+    input: Buffer,
+    from: String,
+    to: String,
+    options: Option<ConvertOptions>,
+) -> Result<Buffer> {
+    let policy = options.unwrap_or_default().policy()?;
+    let translator = Translator::new(encoding(&from)?, encoding(&to)?).map_err(conversion_error)?;
+    let converted = translator
+        .convert_with_policy(&input, policy)
+        .map_err(conversion_error)?;
+    Ok(converted.into())
+}
+
+/// One candidate encoding and its confidence
+#[napi(object)]
+pub struct Candidate {
+    pub encoding: String,
+    pub confidence: f64,
+}
+
+/// Outcome of encoding detection
+#[napi(object)]
+pub struct DetectionResult {
+    /// Most likely encoding
+    pub encoding: String,
+    /// Confidence between 0 and 1
+    pub confidence: f64,
+    /// Whether the data starts with a byte order mark
+    pub bom_detected: bool,
+    /// Candidates, most likely first
+    pub candidates: Vec<Candidate>,
+}
+
+/// Detect the encoding of a buffer from its BOM and byte statistics
+#[napi]
+pub fn detect(input: Buffer, sample_size: Option<u32>) -> DetectionResult {
+    let detector = match sample_size {
+        Some(size) => EncodingDetector::with_sample_size(size as usize),
+        None => EncodingDetector::new(),
+    };
+    let result = detector.detect(&input);
+    DetectionResult {
+        encoding: result.encoding.name().to_string(),
+        confidence: result.confidence,
+        bom_detected: result.bom_detected,
+        candidates: result
+            .candidates
+            .iter()
+            .map(|(encoding, confidence)| Candidate {
+                encoding: encoding.name().to_string(),
+                confidence: *confidence,
+            })
+            .collect(),
+    }
+}
+
+/// Names of every built-in encoding
+#[napi]
+pub fn encodings() -> Vec<&'static str> {
+    Encoding::all()
+        .iter()
+        .map(|encoding| encoding.name())
+        .collect()
+}
+
+/// Streams one file into another off the JavaScript thread
+pub struct ConvertFileTask {
+    input: String,
+    output: String,
+    stream: StreamingTranslator,
+    policy: ErrorPolicy,
+    buffer_size: usize,
+}
+
+impl ConvertFileTask {
+    fn run(&mut self) -> std::result::Result<usize, String> {
+        let reader = File::open(&self.input).map_err(|e| format!("{}: {}", self.input, e))?;
+        let file = File::create(&self.output).map_err(|e| format!("{}: {}", self.output, e))?;
+        let result = self.stream_into(reader, BufWriter::new(file));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&self.output);
+        }
+        result
+    }
+
+    fn stream_into(
+        &mut self,
+        mut reader: File,
+        mut writer: BufWriter<File>,
+    ) -> std::result::Result<usize, String> {
+        let mut buffer = vec![0u8; self.buffer_size];
+        let mut written = 0;
+
+        loop {
+            let read = match reader.read(&mut buffer) {
+                Ok(read) => read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(format!("{}: {}", self.input, e)),
+            };
+            let converted = if read == 0 {
+                self.stream.finish_with_policy(self.policy.clone())
+            } else {
+                self.stream
+                    .process_chunk_with_policy(&buffer[..read], self.policy.clone())
+            }
+            .map_err(|e| e.to_string())?;
+            writer
+                .write_all(&converted)
+                .map_err(|e| format!("{}: {}", self.output, e))?;
+            written += converted.len();
+            if read == 0 {
+                break;
+            }
+        }
+
+        writer
+            .flush()
+            .map_err(|e| format!("{}: {}", self.output, e))?;
+        Ok(written)
+    }
+}
+
+impl Task for ConvertFileTask {
+    type Output = usize;
+    type JsValue = f64;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        self.run()
+            .map_err(|message| Error::new(Status::GenericFailure, message))
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output as f64)
+    }
+}
+
+/// Convert a file on the thread pool; resolves with the number of bytes written
+///
+/// The output is removed if the conversion fails part-way.
+#[napi(ts_return_type = "Promise<number>")]
+pub fn convert_file(
+    input: String,
+    output: String,
+    from: String,
+    to: String,
+    options: Option<ConvertOptions>,
+) -> Result<AsyncTask<ConvertFileTask>> {
+    let options = options.unwrap_or_default();
+    let policy = options.policy()?;
+    let buffer_size = options.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE).max(1) as usize;
+    let stream = StreamingTranslator::new(encoding(&from)?, encoding(&to)?, buffer_size)
+        .map_err(conversion_error)?;
+    Ok(AsyncTask::new(ConvertFileTask {
+        input,
+        output,
+        stream,
+        policy,
+        buffer_size,
+    }))
+}
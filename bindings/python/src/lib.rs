@@ -0,0 +1,312 @@
+//! Python bindings for fast-encode
+//!
+//! Builds the `fast_encode` extension module. With maturin installed:
+//!
+//! ```text
+//! maturin develop --release -m bindings/python/Cargo.toml
+//! ```
+//!
+//! Encoding names accept the same labels as the CLI (`cp1252`, `IBM037`, ...).
+//! Error handling follows Python's codec error handlers: `strict`, `replace`,
+//! `ignore`, `backslashreplace` and `xmlcharrefreplace`.
+
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::PathBuf;
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyLookupError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use fast_encode::detection::EncodingDetector;
+use fast_encode::{Encoding, ErrorPolicy};
+
+create_exception!(
+    fast_encode,
+    EncodingError,
+    PyValueError,
+    "Raised when input cannot be converted under the chosen error handler."
+);
+
+/// Look up an encoding by name, alias or code page number
+fn encoding(label: &str) -> PyResult<Encoding> {
+    Encoding::for_label(label)
+        .ok_or_else(|| PyLookupError::new_err(format!("unknown encoding: {}", label)))
+}
+
+/// Map a Python error handler name to an error policy
+fn error_policy(errors: &str, replacement: Option<&str>) -> PyResult<ErrorPolicy> {
+    Ok(match errors {
+        "strict" => ErrorPolicy::Strict,
+        "replace" => match replacement {
+            None => ErrorPolicy::Replace,
+            Some(text) => {
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => ErrorPolicy::ReplaceWithChar(ch),
+                    _ => ErrorPolicy::ReplaceWithStr(text.into()),
+                }
+            }
+        },
+        "ignore" => ErrorPolicy::Ignore,
+        "backslashreplace" => ErrorPolicy::BackslashReplace,
+        "xmlcharrefreplace" => ErrorPolicy::XmlCharRefReplace,
+        other => {
+            return Err(PyLookupError::new_err(format!(
+                "unknown error handler name '{}'",
+                other
+            )));
+        }
+    })
+}
+
+fn conversion_error(error: fast_encode::Error) -> PyErr {
+    EncodingError::new_err(error.to_string())
+}
+
+/// Converts byte strings from one encoding to another
+#[pyclass(module = "fast_encode", frozen)]
+struct Translator {
+    inner: fast_encode::Translator,
+}
+
+#[pymethods]
+impl Translator {
+    #[new]
+    fn new(from_encoding: &str, to_encoding: &str) -> PyResult<Self> {
+        let inner = fast_encode::Translator::new(encoding(from_encoding)?, encoding(to_encoding)?)
+            .map_err(conversion_error)?;
+        Ok(Self { inner })
+    }
+
+    /// Name of the source encoding
+    #[getter(from_encoding)]
+    fn source_encoding(&self) -> &'static str {
+        self.inner.from_encoding().name()
+    }
+
+    /// Name of the target encoding
+    #[getter(to_encoding)]
+    fn target_encoding(&self) -> &'static str {
+        self.inner.to_encoding().name()
+    }
+
+    /// Convert `data`, releasing the GIL while the conversion runs
+    #[pyo3(signature = (data, errors = "strict", replacement = None))]
+    fn convert<'py>(
+        &self,
+        py: Python<'py>,
+        data: &[u8],
+        errors: &str,
+        replacement: Option<&str>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let policy = error_policy(errors, replacement)?;
+        let converted = py
+            .detach(|| self.inner.convert_with_policy(data, policy))
+            .map_err(conversion_error)?;
+        Ok(PyBytes::new(py, &converted))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Translator('{}', '{}')",
+            self.source_encoding(),
+            self.target_encoding()
+        )
+    }
+}
+
+/// Converts a stream chunk by chunk, carrying incomplete characters between chunks
+#[pyclass(module = "fast_encode")]
+struct StreamingTranslator {
+    inner: fast_encode::StreamingTranslator,
+}
+
+#[pymethods]
+impl StreamingTranslator {
+    #[new]
+    #[pyo3(signature = (from_encoding, to_encoding, buffer_size = 65536))]
+    fn new(from_encoding: &str, to_encoding: &str, buffer_size: usize) -> PyResult<Self> {
+        let inner = fast_encode::StreamingTranslator::new(
+            encoding(from_encoding)?,
+            encoding(to_encoding)?,
+            buffer_size,
+        )
+        .map_err(conversion_error)?;
+        Ok(Self { inner })
+    }
+
+    /// Convert the next chunk of input
+    #[pyo3(signature = (chunk, errors = "strict", replacement = None))]
+    fn process<'py>(
+        &mut self,
+        py: Python<'py>,
+        chunk: &[u8],
+        errors: &str,
+        replacement: Option<&str>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let policy = error_policy(errors, replacement)?;
+        let converted = self
+            .inner
+            .process_chunk_with_policy(chunk, policy)
+            .map_err(conversion_error)?;
+        Ok(PyBytes::new(py, &converted))
+    }
+
+    /// Flush the end of the stream; a truncated final character is an error unless handled
+    #[pyo3(signature = (errors = "strict", replacement = None))]
+    fn finish<'py>(
+        &mut self,
+        py: Python<'py>,
+        errors: &str,
+        replacement: Option<&str>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let policy = error_policy(errors, replacement)?;
+        let converted = self
+            .inner
+            .finish_with_policy(policy)
+            .map_err(conversion_error)?;
+        Ok(PyBytes::new(py, &converted))
+    }
+
+    /// Number of errors handled by a non-strict error handler so far
+    #[getter]
+    fn replacements(&self) -> usize {
+        self.inner.replacements()
+    }
+}
+
+/// Outcome of encoding detection
+#[pyclass(module = "fast_encode", frozen, get_all)]
+struct DetectionResult {
+    /// Most likely encoding
+    encoding: &'static str,
+    /// Confidence between 0.0 and 1.0
+    confidence: f64,
+    /// Whether the data starts with a byte order mark
+    bom_detected: bool,
+    /// `(encoding, confidence)` pairs, most likely first
+    candidates: Vec<(&'static str, f64)>,
+}
+
+#[pymethods]
+impl DetectionResult {
+    fn __repr__(&self) -> String {
+        format!(
+            "DetectionResult(encoding='{}', confidence={:.3}, bom_detected={})",
+            self.encoding,
+            self.confidence,
+            if self.bom_detected { "True" } else { "False" }
+        )
+    }
+}
+
+/// Detect the encoding of `data` from its BOM and byte statistics
+#[pyfunction]
+#[pyo3(signature = (data, sample_size = None))]
+fn detect(data: &[u8], sample_size: Option<usize>) -> DetectionResult {
+    let detector = match sample_size {
+        Some(size) => EncodingDetector::with_sample_size(size),
+        None => EncodingDetector::new(),
+    };
+    let result = detector.detect(data);
+    DetectionResult {
+        encoding: result.encoding.name(),
+        confidence: result.confidence,
+        bom_detected: result.bom_detected,
+        candidates: result
+            .candidates
+            .iter()
+            .map(|(encoding, confidence)| (encoding.name(), *confidence))
+            .collect(),
+    }
+}
+
+/// Stream a file from one encoding to another, releasing the GIL
+///
+/// Returns the number of bytes written. The output is removed if the
+/// conversion fails part-way.
+#[pyfunction]
+#[pyo3(signature = (
+    input,
+    output,
+    from_encoding,
+    to_encoding,
+    errors = "strict",
+    replacement = None,
+    buffer_size = 65536
+))]
+#[allow(clippy::too_many_arguments)]
+fn convert_file(
+    py: Python<'_>,
+    input: PathBuf,
+    output: PathBuf,
+    from_encoding: &str,
+    to_encoding: &str,
+    errors: &str,
+    replacement: Option<&str>,
+    buffer_size: usize,
+) -> PyResult<usize> {
+    let policy = error_policy(errors, replacement)?;
+    let mut stream = fast_encode::StreamingTranslator::new(
+        encoding(from_encoding)?,
+        encoding(to_encoding)?,
+        buffer_size,
+    )
+    .map_err(conversion_error)?;
+
+    py.detach(|| -> PyResult<usize> {
+        let mut reader = File::open(&input)?;
+        let mut writer = BufWriter::new(File::create(&output)?);
+        let mut buffer = vec![0u8; buffer_size.max(1)];
+        let mut written = 0;
+
+        let result = (|| -> PyResult<usize> {
+            loop {
+                let read = reader.read(&mut buffer)?;
+                let converted = if read == 0 {
+                    stream.finish_with_policy(policy.clone())
+                } else {
+                    stream.process_chunk_with_policy(&buffer[..read], policy.clone())
+                }
+                .map_err(conversion_error)?;
+                writer.write_all(&converted)?;
+                written += converted.len();
+                if read == 0 {
+                    break;
+                }
+            }
+            writer.flush()?;
+            Ok(written)
+        })();
+
+        if result.is_err() {
+            drop(writer);
+            let _ = std::fs::remove_file(&output);
+        }
+        result
+    })
+}
+
+/// Names of every built-in encoding
+#[pyfunction]
+fn encodings() -> Vec<&'static str> {
+    Encoding::all()
+        .iter()
+        .map(|encoding| encoding.name())
+        .collect()
+}
+
+#[pymodule]
+#[pyo3(name = "fast_encode")]
+fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Translator>()?;
+    m.add_class::<StreamingTranslator>()?;
+    m.add_class::<DetectionResult>()?;
+    m.add_function(wrap_pyfunction!(detect, m)?)?;
+    m.add_function(wrap_pyfunction!(convert_file, m)?)?;
+    m.add_function(wrap_pyfunction!(encodings, m)?)?;
+    m.add("EncodingError", m.py().get_type::<EncodingError>())?;
+    Ok(())
+}
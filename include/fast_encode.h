@@ -0,0 +1,75 @@
+/*
+ * C API for fast-encode (built with the `capi` feature)
+ *
+ *   cargo rustc --release --lib --no-default-features --features capi --crate-type cdylib
+ *   cargo rustc --release --lib --no-default-features --features capi --crate-type staticlib
+ *
+ * Functions return FE_OK or a negative FE_ERR_* status; fe_error_message()
+ * describes the last failure on the calling thread. All buffers belong to the
+ * caller. A translator handle may be shared between threads.
+ */
+
+#ifndef FAST_ENCODE_H
+#define FAST_ENCODE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define FE_OK 0
+#define FE_ERR_NULL_POINTER (-1)
+#define FE_ERR_UNKNOWN_ENCODING (-2)
+#define FE_ERR_UNSUPPORTED (-3)
+#define FE_ERR_UNMAPPABLE (-4)
+#define FE_ERR_INVALID_INPUT (-5)
+#define FE_ERR_BUFFER_TOO_SMALL (-6)
+#define FE_ERR_INVALID_ARGUMENT (-7)
+#define FE_ERR_INTERNAL (-8)
+
+#define FE_ERRORS_STRICT 0u
+#define FE_ERRORS_REPLACE 1u
+#define FE_ERRORS_IGNORE 2u
+
+typedef struct FeTranslator FeTranslator;
+
+/* Message for the last failed call on this thread, or NULL; valid until the next call */
+const char *fe_error_message(void);
+
+/* Create a translator between two encoding labels ("IBM037", "utf-8", "1252", ...) */
+int32_t fe_translator_new(const char *from, const char *to, uint32_t errors, FeTranslator **out);
+
+/* Release a translator; NULL is ignored */
+void fe_translator_free(FeTranslator *translator);
+
+/* Output size that always holds the conversion of input_len bytes */
+size_t fe_max_output_len(const FeTranslator *translator, size_t input_len);
+
+/*
+ * Convert input into output and store the converted length in *output_len.
+ * FE_ERR_BUFFER_TOO_SMALL stores the needed size instead; pass output NULL
+ * and output_cap 0 to query it.
+ */
+int32_t fe_convert(const FeTranslator *translator,
+                   const uint8_t *input, size_t input_len,
+                   uint8_t *output, size_t output_cap, size_t *output_len);
+
+/*
+ * Like fe_convert, but appends after the *output_len bytes already in output
+ * and leaves them in place on failure.
+ */
+int32_t fe_convert_into(const FeTranslator *translator,
+                        const uint8_t *input, size_t input_len,
+                        uint8_t *output, size_t output_cap, size_t *output_len);
+
+/* Detect the encoding of data; name receives its NUL-terminated canonical name */
+int32_t fe_detect(const uint8_t *data, size_t data_len,
+                  char *name, size_t name_cap, double *confidence);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* FAST_ENCODE_H */
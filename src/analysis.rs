@@ -0,0 +1,154 @@
+//! Byte-level statistics about unknown input
+//!
+//! [`ByteStats`] accumulates a byte histogram, control-character counts and the
+//! mix of line endings, one chunk at a time, so arbitrarily large files can be
+//! profiled without holding them in memory. Line endings and control characters
+//! are counted on raw bytes, which is meaningful for ASCII-compatible data.
+
+/// Counts of each line-ending style
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineEndings {
+    /// Bare `\n`
+    pub lf: u64,
+    /// `\r\n`
+    pub crlf: u64,
+    /// Bare `\r`
+    pub cr: u64,
+}
+
+impl LineEndings {
+    /// Total number of line breaks of any style
+    pub fn total(&self) -> u64 {
+        self.lf + self.crlf + self.cr
+    }
+
+    /// Whether more than one style occurs
+    pub fn is_mixed(&self) -> bool {
+        [self.lf, self.crlf, self.cr]
+            .iter()
+            .filter(|&&count| count > 0)
+            .count()
+            > 1
+    }
+}
+
+/// Byte statistics gathered incrementally over input
+#[derive(Debug, Clone)]
+pub struct ByteStats {
+    histogram: [u64; 256],
+    total: u64,
+    line_endings: LineEndings,
+    /// The previous chunk ended with CR, so a leading LF completes a CRLF
+    pending_cr: bool,
+}
+
+impl Default for ByteStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ByteStats {
+    /// Create empty statistics
+    pub fn new() -> Self {
+        Self {
+            histogram: [0; 256],
+            total: 0,
+            line_endings: LineEndings::default(),
+            pending_cr: false,
+        }
+    }
+
+    /// Gather statistics for a complete buffer
+    pub fn from_bytes(data: &[u8]) -> Self {
+        let mut stats = Self::new();
+        stats.update(data);
+        stats
+    }
+
+    /// Add the next chunk of input
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.histogram[byte as usize] += 1;
+
+            match byte {
+                b'\n' if self.pending_cr => {
+                    self.line_endings.cr -= 1;
+                    self.line_endings.crlf += 1;
+                }
+                b'\n' => self.line_endings.lf += 1,
+                b'\r' => self.line_endings.cr += 1,
+                _ => {}
+            }
+            self.pending_cr = byte == b'\r';
+        }
+        self.total += data.len() as u64;
+    }
+
+    /// Number of bytes seen
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Occurrences of each byte value
+    pub fn histogram(&self) -> &[u64; 256] {
+        &self.histogram
+    }
+
+    /// Number of distinct byte values seen
+    pub fn distinct(&self) -> usize {
+        self.histogram.iter().filter(|&&count| count > 0).count()
+    }
+
+    /// The `n` most frequent byte values, most frequent first
+    pub fn most_common(&self, n: usize) -> Vec<(u8, u64)> {
+        let mut counts: Vec<(u8, u64)> = (0..=255u8)
+            .map(|byte| (byte, self.histogram[byte as usize]))
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts.truncate(n);
+        counts
+    }
+
+    /// Number of bytes at or above 0x80
+    pub fn non_ascii(&self) -> u64 {
+        self.histogram[0x80..].iter().sum()
+    }
+
+    /// Fraction of bytes at or above 0x80 (0.0 for empty input)
+    pub fn non_ascii_ratio(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.non_ascii() as f64 / self.total as f64
+        }
+    }
+
+    /// Number of NUL bytes
+    pub fn nul(&self) -> u64 {
+        self.histogram[0]
+    }
+
+    /// C0 controls and DEL, excluding tab, line feed and carriage return
+    pub fn c0_controls(&self) -> u64 {
+        (0x00..0x20)
+            .chain([0x7F])
+            .filter(|&byte| !matches!(byte, b'\t' | b'\n' | b'\r'))
+            .map(|byte| self.histogram[byte as usize])
+            .sum()
+    }
+
+    /// Bytes in the C1 control range 0x80-0x9F
+    ///
+    /// These are printable in the Windows code pages but controls in ISO-8859,
+    /// which makes them a useful hint when telling the two apart.
+    pub fn c1_range(&self) -> u64 {
+        self.histogram[0x80..0xA0].iter().sum()
+    }
+
+    /// Line breaks seen so far, by style
+    pub fn line_endings(&self) -> LineEndings {
+        self.line_endings
+    }
+}
@@ -0,0 +1,162 @@
+//! Bulk transcoding of Apache Arrow columns
+//!
+//! Enabled by the `arrow` feature. A column is converted as a whole rather
+//! than value by value: between single-byte encodings the values buffer is
+//! translated in one pass and the offsets buffer is shared with the input,
+//! since every value keeps its length. Other conversions rebuild the offsets.
+//! The validity mask is always carried over unchanged and null slots are never
+//! converted.
+//!
+//! Error positions are byte offsets into the column's values, counted from the
+//! start of the first value.
+//!
+//! ```
+//! use arrow_array::{Array, BinaryArray};
+//! use fast_encode::{arrow::decode_binary, Encoding, ErrorPolicy, Translator};
+//!
+//! let column = BinaryArray::from(vec![Some(&[0xC8, 0xC9][..]), None, Some(&[0x5A][..])]);
+//! let translator = Translator::new(Encoding::EBCDIC_037, Encoding::UTF8).unwrap();
+//! let text = decode_binary(&column, &translator, ErrorPolicy::Strict).unwrap();
+//! assert_eq!(text.value(0), "HI");
+//! assert!(text.is_null(1));
+//! assert_eq!(text.value(2), "!");
+//! ```
+
+use arrow_array::{Array, GenericBinaryArray, GenericStringArray, OffsetSizeTrait};
+use arrow_buffer::{Buffer, OffsetBuffer, ScalarBuffer};
+
+use crate::{Encoding, Error, ErrorPolicy, Result, Translator};
+
+/// Convert every value of a binary column with `translator`
+pub fn transcode_binary<O: OffsetSizeTrait>(
+    array: &GenericBinaryArray<O>,
+    translator: &Translator,
+    policy: ErrorPolicy,
+) -> Result<GenericBinaryArray<O>> {
+    let (offsets, values) = match translate_same_length(array, translator) {
+        Ok(Some(converted)) => converted,
+        Ok(None) => rebuild(array, translator, &policy)?,
+        Err(_) if policy != ErrorPolicy::Strict => rebuild(array, translator, &policy)?,
+        Err(e) => return Err(e),
+    };
+
+    GenericBinaryArray::try_new(offsets, values, array.nulls().cloned())
+        .map_err(|e| Error::InvalidInput(e.to_string()))
+}
+
+/// Decode a binary column into a string column; the translator must target UTF-8
+pub fn decode_binary<O: OffsetSizeTrait>(
+    array: &GenericBinaryArray<O>,
+    translator: &Translator,
+    policy: ErrorPolicy,
+) -> Result<GenericStringArray<O>> {
+    if translator.to_encoding() != Encoding::UTF8 {
+        return Err(Error::UnsupportedConversion {
+            from: translator.from_encoding().name(),
+            to: translator.to_encoding().name(),
+        });
+    }
+
+    let converted = transcode_binary(array, translator, policy)?;
+    let (offsets, values, nulls) = converted.into_parts();
+    GenericStringArray::try_new(offsets, values, nulls)
+        .map_err(|e| Error::InvalidInput(e.to_string()))
+}
+
+/// Encode a string column into a binary column; the translator must convert from UTF-8
+pub fn encode_strings<O: OffsetSizeTrait>(
+    array: &GenericStringArray<O>,
+    translator: &Translator,
+    policy: ErrorPolicy,
+) -> Result<GenericBinaryArray<O>> {
+    if translator.from_encoding() != Encoding::UTF8 {
+        return Err(Error::UnsupportedConversion {
+            from: translator.from_encoding().name(),
+            to: translator.to_encoding().name(),
+        });
+    }
+
+    transcode_binary(&GenericBinaryArray::from(array.clone()), translator, policy)
+}
+
+/// Table conversion that keeps every value's length, so the offsets can be reused
+///
+/// Returns `None` when the translator is not a byte-to-byte table.
+fn translate_same_length<O: OffsetSizeTrait>(
+    array: &GenericBinaryArray<O>,
+    translator: &Translator,
+) -> Result<Option<(OffsetBuffer<O>, Buffer)>> {
+    let Some(table) = &translator.table else {
+        return Ok(None);
+    };
+
+    let offsets = array.value_offsets();
+    let first = offsets[0].as_usize();
+    let last = offsets[offsets.len() - 1].as_usize();
+    let mut values = array.values()[first..last].to_vec();
+
+    if array.null_count() == 0 {
+        table.translate_in_place(&mut values)?;
+    } else {
+        for (index, window) in offsets.windows(2).enumerate() {
+            if array.is_valid(index) {
+                let start = window[0].as_usize() - first;
+                let end = window[1].as_usize() - first;
+                table
+                    .translate_in_place(&mut values[start..end])
+                    .map_err(|e| e.offset_by(start))?;
+            }
+        }
+    }
+
+    // A sliced column's offsets do not start at zero and have to be rebased
+    let offsets = if first == 0 {
+        array.offsets().clone()
+    } else {
+        let base = offsets[0];
+        OffsetBuffer::new(ScalarBuffer::from(
+            offsets
+                .iter()
+                .map(|&offset| offset - base)
+                .collect::<Vec<O>>(),
+        ))
+    };
+
+    Ok(Some((offsets, Buffer::from_vec(values))))
+}
+
+/// Convert value by value into a new values buffer with new offsets
+fn rebuild<O: OffsetSizeTrait>(
+    array: &GenericBinaryArray<O>,
+    translator: &Translator,
+    policy: &ErrorPolicy,
+) -> Result<(OffsetBuffer<O>, Buffer)> {
+    let value_offsets = array.value_offsets();
+    let first = value_offsets[0].as_usize();
+    let mut values = Vec::with_capacity(array.values().len());
+    let mut offsets = Vec::with_capacity(array.len() + 1);
+    offsets.push(O::usize_as(0));
+
+    for (index, window) in value_offsets.windows(2).enumerate() {
+        if array.is_valid(index) {
+            let start = window[0].as_usize();
+            let value = &array.values()[start..window[1].as_usize()];
+            let converted = translator
+                .convert_with_policy(value, policy.clone())
+                .map_err(|e| e.offset_by(start - first))?;
+            values.extend_from_slice(&converted);
+        }
+        let end = O::from_usize(values.len()).ok_or_else(|| {
+            Error::InvalidInput(format!(
+                "converted column exceeds the {}-bit offset range",
+                if O::IS_LARGE { 64 } else { 32 }
+            ))
+        })?;
+        offsets.push(end);
+    }
+
+    Ok((
+        OffsetBuffer::new(ScalarBuffer::from(offsets)),
+        Buffer::from_vec(values),
+    ))
+}
@@ -0,0 +1,267 @@
+//! [`tokio::io`] adapters that convert data as it is read or written
+//!
+//! Enabled by the `async` feature. These are the asynchronous counterparts of
+//! [`io::TranslatingReader`](crate::io::TranslatingReader) and
+//! [`io::TranslatingWriter`](crate::io::TranslatingWriter): both wrap a
+//! [`StreamingTranslator`], so conversion happens on the task polling the
+//! stream, one chunk at a time, without blocking a worker thread.
+//!
+//! ```
+//! use fast_encode::Encoding;
+//! use fast_encode::async_io::{AsyncTranslatingReader, AsyncTranslatingWriter};
+//! use tokio::io::{AsyncReadExt, AsyncWriteExt};
+//!
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! let ebcdic: &[u8] = &[0xC8, 0x85, 0x93, 0x93, 0x96];
+//! let mut text = String::new();
+//! AsyncTranslatingReader::new(ebcdic, Encoding::EBCDIC_037, Encoding::UTF8)
+//!     .unwrap()
+//!     .read_to_string(&mut text)
+//!     .await
+//!     .unwrap();
+//! assert_eq!(text, "Hello");
+//!
+//! let mut writer = AsyncTranslatingWriter::new(Vec::new(), Encoding::UTF8, Encoding::UTF16LE).unwrap();
+//! writer.write_all("é".as_bytes()).await.unwrap();
+//! writer.shutdown().await.unwrap();
+//! assert_eq!(writer.into_inner(), [0xE9, 0x00]);
+//! # });
+//! ```
+//!
+//! Conversion errors surface as [`std::io::ErrorKind::InvalidData`] errors
+//! wrapping the crate's [`Error`](crate::Error).
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use pin_project_lite::pin_project;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::{Encoding, ErrorPolicy, Result, StreamingTranslator};
+
+pin_project! {
+    /// Reads from `R` and yields the data converted to the target encoding
+    pub struct AsyncTranslatingReader<R> {
+        #[pin]
+        inner: R,
+        stream: StreamingTranslator,
+        policy: ErrorPolicy,
+        // Raw input read from `inner`
+        input: Vec<u8>,
+        // Converted output not yet handed to the caller
+        output: Vec<u8>,
+        position: usize,
+        finished: bool,
+    }
+}
+
+impl<R: AsyncRead> AsyncTranslatingReader<R> {
+    /// Convert everything read from `inner` from `from` to `to`
+    pub fn new(inner: R, from: Encoding, to: Encoding) -> Result<Self> {
+        Ok(Self::with_translator(
+            inner,
+            StreamingTranslator::with_default_buffer(from, to)?,
+        ))
+    }
+
+    /// Convert with an existing streaming translator, reading its buffer size at a time
+    pub fn with_translator(inner: R, stream: StreamingTranslator) -> Self {
+        let buffer_size = stream.buffer_size().max(1);
+        Self {
+            inner,
+            stream,
+            policy: ErrorPolicy::Strict,
+            input: vec![0; buffer_size],
+            output: Vec::new(),
+            position: 0,
+            finished: false,
+        }
+    }
+
+    /// Handle unmappable input according to `policy` instead of failing
+    pub fn policy(mut self, policy: ErrorPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// The streaming translator, e.g. for [`replacements`](StreamingTranslator::replacements)
+    pub fn translator(&self) -> &StreamingTranslator {
+        &self.stream
+    }
+
+    /// Get a reference to the underlying reader
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the underlying reader
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwrap the underlying reader, discarding any converted data not yet read
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for AsyncTranslatingReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+
+        while *this.position == this.output.len() {
+            if *this.finished || buf.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+
+            // Convert the next chunk of input, or the end of the stream
+            let mut input = ReadBuf::new(&mut this.input[..]);
+            ready!(this.inner.as_mut().poll_read(cx, &mut input))?;
+            let read = input.filled().len();
+            *this.output = if read == 0 {
+                *this.finished = true;
+                this.stream.finish_with_policy(this.policy.clone())?
+            } else {
+                this.stream
+                    .process_chunk_with_policy(&this.input[..read], this.policy.clone())?
+            };
+            *this.position = 0;
+        }
+
+        let available = &this.output[*this.position..];
+        let count = available.len().min(buf.remaining());
+        buf.put_slice(&available[..count]);
+        *this.position += count;
+        Poll::Ready(Ok(()))
+    }
+}
+
+pin_project! {
+    /// Converts data written to it and writes the result to `W`
+    ///
+    /// Call [`shutdown`](tokio::io::AsyncWriteExt::shutdown) once all data is
+    /// written: it converts any held-back input, reports input that ended in
+    /// the middle of a sequence, and shuts down `W`. Unlike the blocking
+    /// writer, dropping it does not finish the conversion.
+    pub struct AsyncTranslatingWriter<W> {
+        #[pin]
+        inner: W,
+        stream: StreamingTranslator,
+        policy: ErrorPolicy,
+        // Converted output not yet accepted by `inner`
+        pending: Vec<u8>,
+        position: usize,
+        finished: bool,
+    }
+}
+
+impl<W: AsyncWrite> AsyncTranslatingWriter<W> {
+    /// Convert everything written from `from` to `to` before passing it to `inner`
+    pub fn new(inner: W, from: Encoding, to: Encoding) -> Result<Self> {
+        Ok(Self::with_translator(
+            inner,
+            StreamingTranslator::with_default_buffer(from, to)?,
+        ))
+    }
+
+    /// Convert with an existing streaming translator
+    pub fn with_translator(inner: W, stream: StreamingTranslator) -> Self {
+        Self {
+            inner,
+            stream,
+            policy: ErrorPolicy::Strict,
+            pending: Vec::new(),
+            position: 0,
+            finished: false,
+        }
+    }
+
+    /// Handle unmappable input according to `policy` instead of failing
+    pub fn policy(mut self, policy: ErrorPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// The streaming translator, e.g. for [`replacements`](StreamingTranslator::replacements)
+    pub fn translator(&self) -> &StreamingTranslator {
+        &self.stream
+    }
+
+    /// Get a reference to the underlying writer
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the underlying writer
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwrap the underlying writer, discarding any converted data not yet written
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Write converted output held back by an earlier call until `inner` has accepted all of it
+    fn poll_drain(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+
+        while *this.position < this.pending.len() {
+            let written = ready!(
+                this.inner
+                    .as_mut()
+                    .poll_write(cx, &this.pending[*this.position..])
+            )?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            *this.position += written;
+        }
+
+        this.pending.clear();
+        *this.position = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite> AsyncWrite for AsyncTranslatingWriter<W> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.as_mut().poll_drain(cx))?;
+
+        let this = self.project();
+        *this.pending = this
+            .stream
+            .process_chunk_with_policy(buf, this.policy.clone())?;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    /// Write all converted output and flush the underlying writer; a partial sequence stays held back
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_drain(cx))?;
+        self.project().inner.poll_flush(cx)
+    }
+
+    /// Convert any held-back input, write everything and shut down the underlying writer
+    ///
+    /// Fails if the input ended in the middle of a multi-byte sequence.
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_drain(cx))?;
+
+        let this = self.as_mut().project();
+        if !*this.finished {
+            *this.finished = true;
+            *this.pending = this.stream.finish_with_policy(this.policy.clone())?;
+            ready!(self.as_mut().poll_drain(cx))?;
+        }
+        self.project().inner.poll_shutdown(cx)
+    }
+}
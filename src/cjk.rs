@@ -0,0 +1,276 @@
+//! Double-byte codecs for Shift_JIS, EUC-JP, GB2312, Big5 and EUC-KR
+//!
+//! Each of these is ASCII in the single-byte range plus a grid of double-byte
+//! characters from a national standard, addressed by a lead and a trail byte.
+//! The grids live in [`tables`]; the reverse mappings used for encoding are
+//! built on first use.
+//!
+//! EUC-JP's three-byte JIS X 0212 sequences are recognised but not mapped.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::{Encoding, Error, Result, tables};
+
+/// One step of decoding from the front of the input
+pub(crate) enum Step {
+    /// A character and the number of bytes it took
+    Char(char, usize),
+    /// This many bytes do not form a character
+    Invalid(usize),
+    /// The input ends in the middle of a character
+    Incomplete,
+}
+
+/// Whether `encoding` is one of the double-byte encodings handled here
+pub(crate) fn is_double_byte(encoding: Encoding) -> bool {
+    matches!(
+        encoding,
+        Encoding::SHIFT_JIS
+            | Encoding::EUC_JP
+            | Encoding::GB2312
+            | Encoding::BIG5
+            | Encoding::EUC_KR
+    )
+}
+
+/// Decode the character at the front of `input`, which must not be empty
+pub(crate) fn decode_step(encoding: Encoding, input: &[u8]) -> Step {
+    let lead = input[0];
+    if lead < 0x80 {
+        return Step::Char(lead as char, 1);
+    }
+
+    match encoding {
+        Encoding::SHIFT_JIS => match lead {
+            0xA1..=0xDF => Step::Char(half_width_katakana(lead), 1),
+            0x81..=0x9F | 0xE0..=0xEF => {
+                with_trail(input, |trail| shift_jis_cell(lead, trail).map(jis_x_0208))
+            }
+            _ => Step::Invalid(1),
+        },
+        Encoding::EUC_JP => match lead {
+            0x8E => with_trail(input, |trail| {
+                matches!(trail, 0xA1..=0xDF).then(|| Some(half_width_katakana(trail)))
+            }),
+            // JIS X 0212: a valid sequence, but not one we can map
+            0x8F => match input.get(1..3) {
+                Some([0xA1..=0xFE, 0xA1..=0xFE]) => Step::Invalid(3),
+                Some(_) => Step::Invalid(1),
+                None if input[1..].iter().all(|b| matches!(b, 0xA1..=0xFE)) => Step::Incomplete,
+                None => Step::Invalid(1),
+            },
+            0xA1..=0xFE => with_trail(input, |trail| {
+                euc_cell(lead, trail).map(|cell| lookup(&tables::JIS_X_0208, cell))
+            }),
+            _ => Step::Invalid(1),
+        },
+        Encoding::GB2312 | Encoding::EUC_KR => match lead {
+            0xA1..=0xFE => with_trail(input, |trail| {
+                let grid = if encoding == Encoding::GB2312 {
+                    &tables::GB_2312
+                } else {
+                    &tables::KS_X_1001
+                };
+                euc_cell(lead, trail).map(|cell| lookup(grid, cell))
+            }),
+            _ => Step::Invalid(1),
+        },
+        Encoding::BIG5 => match lead {
+            0xA1..=0xF9 => with_trail(input, |trail| {
+                big5_cell(lead, trail).map(|cell| lookup(&tables::BIG5, cell))
+            }),
+            _ => Step::Invalid(1),
+        },
+        _ => Step::Invalid(1),
+    }
+}
+
+/// Decode a two-byte sequence starting with `input[0]`
+///
+/// `map` returns `None` if the trail byte is out of range, and `Some(None)` if
+/// the pair is in range but unmapped. An out-of-range ASCII trail byte is left
+/// to be decoded on its own.
+fn with_trail(input: &[u8], map: impl FnOnce(u8) -> Option<Option<char>>) -> Step {
+    let Some(&trail) = input.get(1) else {
+        return Step::Incomplete;
+    };
+
+    match map(trail) {
+        Some(Some(ch)) => Step::Char(ch, 2),
+        Some(None) if trail >= 0x80 => Step::Invalid(2),
+        _ => Step::Invalid(1),
+    }
+}
+
+fn half_width_katakana(byte: u8) -> char {
+    char::from_u32(0xFF61 + (byte - 0xA1) as u32).unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+fn lookup(grid: &[u16], cell: usize) -> Option<char> {
+    match grid[cell] {
+        0 => None,
+        code_point => char::from_u32(code_point as u32),
+    }
+}
+
+fn jis_x_0208(cell: usize) -> Option<char> {
+    lookup(&tables::JIS_X_0208, cell)
+}
+
+/// Grid index of an EUC lead/trail pair
+fn euc_cell(lead: u8, trail: u8) -> Option<usize> {
+    matches!(trail, 0xA1..=0xFE).then(|| (lead - 0xA1) as usize * 94 + (trail - 0xA1) as usize)
+}
+
+/// JIS X 0208 grid index of a Shift_JIS lead/trail pair
+fn shift_jis_cell(lead: u8, trail: u8) -> Option<usize> {
+    if !matches!(trail, 0x40..=0x7E | 0x80..=0xFC) {
+        return None;
+    }
+
+    // Each lead byte covers two rows: trail bytes below 0x9F the odd one
+    let pair = (lead - if lead < 0xA0 { 0x81 } else { 0xC1 }) as usize;
+    let (row, cell) = match trail {
+        0x40..=0x7E => (pair * 2, trail - 0x40),
+        0x80..=0x9E => (pair * 2, trail - 0x41),
+        _ => (pair * 2 + 1, trail - 0x9F),
+    };
+    Some(row * 94 + cell as usize)
+}
+
+/// Big5 grid index of a lead/trail pair
+fn big5_cell(lead: u8, trail: u8) -> Option<usize> {
+    let column = match trail {
+        0x40..=0x7E => trail - 0x40,
+        0xA1..=0xFE => trail - 0xA1 + 63,
+        _ => return None,
+    };
+    Some((lead - 0xA1) as usize * 157 + column as usize)
+}
+
+/// Append the encoding of `ch`, returning `false` if it cannot be encoded
+pub(crate) fn encode(encoding: Encoding, ch: char, output: &mut Vec<u8>) -> bool {
+    if ch.is_ascii() {
+        output.push(ch as u8);
+        return true;
+    }
+
+    if let 0xFF61..=0xFF9F = ch as u32 {
+        let byte = (ch as u32 - 0xFF61) as u8 + 0xA1;
+        match encoding {
+            Encoding::SHIFT_JIS => {
+                output.push(byte);
+                return true;
+            }
+            Encoding::EUC_JP => {
+                output.extend([0x8E, byte]);
+                return true;
+            }
+            _ => {}
+        }
+    }
+
+    let Some(&cell) = reverse(encoding).get(&ch) else {
+        return false;
+    };
+    output.extend(match encoding {
+        Encoding::SHIFT_JIS => {
+            let (row, column) = (cell / 94, (cell % 94) as u8);
+            let lead = (row / 2) as u8 + if row < 62 { 0x81 } else { 0xC1 };
+            let trail = match (row % 2, column) {
+                (0, 0..=62) => column + 0x40,
+                (0, _) => column + 0x41,
+                _ => column + 0x9F,
+            };
+            [lead, trail]
+        }
+        Encoding::BIG5 => {
+            let (lead, column) = ((cell / 157) as u8 + 0xA1, (cell % 157) as u8);
+            let trail = if column < 63 {
+                column + 0x40
+            } else {
+                column - 63 + 0xA1
+            };
+            [lead, trail]
+        }
+        _ => [(cell / 94) as u8 + 0xA1, (cell % 94) as u8 + 0xA1],
+    });
+    true
+}
+
+/// Character-to-grid-index map for `encoding`'s double-byte grid
+fn reverse(encoding: Encoding) -> &'static HashMap<char, usize> {
+    static JIS: OnceLock<HashMap<char, usize>> = OnceLock::new();
+    static GB: OnceLock<HashMap<char, usize>> = OnceLock::new();
+    static KS: OnceLock<HashMap<char, usize>> = OnceLock::new();
+    static BIG5: OnceLock<HashMap<char, usize>> = OnceLock::new();
+
+    let (map, grid): (_, &[u16]) = match encoding {
+        Encoding::GB2312 => (&GB, &tables::GB_2312),
+        Encoding::EUC_KR => (&KS, &tables::KS_X_1001),
+        Encoding::BIG5 => (&BIG5, &tables::BIG5),
+        _ => (&JIS, &tables::JIS_X_0208),
+    };
+
+    map.get_or_init(|| {
+        let mut reverse = HashMap::with_capacity(grid.len());
+        for (cell, &code_point) in grid.iter().enumerate() {
+            if let Some(ch) = char::from_u32(code_point as u32).filter(|_| code_point != 0) {
+                // A few Big5 characters appear twice; encoders use the later position
+                reverse.insert(ch, cell);
+            }
+        }
+        reverse
+    })
+}
+
+/// Decode `input` to UTF-8, failing at the first invalid or truncated sequence
+pub(crate) fn to_utf8(encoding: Encoding, input: &[u8]) -> Result<String> {
+    let mut output = String::with_capacity(input.len());
+    let mut position = 0;
+
+    while position < input.len() {
+        match decode_step(encoding, &input[position..]) {
+            Step::Char(ch, length) => {
+                output.push(ch);
+                position += length;
+            }
+            Step::Invalid(_) | Step::Incomplete => {
+                return Err(Error::UnmappableSource {
+                    byte: input[position],
+                    position,
+                });
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Encode UTF-8 text, failing at the first character `encoding` lacks
+pub(crate) fn from_utf8(encoding: Encoding, input: &str) -> Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len());
+    for (position, character) in input.char_indices() {
+        if !encode(encoding, character, &mut output) {
+            return Err(Error::UnmappableTarget {
+                character,
+                position,
+            });
+        }
+    }
+    Ok(output)
+}
+
+/// Number of trailing bytes in `data` that start a character not yet complete
+pub(crate) fn incomplete_suffix_len(encoding: Encoding, data: &[u8]) -> usize {
+    // Trail bytes overlap the lead byte range, so walk from the start
+    let mut position = 0;
+    while position < data.len() {
+        match decode_step(encoding, &data[position..]) {
+            Step::Char(_, length) | Step::Invalid(length) => position += length,
+            Step::Incomplete => return data.len() - position,
+        }
+    }
+    0
+}
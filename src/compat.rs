@@ -0,0 +1,297 @@
+//! Interoperability with [`encoding_rs`]
+//!
+//! Enabled by the `encoding_rs` feature. [`Encoding`] converts to and from
+//! `&'static encoding_rs::Encoding` wherever both crates implement the same
+//! mapping, and [`EncodingRsTranslator`] offers the [`Translator`](crate::Translator)
+//! API on top of encoding_rs for the encodings this crate lacks (GBK, gb18030,
+//! KOI8-R, ISO-2022-JP, ...), so mixed codebases can migrate one call site at a time.
+//!
+//! encoding_rs implements the WHATWG Encoding Standard, which folds some labels
+//! into supersets: `ISO-8859-1` and `US-ASCII` both mean windows-1252 there.
+//! Such encodings have no equivalent and fail to convert, rather than silently
+//! changing how bytes 0x80-0x9F are mapped.
+//!
+//! ```
+//! use fast_encode::{compat::EncodingRsTranslator, Encoding, ErrorPolicy};
+//!
+//! let koi8: &'static encoding_rs::Encoding = encoding_rs::KOI8_R;
+//! let utf8 = Encoding::UTF8.try_into().unwrap();
+//! let translator = EncodingRsTranslator::new(koi8, utf8).unwrap();
+//! assert_eq!(translator.convert(&[0xD0, 0xD2, 0xC9]).unwrap(), "при".as_bytes());
+//!
+//! let latin = EncodingRsTranslator::new(koi8, encoding_rs::WINDOWS_1252).unwrap();
+//! let escaped = latin.convert_with_policy(&[0xD0, 0xD2, 0xC9], ErrorPolicy::XmlCharRefReplace);
+//! assert_eq!(escaped.unwrap(), b"&#1087;&#1088;&#1080;");
+//! ```
+
+use encoding_rs::{DecoderResult, EncoderResult};
+
+use crate::policy::{self, CharEncoder, Unit};
+use crate::{Encoding, Error, ErrorPolicy, Result};
+
+impl TryFrom<Encoding> for &'static encoding_rs::Encoding {
+    type Error = Error;
+
+    fn try_from(encoding: Encoding) -> Result<Self> {
+        let equivalent = match encoding {
+            Encoding::UTF8 => encoding_rs::UTF_8,
+            Encoding::UTF16LE => encoding_rs::UTF_16LE,
+            Encoding::UTF16BE => encoding_rs::UTF_16BE,
+            Encoding::ISO_8859_2 => encoding_rs::ISO_8859_2,
+            Encoding::ISO_8859_3 => encoding_rs::ISO_8859_3,
+            Encoding::ISO_8859_4 => encoding_rs::ISO_8859_4,
+            Encoding::ISO_8859_5 => encoding_rs::ISO_8859_5,
+            Encoding::ISO_8859_6 => encoding_rs::ISO_8859_6,
+            Encoding::ISO_8859_7 => encoding_rs::ISO_8859_7,
+            Encoding::ISO_8859_8 => encoding_rs::ISO_8859_8,
+            Encoding::ISO_8859_10 => encoding_rs::ISO_8859_10,
+            Encoding::ISO_8859_13 => encoding_rs::ISO_8859_13,
+            Encoding::ISO_8859_14 => encoding_rs::ISO_8859_14,
+            Encoding::ISO_8859_15 => encoding_rs::ISO_8859_15,
+            Encoding::ISO_8859_16 => encoding_rs::ISO_8859_16,
+            Encoding::WINDOWS_1250 => encoding_rs::WINDOWS_1250,
+            Encoding::WINDOWS_1251 => encoding_rs::WINDOWS_1251,
+            Encoding::WINDOWS_1252 => encoding_rs::WINDOWS_1252,
+            Encoding::WINDOWS_1253 => encoding_rs::WINDOWS_1253,
+            Encoding::WINDOWS_1254 => encoding_rs::WINDOWS_1254,
+            Encoding::WINDOWS_1255 => encoding_rs::WINDOWS_1255,
+            Encoding::WINDOWS_1256 => encoding_rs::WINDOWS_1256,
+            Encoding::WINDOWS_1257 => encoding_rs::WINDOWS_1257,
+            Encoding::WINDOWS_1258 => encoding_rs::WINDOWS_1258,
+            Encoding::WINDOWS_874 => encoding_rs::WINDOWS_874,
+            Encoding::CP_866 => encoding_rs::IBM866,
+            Encoding::KOI8_R => encoding_rs::KOI8_R,
+            Encoding::MAC_ROMAN => encoding_rs::MACINTOSH,
+            Encoding::MAC_CYRILLIC => encoding_rs::X_MAC_CYRILLIC,
+            Encoding::SHIFT_JIS => encoding_rs::SHIFT_JIS,
+            Encoding::EUC_JP => encoding_rs::EUC_JP,
+            Encoding::BIG5 => encoding_rs::BIG5,
+            Encoding::EUC_KR => encoding_rs::EUC_KR,
+            other => {
+                return Err(Error::InvalidInput(format!(
+                    "{} has no encoding_rs equivalent",
+                    other.name()
+                )));
+            }
+        };
+        Ok(equivalent)
+    }
+}
+
+impl TryFrom<&'static encoding_rs::Encoding> for Encoding {
+    type Error = Error;
+
+    fn try_from(encoding: &'static encoding_rs::Encoding) -> Result<Self> {
+        // ISO-8859-8-I only differs from ISO-8859-8 in text direction, not in bytes
+        if encoding == encoding_rs::ISO_8859_8_I {
+            return Ok(Encoding::ISO_8859_8);
+        }
+
+        Encoding::all()
+            .iter()
+            .copied()
+            .find(|&candidate| {
+                <&encoding_rs::Encoding>::try_from(candidate).is_ok_and(|rs| rs == encoding)
+            })
+            .ok_or_else(|| {
+                Error::InvalidInput(format!(
+                    "encoding_rs {} has no fast-encode equivalent",
+                    encoding.name()
+                ))
+            })
+    }
+}
+
+/// Converter with the [`Translator`](crate::Translator) API, backed by encoding_rs
+///
+/// Any encoding_rs encoding can be decoded. Every encoding can be a target
+/// except `replacement`, which encoding_rs cannot encode into; UTF-16 targets
+/// are encoded here since encoding_rs only decodes them. Byte order marks are
+/// passed through like any other character.
+#[derive(Debug, Clone, Copy)]
+pub struct EncodingRsTranslator {
+    from: &'static encoding_rs::Encoding,
+    to: &'static encoding_rs::Encoding,
+}
+
+impl EncodingRsTranslator {
+    /// Create a translator between two encoding_rs encodings
+    pub fn new(
+        from: &'static encoding_rs::Encoding,
+        to: &'static encoding_rs::Encoding,
+    ) -> Result<Self> {
+        if to == encoding_rs::REPLACEMENT {
+            return Err(Error::UnsupportedConversion {
+                from: from.name(),
+                to: to.name(),
+            });
+        }
+        Ok(Self { from, to })
+    }
+
+    /// Get source encoding
+    pub fn from_encoding(&self) -> &'static encoding_rs::Encoding {
+        self.from
+    }
+
+    /// Get target encoding
+    pub fn to_encoding(&self) -> &'static encoding_rs::Encoding {
+        self.to
+    }
+
+    /// Convert data from source to target encoding, failing on the first error
+    pub fn convert(&self, input: &[u8]) -> Result<Vec<u8>> {
+        self.convert_with_policy(input, ErrorPolicy::Strict)
+    }
+
+    /// Convert data, handling unmappable input according to `policy`
+    ///
+    /// As with [`Translator::convert_with_policy`](crate::Translator::convert_with_policy),
+    /// input without errors takes a fast path and error positions are byte
+    /// offsets into `input`.
+    pub fn convert_with_policy(&self, input: &[u8], policy: ErrorPolicy) -> Result<Vec<u8>> {
+        if let Some(text) = self
+            .from
+            .decode_without_bom_handling_and_without_replacement(input)
+        {
+            let mut encoder = RsEncoder::new(self.to);
+            let mut output = Vec::with_capacity(text.len());
+            if encoder.encode_str(&text, &mut output) {
+                encoder.finish(&mut output);
+                return Ok(output);
+            }
+        }
+
+        let mut encoder = RsEncoder::new(self.to);
+        let mut output = Vec::with_capacity(input.len());
+        policy::apply(decode(self.from, input), &mut encoder, &policy, &mut output)?;
+        encoder.finish(&mut output);
+        Ok(output)
+    }
+}
+
+/// Decode one byte at a time so every character and malformed run keeps its position
+fn decode<'a>(from: &'static encoding_rs::Encoding, input: &'a [u8]) -> Vec<(usize, Unit<'a>)> {
+    let mut decoder = from.new_decoder_without_bom_handling();
+    let mut units = Vec::with_capacity(input.len());
+    let mut text = String::new();
+    // First byte of the sequence the decoder is currently assembling
+    let mut start = 0;
+
+    for offset in 0..=input.len() {
+        let last = offset == input.len();
+        let byte = if last {
+            &[][..]
+        } else {
+            &input[offset..=offset]
+        };
+        let mut read = 0;
+
+        loop {
+            text.clear();
+            text.reserve(
+                decoder
+                    .max_utf8_buffer_length_without_replacement(byte.len() - read)
+                    .unwrap_or(16)
+                    .max(16),
+            );
+            let (result, consumed) =
+                decoder.decode_to_string_without_replacement(&byte[read..], &mut text, last);
+            read += consumed;
+
+            if !text.is_empty() {
+                units.extend(text.chars().map(|ch| (start, Unit::Char(ch))));
+                start = offset + read;
+            }
+
+            match result {
+                DecoderResult::InputEmpty => break,
+                DecoderResult::OutputFull => continue,
+                DecoderResult::Malformed(length, after) => {
+                    let end = offset + read - after as usize;
+                    let begin = end - length as usize;
+                    units.push((begin, Unit::Invalid(&input[begin..end])));
+                    start = end;
+                }
+            }
+        }
+    }
+
+    units
+}
+
+/// Target side of an [`EncodingRsTranslator`]
+enum RsEncoder {
+    Utf16 { little_endian: bool },
+    Encoder(encoding_rs::Encoder),
+}
+
+impl RsEncoder {
+    fn new(to: &'static encoding_rs::Encoding) -> Self {
+        if to == encoding_rs::UTF_16LE {
+            RsEncoder::Utf16 {
+                little_endian: true,
+            }
+        } else if to == encoding_rs::UTF_16BE {
+            RsEncoder::Utf16 {
+                little_endian: false,
+            }
+        } else {
+            RsEncoder::Encoder(to.new_encoder())
+        }
+    }
+
+    /// Append the encoding of `text`, returning `false` at the first unmappable character
+    fn encode_str(&mut self, text: &str, output: &mut Vec<u8>) -> bool {
+        self.encode_with(text, false, output)
+    }
+
+    /// Flush any trailing state, such as the escape back to ASCII in ISO-2022-JP
+    fn finish(&mut self, output: &mut Vec<u8>) {
+        self.encode_with("", true, output);
+    }
+
+    fn encode_with(&mut self, text: &str, last: bool, output: &mut Vec<u8>) -> bool {
+        match self {
+            RsEncoder::Utf16 { little_endian } => {
+                for unit in text.encode_utf16() {
+                    output.extend(if *little_endian {
+                        unit.to_le_bytes()
+                    } else {
+                        unit.to_be_bytes()
+                    });
+                }
+                true
+            }
+            RsEncoder::Encoder(encoder) => {
+                let mut read = 0;
+                loop {
+                    let remaining = text.len() - read;
+                    output.reserve(
+                        encoder
+                            .max_buffer_length_from_utf8_without_replacement(remaining)
+                            .unwrap_or(remaining)
+                            .max(16),
+                    );
+                    let (result, consumed) = encoder.encode_from_utf8_to_vec_without_replacement(
+                        &text[read..],
+                        output,
+                        last,
+                    );
+                    read += consumed;
+                    match result {
+                        EncoderResult::InputEmpty => return true,
+                        EncoderResult::OutputFull => continue,
+                        EncoderResult::Unmappable(_) => return false,
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl CharEncoder for RsEncoder {
+    fn encode(&mut self, ch: char, output: &mut Vec<u8>) -> bool {
+        self.encode_str(ch.encode_utf8(&mut [0; 4]), output)
+    }
+}
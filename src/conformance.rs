@@ -0,0 +1,321 @@
+//! Conformance checks against reference mapping tables
+//!
+//! The crate bundles a reference byte-to-Unicode table, in the unicode.org
+//! `MAPPINGS` format, for every single-byte encoding with a full built-in
+//! table. [`verify`] compares the built-in table with the reference and runs
+//! every byte through [`Translator`] in both directions, so a build can be
+//! checked exhaustively on the platform and feature set it ships with:
+//!
+//! ```
+//! use fast_encode::{Encoding, conformance};
+//!
+//! let report = conformance::verify(Encoding::WINDOWS_1252).unwrap();
+//! assert!(report.is_conformant(), "{report}");
+//! ```
+//!
+//! Other mapping files, such as vendor tables in unicode.org or ICU `.ucm`
+//! format, can be checked with [`verify_against`].
+
+use std::fmt;
+
+use crate::custom::CustomCodePage;
+use crate::{Encoding, Error, Result, Translator};
+
+/// Bundled reference tables, in unicode.org `MAPPINGS` format
+const REFERENCES: &[(Encoding, &str)] = &[
+    (Encoding::ISO_8859_1, include_str!("../mappings/8859-1.TXT")),
+    (Encoding::ISO_8859_2, include_str!("../mappings/8859-2.TXT")),
+    (Encoding::ISO_8859_3, include_str!("../mappings/8859-3.TXT")),
+    (Encoding::ISO_8859_4, include_str!("../mappings/8859-4.TXT")),
+    (Encoding::ISO_8859_5, include_str!("../mappings/8859-5.TXT")),
+    (Encoding::ISO_8859_6, include_str!("../mappings/8859-6.TXT")),
+    (Encoding::ISO_8859_7, include_str!("../mappings/8859-7.TXT")),
+    (Encoding::ISO_8859_8, include_str!("../mappings/8859-8.TXT")),
+    (Encoding::ISO_8859_9, include_str!("../mappings/8859-9.TXT")),
+    (
+        Encoding::ISO_8859_10,
+        include_str!("../mappings/8859-10.TXT"),
+    ),
+    (
+        Encoding::ISO_8859_11,
+        include_str!("../mappings/8859-11.TXT"),
+    ),
+    (
+        Encoding::ISO_8859_13,
+        include_str!("../mappings/8859-13.TXT"),
+    ),
+    (
+        Encoding::ISO_8859_14,
+        include_str!("../mappings/8859-14.TXT"),
+    ),
+    (
+        Encoding::ISO_8859_15,
+        include_str!("../mappings/8859-15.TXT"),
+    ),
+    (
+        Encoding::ISO_8859_16,
+        include_str!("../mappings/8859-16.TXT"),
+    ),
+    (
+        Encoding::WINDOWS_1250,
+        include_str!("../mappings/CP1250.TXT"),
+    ),
+    (
+        Encoding::WINDOWS_1251,
+        include_str!("../mappings/CP1251.TXT"),
+    ),
+    (
+        Encoding::WINDOWS_1252,
+        include_str!("../mappings/CP1252.TXT"),
+    ),
+    (
+        Encoding::WINDOWS_1253,
+        include_str!("../mappings/CP1253.TXT"),
+    ),
+    (
+        Encoding::WINDOWS_1254,
+        include_str!("../mappings/CP1254.TXT"),
+    ),
+    (
+        Encoding::WINDOWS_1255,
+        include_str!("../mappings/CP1255.TXT"),
+    ),
+    (
+        Encoding::WINDOWS_1256,
+        include_str!("../mappings/CP1256.TXT"),
+    ),
+    (
+        Encoding::WINDOWS_1257,
+        include_str!("../mappings/CP1257.TXT"),
+    ),
+    (
+        Encoding::WINDOWS_1258,
+        include_str!("../mappings/CP1258.TXT"),
+    ),
+    (Encoding::WINDOWS_874, include_str!("../mappings/CP874.TXT")),
+    (Encoding::CP_437, include_str!("../mappings/CP437.TXT")),
+    (Encoding::CP_850, include_str!("../mappings/CP850.TXT")),
+    (Encoding::CP_852, include_str!("../mappings/CP852.TXT")),
+    (Encoding::CP_855, include_str!("../mappings/CP855.TXT")),
+    (Encoding::CP_857, include_str!("../mappings/CP857.TXT")),
+    (Encoding::CP_860, include_str!("../mappings/CP860.TXT")),
+    (Encoding::CP_861, include_str!("../mappings/CP861.TXT")),
+    (Encoding::CP_862, include_str!("../mappings/CP862.TXT")),
+    (Encoding::CP_863, include_str!("../mappings/CP863.TXT")),
+    (Encoding::CP_865, include_str!("../mappings/CP865.TXT")),
+    (Encoding::CP_866, include_str!("../mappings/CP866.TXT")),
+    (Encoding::KOI8_R, include_str!("../mappings/KOI8-R.TXT")),
+    (
+        Encoding::MAC_ROMAN,
+        include_str!("../mappings/MACROMAN.TXT"),
+    ),
+    (
+        Encoding::MAC_CYRILLIC,
+        include_str!("../mappings/MACCYRILLIC.TXT"),
+    ),
+    (Encoding::EBCDIC_037, include_str!("../mappings/CP037.TXT")),
+    (Encoding::EBCDIC_273, include_str!("../mappings/CP273.TXT")),
+    (Encoding::EBCDIC_277, include_str!("../mappings/CP277.TXT")),
+    (Encoding::EBCDIC_278, include_str!("../mappings/CP278.TXT")),
+    (Encoding::EBCDIC_280, include_str!("../mappings/CP280.TXT")),
+    (Encoding::EBCDIC_284, include_str!("../mappings/CP284.TXT")),
+    (Encoding::EBCDIC_285, include_str!("../mappings/CP285.TXT")),
+    (Encoding::EBCDIC_297, include_str!("../mappings/CP297.TXT")),
+    (Encoding::EBCDIC_500, include_str!("../mappings/CP500.TXT")),
+    (
+        Encoding::EBCDIC_1047,
+        include_str!("../mappings/CP1047.TXT"),
+    ),
+];
+
+/// Encodings that [`verify`] has a bundled reference table for
+pub fn encodings() -> impl Iterator<Item = Encoding> {
+    REFERENCES.iter().map(|&(encoding, _)| encoding)
+}
+
+/// Check `encoding` against its bundled reference table
+///
+/// Fails if no reference table is bundled for `encoding`.
+pub fn verify(encoding: Encoding) -> Result<Report> {
+    let (_, text) = REFERENCES
+        .iter()
+        .find(|&&(candidate, _)| candidate == encoding)
+        .ok_or_else(|| {
+            Error::InvalidInput(format!(
+                "No reference mapping bundled for {}",
+                encoding.name()
+            ))
+        })?;
+
+    let reference = parse_mapping(encoding.name(), text)?;
+    Ok(verify_against(encoding, &reference))
+}
+
+/// Check `encoding` against every bundled reference table
+pub fn verify_all() -> Vec<Report> {
+    REFERENCES
+        .iter()
+        .map(|&(encoding, text)| {
+            let reference = parse_mapping(encoding.name(), text)
+                .expect("bundled mapping tables are well-formed");
+            verify_against(encoding, &reference)
+        })
+        .collect()
+}
+
+/// Check `encoding` against a reference table
+///
+/// Every byte is compared with the reference and, where the reference maps
+/// it, decoded to UTF-8 and encoded back again.
+pub fn verify_against(encoding: Encoding, reference: &CustomCodePage) -> Report {
+    let mut mismatches = Vec::new();
+    let table = crate::tables::get_encoding_chars(encoding);
+    let decoder = Translator::new(encoding, Encoding::UTF8);
+    let encoder = Translator::new(Encoding::UTF8, encoding);
+
+    for (byte, (&expected, &actual)) in reference.chars().iter().zip(table.iter()).enumerate() {
+        let byte = byte as u8;
+        if expected != actual {
+            mismatches.push(Mismatch::Table {
+                byte,
+                expected,
+                actual,
+            });
+        }
+
+        let Some(ch) = expected else { continue };
+        let decoded = decoder.as_ref().ok().and_then(|t| t.convert(&[byte]).ok());
+        if decoded.as_deref() != Some(ch.encode_utf8(&mut [0; 4]).as_bytes()) {
+            mismatches.push(Mismatch::Decode {
+                byte,
+                expected: ch,
+                actual: decoded.and_then(|d| String::from_utf8(d).ok()),
+            });
+        }
+
+        let encoded = encoder
+            .as_ref()
+            .ok()
+            .and_then(|t| t.convert(ch.encode_utf8(&mut [0; 4]).as_bytes()).ok());
+        if encoded.as_deref() != Some(&[byte][..]) {
+            mismatches.push(Mismatch::Encode {
+                ch,
+                expected: byte,
+                actual: encoded,
+            });
+        }
+    }
+
+    Report {
+        encoding,
+        reference: reference.name().to_string(),
+        mismatches,
+    }
+}
+
+/// Parse a unicode.org `MAPPINGS` table (`0xNN<TAB>0xNNNN<TAB>#NAME` per line)
+///
+/// ICU `.ucm` files are read with [`CustomCodePage::from_ucm`] instead.
+pub fn parse_mapping(name: &str, text: &str) -> Result<CustomCodePage> {
+    CustomCodePage::from_tsv(name, text)
+}
+
+/// Result of checking one encoding against a reference table
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// Encoding that was checked
+    pub encoding: Encoding,
+    /// Name of the reference table
+    pub reference: String,
+    /// Every difference found
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl Report {
+    /// Whether the encoding matches the reference exactly
+    pub fn is_conformant(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} against {}: {} mismatch(es)",
+            self.encoding.name(),
+            self.reference,
+            self.mismatches.len()
+        )?;
+        for mismatch in &self.mismatches {
+            write!(f, "\n  {}", mismatch)?;
+        }
+        Ok(())
+    }
+}
+
+/// A difference between an encoding and its reference table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// The built-in table maps `byte` differently
+    Table {
+        /// Byte whose mapping differs
+        byte: u8,
+        /// Reference mapping (`None` = undefined)
+        expected: Option<char>,
+        /// Built-in mapping
+        actual: Option<char>,
+    },
+    /// Decoding `byte` did not produce the reference character
+    Decode {
+        /// Byte that was decoded
+        byte: u8,
+        /// Reference character
+        expected: char,
+        /// Decoded text (`None` if decoding failed)
+        actual: Option<String>,
+    },
+    /// Encoding `ch` did not produce the reference byte
+    Encode {
+        /// Character that was encoded
+        ch: char,
+        /// Reference byte
+        expected: u8,
+        /// Encoded bytes (`None` if encoding failed)
+        actual: Option<Vec<u8>>,
+    },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::Table {
+                byte,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "table: 0x{:02X} maps to {:?}, expected {:?}",
+                byte, actual, expected
+            ),
+            Mismatch::Decode {
+                byte,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "decode: 0x{:02X} gives {:?}, expected {:?}",
+                byte, actual, expected
+            ),
+            Mismatch::Encode {
+                ch,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "encode: U+{:04X} gives {:02X?}, expected 0x{:02X}",
+                *ch as u32, actual, expected
+            ),
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! User-defined single-byte code pages loaded at runtime
+//!
+//! Site-specific EBCDIC variants and other in-house code pages can be described
+//! by a 256-entry table, derived from a built-in code page, or loaded from an
+//! ICU `.ucm` or tab-separated mapping file, then used anywhere a built-in
+//! [`Encoding`] is accepted via [`Encoding::Custom`]:
+//!
+//! ```
+//! use fast_encode::{Encoding, Translator, custom::CustomCodePage};
+//!
+//! // EBCDIC 037 with a customer symbol at 0x4A instead of '¢'
+//! let site = CustomCodePage::based_on("SITE037", Encoding::EBCDIC_037)
+//!     .unwrap()
+//!     .map(0x4A, Some('⌘'))
+//!     .into_encoding();
+//!
+//! let translator = Translator::new(site, Encoding::UTF8).unwrap();
+//! assert_eq!(translator.convert(&[0xC1, 0x4A]).unwrap(), "A⌘".as_bytes());
+//! ```
+
+use crate::{Encoding, Error, Result, TranslationTable};
+use std::sync::{Mutex, PoisonError};
+
+/// A single-byte code page supplied by the user
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CustomCodePage {
+    /// Display name of the code page
+    name: String,
+    /// Byte-to-Unicode table (`None` = unmapped)
+    chars: [Option<char>; 256],
+}
+
+impl CustomCodePage {
+    /// Create a code page from a complete byte-to-Unicode table
+    pub fn new(name: impl Into<String>, chars: [Option<char>; 256]) -> Self {
+        Self {
+            name: name.into(),
+            chars,
+        }
+    }
+
+    /// Start from the table of a built-in single-byte encoding
+    ///
+    /// Adjust individual bytes with [`map`](Self::map). Fails for multi-byte
+    /// encodings, which have no complete byte-to-Unicode table.
+    pub fn based_on(name: impl Into<String>, encoding: Encoding) -> Result<Self> {
+        if encoding.is_multibyte() {
+            return Err(Error::InvalidInput(format!(
+                "{} is not a single-byte encoding",
+                encoding.name()
+            )));
+        }
+        Ok(Self::new(
+            name,
+            *crate::tables::get_encoding_chars(encoding),
+        ))
+    }
+
+    /// Map `byte` to `ch` (`None` = unmapped), replacing its current mapping
+    pub fn map(mut self, byte: u8, ch: Option<char>) -> Self {
+        self.chars[byte as usize] = ch;
+        self
+    }
+
+    /// Parse a tab-separated mapping file with one `byte<TAB>code point` pair per line
+    ///
+    /// Both columns are hexadecimal; the byte may carry a `0x` prefix and the
+    /// code point a `0x` or `U+` prefix. Text after `#` is a comment, and a
+    /// byte with no code point column is unmapped, so unicode.org `MAPPINGS`
+    /// tables load as they are. Unlisted bytes are unmapped too.
+    pub fn from_tsv(name: impl Into<String>, text: &str) -> Result<Self> {
+        let mut chars = [None; 256];
+
+        for (index, raw_line) in text.lines().enumerate() {
+            let line = raw_line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let invalid =
+                || Error::InvalidInput(format!("Mapping line {}: {}", index + 1, raw_line));
+            let mut columns = line.split_whitespace();
+            let byte = columns
+                .next()
+                .and_then(|column| parse_hex(column, &["0x", "0X"]))
+                .and_then(|byte| u8::try_from(byte).ok())
+                .ok_or_else(invalid)?;
+
+            if let Some(column) = columns.next() {
+                let code_point =
+                    parse_hex(column, &["0x", "0X", "U+", "u+"]).ok_or_else(invalid)?;
+                chars[byte as usize] = Some(char::from_u32(code_point).ok_or_else(invalid)?);
+            }
+        }
+
+        Ok(Self::new(name, chars))
+    }
+
+    /// Parse a mapping file in either supported format
+    ///
+    /// Text with a `CHARMAP` section is read with [`from_ucm`](Self::from_ucm),
+    /// anything else with [`from_tsv`](Self::from_tsv) under `name`.
+    pub fn parse(name: impl Into<String>, text: &str) -> Result<Self> {
+        if text.lines().any(|line| line.trim() == "CHARMAP") {
+            Self::from_ucm(text)
+        } else {
+            Self::from_tsv(name, text)
+        }
+    }
+
+    /// Parse an ICU `.ucm` mapping file describing a single-byte code page
+    ///
+    /// Round-trip (`|0`) and decode-only fallback (`|3`) mappings populate the
+    /// table; encode-only fallbacks (`|1`) and substitution entries (`|2`) are
+    /// ignored. The name is taken from `<code_set_name>` if present.
+    pub fn from_ucm(text: &str) -> Result<Self> {
+        let mut name = None;
+        let mut chars = [None; 256];
+        let mut in_charmap = false;
+        let mut mappings = 0;
+
+        for (index, raw_line) in text.lines().enumerate() {
+            let line_no = index + 1;
+            let line = raw_line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if !in_charmap {
+                if line == "CHARMAP" {
+                    in_charmap = true;
+                } else if let Some(value) = line.strip_prefix("<code_set_name>") {
+                    name = Some(value.trim().trim_matches('"').to_string());
+                } else if let Some(value) = line.strip_prefix("<mb_cur_max>")
+                    && value.trim() != "1"
+                {
+                    return Err(Error::InvalidInput(format!(
+                        "UCM line {}: only single-byte code pages are supported",
+                        line_no
+                    )));
+                }
+                continue;
+            }
+
+            if line == "END CHARMAP" {
+                break;
+            }
+
+            let (ch, byte, precision) = parse_ucm_mapping(line)
+                .ok_or_else(|| Error::InvalidInput(format!("UCM line {}: {}", line_no, line)))?;
+
+            if matches!(precision, 0 | 3) && chars[byte as usize].is_none() {
+                chars[byte as usize] = Some(ch);
+                mappings += 1;
+            }
+        }
+
+        if mappings == 0 {
+            return Err(Error::InvalidInput(
+                "UCM file contains no CHARMAP mappings".to_string(),
+            ));
+        }
+
+        Ok(Self::new(
+            name.unwrap_or_else(|| "custom".to_string()),
+            chars,
+        ))
+    }
+
+    /// Get the name of this code page
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the byte-to-Unicode table
+    pub fn chars(&self) -> &[Option<char>; 256] {
+        &self.chars
+    }
+
+    /// Turn this code page into an [`Encoding`] usable with every translator
+    ///
+    /// This is a once-per-process call: `Encoding` is `Copy` and needs a
+    /// `'static` table, so each distinct page is leaked on first use and
+    /// stays alive until exit. Registering an identical page again returns
+    /// the existing encoding, so memory grows with the number of different
+    /// pages rather than with the number of calls. To convert with a page
+    /// whose lifetime you control, use [`table_to`](Self::table_to) or
+    /// [`table_from`](Self::table_from) instead.
+    pub fn into_encoding(self) -> Encoding {
+        static REGISTERED: Mutex<Vec<&'static CustomCodePage>> = Mutex::new(Vec::new());
+
+        let mut registered = REGISTERED.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(&page) = registered.iter().find(|&&page| *page == self) {
+            return Encoding::Custom(page);
+        }
+        let page: &'static CustomCodePage = Box::leak(Box::new(self));
+        registered.push(page);
+        Encoding::Custom(page)
+    }
+
+    /// Build a byte-to-byte table from this page to a single-byte encoding
+    ///
+    /// Borrows the page instead of registering it, so nothing is leaked.
+    pub fn table_to(&self, to: Encoding) -> Result<TranslationTable> {
+        if to.is_multibyte() {
+            return Err(Error::UnsupportedConversion {
+                from: "custom",
+                to: to.name(),
+            });
+        }
+        Ok(TranslationTable::from_chars(
+            &self.chars,
+            crate::tables::get_encoding_chars(to),
+        ))
+    }
+
+    /// Build a byte-to-byte table from a single-byte encoding to this page
+    ///
+    /// Borrows the page instead of registering it, so nothing is leaked.
+    pub fn table_from(&self, from: Encoding) -> Result<TranslationTable> {
+        if from.is_multibyte() {
+            return Err(Error::UnsupportedConversion {
+                from: from.name(),
+                to: "custom",
+            });
+        }
+        Ok(TranslationTable::from_chars(
+            crate::tables::get_encoding_chars(from),
+            &self.chars,
+        ))
+    }
+
+    /// Whether bytes 0-127 decode to the corresponding ASCII characters
+    pub fn is_ascii_compatible(&self) -> bool {
+        self.chars[..128]
+            .iter()
+            .enumerate()
+            .all(|(byte, ch)| *ch == Some(byte as u8 as char))
+    }
+}
+
+/// Parse a hexadecimal number with an optional prefix
+fn parse_hex(column: &str, prefixes: &[&str]) -> Option<u32> {
+    let digits = prefixes
+        .iter()
+        .find_map(|prefix| column.strip_prefix(prefix))
+        .unwrap_or(column);
+    u32::from_str_radix(digits, 16).ok()
+}
+
+/// Parse `<UXXXX> \xNN |P` into (character, byte, precision)
+fn parse_ucm_mapping(line: &str) -> Option<(char, u8, u8)> {
+    let mut parts = line.split_whitespace();
+
+    let code_point = parts.next()?.strip_prefix("<U")?.strip_suffix('>')?;
+    let ch = char::from_u32(u32::from_str_radix(code_point, 16).ok()?)?;
+
+    // Single-byte tables have exactly one \xNN per mapping
+    let bytes = parts.next()?.strip_prefix("\\x")?;
+    let byte = u8::from_str_radix(bytes, 16).ok()?;
+
+    let precision = match parts.next() {
+        Some(flag) => flag.strip_prefix('|')?.parse().ok()?,
+        None => 0,
+    };
+
+    Some((ch, byte, precision))
+}
@@ -2,8 +2,32 @@
 //!
 //! This module provides sophisticated encoding detection capabilities for
 //! automatically identifying the character encoding of binary data.
+//!
+//! [`EncodingDetector::detect`] looks at a complete sample. To sniff a stream
+//! without holding on to it, [`feed`](EncodingDetector::feed) the chunks as
+//! they arrive and [`conclude`](EncodingDetector::conclude) once enough has
+//! been seen; the detector keeps running statistics, not the data.
+//!
+//! ```
+//! use fast_encode::Encoding;
+//! use fast_encode::detection::EncodingDetector;
+//!
+//! let mut detector = EncodingDetector::new();
+//! for chunk in [&b"Gr\xFC\xDFe aus "[..], b"M\xFCnchen und K\xF6ln"] {
+//!     if !detector.feed(chunk) {
+//!         break;
+//!     }
+//! }
+//! assert_eq!(detector.conclude().encoding, Encoding::ISO_8859_1);
+//! ```
+//!
+//! HTML and XML documents usually declare their own encoding;
+//! [`detect_with_document_hints`](EncodingDetector::detect_with_document_hints)
+//! reads the declaration and checks it against the data.
+
+use std::collections::HashMap;
 
-use crate::Encoding;
+use crate::{Encoding, Translator, multibyte, tables, telemetry};
 
 /// Result of encoding detection with confidence score
 #[derive(Debug, Clone)]
@@ -16,19 +40,158 @@ pub struct DetectionResult {
     pub bom_detected: bool,
     /// All candidate encodings with their scores
     pub candidates: Vec<(Encoding, f64)>,
+    /// What decided `encoding`
+    pub source: DetectionSource,
+    /// Encoding the document declares, if
+    /// [`detect_with_document_hints`](EncodingDetector::detect_with_document_hints)
+    /// found one, whether or not it was used
+    pub declared: Option<Encoding>,
+}
+
+/// What decided the encoding of a [`DetectionResult`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DetectionSource {
+    /// A byte order mark at the start of the data
+    Bom,
+    /// A charset declared in the document that the data decodes in
+    Declaration,
+    /// Byte statistics
+    Statistics,
+}
+
+impl DetectionSource {
+    /// Lower-case name, e.g. for reports
+    pub fn name(self) -> &'static str {
+        match self {
+            DetectionSource::Bom => "bom",
+            DetectionSource::Declaration => "declaration",
+            DetectionSource::Statistics => "statistics",
+        }
+    }
+}
+
+/// Bytes searched for a charset declaration, as in the HTML prescan
+const DECLARATION_WINDOW: usize = 1024;
+
+/// Encoding declared inline by an XML declaration, an HTML `<meta>` tag or a `Content-Type` header
+///
+/// Only the first 1024 bytes are searched. Labels are resolved with
+/// [`Encoding::for_label`]; unknown labels count as no declaration.
+///
+/// ```
+/// use fast_encode::Encoding;
+/// use fast_encode::detection::declared_encoding;
+///
+/// let page = b"<html><head><meta charset=\"windows-1251\"><title>...";
+/// assert_eq!(declared_encoding(page), Some(Encoding::WINDOWS_1251));
+/// let xml = b"<?xml version='1.0' encoding='ISO-8859-15'?><doc/>";
+/// assert_eq!(declared_encoding(xml), Some(Encoding::ISO_8859_15));
+/// ```
+pub fn declared_encoding(data: &[u8]) -> Option<Encoding> {
+    let text = data[..data.len().min(DECLARATION_WINDOW)].to_ascii_lowercase();
+    let text = text.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&text);
+
+    let declared = xml_declaration(text)
+        .or_else(|| meta_charset(text))
+        .or_else(|| header_charset(text))?;
+
+    // A declaration readable as ASCII is not in UTF-16 or UTF-32, whatever it
+    // says; HTML treats such labels as UTF-8
+    Some(match declared {
+        Encoding::UTF16LE | Encoding::UTF16BE | Encoding::UTF32LE | Encoding::UTF32BE => {
+            Encoding::UTF8
+        }
+        encoding => encoding,
+    })
+}
+
+/// `<?xml version="1.0" encoding="..."?>`, which means UTF-8 without an encoding
+fn xml_declaration(text: &[u8]) -> Option<Encoding> {
+    let declaration = text.trim_ascii_start().strip_prefix(b"<?xml")?;
+    let end = find(declaration, b"?>").unwrap_or(declaration.len());
+    match attribute_value(&declaration[..end], b"encoding") {
+        Some(label) => Encoding::for_label(label),
+        None => Some(Encoding::UTF8),
+    }
+}
+
+/// `<meta charset="...">` or `<meta http-equiv="Content-Type" content="text/html; charset=...">`
+fn meta_charset(text: &[u8]) -> Option<Encoding> {
+    let mut offset = 0;
+    while let Some(start) = find(&text[offset..], b"<meta") {
+        let tag = &text[offset + start..];
+        let end = find(tag, b">").unwrap_or(tag.len());
+        if let Some(encoding) =
+            attribute_value(&tag[..end], b"charset").and_then(Encoding::for_label)
+        {
+            return Some(encoding);
+        }
+        offset += start + end.max(1);
+    }
+    None
+}
+
+/// `Content-Type: text/plain; charset=...` at the start of a line, as in mail and HTTP dumps
+fn header_charset(text: &[u8]) -> Option<Encoding> {
+    text.split(|&byte| byte == b'\n')
+        .filter_map(|line| line.strip_prefix(b"content-type:"))
+        .find_map(|value| attribute_value(value, b"charset").and_then(Encoding::for_label))
+}
+
+/// Value of the first `name=value` in `text`, quoted or not
+fn attribute_value<'a>(text: &'a [u8], name: &[u8]) -> Option<&'a str> {
+    let mut offset = 0;
+    while let Some(found) = find(&text[offset..], name) {
+        offset += found + name.len();
+        let Some(value) = text[offset..].trim_ascii_start().strip_prefix(b"=") else {
+            continue;
+        };
+        let value = value.trim_ascii_start();
+        let value = value
+            .strip_prefix(b"\"")
+            .or_else(|| value.strip_prefix(b"'"))
+            .unwrap_or(value);
+        let end = value
+            .iter()
+            .position(|&byte| !(byte.is_ascii_alphanumeric() || b"-_.:".contains(&byte)))
+            .unwrap_or(value.len());
+        if end > 0 {
+            return std::str::from_utf8(&value[..end]).ok();
+        }
+    }
+    None
+}
+
+/// Position of the first occurrence of `needle` in `haystack`
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Whether `sample` decodes in `encoding` without errors, ignoring a character cut off at its end
+fn decodes(encoding: Encoding, sample: &[u8]) -> bool {
+    let complete = sample.len() - multibyte::incomplete_suffix_len(encoding, sample);
+    Translator::new(encoding, Encoding::UTF8)
+        .and_then(|translator| translator.convert(&sample[..complete]))
+        .is_ok()
 }
 
 /// Encoding detector using multiple detection methods
+///
+/// Holds the statistics of the data fed so far; [`detect`](Self::detect)
+/// does not touch them.
+#[derive(Debug, Clone)]
 pub struct EncodingDetector {
     /// Maximum bytes to analyze for detection
     max_sample_size: usize,
+    /// Statistics of the bytes fed so far
+    stats: Statistics,
 }
 
 impl Default for EncodingDetector {
     fn default() -> Self {
-        Self {
-            max_sample_size: 8192,
-        }
+        Self::with_sample_size(8192)
     }
 }
 
@@ -40,25 +203,250 @@ impl EncodingDetector {
 
     /// Create detector with custom sample size
     pub fn with_sample_size(max_sample_size: usize) -> Self {
-        Self { max_sample_size }
+        Self {
+            max_sample_size,
+            stats: Statistics::new(),
+        }
     }
 
     /// Detect encoding of the given data
     pub fn detect(&self, data: &[u8]) -> DetectionResult {
-        // Limit sample size
-        let sample = if data.len() > self.max_sample_size {
-            &data[..self.max_sample_size]
+        let mut detector = Self::with_sample_size(self.max_sample_size);
+        detector.feed(data);
+        detector.conclude()
+    }
+
+    /// Add the next chunk of a stream to the statistics
+    ///
+    /// Only the first [`with_sample_size`](Self::with_sample_size) bytes
+    /// count. Returns `false` once they have all been seen and more input
+    /// would not change the result.
+    pub fn feed(&mut self, chunk: &[u8]) -> bool {
+        let room = self.max_sample_size - self.stats.seen;
+        self.stats.add(&chunk[..chunk.len().min(room)]);
+        self.stats.seen < self.max_sample_size
+    }
+
+    /// Detect the encoding from everything fed so far
+    ///
+    /// Feeding can continue afterwards to refine the result.
+    pub fn conclude(&self) -> DetectionResult {
+        let _span = telemetry::detection(self.stats.seen);
+        let result = self.stats.conclude();
+        telemetry::detected(&result);
+        result
+    }
+
+    /// Forget everything fed so far
+    pub fn reset(&mut self) {
+        self.stats = Statistics::new();
+    }
+
+    /// Number of bytes fed so far that count towards the result
+    pub fn bytes_seen(&self) -> usize {
+        self.stats.seen
+    }
+
+    /// Detect encoding, taking a charset declared in the document into account
+    ///
+    /// Looks for a [`declared_encoding`] in the data. A byte order mark still
+    /// wins; otherwise the declared encoding is used when the sample decodes in
+    /// it without errors, and statistics decide when it does not, as for a page
+    /// re-saved as UTF-8 that still declares its old code page. A declared
+    /// ISO-8859-1 with bytes in 0x80-0x9F is read as Windows-1252, as browsers
+    /// do. [`DetectionResult::source`] tells which way it went.
+    ///
+    /// ```
+    /// use fast_encode::Encoding;
+    /// use fast_encode::detection::{DetectionSource, EncodingDetector};
+    ///
+    /// let page = b"<meta charset=\"iso-8859-15\"><p>Prix : 5 \xA4</p>";
+    /// let result = EncodingDetector::new().detect_with_document_hints(page);
+    /// assert_eq!(result.encoding, Encoding::ISO_8859_15);
+    /// assert_eq!(result.source, DetectionSource::Declaration);
+    /// ```
+    pub fn detect_with_document_hints(&self, data: &[u8]) -> DetectionResult {
+        let mut result = self.detect(data);
+        let Some(mut declared) = declared_encoding(data) else {
+            return result;
+        };
+        result.declared = Some(declared);
+
+        let sample = &data[..data.len().min(self.max_sample_size)];
+        if declared == Encoding::ISO_8859_1 && sample.iter().any(|byte| (0x80..0xA0).contains(byte))
+        {
+            declared = Encoding::WINDOWS_1252;
+        }
+        if result.source == DetectionSource::Bom || !decodes(declared, sample) {
+            return result;
+        }
+
+        // A declaration is usually right, and near certain when the statistics agree
+        let confidence = if result.encoding == declared {
+            0.99
         } else {
-            data
+            result
+                .candidates
+                .iter()
+                .find(|&&(encoding, _)| encoding == declared)
+                .map_or(0.9, |&(_, confidence)| confidence.max(0.9))
         };
+        result
+            .candidates
+            .retain(|&(encoding, _)| encoding != declared);
+        result.candidates.insert(0, (declared, confidence));
+        result.encoding = declared;
+        result.confidence = confidence;
+        result.source = DetectionSource::Declaration;
+        result
+    }
 
+    /// Detect encoding with language hint
+    pub fn detect_with_hint(&self, data: &[u8], language_hint: &str) -> DetectionResult {
+        let mut result = self.detect(data);
+
+        // Adjust confidence based on language hint
+        match language_hint.to_lowercase().as_str() {
+            "english" | "en" if result.encoding.is_ascii_compatible() => {
+                // Boost confidence for ASCII-compatible encodings
+                result.confidence = (result.confidence * 1.2).min(1.0);
+            }
+            "german" | "de" | "french" | "fr" | "spanish" | "es" => {
+                // Boost confidence for Latin encodings
+                if matches!(
+                    result.encoding,
+                    Encoding::ISO_8859_1 | Encoding::ISO_8859_15 | Encoding::WINDOWS_1252
+                ) {
+                    result.confidence = (result.confidence * 1.3).min(1.0);
+                }
+            }
+            "polish" | "pl" | "czech" | "cz" | "hungarian" | "hu" => {
+                // Boost confidence for Central European encodings
+                if matches!(
+                    result.encoding,
+                    Encoding::WINDOWS_1250 | Encoding::ISO_8859_2
+                ) {
+                    result.confidence = (result.confidence * 1.3).min(1.0);
+                }
+            }
+            "russian" | "ru" | "cyrillic" => {
+                // Boost confidence for Cyrillic encodings
+                if matches!(
+                    result.encoding,
+                    Encoding::WINDOWS_1251
+                        | Encoding::KOI8_R
+                        | Encoding::ISO_8859_5
+                        | Encoding::CP_866
+                ) {
+                    result.confidence = (result.confidence * 1.3).min(1.0);
+                }
+            }
+            "greek" | "el" => {
+                if matches!(
+                    result.encoding,
+                    Encoding::WINDOWS_1253 | Encoding::ISO_8859_7
+                ) {
+                    result.confidence = (result.confidence * 1.3).min(1.0);
+                }
+            }
+            "hebrew" | "he" => {
+                if matches!(
+                    result.encoding,
+                    Encoding::WINDOWS_1255 | Encoding::ISO_8859_8
+                ) {
+                    result.confidence = (result.confidence * 1.3).min(1.0);
+                }
+            }
+            "arabic" | "ar" => {
+                if matches!(
+                    result.encoding,
+                    Encoding::WINDOWS_1256 | Encoding::ISO_8859_6
+                ) {
+                    result.confidence = (result.confidence * 1.3).min(1.0);
+                }
+            }
+            _ => {} // No adjustment for unknown languages
+        }
+
+        result
+    }
+}
+
+/// Running statistics of the bytes fed to a detector
+#[derive(Debug, Clone)]
+struct Statistics {
+    /// Bytes counted so far
+    seen: usize,
+    /// The first four bytes, for byte order marks
+    head: Vec<u8>,
+    /// Occurrences of each byte value
+    histogram: [usize; 256],
+    /// Occurrences of each pair of adjacent bytes where either is 0x80 or above
+    pairs: HashMap<(u8, u8), usize>,
+    /// The last byte counted
+    previous: Option<u8>,
+    /// UTF-8 structure seen so far
+    utf8: Utf8State,
+    /// Scores of both UTF-16 byte orders so far
+    utf16: Utf16State,
+    /// Letter statistics of each code page with a language model
+    languages: Vec<LanguageScore>,
+}
+
+impl Statistics {
+    fn new() -> Self {
+        Self {
+            seen: 0,
+            head: Vec::with_capacity(4),
+            histogram: [0; 256],
+            pairs: HashMap::new(),
+            previous: None,
+            utf8: Utf8State::default(),
+            utf16: Utf16State::default(),
+            languages: LANGUAGE_MODELS
+                .iter()
+                .flat_map(|model| {
+                    model
+                        .encodings
+                        .iter()
+                        .map(move |&encoding| LanguageScore::new(model, encoding))
+                })
+                .collect(),
+        }
+    }
+
+    fn add(&mut self, chunk: &[u8]) {
+        let head = (4 - self.head.len()).min(chunk.len());
+        self.head.extend_from_slice(&chunk[..head]);
+
+        for &byte in chunk {
+            self.histogram[byte as usize] += 1;
+            if let Some(previous) = self.previous
+                && (previous >= 0x80 || byte >= 0x80)
+            {
+                *self.pairs.entry((previous, byte)).or_insert(0) += 1;
+            }
+            self.previous = Some(byte);
+
+            self.utf8.add(byte);
+            self.utf16.add(byte);
+            for language in &mut self.languages {
+                language.add(byte);
+            }
+        }
+        self.seen += chunk.len();
+    }
+
+    fn conclude(&self) -> DetectionResult {
         // Check for BOM first (highest confidence)
-        if let Some((encoding, _bom_len)) = self.detect_bom(sample) {
+        if let Some((encoding, _bom_len)) = self.detect_bom() {
             return DetectionResult {
                 encoding,
                 confidence: 1.0,
                 bom_detected: true,
                 candidates: vec![(encoding, 1.0)],
+                source: DetectionSource::Bom,
+                declared: None,
             };
         }
 
@@ -66,35 +454,50 @@ impl EncodingDetector {
         let mut candidates = Vec::new();
 
         // UTF-8 detection
-        if let Some(confidence) = self.detect_utf8(sample) {
+        if let Some(confidence) = self.utf8.confidence() {
             candidates.push((Encoding::UTF8, confidence));
         }
 
         // UTF-16 detection
-        if let Some((encoding, confidence)) = self.detect_utf16(sample) {
+        if let Some((encoding, confidence)) = self.utf16.confidence() {
             candidates.push((encoding, confidence));
         }
 
         // ASCII detection
-        if let Some(confidence) = self.detect_ascii(sample) {
+        if let Some(confidence) = self.detect_ascii() {
             candidates.push((Encoding::ASCII, confidence));
         }
 
-        // Windows code page detection
-        candidates.extend(self.detect_windows_codepages(sample));
+        // Cyrillic, Greek, Hebrew and Arabic code pages
+        candidates.extend(
+            self.languages
+                .iter()
+                .filter_map(|language| Some((language.encoding, language.confidence()?))),
+        );
 
-        // ISO detection
-        candidates.extend(self.detect_iso_encodings(sample));
+        // Every other single-byte code page, by how much its decoding reads like text
+        let ebcdic = self.detect_ebcdic();
+        for &encoding in Encoding::all() {
+            if encoding.is_multibyte()
+                || encoding == Encoding::ASCII
+                || LANGUAGE_MODELS
+                    .iter()
+                    .any(|model| model.encodings.contains(&encoding))
+            {
+                continue;
+            }
 
-        // EBCDIC detection
-        if let Some(confidence) = self.detect_ebcdic(sample) {
-            candidates.push((Encoding::EBCDIC_037, confidence));
+            let confidence = match (encoding.category(), ebcdic) {
+                ("ebcdic", None) => continue,
+                ("ebcdic", Some(score)) => self.plausibility(encoding).map(|p| p * score),
+                _ => self.plausibility(encoding).map(|p| p * 0.6),
+            };
+            if let Some(confidence) = confidence.filter(|&c| c > 0.1) {
+                candidates.push((encoding, confidence));
+            }
         }
 
-        // DOS/OEM detection
-        candidates.extend(self.detect_dos_codepages(sample));
-
-        // Sort by confidence
+        // Sort by confidence; the sort is stable, so ties keep the order above
         candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
 
         // Return best match or ASCII as fallback
@@ -108,13 +511,21 @@ impl EncodingDetector {
             confidence,
             bom_detected: false,
             candidates,
+            source: DetectionSource::Statistics,
+            declared: None,
         }
     }
 
     /// Detect BOM (Byte Order Mark)
-    fn detect_bom(&self, data: &[u8]) -> Option<(Encoding, usize)> {
+    fn detect_bom(&self) -> Option<(Encoding, usize)> {
+        let data = self.head.as_slice();
+        // The UTF-32LE mark starts with the UTF-16LE one, so it is checked first
         if data.starts_with(&[0xEF, 0xBB, 0xBF]) {
             Some((Encoding::UTF8, 3))
+        } else if data.starts_with(&[0xFF, 0xFE, 0x00, 0x00]) {
+            Some((Encoding::UTF32LE, 4))
+        } else if data.starts_with(&[0x00, 0x00, 0xFE, 0xFF]) {
+            Some((Encoding::UTF32BE, 4))
         } else if data.starts_with(&[0xFF, 0xFE]) {
             Some((Encoding::UTF16LE, 2))
         } else if data.starts_with(&[0xFE, 0xFF]) {
@@ -124,268 +535,567 @@ impl EncodingDetector {
         }
     }
 
-    /// Detect UTF-8 encoding
-    fn detect_utf8(&self, data: &[u8]) -> Option<f64> {
-        let mut valid_sequences = 0;
-        let mut total_bytes = 0;
-        let mut i = 0;
-
-        while i < data.len() {
-            let byte = data[i];
-
-            if byte < 0x80 {
-                // ASCII character
-                i += 1;
-                total_bytes += 1;
-            } else if (byte & 0xE0) == 0xC0 {
-                // 2-byte sequence
-                if i + 1 < data.len() && (data[i + 1] & 0xC0) == 0x80 {
-                    valid_sequences += 1;
-                    total_bytes += 2;
-                    i += 2;
-                } else {
-                    return None; // Invalid UTF-8
-                }
-            } else if (byte & 0xF0) == 0xE0 {
-                // 3-byte sequence
-                if i + 2 < data.len()
-                    && (data[i + 1] & 0xC0) == 0x80
-                    && (data[i + 2] & 0xC0) == 0x80
-                {
-                    valid_sequences += 1;
-                    total_bytes += 3;
-                    i += 3;
-                } else {
-                    return None; // Invalid UTF-8
-                }
-            } else if (byte & 0xF8) == 0xF0 {
-                // 4-byte sequence
-                if i + 3 < data.len()
-                    && (data[i + 1] & 0xC0) == 0x80
-                    && (data[i + 2] & 0xC0) == 0x80
-                    && (data[i + 3] & 0xC0) == 0x80
-                {
-                    valid_sequences += 1;
-                    total_bytes += 4;
-                    i += 4;
-                } else {
-                    return None; // Invalid UTF-8
-                }
-            } else {
-                return None; // Invalid UTF-8
-            }
+    /// Detect ASCII encoding
+    fn detect_ascii(&self) -> Option<f64> {
+        if self.histogram[0x80..].iter().all(|&count| count == 0) {
+            Some(0.8) // High confidence for pure ASCII
+        } else {
+            None
         }
+    }
 
-        if total_bytes == 0 {
-            return Some(0.5); // All ASCII, could be UTF-8
-        }
+    /// Score how much the data looks like EBCDIC, before decoding it with a particular variant
+    fn detect_ebcdic(&self) -> Option<f64> {
+        // Look for characteristic EBCDIC patterns
+        let ebcdic_chars = [
+            0x40, 0xF0, 0xF1, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6, 0xF7, 0xF8, 0xF9,
+        ]; // space, 0-9
+        let ebcdic_letters = [0xC1, 0xC2, 0xC3, 0xC4, 0xC5, 0x81, 0x82, 0x83, 0x84, 0x85]; // A-E, a-e
 
-        // Higher confidence if we found multi-byte sequences
-        let multibyte_ratio = valid_sequences as f64 / total_bytes as f64;
-        Some(0.7 + multibyte_ratio * 0.3)
-    }
+        let mut score = 0.0;
+        score += self.score_characteristic_bytes(&ebcdic_chars);
+        score += self.score_characteristic_bytes(&ebcdic_letters) * 2.0; // Letters are more distinctive
 
-    /// Detect UTF-16 encoding
-    fn detect_utf16(&self, data: &[u8]) -> Option<(Encoding, f64)> {
-        if data.len() < 2 || data.len() % 2 != 0 {
+        // Text without EBCDIC spaces, or with more ASCII spaces than EBCDIC ones, is not EBCDIC
+        if self.histogram[0x40] == 0 || self.histogram[0x20] > self.histogram[0x40] {
             return None;
         }
 
-        let mut le_score = 0.0;
-        let mut be_score = 0.0;
-        let mut total_chars = 0;
-
-        // Check both endianness interpretations
-        for chunk in data.chunks_exact(2) {
-            let le_char = u16::from_le_bytes([chunk[0], chunk[1]]);
-            let be_char = u16::from_be_bytes([chunk[0], chunk[1]]);
-
-            total_chars += 1;
-
-            // Score based on likelihood of being valid text characters
-            if le_char < 0x80 || (le_char >= 0x20 && le_char < 0x7F) {
-                le_score += 1.0;
-            }
+        // EBCDIC rarely has bytes in 0x00-0x3F range for printable text
+        let low_bytes: usize = self.histogram[..0x40].iter().sum();
+        if low_bytes as f64 / self.seen as f64 > 0.3 {
+            score *= 0.5; // Reduce confidence
+        }
 
-            if be_char < 0x80 || (be_char >= 0x20 && be_char < 0x7F) {
-                be_score += 1.0;
-            }
+        if score > 0.1 {
+            Some(score.min(0.8))
+        } else {
+            None
+        }
+    }
 
-            // Look for null bytes in wrong positions
-            if chunk[0] == 0 && chunk[1] != 0 {
-                be_score += 0.5; // Likely UTF-16BE
-            } else if chunk[1] == 0 && chunk[0] != 0 {
-                le_score += 0.5; // Likely UTF-16LE
+    /// How much `encoding` decodes the data to plausible text, from 0 to 1
+    ///
+    /// Judged on the pairs of adjacent bytes that involve a byte of 0x80 or
+    /// above, since the others decode alike in most code pages: a lower-case
+    /// letter followed by a capital, a letter next to a symbol or letters of
+    /// different scripts side by side are unlikely, and bytes the code page
+    /// leaves undefined or maps to controls count heavily against it.
+    fn plausibility(&self, encoding: Encoding) -> Option<f64> {
+        let chars = tables::get_encoding_chars(encoding);
+        let (mut likely, mut unlikely) = (0, 0);
+        for (&(first, second), &count) in &self.pairs {
+            let first = CharClass::of(chars[first as usize]);
+            let second = CharClass::of(chars[second as usize]);
+            match CharClass::pair_is_likely(first, second) {
+                Some(true) => likely += count,
+                Some(false) => unlikely += count,
+                None => unlikely += 4 * count,
             }
         }
-
-        if total_chars == 0 {
+        if likely + unlikely == 0 {
             return None;
         }
 
-        le_score /= total_chars as f64;
-        be_score /= total_chars as f64;
+        let quality = likely as f64 / (likely + unlikely) as f64;
+        // A handful of bytes says little either way
+        let high: usize = self.histogram[0x80..].iter().sum();
+        let reliability = high as f64 / (high + 4) as f64;
+        Some(quality * reliability)
+    }
 
-        if le_score > be_score && le_score > 0.6 {
-            Some((Encoding::UTF16LE, le_score * 0.8))
-        } else if be_score > le_score && be_score > 0.6 {
-            Some((Encoding::UTF16BE, be_score * 0.8))
-        } else {
-            None
-        }
+    /// Score presence of characteristic bytes for an encoding
+    fn score_characteristic_bytes(&self, chars: &[u8]) -> f64 {
+        let found = chars
+            .iter()
+            .filter(|&&ch| self.histogram[ch as usize] > 0)
+            .count();
+        found as f64 / chars.len() as f64
     }
+}
 
-    /// Detect ASCII encoding
-    fn detect_ascii(&self, data: &[u8]) -> Option<f64> {
-        if data.iter().all(|&b| b < 0x80) {
-            Some(0.8) // High confidence for pure ASCII
-        } else {
-            None
+/// Rough kind of a decoded character, for judging whether neighbours fit together
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Lower,
+    Upper,
+    /// A letter of a script without case (Thai, Hebrew, Arabic, ...)
+    Caseless,
+    /// A combining vowel sign, tone mark or accent
+    Mark,
+    Digit,
+    Space,
+    /// ASCII punctuation
+    Punctuation,
+    /// Quotes, dashes and currency signs common in running text
+    Typography,
+    /// Any other symbol, e.g. box drawing or mathematical signs
+    Symbol,
+    /// Undefined in the code page, or a control character
+    Invalid,
+}
+
+impl CharClass {
+    fn of(ch: Option<char>) -> Self {
+        match ch {
+            None => CharClass::Invalid,
+            Some(ch) if ch.is_whitespace() => CharClass::Space,
+            Some(ch) if ch.is_control() => CharClass::Invalid,
+            Some(ch) if ch.is_lowercase() => CharClass::Lower,
+            Some(ch) if ch.is_uppercase() => CharClass::Upper,
+            Some(ch) if COMBINING_MARKS.iter().any(|range| range.contains(&ch)) => CharClass::Mark,
+            Some(ch) if ch.is_alphabetic() => CharClass::Caseless,
+            Some(ch) if ch.is_numeric() => CharClass::Digit,
+            Some(ch) if ch.is_ascii_punctuation() => CharClass::Punctuation,
+            Some(ch) if TYPOGRAPHY.contains(&ch) => CharClass::Typography,
+            Some(_) => CharClass::Symbol,
         }
     }
 
-    /// Detect Windows code pages
-    fn detect_windows_codepages(&self, data: &[u8]) -> Vec<(Encoding, f64)> {
-        let mut results = Vec::new();
+    /// Whether `second` is likely to follow `first` in text, or `None` if either is invalid
+    fn pair_is_likely(first: Self, second: Self) -> Option<bool> {
+        use CharClass::*;
+
+        let letter = |class| matches!(class, Lower | Upper | Caseless);
+        Some(match (first, second) {
+            (Invalid, _) | (_, Invalid) => return None,
+            (Lower, Upper) => false,
+            (_, Mark) => letter(first) || first == Mark,
+            (Mark, _) => true,
+            (Caseless, Lower | Upper) | (Lower | Upper, Caseless) => false,
+            (Symbol, other) | (other, Symbol) => !letter(other),
+            (Typography, Typography) => false,
+            _ => true,
+        })
+    }
+}
+
+/// Combining marks found in single-byte code pages: Latin accents, Hebrew
+/// points, Arabic harakat and Thai vowel signs and tone marks
+const COMBINING_MARKS: &[std::ops::RangeInclusive<char>] = &[
+    '\u{0300}'..='\u{036F}',
+    '\u{0591}'..='\u{05C7}',
+    '\u{064B}'..='\u{065F}',
+    '\u{0E31}'..='\u{0E31}',
+    '\u{0E34}'..='\u{0E3A}',
+    '\u{0E47}'..='\u{0E4E}',
+];
+
+/// Non-ASCII punctuation and signs that sit next to letters in ordinary text
+const TYPOGRAPHY: &[char] = &[
+    '‘', '’', '“', '”', '«', '»', '‹', '›', '–', '—', '…', '¡', '¿', '·', '€', '£', '¥', '¢', '°',
+    '§', '©', '®', '™',
+];
+
+/// UTF-8 structure of the bytes seen so far
+#[derive(Debug, Clone, Default)]
+struct Utf8State {
+    /// A byte broke the UTF-8 structure
+    invalid: bool,
+    /// Continuation bytes still expected for the current sequence
+    pending: u8,
+    /// Complete multi-byte sequences
+    sequences: usize,
+    /// Bytes in complete sequences
+    bytes: usize,
+}
 
-        // Look for characteristic Windows-1252 bytes
-        let windows_1252_chars = [
-            0x80, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8A, 0x99,
-        ]; // €, ‚, ƒ, „, …, †, ‡, ˆ, ‰, Š, ™
-        let cp1252_score = self.score_characteristic_bytes(data, &windows_1252_chars);
+impl Utf8State {
+    fn add(&mut self, byte: u8) {
+        if self.invalid {
+            return;
+        }
 
-        if cp1252_score > 0.0 {
-            results.push((Encoding::WINDOWS_1252, cp1252_score * 0.6));
+        if self.pending > 0 {
+            if byte & 0xC0 != 0x80 {
+                self.invalid = true;
+                return;
+            }
+            self.pending -= 1;
+            if self.pending == 0 {
+                self.sequences += 1;
+            }
+            self.bytes += 1;
+            return;
         }
 
-        // Windows-1250 (Central European) detection
-        let windows_1250_chars = [0x8A, 0x8C, 0x8D, 0x8F, 0x9A, 0x9C, 0x9D, 0x9F]; // Š, Ś, Ť, Ź, š, ś, ť, ź
-        let cp1250_score = self.score_characteristic_bytes(data, &windows_1250_chars);
+        self.pending = match byte {
+            0x00..=0x7F => 0,
+            0xC0..=0xDF => 1,
+            0xE0..=0xEF => 2,
+            0xF0..=0xF7 => 3,
+            _ => {
+                self.invalid = true;
+                return;
+            }
+        };
+        self.bytes += 1;
+    }
 
-        if cp1250_score > 0.0 {
-            results.push((Encoding::WINDOWS_1250, cp1250_score * 0.6));
+    /// Confidence that the data is UTF-8; a sequence cut off at the end is allowed
+    fn confidence(&self) -> Option<f64> {
+        if self.invalid {
+            return None;
+        }
+        if self.sequences == 0 {
+            return Some(0.5); // All ASCII, could be UTF-8
         }
 
-        results
+        // Higher confidence if we found multi-byte sequences
+        let multibyte_ratio = self.sequences as f64 / self.bytes as f64;
+        Some(0.7 + multibyte_ratio * 0.3)
     }
+}
 
-    /// Detect ISO encodings
-    fn detect_iso_encodings(&self, data: &[u8]) -> Vec<(Encoding, f64)> {
-        let mut results = Vec::new();
+/// UTF-16 scores of the bytes seen so far
+#[derive(Debug, Clone, Default)]
+struct Utf16State {
+    /// First byte of a code unit still waiting for its second
+    pending: Option<u8>,
+    le_score: f64,
+    be_score: f64,
+    /// Complete code units
+    units: usize,
+}
 
-        // ISO-8859-1 is a superset of ASCII with Latin characters
-        let has_high_latin = data.iter().any(|&b| b >= 0xA0);
+impl Utf16State {
+    fn add(&mut self, byte: u8) {
+        let Some(first) = self.pending.take() else {
+            self.pending = Some(byte);
+            return;
+        };
 
-        if has_high_latin {
-            results.push((Encoding::ISO_8859_1, 0.5));
-        }
+        let le_char = u16::from_le_bytes([first, byte]);
+        let be_char = u16::from_be_bytes([first, byte]);
+        self.units += 1;
 
-        // ISO-8859-15 (has Euro symbol at 0xA4)
-        if data.contains(&0xA4) {
-            results.push((Encoding::ISO_8859_15, 0.6));
+        // Score based on likelihood of being valid text characters
+        if le_char < 0x80 {
+            self.le_score += 1.0;
+        }
+        if be_char < 0x80 {
+            self.be_score += 1.0;
         }
 
-        results
+        // Look for null bytes in wrong positions
+        if first == 0 && byte != 0 {
+            self.be_score += 0.5; // Likely UTF-16BE
+        } else if byte == 0 && first != 0 {
+            self.le_score += 0.5; // Likely UTF-16LE
+        }
     }
 
-    /// Detect EBCDIC encoding
-    fn detect_ebcdic(&self, data: &[u8]) -> Option<f64> {
-        // Look for characteristic EBCDIC patterns
-        let ebcdic_chars = [
-            0x40, 0xF0, 0xF1, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6, 0xF7, 0xF8, 0xF9,
-        ]; // space, 0-9
-        let ebcdic_letters = [0xC1, 0xC2, 0xC3, 0xC4, 0xC5, 0x81, 0x82, 0x83, 0x84, 0x85]; // A-E, a-e
-
-        let mut score = 0.0;
-        score += self.score_characteristic_bytes(data, &ebcdic_chars);
-        score += self.score_characteristic_bytes(data, &ebcdic_letters) * 2.0; // Letters are more distinctive
-
-        // EBCDIC rarely has bytes in 0x00-0x3F range for printable text
-        let low_bytes = data.iter().filter(|&&b| b < 0x40).count();
-        if low_bytes as f64 / data.len() as f64 > 0.3 {
-            score *= 0.5; // Reduce confidence
+    /// Detect UTF-16 encoding; the data must be whole code units
+    fn confidence(&self) -> Option<(Encoding, f64)> {
+        if self.units == 0 || self.pending.is_some() {
+            return None;
         }
 
-        if score > 0.1 {
-            Some(score.min(0.8))
+        let le_score = self.le_score / self.units as f64;
+        let be_score = self.be_score / self.units as f64;
+
+        if le_score > be_score && le_score > 0.6 {
+            Some((Encoding::UTF16LE, le_score * 0.8))
+        } else if be_score > le_score && be_score > 0.6 {
+            Some((Encoding::UTF16BE, be_score * 0.8))
         } else {
             None
         }
     }
+}
 
-    /// Detect DOS code pages
-    fn detect_dos_codepages(&self, data: &[u8]) -> Vec<(Encoding, f64)> {
-        let mut results = Vec::new();
-
-        // CP437 has distinctive box-drawing characters
-        let cp437_chars = [0xB0, 0xB1, 0xB2, 0xDB, 0xC9, 0xBB, 0xC8, 0xBC]; // Various box chars
-        let cp437_score = self.score_characteristic_bytes(data, &cp437_chars);
+/// Letter statistics of one code page under a language model
+///
+/// A code page that puts the language's letters elsewhere still decodes the
+/// data to letters of the right script, but with the wrong frequencies and
+/// with capitals in the middle of words.
+#[derive(Debug, Clone)]
+struct LanguageScore {
+    model: &'static LanguageModel,
+    encoding: Encoding,
+    chars: &'static [Option<char>; 256],
+    /// Occurrences of each of the model's letters
+    counts: Vec<usize>,
+    letters: usize,
+    other_letters: usize,
+    suspicious: usize,
+    previous: char,
+}
 
-        if cp437_score > 0.0 {
-            results.push((Encoding::CP_437, cp437_score * 0.7));
+impl LanguageScore {
+    fn new(model: &'static LanguageModel, encoding: Encoding) -> Self {
+        Self {
+            model,
+            encoding,
+            chars: tables::get_encoding_chars(encoding),
+            counts: vec![0; model.letters.len()],
+            letters: 0,
+            other_letters: 0,
+            suspicious: 0,
+            previous: ' ',
         }
-
-        results
     }
 
-    /// Score presence of characteristic bytes for an encoding
-    fn score_characteristic_bytes(&self, data: &[u8], chars: &[u8]) -> f64 {
-        let mut found = 0;
-        for &ch in chars {
-            if data.contains(&ch) {
-                found += 1;
+    fn add(&mut self, byte: u8) {
+        let ch = match self.chars[byte as usize] {
+            Some(ch) if !(ch.is_control() && byte >= 0x80) => ch,
+            // Unmapped, or a C1 control: not text in this code page
+            _ => {
+                self.suspicious += 1;
+                self.previous = ' ';
+                return;
             }
+        };
+
+        let model = self.model;
+        match model
+            .letters
+            .iter()
+            .position(|&(letter, _)| letter == model.fold(ch))
+        {
+            Some(index) => {
+                self.counts[index] += 1;
+                self.letters += 1;
+                // Capitals rarely follow lower-case letters within a word
+                if ch.is_uppercase() && self.previous.is_lowercase() {
+                    self.suspicious += 1;
+                }
+            }
+            None if ch.is_alphabetic() => self.other_letters += 1,
+            None => {}
         }
+        self.previous = ch;
+    }
 
-        if found > 0 {
-            found as f64 / chars.len() as f64
-        } else {
-            0.0
+    /// Score the code page by how closely the letters it decodes match the
+    /// language's letter frequencies
+    fn confidence(&self) -> Option<f64> {
+        if self.letters == 0 {
+            return None;
         }
+
+        let letters = self.letters as f64;
+        let similarity = self.model.similarity(&self.counts, self.letters);
+        let share = letters / (letters + self.other_letters as f64);
+        let clean = 1.0 - (self.suspicious as f64 / letters).min(1.0);
+        // A handful of letters says little either way
+        let reliability = letters / (letters + 4.0);
+
+        let confidence = similarity * share * clean * reliability;
+        (confidence > 0.1).then_some(confidence.min(0.95))
     }
+}
 
-    /// Detect encoding with language hint
-    pub fn detect_with_hint(&self, data: &[u8], language_hint: &str) -> DetectionResult {
-        let mut result = self.detect(data);
+/// Letter frequencies of a language and the code pages that encode it
+#[derive(Debug)]
+struct LanguageModel {
+    /// Candidate code pages, most common first (ties go to the first)
+    encodings: &'static [Encoding],
+    /// Lower-case letters and their frequency in running text, in percent
+    letters: &'static [(char, f64)],
+}
 
-        // Adjust confidence based on language hint
-        match language_hint.to_lowercase().as_str() {
-            "english" | "en" => {
-                // Boost confidence for ASCII-compatible encodings
-                if result.encoding.is_ascii_compatible() {
-                    result.confidence = (result.confidence * 1.2).min(1.0);
-                }
-            }
-            "german" | "de" | "french" | "fr" | "spanish" | "es" => {
-                // Boost confidence for Latin encodings
-                if matches!(
-                    result.encoding,
-                    Encoding::ISO_8859_1 | Encoding::ISO_8859_15 | Encoding::WINDOWS_1252
-                ) {
-                    result.confidence = (result.confidence * 1.3).min(1.0);
-                }
-            }
-            "polish" | "pl" | "czech" | "cz" | "hungarian" | "hu" => {
-                // Boost confidence for Central European encodings
-                if matches!(
-                    result.encoding,
-                    Encoding::WINDOWS_1250 | Encoding::ISO_8859_2
-                ) {
-                    result.confidence = (result.confidence * 1.3).min(1.0);
-                }
-            }
-            "russian" | "ru" | "cyrillic" => {
-                // Would boost Cyrillic encodings if implemented
-                if matches!(
-                    result.encoding,
-                    Encoding::WINDOWS_1251 | Encoding::ISO_8859_5
-                ) {
-                    result.confidence = (result.confidence * 1.3).min(1.0);
-                }
-            }
-            _ => {} // No adjustment for unknown languages
-        }
+impl LanguageModel {
+    /// How typical of the language the letter counts are, from 0 (no more than
+    /// letters picked at random) to 1 (as typical as running text)
+    ///
+    /// This is the average log-likelihood ratio of the letters against a
+    /// uniform choice, scaled between its expected values for random letters
+    /// and for text in the language.
+    fn similarity(&self, counts: &[usize], letters: usize) -> f64 {
+        let total: f64 = self.letters.iter().map(|&(_, frequency)| frequency).sum();
+        let uniform = 1.0 / self.letters.len() as f64;
+        let ratio = |frequency: f64| (frequency / total / uniform).ln();
 
-        result
+        let observed = counts
+            .iter()
+            .zip(self.letters)
+            .map(|(&count, &(_, frequency))| count as f64 * ratio(frequency))
+            .sum::<f64>()
+            / letters as f64;
+        let typical: f64 = self
+            .letters
+            .iter()
+            .map(|&(_, frequency)| frequency / total * ratio(frequency))
+            .sum();
+        let random: f64 = self
+            .letters
+            .iter()
+            .map(|&(_, frequency)| uniform * ratio(frequency))
+            .sum();
+
+        ((observed - random) / (typical - random)).clamp(0.0, 1.0)
+    }
+
+    /// Map a letter to its entry in `letters`: lower case, without Greek accents
+    fn fold(&self, ch: char) -> char {
+        match ch.to_lowercase().next().unwrap_or(ch) {
+            'ά' => 'α',
+            'έ' => 'ε',
+            'ή' => 'η',
+            'ί' | 'ϊ' | 'ΐ' => 'ι',
+            'ό' => 'ο',
+            'ύ' | 'ϋ' | 'ΰ' => 'υ',
+            'ώ' => 'ω',
+            'ς' => 'σ',
+            'ё' => 'е',
+            lower => lower,
+        }
     }
 }
+
+const LANGUAGE_MODELS: &[LanguageModel] = &[RUSSIAN, GREEK, HEBREW, ARABIC];
+
+const RUSSIAN: LanguageModel = LanguageModel {
+    encodings: &[
+        Encoding::WINDOWS_1251,
+        Encoding::KOI8_R,
+        Encoding::CP_866,
+        Encoding::ISO_8859_5,
+        Encoding::MAC_CYRILLIC,
+        Encoding::CP_855,
+    ],
+    letters: &[
+        ('о', 10.97),
+        ('е', 8.49),
+        ('а', 8.01),
+        ('и', 7.35),
+        ('н', 6.70),
+        ('т', 6.26),
+        ('с', 5.47),
+        ('р', 4.73),
+        ('в', 4.54),
+        ('л', 4.40),
+        ('к', 3.49),
+        ('м', 3.21),
+        ('д', 2.98),
+        ('п', 2.81),
+        ('у', 2.62),
+        ('я', 2.01),
+        ('ы', 1.90),
+        ('ь', 1.74),
+        ('г', 1.70),
+        ('з', 1.65),
+        ('б', 1.59),
+        ('ч', 1.44),
+        ('й', 1.21),
+        ('х', 0.97),
+        ('ж', 0.94),
+        ('ш', 0.73),
+        ('ю', 0.64),
+        ('ц', 0.48),
+        ('щ', 0.36),
+        ('э', 0.32),
+        ('ф', 0.26),
+        ('ъ', 0.04),
+    ],
+};
+
+const GREEK: LanguageModel = LanguageModel {
+    encodings: &[Encoding::WINDOWS_1253, Encoding::ISO_8859_7],
+    letters: &[
+        ('α', 11.7),
+        ('ο', 9.9),
+        ('ι', 9.0),
+        ('ε', 8.0),
+        ('τ', 8.0),
+        ('σ', 7.6),
+        ('ν', 6.8),
+        ('η', 5.1),
+        ('υ', 4.5),
+        ('ρ', 4.4),
+        ('π', 4.0),
+        ('κ', 4.0),
+        ('μ', 3.4),
+        ('λ', 2.8),
+        ('ω', 2.0),
+        ('δ', 1.8),
+        ('γ', 1.8),
+        ('θ', 1.3),
+        ('χ', 1.2),
+        ('φ', 0.8),
+        ('β', 0.7),
+        ('ξ', 0.5),
+        ('ζ', 0.4),
+        ('ψ', 0.2),
+    ],
+};
+
+const HEBREW: LanguageModel = LanguageModel {
+    encodings: &[
+        Encoding::WINDOWS_1255,
+        Encoding::ISO_8859_8,
+        Encoding::CP_862,
+    ],
+    letters: &[
+        ('י', 11.1),
+        ('ו', 10.5),
+        ('ה', 9.3),
+        ('ל', 7.4),
+        ('א', 6.3),
+        ('ר', 5.7),
+        ('ת', 5.5),
+        ('ב', 4.8),
+        ('מ', 4.6),
+        ('ש', 4.3),
+        ('נ', 3.0),
+        ('ם', 2.8),
+        ('ע', 2.8),
+        ('ד', 2.6),
+        ('כ', 2.4),
+        ('ח', 2.2),
+        ('ק', 2.0),
+        ('פ', 1.6),
+        ('ן', 1.3),
+        ('ט', 1.3),
+        ('ג', 1.2),
+        ('ס', 1.1),
+        ('צ', 1.0),
+        ('ז', 0.8),
+        ('ך', 0.5),
+        ('ף', 0.3),
+        ('ץ', 0.2),
+    ],
+};
+
+const ARABIC: LanguageModel = LanguageModel {
+    encodings: &[Encoding::WINDOWS_1256, Encoding::ISO_8859_6],
+    letters: &[
+        ('ا', 12.4),
+        ('ل', 10.0),
+        ('ي', 6.7),
+        ('م', 6.0),
+        ('و', 5.8),
+        ('ن', 5.5),
+        ('ر', 4.4),
+        ('ه', 4.0),
+        ('ت', 4.0),
+        ('ب', 3.8),
+        ('ع', 3.5),
+        ('د', 3.0),
+        ('ة', 3.0),
+        ('ف', 2.8),
+        ('س', 2.6),
+        ('أ', 2.5),
+        ('ق', 2.4),
+        ('ك', 2.3),
+        ('ح', 2.0),
+        ('ج', 1.4),
+        ('إ', 1.2),
+        ('ش', 1.2),
+        ('ط', 1.1),
+        ('ص', 1.1),
+        ('ى', 1.1),
+        ('خ', 0.9),
+        ('ذ', 0.9),
+        ('ز', 0.6),
+        ('ض', 0.6),
+        ('ث', 0.6),
+        ('ئ', 0.6),
+        ('غ', 0.5),
+        ('ء', 0.5),
+        ('ظ', 0.3),
+        ('ؤ', 0.2),
+        ('آ', 0.2),
+    ],
+};
@@ -0,0 +1,338 @@
+//! C API for calling fast-encode from C, COBOL and other non-Rust code
+//!
+//! Enabled by the `capi` feature. Build a shared or static library with
+//!
+//! ```text
+//! cargo rustc --release --lib --no-default-features --features capi --crate-type cdylib
+//! cargo rustc --release --lib --no-default-features --features capi --crate-type staticlib
+//! ```
+//!
+//! and declare the functions with `include/fast_encode.h`. A translator is an
+//! opaque handle created by [`fe_translator_new`] and released with
+//! [`fe_translator_free`]; it can be shared between threads. Every buffer
+//! belongs to the caller: conversions write into the memory they are given and
+//! report the size they need when it is too small.
+//!
+//! Functions return [`FE_OK`] or a negative `FE_ERR_*` status, and
+//! [`fe_error_message`] describes the last failure on the calling thread.
+//! The status values and function signatures are part of the stable ABI; new
+//! statuses and functions may be added, existing ones do not change.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use crate::detection::EncodingDetector;
+use crate::{Encoding, Error, ErrorPolicy, Translator};
+
+/// The call succeeded
+pub const FE_OK: i32 = 0;
+/// A required pointer argument was NULL
+pub const FE_ERR_NULL_POINTER: i32 = -1;
+/// An encoding label was not recognised
+pub const FE_ERR_UNKNOWN_ENCODING: i32 = -2;
+/// The library cannot convert between the two encodings
+pub const FE_ERR_UNSUPPORTED: i32 = -3;
+/// The input holds a byte or character the conversion cannot map
+pub const FE_ERR_UNMAPPABLE: i32 = -4;
+/// The input is not valid in the source encoding
+pub const FE_ERR_INVALID_INPUT: i32 = -5;
+/// The output buffer is too small; the needed size was written to the length argument
+pub const FE_ERR_BUFFER_TOO_SMALL: i32 = -6;
+/// An argument was out of range, e.g. an unknown `FE_ERRORS_*` value
+pub const FE_ERR_INVALID_ARGUMENT: i32 = -7;
+/// The library failed internally; the handle should not be used again
+pub const FE_ERR_INTERNAL: i32 = -8;
+
+/// Fail on the first unmappable or invalid input
+pub const FE_ERRORS_STRICT: u32 = 0;
+/// Write U+FFFD, or `?` where the target lacks it, for unmappable input
+pub const FE_ERRORS_REPLACE: u32 = 1;
+/// Drop unmappable input
+pub const FE_ERRORS_IGNORE: u32 = 2;
+
+/// Opaque translator handle
+pub struct FeTranslator {
+    translator: Translator,
+}
+
+thread_local! {
+    /// Message for the last failed call on this thread
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Describe the last failed call on the calling thread
+///
+/// Returns a NUL-terminated string that stays valid until the next call on
+/// the same thread, or NULL if no call has failed yet.
+#[unsafe(no_mangle)]
+pub extern "C" fn fe_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Create a translator between two encodings named by label
+///
+/// Labels are the ones [`Encoding::for_label`] accepts: `"IBM037"`, `"utf-8"`,
+/// `"cp1252"`, `"1047"`, ... `errors` is one of the `FE_ERRORS_*` values. On
+/// success `*out` receives a handle to release with [`fe_translator_free`].
+///
+/// # Safety
+///
+/// `from` and `to` must be NUL-terminated strings and `out` must be valid for
+/// writing a pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fe_translator_new(
+    from: *const c_char,
+    to: *const c_char,
+    errors: u32,
+    out: *mut *mut FeTranslator,
+) -> i32 {
+    guard(|| {
+        if out.is_null() {
+            return fail(FE_ERR_NULL_POINTER, "out is NULL");
+        }
+        let (from, to) = match unsafe { (encoding(from), encoding(to)) } {
+            (Ok(from), Ok(to)) => (from, to),
+            (Err(status), _) | (_, Err(status)) => return status,
+        };
+        let policy = match errors {
+            FE_ERRORS_STRICT => ErrorPolicy::Strict,
+            FE_ERRORS_REPLACE => ErrorPolicy::Replace,
+            FE_ERRORS_IGNORE => ErrorPolicy::Ignore,
+            other => {
+                return fail(
+                    FE_ERR_INVALID_ARGUMENT,
+                    format!("unknown error policy {}", other),
+                );
+            }
+        };
+
+        match Translator::builder(from, to).policy(policy).build() {
+            Ok(translator) => {
+                let handle = Box::new(FeTranslator { translator });
+                unsafe { *out = Box::into_raw(handle) };
+                FE_OK
+            }
+            Err(error) => report(&error),
+        }
+    })
+}
+
+/// Release a translator created by [`fe_translator_new`]
+///
+/// # Safety
+///
+/// `translator` must be NULL or a handle from [`fe_translator_new`] that has
+/// not been freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fe_translator_free(translator: *mut FeTranslator) {
+    if !translator.is_null() {
+        drop(unsafe { Box::from_raw(translator) });
+    }
+}
+
+/// Size of an output buffer that can always hold the conversion of `input_len` bytes
+///
+/// Returns 0 if `translator` is NULL.
+///
+/// # Safety
+///
+/// `translator` must be NULL or a live handle from [`fe_translator_new`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fe_max_output_len(
+    translator: *const FeTranslator,
+    input_len: usize,
+) -> usize {
+    match unsafe { translator.as_ref() } {
+        Some(handle) => handle.translator.max_output_len(input_len),
+        None => 0,
+    }
+}
+
+/// Convert `input` into `output`, writing the converted length to `*output_len`
+///
+/// If `output` is too small, returns [`FE_ERR_BUFFER_TOO_SMALL`] with the
+/// needed size in `*output_len`; passing a NULL `output` with `output_cap` 0
+/// asks for the size without converting into anything.
+///
+/// # Safety
+///
+/// `translator` must be a live handle, `input` must be readable for
+/// `input_len` bytes, `output` writable for `output_cap` bytes and
+/// `output_len` valid for writing. Pointers may be NULL when their length is 0.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fe_convert(
+    translator: *const FeTranslator,
+    input: *const u8,
+    input_len: usize,
+    output: *mut u8,
+    output_cap: usize,
+    output_len: *mut usize,
+) -> i32 {
+    if !output_len.is_null() {
+        unsafe { *output_len = 0 };
+    }
+    unsafe { fe_convert_into(translator, input, input_len, output, output_cap, output_len) }
+}
+
+/// Convert `input`, appending it after the `*output_len` bytes already in `output`
+///
+/// On success `*output_len` is the new used length. If the rest of `output`
+/// is too small, returns [`FE_ERR_BUFFER_TOO_SMALL`] with the total length
+/// needed in `*output_len`; on any failure the bytes already in `output` are
+/// kept.
+///
+/// # Safety
+///
+/// As for [`fe_convert`]; `*output_len` must not exceed `output_cap` on entry.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fe_convert_into(
+    translator: *const FeTranslator,
+    input: *const u8,
+    input_len: usize,
+    output: *mut u8,
+    output_cap: usize,
+    output_len: *mut usize,
+) -> i32 {
+    guard(|| {
+        let Some(handle) = (unsafe { translator.as_ref() }) else {
+            return fail(FE_ERR_NULL_POINTER, "translator is NULL");
+        };
+        if output_len.is_null() {
+            return fail(FE_ERR_NULL_POINTER, "output_len is NULL");
+        }
+        let Some(input) = (unsafe { bytes(input, input_len) }) else {
+            return fail(FE_ERR_NULL_POINTER, "input is NULL");
+        };
+        let Some(output) = (unsafe { bytes_mut(output, output_cap) }) else {
+            return fail(FE_ERR_NULL_POINTER, "output is NULL");
+        };
+
+        let used = unsafe { *output_len };
+        let Some(free) = output.get_mut(used..) else {
+            return fail(
+                FE_ERR_INVALID_ARGUMENT,
+                format!("output_len {} exceeds output_cap {}", used, output_cap),
+            );
+        };
+        match handle.translator.convert_to_slice(input, free) {
+            Ok(written) => {
+                unsafe { *output_len = used + written };
+                FE_OK
+            }
+            Err(error) => {
+                if let Error::OutputTooSmall { needed, .. } = error {
+                    unsafe { *output_len = used + needed };
+                }
+                report(&error)
+            }
+        }
+    })
+}
+
+/// Detect the encoding of `data`, writing its canonical name to `name`
+///
+/// The name is NUL-terminated and at most 32 bytes with the terminator.
+/// `confidence` may be NULL; otherwise it receives a score from 0 to 1.
+///
+/// # Safety
+///
+/// `data` must be readable for `data_len` bytes, `name` writable for
+/// `name_cap` bytes and `confidence` NULL or valid for writing.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fe_detect(
+    data: *const u8,
+    data_len: usize,
+    name: *mut c_char,
+    name_cap: usize,
+    confidence: *mut f64,
+) -> i32 {
+    guard(|| {
+        let Some(data) = (unsafe { bytes(data, data_len) }) else {
+            return fail(FE_ERR_NULL_POINTER, "data is NULL");
+        };
+        if name.is_null() {
+            return fail(FE_ERR_NULL_POINTER, "name is NULL");
+        }
+
+        let result = EncodingDetector::new().detect(data);
+        let detected = result.encoding.name().as_bytes();
+        if detected.len() >= name_cap {
+            return fail(
+                FE_ERR_BUFFER_TOO_SMALL,
+                format!("name needs {} bytes", detected.len() + 1),
+            );
+        }
+        unsafe {
+            ptr::copy_nonoverlapping(detected.as_ptr(), name.cast::<u8>(), detected.len());
+            *name.add(detected.len()) = 0;
+            if !confidence.is_null() {
+                *confidence = result.confidence;
+            }
+        }
+        FE_OK
+    })
+}
+
+/// Run `call`, turning a panic into [`FE_ERR_INTERNAL`] instead of unwinding into C
+fn guard(call: impl FnOnce() -> i32) -> i32 {
+    panic::catch_unwind(AssertUnwindSafe(call))
+        .unwrap_or_else(|_| fail(FE_ERR_INTERNAL, "internal error"))
+}
+
+/// Record `message` for [`fe_error_message`] and return `status`
+fn fail(status: i32, message: impl Into<String>) -> i32 {
+    let message = message.into().replace('\0', "\\0");
+    let message = CString::new(message).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    status
+}
+
+/// Record a library error and return its status
+fn report(error: &Error) -> i32 {
+    let status = match error {
+        Error::UnmappableSource { .. } | Error::UnmappableTarget { .. } => FE_ERR_UNMAPPABLE,
+        Error::InvalidInput(_) => FE_ERR_INVALID_INPUT,
+        Error::UnsupportedConversion { .. } => FE_ERR_UNSUPPORTED,
+        Error::OutputTooSmall { .. } => FE_ERR_BUFFER_TOO_SMALL,
+    };
+    fail(status, error.to_string())
+}
+
+/// Look up the encoding named by a C string
+unsafe fn encoding(label: *const c_char) -> Result<Encoding, i32> {
+    if label.is_null() {
+        return Err(fail(FE_ERR_NULL_POINTER, "encoding label is NULL"));
+    }
+    let label = unsafe { CStr::from_ptr(label) }.to_string_lossy();
+    Encoding::for_label(&label).ok_or_else(|| {
+        fail(
+            FE_ERR_UNKNOWN_ENCODING,
+            format!("unknown encoding: {}", label),
+        )
+    })
+}
+
+/// Borrow `len` bytes at `data`; NULL is only accepted for an empty slice
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    match (data.is_null(), len) {
+        (_, 0) => Some(&[]),
+        (true, _) => None,
+        (false, _) => Some(unsafe { slice::from_raw_parts(data, len) }),
+    }
+}
+
+/// Mutably borrow `len` bytes at `data`; NULL is only accepted for an empty slice
+unsafe fn bytes_mut<'a>(data: *mut u8, len: usize) -> Option<&'a mut [u8]> {
+    match (data.is_null(), len) {
+        (_, 0) => Some(&mut []),
+        (true, _) => None,
+        (false, _) => Some(unsafe { slice::from_raw_parts_mut(data, len) }),
+    }
+}
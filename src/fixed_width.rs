@@ -0,0 +1,844 @@
+//! Serde deserialization of fixed-width records
+//!
+//! A [`Layout`] describes where each field sits in a record and how it is
+//! stored, either built in code or read from a COBOL copybook. [`from_bytes`]
+//! then maps one record onto any `Deserialize` type, decoding text and zoned
+//! decimal fields through a [`Translator`] whose target is UTF-8. Packed
+//! decimal (`COMP-3`) and binary (`COMP`) fields are read directly.
+//!
+//! ```
+//! use fast_encode::{fixed_width::{from_bytes, Layout}, Encoding, Translator};
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Customer {
+//!     cust_id: u32,
+//!     cust_name: String,
+//!     balance: f64,
+//! }
+//!
+//! let layout = Layout::from_copybook(
+//!     "01 CUSTOMER.
+//!         05 CUST-ID    PIC 9(4).
+//!         05 CUST-NAME  PIC X(6).
+//!         05 BALANCE    PIC S9(3)V99 COMP-3.",
+//! ).unwrap();
+//! let translator = Translator::new(Encoding::EBCDIC_037, Encoding::UTF8).unwrap();
+//!
+//! let record = [
+//!     0xF0, 0xF0, 0xF4, 0xF2, // 0042
+//!     0xC1, 0xD3, 0xC9, 0xC3, 0xC5, 0x40, // "ALICE "
+//!     0x01, 0x23, 0x4D, // -12.34
+//! ];
+//! let customer: Customer = from_bytes(&record, &layout, &translator).unwrap();
+//! assert_eq!(customer.cust_id, 42);
+//! assert_eq!(customer.cust_name, "ALICE");
+//! assert_eq!(customer.balance, -12.34);
+//! ```
+//!
+//! Field names are matched in lower case with hyphens turned into underscores,
+//! so `CUST-NAME` fills `cust_name`. Text has trailing spaces removed, and a
+//! field that is blank (all spaces, or all zero bytes) deserializes as `None`
+//! into an `Option`.
+
+use std::fmt;
+
+use serde::de::{
+    self, DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+};
+
+use crate::numeric::{Decimal, PackedDecimal, ZonedDecimal};
+use crate::{Encoding, Error, Result, Translator};
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::InvalidInput(msg.to_string())
+    }
+}
+
+/// How a field's bytes are stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    /// Characters in the record's encoding (`PIC X`)
+    Text,
+    /// Zoned decimal: one digit per character, sign overpunched on the last (`PIC 9` / `PIC S9`)
+    Zoned {
+        /// Whether the last digit may carry a negative sign
+        signed: bool,
+        /// Implied decimal places (`V`)
+        scale: u32,
+    },
+    /// Packed decimal, two digits per byte with a trailing sign nibble (`COMP-3`)
+    Packed {
+        /// Implied decimal places (`V`)
+        scale: u32,
+    },
+    /// Big-endian binary integer (`COMP` / `BINARY`)
+    Binary {
+        /// Whether the value is two's complement
+        signed: bool,
+        /// Implied decimal places (`V`)
+        scale: u32,
+    },
+    /// Bytes that are skipped (`FILLER`)
+    Filler,
+}
+
+/// One field of a [`Layout`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    /// Name the field is deserialized as
+    pub name: String,
+    /// Byte offset within the record
+    pub offset: usize,
+    /// Length in bytes
+    pub length: usize,
+    /// Storage format
+    pub kind: FieldKind,
+}
+
+/// Positions and formats of the fields in a fixed-width record
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Layout {
+    fields: Vec<Field>,
+    length: usize,
+}
+
+impl Layout {
+    /// Create an empty layout; fields are appended in record order
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a field of `length` bytes
+    pub fn field(mut self, name: &str, length: usize, kind: FieldKind) -> Self {
+        self.fields.push(Field {
+            name: name.to_string(),
+            offset: self.length,
+            length,
+            kind,
+        });
+        self.length += length;
+        self
+    }
+
+    /// Append a text field
+    pub fn text(self, name: &str, length: usize) -> Self {
+        self.field(name, length, FieldKind::Text)
+    }
+
+    /// Skip `length` bytes
+    pub fn filler(self, length: usize) -> Self {
+        self.field("", length, FieldKind::Filler)
+    }
+
+    /// Build a layout from the elementary items of a COBOL copybook
+    ///
+    /// Supports `PIC X`/`A`/`9`/`S9` with `V` and repeat counts, and `DISPLAY`,
+    /// `COMP`/`BINARY`/`COMP-4`/`COMP-5` and `COMP-3`/`PACKED-DECIMAL` usage.
+    /// Group items only contribute their children; level 66 and 88 entries
+    /// and `VALUE` clauses are ignored. Sequence numbers in columns 1-6 and
+    /// comment lines are skipped. `OCCURS`, `REDEFINES`, `SIGN SEPARATE` and
+    /// `P` scaling are rejected.
+    pub fn from_copybook(source: &str) -> Result<Self> {
+        let mut layout = Layout::new();
+        for entry in copybook_entries(source) {
+            let tokens = tokenize(&entry);
+            if let Some(field) = parse_entry(&tokens)? {
+                let (name, length, kind) = field;
+                layout = layout.field(&name, length, kind);
+            }
+        }
+
+        if layout.fields.is_empty() {
+            return Err(Error::InvalidInput(
+                "copybook has no elementary items".to_string(),
+            ));
+        }
+        Ok(layout)
+    }
+
+    /// Total record length in bytes
+    pub fn record_length(&self) -> usize {
+        self.length
+    }
+
+    /// The fields, in record order
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+}
+
+/// Split copybook source into period-terminated entries
+fn copybook_entries(source: &str) -> Vec<String> {
+    let mut text = String::new();
+    for line in source.lines() {
+        let mut line = line;
+        // Columns 1-6 hold sequence numbers and column 7 the indicator
+        if line.len() > 6
+            && line.is_char_boundary(6)
+            && line[..6].chars().all(|c| c.is_ascii_digit() || c == ' ')
+        {
+            if matches!(line.as_bytes()[6], b'*' | b'/') {
+                continue;
+            }
+            if line[..6].chars().any(|c| c.is_ascii_digit()) {
+                line = &line[7.min(line.len())..];
+            }
+        }
+        if line.trim_start().starts_with('*') {
+            continue;
+        }
+        text.push_str(line);
+        text.push(' ');
+    }
+
+    let mut entries = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match quote {
+            Some(q) if ch == q => quote = None,
+            Some(_) => {}
+            None if ch == '\'' || ch == '"' => quote = Some(ch),
+            None if ch == '.' && chars.peek().is_none_or(|c| c.is_whitespace()) => {
+                entries.push(std::mem::take(&mut current));
+                continue;
+            }
+            None => {}
+        }
+        current.push(ch);
+    }
+    if !current.trim().is_empty() {
+        entries.push(current);
+    }
+    entries
+}
+
+/// Split an entry into words, keeping quoted literals whole
+fn tokenize(entry: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    for ch in entry.chars() {
+        match quote {
+            Some(q) => {
+                current.push(ch);
+                if ch == q {
+                    quote = None;
+                }
+            }
+            None if ch.is_whitespace() => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            None => {
+                if ch == '\'' || ch == '"' {
+                    quote = Some(ch);
+                }
+                current.push(ch);
+            }
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+/// Parse one entry, returning the field for an elementary item
+fn parse_entry(tokens: &[String]) -> Result<Option<(String, usize, FieldKind)>> {
+    let Some(level) = tokens.first() else {
+        return Ok(None);
+    };
+    let level: u32 = level
+        .parse()
+        .map_err(|_| Error::InvalidInput(format!("expected a level number, found {}", level)))?;
+    if level == 66 || level == 88 {
+        return Ok(None);
+    }
+
+    let name = match tokens.get(1) {
+        Some(name) if !is_clause(name) => name.as_str(),
+        _ => "FILLER",
+    };
+    let mut picture = None;
+    let mut usage = "DISPLAY".to_string();
+
+    let mut words = tokens.iter().skip(1).map(|t| t.to_ascii_uppercase());
+    while let Some(word) = words.next() {
+        match word.as_str() {
+            "PIC" | "PICTURE" => {
+                let mut value = words.next();
+                if value.as_deref() == Some("IS") {
+                    value = words.next();
+                }
+                picture = value;
+            }
+            "USAGE" => {
+                let mut value = words.next();
+                if value.as_deref() == Some("IS") {
+                    value = words.next();
+                }
+                usage = value.unwrap_or_default();
+            }
+            "DISPLAY" | "COMP" | "COMPUTATIONAL" | "BINARY" | "COMP-4" | "COMPUTATIONAL-4"
+            | "COMP-5" | "COMPUTATIONAL-5" | "COMP-3" | "COMPUTATIONAL-3" | "PACKED-DECIMAL" => {
+                usage = word;
+            }
+            "OCCURS" | "REDEFINES" | "SEPARATE" => {
+                return Err(Error::InvalidInput(format!(
+                    "{}: {} is not supported",
+                    name, word
+                )));
+            }
+            "VALUE" | "VALUES" => break,
+            _ => {}
+        }
+    }
+
+    let Some(picture) = picture else {
+        // A group item; its children describe the bytes
+        return Ok(None);
+    };
+    let picture = parse_picture(&picture)
+        .map_err(|message| Error::InvalidInput(format!("{}: {}", name, message)))?;
+
+    let field_name = if name.eq_ignore_ascii_case("FILLER") {
+        String::new()
+    } else {
+        name.to_ascii_lowercase().replace('-', "_")
+    };
+
+    let (length, kind) = match usage.as_str() {
+        "DISPLAY" if !picture.numeric => (picture.characters, FieldKind::Text),
+        "DISPLAY" => (
+            picture.characters,
+            FieldKind::Zoned {
+                signed: picture.signed,
+                scale: picture.scale,
+            },
+        ),
+        "COMP-3" | "COMPUTATIONAL-3" | "PACKED-DECIMAL" if picture.numeric => (
+            picture.digits / 2 + 1,
+            FieldKind::Packed {
+                scale: picture.scale,
+            },
+        ),
+        _ if picture.numeric => {
+            let length = match picture.digits {
+                0..=4 => 2,
+                5..=9 => 4,
+                _ => 8,
+            };
+            (
+                length,
+                FieldKind::Binary {
+                    signed: picture.signed,
+                    scale: picture.scale,
+                },
+            )
+        }
+        other => {
+            return Err(Error::InvalidInput(format!(
+                "{}: {} usage needs a numeric picture",
+                name, other
+            )));
+        }
+    };
+
+    let kind = if field_name.is_empty() {
+        FieldKind::Filler
+    } else {
+        kind
+    };
+    Ok(Some((field_name, length, kind)))
+}
+
+fn is_clause(word: &str) -> bool {
+    matches!(
+        word.to_ascii_uppercase().as_str(),
+        "PIC" | "PICTURE" | "USAGE" | "VALUE" | "COMP" | "COMP-3" | "BINARY" | "DISPLAY"
+    )
+}
+
+/// What a `PIC` string describes
+struct Picture {
+    numeric: bool,
+    signed: bool,
+    /// Total digit positions
+    digits: usize,
+    /// Digits after the implied decimal point
+    scale: u32,
+    /// Display length in characters
+    characters: usize,
+}
+
+fn parse_picture(picture: &str) -> std::result::Result<Picture, String> {
+    let mut result = Picture {
+        numeric: true,
+        signed: false,
+        digits: 0,
+        scale: 0,
+        characters: 0,
+    };
+    let mut after_point = false;
+    let mut chars = picture.chars().peekable();
+
+    while let Some(symbol) = chars.next() {
+        let mut count = 1;
+        if chars.peek() == Some(&'(') {
+            chars.next();
+            let digits: String = chars.by_ref().take_while(|&c| c != ')').collect();
+            count = digits
+                .parse()
+                .map_err(|_| format!("invalid repeat count in PIC {}", picture))?;
+        }
+
+        match symbol.to_ascii_uppercase() {
+            'X' | 'A' => {
+                result.numeric = false;
+                result.characters += count;
+            }
+            '9' => {
+                result.digits += count;
+                result.characters += count;
+                if after_point {
+                    result.scale += count as u32;
+                }
+            }
+            'S' => result.signed = true,
+            'V' => after_point = true,
+            other => return Err(format!("unsupported PIC symbol {}", other)),
+        }
+    }
+
+    if result.characters == 0 {
+        return Err(format!("empty PIC {}", picture));
+    }
+    if result.numeric && result.digits > 18 {
+        return Err(format!("PIC {} has more than 18 digits", picture));
+    }
+    Ok(result)
+}
+
+/// Deserialize one record
+///
+/// The record must be at least [`Layout::record_length`] bytes; anything
+/// after that is ignored. The translator must target UTF-8.
+pub fn from_bytes<'de, T: de::Deserialize<'de>>(
+    record: &[u8],
+    layout: &Layout,
+    translator: &Translator,
+) -> Result<T> {
+    T::deserialize(RecordDeserializer::new(record, layout, translator)?)
+}
+
+/// Deserialize consecutive records from a buffer holding whole records
+pub fn from_records<'a, T: de::DeserializeOwned + 'a>(
+    data: &'a [u8],
+    layout: &'a Layout,
+    translator: &'a Translator,
+) -> impl Iterator<Item = Result<T>> + 'a {
+    let length = layout.record_length().max(1);
+    data.chunks(length).enumerate().map(move |(index, record)| {
+        if record.len() < length {
+            return Err(Error::InvalidInput(format!(
+                "record {} is truncated: {} of {} bytes",
+                index,
+                record.len(),
+                length
+            )));
+        }
+        from_bytes(record, layout, translator).map_err(|e| e.offset_by(index * length))
+    })
+}
+
+/// Deserializer for a whole record, presented as a map of its fields
+pub struct RecordDeserializer<'a> {
+    record: &'a [u8],
+    layout: &'a Layout,
+    translator: &'a Translator,
+}
+
+impl<'a> RecordDeserializer<'a> {
+    /// Prepare to deserialize `record` according to `layout`
+    pub fn new(record: &'a [u8], layout: &'a Layout, translator: &'a Translator) -> Result<Self> {
+        if translator.to_encoding() != Encoding::UTF8 {
+            return Err(Error::UnsupportedConversion {
+                from: translator.from_encoding().name(),
+                to: translator.to_encoding().name(),
+            });
+        }
+        if record.len() < layout.record_length() {
+            return Err(Error::InvalidInput(format!(
+                "record is {} bytes, layout needs {}",
+                record.len(),
+                layout.record_length()
+            )));
+        }
+        Ok(Self {
+            record,
+            layout,
+            translator,
+        })
+    }
+
+    fn fields(&self) -> Fields<'a> {
+        Fields {
+            record: self.record,
+            translator: self.translator,
+            fields: self.layout.fields.iter(),
+            current: None,
+        }
+    }
+}
+
+impl<'de> Deserializer<'de> for RecordDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_map(self.fields())
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(self.fields())
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+/// Walks the non-filler fields of a record
+struct Fields<'a> {
+    record: &'a [u8],
+    translator: &'a Translator,
+    fields: std::slice::Iter<'a, Field>,
+    current: Option<&'a Field>,
+}
+
+impl<'a> Fields<'a> {
+    fn next_field(&mut self) -> Option<&'a Field> {
+        self.fields.by_ref().find(|f| f.kind != FieldKind::Filler)
+    }
+
+    fn value<'de, S: DeserializeSeed<'de>>(&self, field: &Field, seed: S) -> Result<S::Value> {
+        let bytes = &self.record[field.offset..field.offset + field.length];
+        seed.deserialize(FieldDeserializer {
+            field,
+            bytes,
+            translator: self.translator,
+        })
+        .map_err(|e| match e {
+            Error::InvalidInput(message) => {
+                Error::InvalidInput(format!("{}: {}", field.name, message))
+            }
+            other => other.offset_by(field.offset),
+        })
+    }
+}
+
+impl<'de> MapAccess<'de> for Fields<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        self.current = self.next_field();
+        match self.current {
+            Some(field) => seed
+                .deserialize(field.name.as_str().into_deserializer())
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let field = self
+            .current
+            .take()
+            .ok_or_else(|| Error::InvalidInput("value requested before key".to_string()))?;
+        self.value(field, seed)
+    }
+}
+
+impl<'de> SeqAccess<'de> for Fields<'_> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.next_field() {
+            Some(field) => self.value(field, seed).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// A decoded field value
+enum Value {
+    Text(String),
+    Number(Decimal),
+    Blank,
+}
+
+impl Value {
+    fn describe(&self) -> String {
+        match self {
+            Value::Text(text) => format!("\"{}\"", text),
+            Value::Number(number) => number.to_string(),
+            Value::Blank => "a blank field".to_string(),
+        }
+    }
+}
+
+/// Deserializer for a single field
+struct FieldDeserializer<'a> {
+    field: &'a Field,
+    bytes: &'a [u8],
+    translator: &'a Translator,
+}
+
+impl FieldDeserializer<'_> {
+    fn text(&self) -> Result<String> {
+        let converted = self.translator.convert(self.bytes)?;
+        String::from_utf8(converted).map_err(|e| Error::InvalidInput(e.to_string()))
+    }
+
+    fn decode(&self) -> Result<Value> {
+        if self.bytes.iter().all(|&b| b == 0) {
+            return Ok(Value::Blank);
+        }
+
+        match self.field.kind {
+            FieldKind::Text | FieldKind::Filler => {
+                let text = self.text()?;
+                let trimmed = text.trim_end_matches(' ');
+                Ok(if trimmed.is_empty() {
+                    Value::Blank
+                } else {
+                    Value::Text(trimmed.to_string())
+                })
+            }
+            FieldKind::Zoned { signed, scale } => {
+                let text = self.text()?;
+                let digits = text.trim();
+                if digits.is_empty() {
+                    return Ok(Value::Blank);
+                }
+                // Converted to UTF-8, EBCDIC zoned digits read as ASCII overpunch
+                let zoned = ZonedDecimal::new(digits.len() as u32, scale).ascii();
+                let zoned = if signed { zoned } else { zoned.unsigned() };
+                zoned
+                    .decode(digits.as_bytes())
+                    .map(Value::Number)
+                    .map_err(|_| Error::InvalidInput(format!("invalid zoned decimal \"{}\"", text)))
+            }
+            FieldKind::Packed { scale } => {
+                let digits = (self.bytes.len() * 2).saturating_sub(1) as u32;
+                PackedDecimal::new(digits, scale)
+                    .decode(self.bytes)
+                    .map(Value::Number)
+            }
+            FieldKind::Binary { signed, scale } => {
+                let mut value: i64 = 0;
+                for &byte in self.bytes {
+                    value = (value << 8) | byte as i64;
+                }
+                let bits = self.bytes.len() as u32 * 8;
+                if signed && bits < 64 && value & (1 << (bits - 1)) != 0 {
+                    value -= 1 << bits;
+                }
+                Ok(Value::Number(Decimal::new(value as i128, scale)))
+            }
+        }
+    }
+
+    fn integer<T: TryFrom<i64>>(&self) -> Result<T> {
+        let value = self.decode()?;
+        let parsed = match &value {
+            Value::Number(number) if number.scale() == 0 => i64::try_from(number.unscaled()).ok(),
+            Value::Text(text) => text.trim().parse().ok(),
+            _ => None,
+        };
+        parsed.and_then(|n| T::try_from(n).ok()).ok_or_else(|| {
+            Error::InvalidInput(format!("expected an integer, found {}", value.describe()))
+        })
+    }
+
+    fn float(&self) -> Result<f64> {
+        let value = self.decode()?;
+        match &value {
+            Value::Number(number) => Ok(number.to_f64()),
+            Value::Text(text) => text.trim().parse().map_err(|_| {
+                Error::InvalidInput(format!("expected a number, found {}", value.describe()))
+            }),
+            Value::Blank => Err(Error::InvalidInput(
+                "expected a number, found a blank field".to_string(),
+            )),
+        }
+    }
+}
+
+impl<'de> Deserializer<'de> for FieldDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.decode()? {
+            Value::Text(text) => visitor.visit_string(text),
+            Value::Number(number) => match i64::try_from(number.unscaled()) {
+                Ok(integer) if number.scale() == 0 => visitor.visit_i64(integer),
+                _ => visitor.visit_f64(number.to_f64()),
+            },
+            Value::Blank => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = self.decode()?;
+        let flag = match &value {
+            Value::Number(number) if number.scale() == 0 && matches!(number.unscaled(), 0 | 1) => {
+                Some(number.unscaled() == 1)
+            }
+            Value::Text(text) => match text.trim().to_ascii_uppercase().as_str() {
+                "Y" | "YES" | "T" | "TRUE" | "1" => Some(true),
+                "N" | "NO" | "F" | "FALSE" | "0" => Some(false),
+                _ => None,
+            },
+            _ => None,
+        };
+        match flag {
+            Some(flag) => visitor.visit_bool(flag),
+            None => Err(Error::InvalidInput(format!(
+                "expected a flag, found {}",
+                value.describe()
+            ))),
+        }
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i8(self.integer()?)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i16(self.integer()?)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i32(self.integer()?)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i64(self.integer()?)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u8(self.integer()?)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u16(self.integer()?)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u32(self.integer()?)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u64(self.integer()?)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f32(self.float()? as f32)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f64(self.float()?)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = self.decode()?;
+        if let Value::Text(text) = &value {
+            let mut chars = text.chars();
+            if let (Some(ch), None) = (chars.next(), chars.next()) {
+                return visitor.visit_char(ch);
+            }
+        }
+        Err(Error::InvalidInput(format!(
+            "expected a single character, found {}",
+            value.describe()
+        )))
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.decode()? {
+            Value::Text(text) => visitor.visit_string(text),
+            Value::Number(number) => visitor.visit_string(number.to_string()),
+            Value::Blank => visitor.visit_string(String::new()),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_bytes(self.bytes)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_byte_buf(self.bytes.to_vec())
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.decode()? {
+            Value::Blank => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.decode()? {
+            Value::Text(text) => visitor.visit_enum(text.trim().to_string().into_deserializer()),
+            value => Err(Error::InvalidInput(format!(
+                "expected a variant name, found {}",
+                value.describe()
+            ))),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
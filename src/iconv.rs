@@ -0,0 +1,269 @@
+//! An `iconv(3)`-shaped interface for code ported from C
+//!
+//! [`iconv_open`] takes the target encoding first, like its C namesake, and
+//! [`Iconv::iconv`] advances the input and output slices past whatever it
+//! converted, the way `iconv` advances `inbuf`/`outbuf` and decrements the
+//! byte counts. Failures map onto the C errno values through [`IconvError`].
+//! There is no `iconv_close`: dropping the handle releases it.
+//!
+//! ```
+//! use fast_encode::iconv::{iconv_open, IconvError};
+//!
+//! let mut cd = iconv_open("UTF-8", "CP1252").unwrap();
+//! let mut input: &[u8] = b"caf\xE9";
+//! let mut buffer = [0u8; 4];
+//! let mut output: &mut [u8] = &mut buffer;
+//!
+//! // Only "caf" fits: the input stops at the byte that did not
+//! assert_eq!(cd.iconv(&mut input, &mut output), Err(IconvError::TooBig));
+//! assert_eq!(input, b"\xE9");
+//! assert_eq!(output.len(), 1);
+//! ```
+//!
+//! As with GNU iconv, `//TRANSLIT` or `//IGNORE` may be appended to the target
+//! name. `//TRANSLIT` writes `?` for characters the target cannot encode;
+//! `//IGNORE` skips them along with invalid input. Either way the count of such
+//! irreversible conversions is the call's return value.
+
+use std::fmt;
+use std::mem;
+
+use crate::policy::{CharEncoder, Encoder};
+use crate::{Encoding, Error, Result, cjk, multibyte, tables};
+
+/// Why a call to [`Iconv::iconv`] stopped early
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IconvError {
+    /// `E2BIG`: the output buffer has no room for the next character
+    TooBig,
+    /// `EILSEQ`: the next input is not valid in the source encoding, or is a
+    /// character the target cannot encode; the input slice starts at it
+    IllegalSequence,
+    /// `EINVAL`: the input ends in the middle of a multi-byte sequence, which
+    /// is left unconsumed so it can be completed by the next call
+    Incomplete,
+}
+
+impl IconvError {
+    /// The C `errno` name this error corresponds to
+    pub fn errno_name(self) -> &'static str {
+        match self {
+            IconvError::TooBig => "E2BIG",
+            IconvError::IllegalSequence => "EILSEQ",
+            IconvError::Incomplete => "EINVAL",
+        }
+    }
+}
+
+impl fmt::Display for IconvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IconvError::TooBig => write!(f, "Output buffer is full"),
+            IconvError::IllegalSequence => write!(f, "Illegal input sequence"),
+            IconvError::Incomplete => write!(f, "Incomplete multi-byte sequence at end of input"),
+        }
+    }
+}
+
+impl std::error::Error for IconvError {}
+
+/// What `//TRANSLIT` and `//IGNORE` ask for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fallback {
+    None,
+    Translit,
+    Ignore,
+}
+
+/// Conversion descriptor returned by [`iconv_open`]
+pub struct Iconv {
+    from: Encoding,
+    to: Encoding,
+    encoder: Encoder,
+    fallback: Fallback,
+}
+
+impl fmt::Debug for Iconv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Iconv")
+            .field("from", &self.from)
+            .field("to", &self.to)
+            .field("fallback", &self.fallback)
+            .finish()
+    }
+}
+
+/// Open a converter from `fromcode` to `tocode`
+///
+/// Names are matched case-insensitively against each encoding's canonical name
+/// and aliases. Unknown names fail with [`Error::InvalidInput`], where C's
+/// `iconv_open` would set `EINVAL`.
+pub fn iconv_open(tocode: &str, fromcode: &str) -> Result<Iconv> {
+    let (name, fallback) = match tocode.split_once("//") {
+        Some((name, suffix)) if suffix.eq_ignore_ascii_case("TRANSLIT") => {
+            (name, Fallback::Translit)
+        }
+        Some((name, suffix)) if suffix.eq_ignore_ascii_case("IGNORE") => (name, Fallback::Ignore),
+        Some(_) => {
+            return Err(Error::InvalidInput(format!(
+                "unsupported conversion suffix in {}",
+                tocode
+            )));
+        }
+        None => (tocode, Fallback::None),
+    };
+
+    let to = lookup(name)?;
+    Ok(Iconv {
+        from: lookup(fromcode)?,
+        to,
+        encoder: Encoder::new(to),
+        fallback,
+    })
+}
+
+fn lookup(label: &str) -> Result<Encoding> {
+    Encoding::for_label(label)
+        .ok_or_else(|| Error::InvalidInput(format!("unknown encoding: {}", label)))
+}
+
+/// One step of decoding from the front of the input
+enum Step {
+    Char(char, usize),
+    Invalid(usize),
+    Incomplete,
+}
+
+impl Iconv {
+    /// Source encoding
+    pub fn from_encoding(&self) -> Encoding {
+        self.from
+    }
+
+    /// Target encoding
+    pub fn to_encoding(&self) -> Encoding {
+        self.to
+    }
+
+    /// Convert as much of `inbuf` into `outbuf` as possible
+    ///
+    /// Both slices are advanced past what was consumed and written, so on
+    /// return `inbuf` holds the unconverted input and `outbuf` the unused
+    /// space. Characters are converted whole: a character whose encoding does
+    /// not fit stops the call with [`IconvError::TooBig`] before any of it is
+    /// written. Returns the number of irreversible conversions made under
+    /// `//TRANSLIT` or `//IGNORE`.
+    pub fn iconv(
+        &mut self,
+        inbuf: &mut &[u8],
+        outbuf: &mut &mut [u8],
+    ) -> std::result::Result<usize, IconvError> {
+        let mut irreversible = 0;
+        let mut encoded = Vec::with_capacity(8);
+
+        while !inbuf.is_empty() {
+            encoded.clear();
+            let consumed = match self.decode_step(inbuf) {
+                Step::Incomplete => return Err(IconvError::Incomplete),
+                Step::Invalid(length) => {
+                    if self.fallback != Fallback::Ignore {
+                        return Err(IconvError::IllegalSequence);
+                    }
+                    irreversible += 1;
+                    length
+                }
+                Step::Char(ch, length) => {
+                    if !self.encoder.encode(ch, &mut encoded) {
+                        match self.fallback {
+                            Fallback::None => return Err(IconvError::IllegalSequence),
+                            Fallback::Ignore => {}
+                            Fallback::Translit => {
+                                if !self.encoder.encode('?', &mut encoded) {
+                                    return Err(IconvError::IllegalSequence);
+                                }
+                            }
+                        }
+                        irreversible += 1;
+                    }
+                    length
+                }
+            };
+
+            if encoded.len() > outbuf.len() {
+                return Err(IconvError::TooBig);
+            }
+            let (written, rest) = mem::take(outbuf).split_at_mut(encoded.len());
+            written.copy_from_slice(&encoded);
+            *outbuf = rest;
+            *inbuf = &inbuf[consumed..];
+        }
+
+        Ok(irreversible)
+    }
+
+    /// Decode the character at the front of `input`
+    fn decode_step(&self, input: &[u8]) -> Step {
+        match self.from {
+            Encoding::UTF8 => {
+                let head = &input[..input.len().min(4)];
+                let valid = match std::str::from_utf8(head) {
+                    Ok(text) => text,
+                    Err(e) if e.valid_up_to() > 0 => {
+                        std::str::from_utf8(&head[..e.valid_up_to()]).unwrap_or_default()
+                    }
+                    Err(e) => {
+                        return match e.error_len() {
+                            Some(length) => Step::Invalid(length),
+                            None => Step::Incomplete,
+                        };
+                    }
+                };
+                match valid.chars().next() {
+                    Some(ch) => Step::Char(ch, ch.len_utf8()),
+                    None => Step::Incomplete,
+                }
+            }
+            Encoding::UTF32LE | Encoding::UTF32BE => {
+                if input.len() < 4 {
+                    return Step::Incomplete;
+                }
+                let value = multibyte::read_unit(input, 4, self.from == Encoding::UTF32LE);
+                match char::from_u32(value) {
+                    Some(ch) => Step::Char(ch, 4),
+                    None => Step::Invalid(4),
+                }
+            }
+            Encoding::UTF16LE | Encoding::UTF16BE => {
+                let unit_at = |i: usize| match self.from {
+                    Encoding::UTF16LE => u16::from_le_bytes([input[i], input[i + 1]]),
+                    _ => u16::from_be_bytes([input[i], input[i + 1]]),
+                };
+
+                if input.len() < 2 {
+                    return Step::Incomplete;
+                }
+                let first = unit_at(0);
+                match first {
+                    0xD800..=0xDBFF if input.len() < 4 => Step::Incomplete,
+                    0xD800..=0xDBFF => match char::decode_utf16([first, unit_at(2)]).next() {
+                        Some(Ok(ch)) => Step::Char(ch, 4),
+                        _ => Step::Invalid(2),
+                    },
+                    _ => match char::from_u32(first as u32) {
+                        Some(ch) => Step::Char(ch, 2),
+                        None => Step::Invalid(2),
+                    },
+                }
+            }
+            from if cjk::is_double_byte(from) => match cjk::decode_step(from, input) {
+                cjk::Step::Char(ch, length) => Step::Char(ch, length),
+                cjk::Step::Invalid(length) => Step::Invalid(length),
+                cjk::Step::Incomplete => Step::Incomplete,
+            },
+            from => match tables::get_encoding_chars(from)[input[0] as usize] {
+                Some(ch) => Step::Char(ch, 1),
+                None => Step::Invalid(1),
+            },
+        }
+    }
+}
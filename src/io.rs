@@ -0,0 +1,221 @@
+//! [`std::io`] adapters that convert data as it is read or written
+//!
+//! Both wrap a [`StreamingTranslator`], so only one chunk of input is held in
+//! memory at a time and sequences split between reads or writes are carried
+//! over rather than rejected.
+//!
+//! ```
+//! use std::io::{Read, Write};
+//! use fast_encode::Encoding;
+//! use fast_encode::io::{TranslatingReader, TranslatingWriter};
+//!
+//! let ebcdic: &[u8] = &[0xC8, 0x85, 0x93, 0x93, 0x96];
+//! let mut text = String::new();
+//! TranslatingReader::new(ebcdic, Encoding::EBCDIC_037, Encoding::UTF8)
+//!     .unwrap()
+//!     .read_to_string(&mut text)
+//!     .unwrap();
+//! assert_eq!(text, "Hello");
+//!
+//! let mut writer = TranslatingWriter::new(Vec::new(), Encoding::UTF8, Encoding::UTF16LE).unwrap();
+//! writer.write_all(&"é".as_bytes()[..1]).unwrap();
+//! writer.write_all(&"é".as_bytes()[1..]).unwrap();
+//! assert_eq!(writer.finish().unwrap(), [0xE9, 0x00]);
+//! ```
+//!
+//! Conversion errors surface as [`std::io::ErrorKind::InvalidData`] errors
+//! wrapping the crate's [`Error`](crate::Error).
+
+use std::io::{self, Read, Write};
+
+use crate::{Encoding, ErrorPolicy, Result, StreamingTranslator};
+
+/// Reads from `R` and yields the data converted to the target encoding
+pub struct TranslatingReader<R> {
+    inner: R,
+    stream: StreamingTranslator,
+    policy: ErrorPolicy,
+    /// Raw input read from `inner`
+    input: Vec<u8>,
+    /// Converted output not yet handed to the caller
+    output: Vec<u8>,
+    position: usize,
+    finished: bool,
+}
+
+impl<R: Read> TranslatingReader<R> {
+    /// Convert everything read from `inner` from `from` to `to`
+    pub fn new(inner: R, from: Encoding, to: Encoding) -> Result<Self> {
+        Ok(Self::with_translator(
+            inner,
+            StreamingTranslator::with_default_buffer(from, to)?,
+        ))
+    }
+
+    /// Convert with an existing streaming translator, reading its buffer size at a time
+    pub fn with_translator(inner: R, stream: StreamingTranslator) -> Self {
+        let buffer_size = stream.buffer_size().max(1);
+        Self {
+            inner,
+            stream,
+            policy: ErrorPolicy::Strict,
+            input: vec![0; buffer_size],
+            output: Vec::new(),
+            position: 0,
+            finished: false,
+        }
+    }
+
+    /// Handle unmappable input according to `policy` instead of failing
+    pub fn policy(mut self, policy: ErrorPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// The streaming translator, e.g. for [`replacements`](StreamingTranslator::replacements)
+    pub fn translator(&self) -> &StreamingTranslator {
+        &self.stream
+    }
+
+    /// Get a reference to the underlying reader
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the underlying reader
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwrap the underlying reader, discarding any converted data not yet read
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Convert the next chunk of input, or the end of the stream
+    fn fill(&mut self) -> io::Result<()> {
+        let read = loop {
+            match self.inner.read(&mut self.input) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                result => break result?,
+            }
+        };
+
+        self.output = if read == 0 {
+            self.finished = true;
+            self.stream.finish_with_policy(self.policy.clone())?
+        } else {
+            self.stream
+                .process_chunk_with_policy(&self.input[..read], self.policy.clone())?
+        };
+        self.position = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for TranslatingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.output.len() {
+            if self.finished || buf.is_empty() {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+
+        let available = &self.output[self.position..];
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+/// Converts data written to it and writes the result to `W`
+///
+/// Call [`finish`](Self::finish) once all data is written: it reports input
+/// that ended in the middle of a sequence. Dropping the writer finishes it too,
+/// ignoring any error.
+pub struct TranslatingWriter<W: Write> {
+    /// Always `Some` until [`finish`](Self::finish) takes it
+    inner: Option<W>,
+    stream: StreamingTranslator,
+    policy: ErrorPolicy,
+}
+
+impl<W: Write> TranslatingWriter<W> {
+    /// Convert everything written from `from` to `to` before passing it to `inner`
+    pub fn new(inner: W, from: Encoding, to: Encoding) -> Result<Self> {
+        Ok(Self::with_translator(
+            inner,
+            StreamingTranslator::with_default_buffer(from, to)?,
+        ))
+    }
+
+    /// Convert with an existing streaming translator
+    pub fn with_translator(inner: W, stream: StreamingTranslator) -> Self {
+        Self {
+            inner: Some(inner),
+            stream,
+            policy: ErrorPolicy::Strict,
+        }
+    }
+
+    /// Handle unmappable input according to `policy` instead of failing
+    pub fn policy(mut self, policy: ErrorPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// The streaming translator, e.g. for [`replacements`](StreamingTranslator::replacements)
+    pub fn translator(&self) -> &StreamingTranslator {
+        &self.stream
+    }
+
+    /// Get a reference to the underlying writer
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().expect("writer present until finished")
+    }
+
+    /// Get a mutable reference to the underlying writer
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.as_mut().expect("writer present until finished")
+    }
+
+    /// Convert any held-back input, flush, and return the underlying writer
+    ///
+    /// Fails if the input ended in the middle of a multi-byte sequence.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.finish_stream()?;
+        Ok(self.inner.take().expect("writer present until finished"))
+    }
+
+    fn finish_stream(&mut self) -> io::Result<()> {
+        let tail = self.stream.finish_with_policy(self.policy.clone())?;
+        let inner = self.get_mut();
+        inner.write_all(&tail)?;
+        inner.flush()
+    }
+}
+
+impl<W: Write> Write for TranslatingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let converted = self
+            .stream
+            .process_chunk_with_policy(buf, self.policy.clone())?;
+        self.get_mut().write_all(&converted)?;
+        Ok(buf.len())
+    }
+
+    /// Flush the underlying writer; a partial sequence stays held back
+    fn flush(&mut self) -> io::Result<()> {
+        self.get_mut().flush()
+    }
+}
+
+impl<W: Write> Drop for TranslatingWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.finish_stream();
+        }
+    }
+}
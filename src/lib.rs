@@ -29,18 +29,45 @@
 //! assert_eq!(std::str::from_utf8(&utf8_result).unwrap(), "HELLO");
 //! ```
 
-#![cfg_attr(feature = "simd", feature(portable_simd))]
 #![deny(missing_docs)]
 
+use std::convert::Infallible;
 use std::fmt;
-
+use std::sync::Arc;
+
+pub mod analysis;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "async")]
+pub mod async_io;
+mod cjk;
+#[cfg(feature = "encoding_rs")]
+pub mod compat;
+#[cfg(feature = "conformance")]
+pub mod conformance;
+pub mod custom;
 pub mod detection;
+#[cfg(feature = "capi")]
+pub mod ffi;
+pub mod fixed_width;
+pub mod iconv;
+pub mod io;
+pub mod mime;
+pub mod mojibake;
 mod multibyte;
-mod tables;
-
-// SIMD imports when feature is enabled
+mod newline;
+pub mod numeric;
+pub mod os_str;
+pub mod pipeline;
+mod policy;
+pub mod records;
 #[cfg(feature = "simd")]
-use std::simd::{Simd, SimdPartialEq, u8x16, u8x32};
+mod simd;
+mod tables;
+mod telemetry;
+pub mod translit;
+pub mod validation;
+mod vietnamese;
 
 /// Result type for encoding operations
 pub type Result<T> = std::result::Result<T, Error>;
@@ -71,6 +98,13 @@ pub enum Error {
         /// Target encoding name
         to: &'static str,
     },
+    /// Caller-provided output buffer cannot hold the converted data
+    OutputTooSmall {
+        /// Bytes the converted data needs
+        needed: usize,
+        /// Bytes the buffer has
+        available: usize,
+    },
 }
 
 impl fmt::Display for Error {
@@ -97,12 +131,43 @@ impl fmt::Display for Error {
             Error::UnsupportedConversion { from, to } => {
                 write!(f, "Unsupported conversion from {} to {}", from, to)
             }
+            Error::OutputTooSmall { needed, available } => write!(
+                f,
+                "Output buffer too small: {} bytes needed, {} available",
+                needed, available
+            ),
         }
     }
 }
 
 impl std::error::Error for Error {}
 
+impl From<Error> for std::io::Error {
+    fn from(error: Error) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+    }
+}
+
+impl Error {
+    /// Shift the reported position by `offset` bytes
+    pub(crate) fn offset_by(self, offset: usize) -> Self {
+        match self {
+            Error::UnmappableSource { byte, position } => Error::UnmappableSource {
+                byte,
+                position: position + offset,
+            },
+            Error::UnmappableTarget {
+                character,
+                position,
+            } => Error::UnmappableTarget {
+                character,
+                position: position + offset,
+            },
+            other => other,
+        }
+    }
+}
+
 /// Supported character encodings
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[allow(non_camel_case_types)]
@@ -114,6 +179,10 @@ pub enum Encoding {
     UTF16LE,
     /// UTF-16BE Unicode encoding (big endian)  
     UTF16BE,
+    /// UTF-32LE Unicode encoding (little endian, 4 bytes per character)
+    UTF32LE,
+    /// UTF-32BE Unicode encoding (big endian, 4 bytes per character)
+    UTF32BE,
 
     // ASCII and Latin encodings
     /// ASCII (7-bit, 0-127)
@@ -217,6 +286,10 @@ pub enum Encoding {
     /// DOS Code Page 866 (Russian OEM)
     CP_866,
 
+    // Russian national standard
+    /// KOI8-R (Russian, RFC 1489)
+    KOI8_R,
+
     // Mac encodings
     /// Macintosh Roman
     MAC_ROMAN,
@@ -234,6 +307,10 @@ pub enum Encoding {
     BIG5,
     /// EUC-KR (Korean)
     EUC_KR,
+
+    // User-defined code pages
+    /// Single-byte code page supplied at runtime (see [`custom::CustomCodePage`])
+    Custom(&'static custom::CustomCodePage),
 }
 
 impl Encoding {
@@ -244,6 +321,8 @@ impl Encoding {
             Encoding::UTF8 => "UTF-8",
             Encoding::UTF16LE => "UTF-16LE",
             Encoding::UTF16BE => "UTF-16BE",
+            Encoding::UTF32LE => "UTF-32LE",
+            Encoding::UTF32BE => "UTF-32BE",
 
             // ASCII and Latin
             Encoding::ASCII => "US-ASCII",
@@ -300,6 +379,9 @@ impl Encoding {
             Encoding::CP_865 => "CP865",
             Encoding::CP_866 => "CP866",
 
+            // KOI8
+            Encoding::KOI8_R => "KOI8-R",
+
             // Mac
             Encoding::MAC_ROMAN => "MacRoman",
             Encoding::MAC_CYRILLIC => "MacCyrillic",
@@ -310,11 +392,480 @@ impl Encoding {
             Encoding::GB2312 => "GB2312",
             Encoding::BIG5 => "Big5",
             Encoding::EUC_KR => "EUC-KR",
+
+            // User-defined
+            Encoding::Custom(page) => page.name(),
+        }
+    }
+
+    /// Every built-in encoding, in declaration order
+    ///
+    /// [`Encoding::Custom`] code pages are created at runtime and not included.
+    pub fn all() -> &'static [Encoding] {
+        ALL_ENCODINGS
+    }
+
+    /// Family of this encoding: `unicode`, `ascii`, `iso`, `windows`, `ebcdic`,
+    /// `dos`, `koi`, `mac`, `asian` or `custom`
+    pub fn category(self) -> &'static str {
+        match self {
+            Encoding::UTF8
+            | Encoding::UTF16LE
+            | Encoding::UTF16BE
+            | Encoding::UTF32LE
+            | Encoding::UTF32BE => "unicode",
+            Encoding::ASCII => "ascii",
+            Encoding::ISO_8859_1
+            | Encoding::ISO_8859_2
+            | Encoding::ISO_8859_3
+            | Encoding::ISO_8859_4
+            | Encoding::ISO_8859_5
+            | Encoding::ISO_8859_6
+            | Encoding::ISO_8859_7
+            | Encoding::ISO_8859_8
+            | Encoding::ISO_8859_9
+            | Encoding::ISO_8859_10
+            | Encoding::ISO_8859_11
+            | Encoding::ISO_8859_13
+            | Encoding::ISO_8859_14
+            | Encoding::ISO_8859_15
+            | Encoding::ISO_8859_16 => "iso",
+            Encoding::WINDOWS_1250
+            | Encoding::WINDOWS_1251
+            | Encoding::WINDOWS_1252
+            | Encoding::WINDOWS_1253
+            | Encoding::WINDOWS_1254
+            | Encoding::WINDOWS_1255
+            | Encoding::WINDOWS_1256
+            | Encoding::WINDOWS_1257
+            | Encoding::WINDOWS_1258
+            | Encoding::WINDOWS_874 => "windows",
+            Encoding::EBCDIC_037
+            | Encoding::EBCDIC_273
+            | Encoding::EBCDIC_277
+            | Encoding::EBCDIC_278
+            | Encoding::EBCDIC_280
+            | Encoding::EBCDIC_284
+            | Encoding::EBCDIC_285
+            | Encoding::EBCDIC_297
+            | Encoding::EBCDIC_500
+            | Encoding::EBCDIC_1047 => "ebcdic",
+            Encoding::CP_437
+            | Encoding::CP_850
+            | Encoding::CP_852
+            | Encoding::CP_855
+            | Encoding::CP_857
+            | Encoding::CP_860
+            | Encoding::CP_861
+            | Encoding::CP_862
+            | Encoding::CP_863
+            | Encoding::CP_865
+            | Encoding::CP_866 => "dos",
+            Encoding::KOI8_R => "koi",
+            Encoding::MAC_ROMAN | Encoding::MAC_CYRILLIC => "mac",
+            Encoding::SHIFT_JIS
+            | Encoding::EUC_JP
+            | Encoding::GB2312
+            | Encoding::BIG5
+            | Encoding::EUC_KR => "asian",
+            Encoding::Custom(_) => "custom",
+        }
+    }
+
+    /// Short human-readable description of the script or region covered
+    pub fn description(self) -> &'static str {
+        match self {
+            Encoding::UTF8 => "UTF-8 Unicode",
+            Encoding::UTF16LE => "UTF-16 Little Endian",
+            Encoding::UTF16BE => "UTF-16 Big Endian",
+            Encoding::UTF32LE => "UTF-32 Little Endian",
+            Encoding::UTF32BE => "UTF-32 Big Endian",
+            Encoding::ASCII => "US-ASCII (7-bit)",
+            Encoding::ISO_8859_1 => "Latin-1, Western European",
+            Encoding::ISO_8859_2 => "Latin-2, Central/Eastern European",
+            Encoding::ISO_8859_3 => "Latin-3, South European",
+            Encoding::ISO_8859_4 => "Latin-4, North European",
+            Encoding::ISO_8859_5 => "Cyrillic",
+            Encoding::ISO_8859_6 => "Arabic",
+            Encoding::ISO_8859_7 => "Greek",
+            Encoding::ISO_8859_8 => "Hebrew",
+            Encoding::ISO_8859_9 => "Latin-5, Turkish",
+            Encoding::ISO_8859_10 => "Latin-6, Nordic",
+            Encoding::ISO_8859_11 => "Thai",
+            Encoding::ISO_8859_13 => "Latin-7, Baltic Rim",
+            Encoding::ISO_8859_14 => "Latin-8, Celtic",
+            Encoding::ISO_8859_15 => "Latin-9, Western European with Euro",
+            Encoding::ISO_8859_16 => "Latin-10, South-Eastern European",
+            Encoding::WINDOWS_1250 => "Central/Eastern European",
+            Encoding::WINDOWS_1251 => "Cyrillic",
+            Encoding::WINDOWS_1252 => "Western European",
+            Encoding::WINDOWS_1253 => "Greek",
+            Encoding::WINDOWS_1254 => "Turkish",
+            Encoding::WINDOWS_1255 => "Hebrew",
+            Encoding::WINDOWS_1256 => "Arabic",
+            Encoding::WINDOWS_1257 => "Baltic",
+            Encoding::WINDOWS_1258 => "Vietnamese",
+            Encoding::WINDOWS_874 => "Thai",
+            Encoding::EBCDIC_037 => "US/Canada",
+            Encoding::EBCDIC_273 => "Germany/Austria",
+            Encoding::EBCDIC_277 => "Denmark/Norway",
+            Encoding::EBCDIC_278 => "Finland/Sweden",
+            Encoding::EBCDIC_280 => "Italy",
+            Encoding::EBCDIC_284 => "Spain",
+            Encoding::EBCDIC_285 => "United Kingdom",
+            Encoding::EBCDIC_297 => "France",
+            Encoding::EBCDIC_500 => "International",
+            Encoding::EBCDIC_1047 => "Latin-1 Open Systems",
+            Encoding::CP_437 => "US OEM",
+            Encoding::CP_850 => "Western European OEM",
+            Encoding::CP_852 => "Central European OEM",
+            Encoding::CP_855 => "Cyrillic OEM",
+            Encoding::CP_857 => "Turkish OEM",
+            Encoding::CP_860 => "Portuguese OEM",
+            Encoding::CP_861 => "Icelandic OEM",
+            Encoding::CP_862 => "Hebrew OEM",
+            Encoding::CP_863 => "French Canadian OEM",
+            Encoding::CP_865 => "Nordic OEM",
+            Encoding::CP_866 => "Russian OEM",
+            Encoding::KOI8_R => "Russian",
+            Encoding::MAC_ROMAN => "Macintosh Roman",
+            Encoding::MAC_CYRILLIC => "Macintosh Cyrillic",
+            Encoding::SHIFT_JIS => "Japanese",
+            Encoding::EUC_JP => "Japanese",
+            Encoding::GB2312 => "Simplified Chinese",
+            Encoding::BIG5 => "Traditional Chinese",
+            Encoding::EUC_KR => "Korean",
+            Encoding::Custom(_) => "User-defined code page",
+        }
+    }
+
+    /// Alternative labels accepted for this encoding besides [`Encoding::name`]
+    pub fn aliases(self) -> &'static [&'static str] {
+        match self {
+            Encoding::UTF8 => &[
+                "UTF8",
+                "unicode-1-1-utf-8",
+                "unicode11utf8",
+                "unicode20utf8",
+                "x-unicode20utf8",
+            ],
+            Encoding::UTF16LE => &[
+                "UTF16LE",
+                "UTF-16",
+                "UCS-2",
+                "unicode",
+                "unicodefeff",
+                "csUnicode",
+                "ISO-10646-UCS-2",
+            ],
+            Encoding::UTF16BE => &["UTF16BE", "unicodefffe"],
+            Encoding::UTF32LE => &["UTF32LE", "UTF-32", "UCS-4LE"],
+            Encoding::UTF32BE => &["UTF32BE", "UCS-4BE"],
+            Encoding::ASCII => &[
+                "ASCII",
+                "US",
+                "ANSI_X3.4-1968",
+                "ISO646-US",
+                "iso-ir-6",
+                "csASCII",
+            ],
+            Encoding::ISO_8859_1 => &[
+                "ISO88591",
+                "LATIN1",
+                "L1",
+                "ISO_8859-1:1987",
+                "iso-ir-100",
+                "csISOLatin1",
+            ],
+            Encoding::ISO_8859_2 => &[
+                "ISO88592",
+                "LATIN2",
+                "L2",
+                "ISO_8859-2:1987",
+                "iso-ir-101",
+                "csISOLatin2",
+            ],
+            Encoding::ISO_8859_3 => &["ISO88593", "LATIN3", "L3", "iso-ir-109", "csISOLatin3"],
+            Encoding::ISO_8859_4 => &["ISO88594", "LATIN4", "L4", "iso-ir-110", "csISOLatin4"],
+            Encoding::ISO_8859_5 => &["ISO88595", "cyrillic", "iso-ir-144", "csISOLatinCyrillic"],
+            Encoding::ISO_8859_6 => &[
+                "ISO88596",
+                "arabic",
+                "ASMO-708",
+                "ECMA-114",
+                "iso-ir-127",
+                "csISOLatinArabic",
+                "ISO-8859-6-E",
+                "ISO-8859-6-I",
+            ],
+            Encoding::ISO_8859_7 => &[
+                "ISO88597",
+                "greek",
+                "greek8",
+                "ELOT_928",
+                "ECMA-118",
+                "iso-ir-126",
+                "csISOLatinGreek",
+                "sun_eu_greek",
+            ],
+            Encoding::ISO_8859_8 => &[
+                "ISO88598",
+                "hebrew",
+                "visual",
+                "logical",
+                "iso-ir-138",
+                "csISOLatinHebrew",
+                "ISO-8859-8-E",
+                "ISO-8859-8-I",
+                "csISO88598I",
+            ],
+            Encoding::ISO_8859_9 => &["ISO88599", "LATIN5", "L5", "iso-ir-148", "csISOLatin5"],
+            Encoding::ISO_8859_10 => &["ISO885910", "LATIN6", "L6", "iso-ir-157", "csISOLatin6"],
+            Encoding::ISO_8859_11 => &["ISO885911", "TIS-620"],
+            Encoding::ISO_8859_13 => &["ISO885913", "LATIN7"],
+            Encoding::ISO_8859_14 => &["ISO885914", "LATIN8", "L8", "iso-ir-199"],
+            Encoding::ISO_8859_15 => &["ISO885915", "LATIN9", "L9", "csISOLatin9"],
+            Encoding::ISO_8859_16 => &["ISO885916", "LATIN10", "L10", "iso-ir-226"],
+            Encoding::WINDOWS_1250 => &["WINDOWS1250", "WIN1250", "CP1250", "x-cp1250"],
+            Encoding::WINDOWS_1251 => &["WINDOWS1251", "WIN1251", "CP1251", "x-cp1251"],
+            Encoding::WINDOWS_1252 => &["WINDOWS1252", "WIN1252", "CP1252", "x-cp1252"],
+            Encoding::WINDOWS_1253 => &["WINDOWS1253", "WIN1253", "CP1253", "x-cp1253"],
+            Encoding::WINDOWS_1254 => &["WINDOWS1254", "WIN1254", "CP1254", "x-cp1254"],
+            Encoding::WINDOWS_1255 => &["WINDOWS1255", "WIN1255", "CP1255", "x-cp1255"],
+            Encoding::WINDOWS_1256 => &["WINDOWS1256", "WIN1256", "CP1256", "x-cp1256"],
+            Encoding::WINDOWS_1257 => &["WINDOWS1257", "WIN1257", "CP1257", "x-cp1257"],
+            Encoding::WINDOWS_1258 => &["WINDOWS1258", "WIN1258", "CP1258", "x-cp1258"],
+            Encoding::WINDOWS_874 => &["WINDOWS874", "WIN874", "CP874", "DOS-874"],
+            Encoding::EBCDIC_037 => &[
+                "EBCDIC037",
+                "CP037",
+                "ebcdic-cp-us",
+                "ebcdic-cp-ca",
+                "ebcdic-cp-nl",
+                "ebcdic-cp-wt",
+                "csIBM037",
+            ],
+            Encoding::EBCDIC_273 => &["EBCDIC273", "CP273", "csIBM273"],
+            Encoding::EBCDIC_277 => &[
+                "EBCDIC277",
+                "CP277",
+                "ebcdic-cp-dk",
+                "ebcdic-cp-no",
+                "csIBM277",
+            ],
+            Encoding::EBCDIC_278 => &[
+                "EBCDIC278",
+                "CP278",
+                "ebcdic-cp-fi",
+                "ebcdic-cp-se",
+                "csIBM278",
+            ],
+            Encoding::EBCDIC_280 => &["EBCDIC280", "CP280", "ebcdic-cp-it", "csIBM280"],
+            Encoding::EBCDIC_284 => &["EBCDIC284", "CP284", "ebcdic-cp-es", "csIBM284"],
+            Encoding::EBCDIC_285 => &["EBCDIC285", "CP285", "ebcdic-cp-gb", "csIBM285"],
+            Encoding::EBCDIC_297 => &["EBCDIC297", "CP297", "ebcdic-cp-fr", "csIBM297"],
+            Encoding::EBCDIC_500 => &[
+                "EBCDIC500",
+                "CP500",
+                "ebcdic-cp-be",
+                "ebcdic-cp-ch",
+                "csIBM500",
+            ],
+            Encoding::EBCDIC_1047 => &["EBCDIC1047", "CP1047"],
+            Encoding::CP_437 => &["DOS437", "csPC8CodePage437"],
+            Encoding::CP_850 => &["DOS850", "csPC850Multilingual"],
+            Encoding::CP_852 => &["DOS852", "csPCp852"],
+            Encoding::CP_855 => &["DOS855", "csIBM855"],
+            Encoding::CP_857 => &["DOS857", "csIBM857"],
+            Encoding::CP_860 => &["DOS860", "csIBM860"],
+            Encoding::CP_861 => &["DOS861", "cp-is", "csIBM861"],
+            Encoding::CP_862 => &["DOS862", "csPC862LatinHebrew"],
+            Encoding::CP_863 => &["DOS863", "csIBM863"],
+            Encoding::CP_865 => &["DOS865", "csIBM865"],
+            Encoding::CP_866 => &["DOS866", "csIBM866"],
+            Encoding::KOI8_R => &["KOI8R", "KOI8", "KOI", "CSKOI8R"],
+            Encoding::MAC_ROMAN => &[
+                "MAC-ROMAN",
+                "macintosh",
+                "mac",
+                "csMacintosh",
+                "x-mac-roman",
+            ],
+            Encoding::MAC_CYRILLIC => &["MAC-CYRILLIC", "x-mac-cyrillic", "x-mac-ukrainian"],
+            Encoding::SHIFT_JIS => &[
+                "SHIFTJIS",
+                "SHIFT-JIS",
+                "SJIS",
+                "MS_Kanji",
+                "csShiftJIS",
+                "Windows-31J",
+                "x-sjis",
+            ],
+            Encoding::EUC_JP => &["EUCJP", "EUC_JP", "x-euc-jp", "csEUCPkdFmtJapanese"],
+            Encoding::GB2312 => &[
+                "chinese",
+                "GB_2312-80",
+                "iso-ir-58",
+                "csGB2312",
+                "csISO58GB231280",
+            ],
+            Encoding::BIG5 => &["Big5-Tw", "cn-big5", "csBig5", "x-x-big5"],
+            Encoding::EUC_KR => &[
+                "EUCKR",
+                "EUC_KR",
+                "korean",
+                "KS_C_5601-1987",
+                "KS_C_5601-1989",
+                "KSC5601",
+                "iso-ir-149",
+                "csEUCKR",
+                "csKSC56011987",
+                "windows-949",
+            ],
+            Encoding::Custom(_) => &[],
+        }
+    }
+
+    /// Look up a built-in encoding by a label from a configuration file, MIME
+    /// header, HTML `charset` or similar
+    ///
+    /// The label may be the [`name`](Encoding::name), one of the
+    /// [`aliases`](Encoding::aliases) (which include the IANA and WHATWG labels),
+    /// or a code page number, bare or after `CP`, `IBM` or `CCSID`. Case,
+    /// surrounding whitespace and punctuation are ignored, so `iso_8859-1`,
+    /// `ISO8859-1` and `Latin-1` all match.
+    ///
+    /// Unlike the WHATWG Encoding Standard, labels are not widened to a superset:
+    /// `iso-8859-1` and `us-ascii` mean exactly those encodings, not Windows-1252.
+    ///
+    /// ```
+    /// use fast_encode::Encoding;
+    ///
+    /// assert_eq!(Encoding::for_label(" Shift_JIS "), Some(Encoding::SHIFT_JIS));
+    /// assert_eq!(Encoding::for_label("csISOLatinCyrillic"), Some(Encoding::ISO_8859_5));
+    /// assert_eq!(Encoding::for_label("IBM-1047"), Some(Encoding::EBCDIC_1047));
+    /// assert_eq!(Encoding::for_label("cp65001"), Some(Encoding::UTF8));
+    /// assert_eq!(Encoding::for_label("klingon"), None);
+    /// ```
+    pub fn for_label(label: &str) -> Option<Encoding> {
+        let key = normalize_label(label);
+        if key.is_empty() {
+            return None;
         }
+
+        let by_name = Encoding::all().iter().copied().find(|encoding| {
+            std::iter::once(encoding.name())
+                .chain(encoding.aliases().iter().copied())
+                .any(|candidate| normalize_label(candidate) == key)
+        });
+
+        by_name.or_else(|| {
+            let number = ["CP", "IBM", "CCSID"]
+                .iter()
+                .find_map(|prefix| key.strip_prefix(prefix))
+                .unwrap_or(&key);
+            if !number.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            Encoding::from_code_page(number.parse().ok()?)
+        })
+    }
+
+    /// Windows code page number of this encoding, or the IBM CCSID for EBCDIC
+    ///
+    /// Double-byte encodings report the Windows code page of their usual
+    /// Microsoft variant (932 for Shift_JIS, 936 for GB2312, 950 for Big5).
+    pub fn code_page(self) -> Option<u16> {
+        let code_page = match self {
+            Encoding::UTF8 => 65001,
+            Encoding::UTF16LE => 1200,
+            Encoding::UTF16BE => 1201,
+            Encoding::UTF32LE => 12000,
+            Encoding::UTF32BE => 12001,
+            Encoding::ASCII => 20127,
+            Encoding::ISO_8859_1 => 28591,
+            Encoding::ISO_8859_2 => 28592,
+            Encoding::ISO_8859_3 => 28593,
+            Encoding::ISO_8859_4 => 28594,
+            Encoding::ISO_8859_5 => 28595,
+            Encoding::ISO_8859_6 => 28596,
+            Encoding::ISO_8859_7 => 28597,
+            Encoding::ISO_8859_8 => 28598,
+            Encoding::ISO_8859_9 => 28599,
+            Encoding::ISO_8859_13 => 28603,
+            Encoding::ISO_8859_15 => 28605,
+            Encoding::WINDOWS_1250 => 1250,
+            Encoding::WINDOWS_1251 => 1251,
+            Encoding::WINDOWS_1252 => 1252,
+            Encoding::WINDOWS_1253 => 1253,
+            Encoding::WINDOWS_1254 => 1254,
+            Encoding::WINDOWS_1255 => 1255,
+            Encoding::WINDOWS_1256 => 1256,
+            Encoding::WINDOWS_1257 => 1257,
+            Encoding::WINDOWS_1258 => 1258,
+            Encoding::WINDOWS_874 => 874,
+            Encoding::EBCDIC_037 => 37,
+            Encoding::EBCDIC_273 => 273,
+            Encoding::EBCDIC_277 => 277,
+            Encoding::EBCDIC_278 => 278,
+            Encoding::EBCDIC_280 => 280,
+            Encoding::EBCDIC_284 => 284,
+            Encoding::EBCDIC_285 => 285,
+            Encoding::EBCDIC_297 => 297,
+            Encoding::EBCDIC_500 => 500,
+            Encoding::EBCDIC_1047 => 1047,
+            Encoding::CP_437 => 437,
+            Encoding::CP_850 => 850,
+            Encoding::CP_852 => 852,
+            Encoding::CP_855 => 855,
+            Encoding::CP_857 => 857,
+            Encoding::CP_860 => 860,
+            Encoding::CP_861 => 861,
+            Encoding::CP_862 => 862,
+            Encoding::CP_863 => 863,
+            Encoding::CP_865 => 865,
+            Encoding::CP_866 => 866,
+            Encoding::KOI8_R => 20866,
+            Encoding::MAC_ROMAN => 10000,
+            Encoding::MAC_CYRILLIC => 10007,
+            Encoding::SHIFT_JIS => 932,
+            Encoding::EUC_JP => 51932,
+            Encoding::GB2312 => 936,
+            Encoding::BIG5 => 950,
+            Encoding::EUC_KR => 51949,
+            Encoding::ISO_8859_10
+            | Encoding::ISO_8859_11
+            | Encoding::ISO_8859_14
+            | Encoding::ISO_8859_16
+            | Encoding::Custom(_) => return None,
+        };
+        Some(code_page)
+    }
+
+    /// Look up a built-in encoding by Windows code page number or IBM CCSID
+    ///
+    /// Accepts every number [`code_page`](Encoding::code_page) returns, plus the
+    /// IBM CCSIDs of the ISO-8859 and Unicode encodings (819 for ISO-8859-1,
+    /// 1208 for UTF-8, ...).
+    pub fn from_code_page(code_page: u16) -> Option<Encoding> {
+        Encoding::all()
+            .iter()
+            .copied()
+            .find(|encoding| encoding.code_page() == Some(code_page))
+            .or_else(|| {
+                IBM_CCSIDS
+                    .iter()
+                    .find(|&&(ccsid, _)| ccsid == code_page)
+                    .map(|&(_, encoding)| encoding)
+            })
     }
 
     /// Check if this encoding is ASCII-compatible (ASCII bytes 0-127 have same meaning)
     pub fn is_ascii_compatible(self) -> bool {
+        if let Encoding::Custom(page) = self {
+            return page.is_ascii_compatible();
+        }
+
         matches!(
             self,
             // Unicode encodings
@@ -335,6 +886,8 @@ impl Encoding {
             Encoding::CP_437 | Encoding::CP_850 | Encoding::CP_852 | Encoding::CP_855 |
             Encoding::CP_857 | Encoding::CP_860 | Encoding::CP_861 | Encoding::CP_862 |
             Encoding::CP_863 | Encoding::CP_865 | Encoding::CP_866 |
+            // KOI8 (ASCII-compatible)
+            Encoding::KOI8_R |
             // Mac encodings (ASCII-compatible)
             Encoding::MAC_ROMAN | Encoding::MAC_CYRILLIC
         )
@@ -347,6 +900,8 @@ impl Encoding {
             Encoding::UTF8
                 | Encoding::UTF16LE
                 | Encoding::UTF16BE
+                | Encoding::UTF32LE
+                | Encoding::UTF32BE
                 | Encoding::SHIFT_JIS
                 | Encoding::EUC_JP
                 | Encoding::GB2312
@@ -361,15 +916,316 @@ impl Encoding {
             Encoding::UTF8 => Some(&[0xEF, 0xBB, 0xBF]),
             Encoding::UTF16LE => Some(&[0xFF, 0xFE]),
             Encoding::UTF16BE => Some(&[0xFE, 0xFF]),
+            Encoding::UTF32LE => Some(&[0xFF, 0xFE, 0x00, 0x00]),
+            Encoding::UTF32BE => Some(&[0x00, 0x00, 0xFE, 0xFF]),
+            _ => None,
+        }
+    }
+
+    /// Character that `byte` decodes to on its own
+    ///
+    /// `None` if the byte is unassigned or only part of a longer sequence: every
+    /// byte of UTF-16 and UTF-32, and the non-ASCII bytes of UTF-8 and the
+    /// double-byte encodings.
+    ///
+    /// ```
+    /// use fast_encode::Encoding;
+    ///
+    /// assert_eq!(Encoding::EBCDIC_037.decode_byte(0xC1), Some('A'));
+    /// assert_eq!(Encoding::WINDOWS_1252.decode_byte(0x80), Some('€'));
+    /// assert_eq!(Encoding::WINDOWS_1252.decode_byte(0x81), None);
+    /// ```
+    pub fn decode_byte(self, byte: u8) -> Option<char> {
+        if multibyte::wide_layout(self).is_some() {
+            return None;
+        }
+        tables::get_encoding_chars(self)[byte as usize]
+    }
+
+    /// Single byte that encodes `ch`, if there is one
+    ///
+    /// The inverse of [`decode_byte`](Self::decode_byte); where several bytes
+    /// decode to `ch`, the lowest is returned.
+    ///
+    /// ```
+    /// use fast_encode::Encoding;
+    ///
+    /// assert_eq!(Encoding::EBCDIC_037.encode_char('a'), Some(0x81));
+    /// assert_eq!(Encoding::EBCDIC_037.encode_char('€'), None);
+    /// ```
+    pub fn encode_char(self, ch: char) -> Option<u8> {
+        if multibyte::wide_layout(self).is_some() {
+            return None;
+        }
+        tables::get_encoding_chars(self)
+            .iter()
+            .position(|&c| c == Some(ch))
+            .map(|byte| byte as u8)
+    }
+}
+
+/// IBM CCSIDs accepted by [`Encoding::from_code_page`] besides the Windows numbers
+const IBM_CCSIDS: &[(u16, Encoding)] = &[
+    (367, Encoding::ASCII),
+    (813, Encoding::ISO_8859_7),
+    (819, Encoding::ISO_8859_1),
+    (878, Encoding::KOI8_R),
+    (912, Encoding::ISO_8859_2),
+    (913, Encoding::ISO_8859_3),
+    (914, Encoding::ISO_8859_4),
+    (915, Encoding::ISO_8859_5),
+    (916, Encoding::ISO_8859_8),
+    (920, Encoding::ISO_8859_9),
+    (923, Encoding::ISO_8859_15),
+    (1089, Encoding::ISO_8859_6),
+    (1208, Encoding::UTF8),
+    (1275, Encoding::MAC_ROMAN),
+];
+
+/// Fold a label for comparison: upper case, letters and digits only
+fn normalize_label(label: &str) -> String {
+    label
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|ch| ch.to_ascii_uppercase())
+        .collect()
+}
+
+/// Built-in encodings returned by [`Encoding::all`]
+const ALL_ENCODINGS: &[Encoding] = &[
+    Encoding::UTF8,
+    Encoding::UTF16LE,
+    Encoding::UTF16BE,
+    Encoding::UTF32LE,
+    Encoding::UTF32BE,
+    Encoding::ASCII,
+    Encoding::ISO_8859_1,
+    Encoding::ISO_8859_2,
+    Encoding::ISO_8859_3,
+    Encoding::ISO_8859_4,
+    Encoding::ISO_8859_5,
+    Encoding::ISO_8859_6,
+    Encoding::ISO_8859_7,
+    Encoding::ISO_8859_8,
+    Encoding::ISO_8859_9,
+    Encoding::ISO_8859_10,
+    Encoding::ISO_8859_11,
+    Encoding::ISO_8859_13,
+    Encoding::ISO_8859_14,
+    Encoding::ISO_8859_15,
+    Encoding::ISO_8859_16,
+    Encoding::WINDOWS_1250,
+    Encoding::WINDOWS_1251,
+    Encoding::WINDOWS_1252,
+    Encoding::WINDOWS_1253,
+    Encoding::WINDOWS_1254,
+    Encoding::WINDOWS_1255,
+    Encoding::WINDOWS_1256,
+    Encoding::WINDOWS_1257,
+    Encoding::WINDOWS_1258,
+    Encoding::WINDOWS_874,
+    Encoding::EBCDIC_037,
+    Encoding::EBCDIC_273,
+    Encoding::EBCDIC_277,
+    Encoding::EBCDIC_278,
+    Encoding::EBCDIC_280,
+    Encoding::EBCDIC_284,
+    Encoding::EBCDIC_285,
+    Encoding::EBCDIC_297,
+    Encoding::EBCDIC_500,
+    Encoding::EBCDIC_1047,
+    Encoding::CP_437,
+    Encoding::CP_850,
+    Encoding::CP_852,
+    Encoding::CP_855,
+    Encoding::CP_857,
+    Encoding::CP_860,
+    Encoding::CP_861,
+    Encoding::CP_862,
+    Encoding::CP_863,
+    Encoding::CP_865,
+    Encoding::CP_866,
+    Encoding::KOI8_R,
+    Encoding::MAC_ROMAN,
+    Encoding::MAC_CYRILLIC,
+    Encoding::SHIFT_JIS,
+    Encoding::EUC_JP,
+    Encoding::GB2312,
+    Encoding::BIG5,
+    Encoding::EUC_KR,
+];
+
+/// Implementation used for bulk table lookups
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// Portable byte-at-a-time lookup
+    Scalar,
+    /// Vectorized lookup with AVX2, SSSE3 or NEON, picked at runtime
+    /// (requires the `simd` feature)
+    Simd,
+}
+
+impl Backend {
+    /// Get the name of this backend
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Scalar => "scalar",
+            Backend::Simd => "simd",
+        }
+    }
+
+    /// Vector instruction set this backend uses on the running CPU
+    ///
+    /// `None` for [`Backend::Scalar`], and for [`Backend::Simd`] on CPUs
+    /// without a supported instruction set, where it runs the scalar loop.
+    pub fn instruction_set(self) -> Option<&'static str> {
+        match self {
+            #[cfg(feature = "simd")]
+            Backend::Simd => simd::instruction_set(),
             _ => None,
         }
     }
+
+    /// Backends compiled into this build
+    pub fn available() -> &'static [Backend] {
+        #[cfg(feature = "simd")]
+        {
+            &[Backend::Scalar, Backend::Simd]
+        }
+
+        #[cfg(not(feature = "simd"))]
+        {
+            &[Backend::Scalar]
+        }
+    }
+
+    /// Backend used by [`TranslationTable::translate`]
+    pub fn default_backend() -> Backend {
+        if cfg!(feature = "simd") {
+            Backend::Simd
+        } else {
+            Backend::Scalar
+        }
+    }
+}
+
+/// How conversion errors are handled
+///
+/// The names follow Python's codec error handlers. Bytes that cannot be decoded
+/// and characters that cannot be encoded are both handed to the policy.
+///
+/// Two [`ErrorPolicy::Custom`] policies compare equal when they hold the same
+/// function pointer, which the compiler does not guarantee for the same function.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[allow(unpredictable_function_pointer_comparisons)]
+pub enum ErrorPolicy {
+    /// Fail on the first error
+    #[default]
+    Strict,
+    /// Substitute U+FFFD, or `?` if the target cannot encode it
+    Replace,
+    /// Substitute the given character
+    ReplaceWithChar(char),
+    /// Substitute the given string, which may be empty or contain any characters
+    /// the target can encode
+    ReplaceWithStr(Arc<str>),
+    /// Drop the offending input (also known as skipping it)
+    Ignore,
+    /// Substitute a backslash escape (`\xNN` for undecodable bytes, `\uXXXX` for characters)
+    BackslashReplace,
+    /// Substitute an XML character reference (`&#NNNN;`) for unencodable characters;
+    /// undecodable bytes are replaced as with [`ErrorPolicy::Replace`]
+    XmlCharRefReplace,
+    /// Substitute whatever the function returns for each error; an empty string
+    /// drops the offending input
+    ///
+    /// The substitution must be encodable in the target encoding.
+    Custom(fn(Unmappable<'_>) -> String),
+    /// Substitute an approximation from the transliterator's table ("€" as
+    /// "EUR", "œ" as "oe") and fall back to its own policy for characters
+    /// without one the target can encode
+    ///
+    /// Undecodable bytes go straight to the fallback. See [`translit`].
+    Transliterate(Arc<translit::Transliterator>),
+}
+
+/// An error handed to [`ErrorPolicy::Custom`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unmappable<'a> {
+    /// Bytes that do not form a character in the source encoding
+    Source {
+        /// The undecodable bytes
+        bytes: &'a [u8],
+        /// Offset of the bytes in the input
+        position: usize,
+    },
+    /// A character the target encoding cannot represent
+    Target {
+        /// The unencodable character
+        character: char,
+        /// Offset of the character's source bytes in the input
+        position: usize,
+    },
+}
+
+/// Line endings written by a streaming conversion
+///
+/// Every CR, LF, CRLF and NEL in the converted output is rewritten to the chosen
+/// newline, which must be representable in the target encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum NewlineMode {
+    /// Leave line endings as they are
+    #[default]
+    Preserve,
+    /// Line feed (Unix)
+    Lf,
+    /// Carriage return + line feed (Windows)
+    CrLf,
+    /// Carriage return (classic Mac OS)
+    Cr,
+    /// Next line, U+0085 (EBCDIC)
+    Nel,
+}
+
+/// Control characters in converted output
+///
+/// Covers the C0 and C1 controls except tab and the line breaks handled by
+/// [`NewlineMode`] (CR, LF and NEL). A replacement must be representable in the
+/// target encoding; CR, LF or NEL as the replacement is written as the chosen
+/// newline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ControlMode {
+    /// Leave control characters as they are
+    #[default]
+    Preserve,
+    /// Drop control characters
+    Strip,
+    /// Substitute the given character
+    Replace(char),
+}
+
+/// Unpaired surrogates in UTF-16 or UTF-32 input
+///
+/// A high surrogate without a following low surrogate, or a low surrogate on
+/// its own, is not a character. Windows file names and some JavaScript strings
+/// contain them anyway. The mode applies to strict conversion; the fallback of
+/// an [`ErrorPolicy`] treats them as undecodable input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SurrogateMode {
+    /// Fail with [`Error::InvalidInput`] naming the surrogate and its byte position
+    #[default]
+    Reject,
+    /// Substitute U+FFFD REPLACEMENT CHARACTER
+    Replace,
+    /// Keep the surrogate: as is in UTF-16 and UTF-32 output, as its
+    /// three-byte WTF-8 form in UTF-8 output; other targets still fail
+    Preserve,
 }
 
 /// Pre-computed translation table for ultra-fast byte-to-byte conversion
 #[derive(Debug, Clone)]
 pub struct TranslationTable {
-    /// Direct lookup table: source_byte -> target_byte (0xFF = unmappable)
+    /// Direct lookup table: source_byte -> target_byte (unmappable bytes are flagged in `unmappable_mask`)
     table: [u8; 256],
     /// Bitmask of unmappable bytes for fast checking
     unmappable_mask: [u64; 4], // 256 bits = 4 u64s
@@ -389,27 +1245,34 @@ impl TranslationTable {
             });
         }
 
-        let from_chars = tables::get_encoding_chars(from);
-        let to_chars = tables::get_encoding_chars(to);
+        Ok(Self::from_chars(
+            tables::get_encoding_chars(from),
+            tables::get_encoding_chars(to),
+        ))
+    }
 
+    /// Build a table from the byte-to-Unicode tables of both sides
+    pub(crate) fn from_chars(
+        from_chars: &[Option<char>; 256],
+        to_chars: &[Option<char>; 256],
+    ) -> Self {
         // Build reverse lookup for target encoding
-        let mut to_lookup = [0xFFu8; 65536]; // Unicode code point -> byte
-        for (byte, &ch_opt) in to_chars.iter().enumerate() {
-            if let Some(ch) = ch_opt {
-                if (ch as u32) < 65536 {
-                    to_lookup[ch as usize] = byte as u8;
-                }
+        let mut to_lookup = vec![None; 65536]; // Unicode code point -> byte
+        for (byte, &ch_opt) in to_chars.iter().enumerate().rev() {
+            if let Some(ch) = ch_opt
+                && (ch as u32) < 65536
+            {
+                to_lookup[ch as usize] = Some(byte as u8);
             }
         }
 
         // Build translation table
-        let mut table = [0xFFu8; 256]; // 0xFF = unmappable marker
+        let mut table = [0xFFu8; 256]; // Placeholder for unmappable bytes
         let mut unmappable_mask = [0u64; 4];
 
         for (src_byte, &ch_opt) in from_chars.iter().enumerate() {
             if let Some(ch) = ch_opt {
-                let target_byte = to_lookup[ch as usize];
-                if target_byte != 0xFF {
+                if let Some(&Some(target_byte)) = to_lookup.get(ch as usize) {
                     table[src_byte] = target_byte;
                 } else {
                     // Mark as unmappable in bitmask
@@ -425,10 +1288,10 @@ impl TranslationTable {
             }
         }
 
-        Ok(Self {
+        Self {
             table,
             unmappable_mask,
-        })
+        }
     }
 
     /// Check if a byte is mappable
@@ -448,15 +1311,39 @@ impl TranslationTable {
     /// Translate bytes with error checking
     pub fn translate(&self, input: &[u8]) -> Result<Vec<u8>> {
         let mut output = Vec::with_capacity(input.len());
+        self.translate_into(input, &mut output)?;
+        Ok(output)
+    }
+
+    /// Translate bytes, appending them to `output`
+    ///
+    /// On error `output` is left as it was.
+    pub fn translate_into(&self, input: &[u8], output: &mut Vec<u8>) -> Result<()> {
+        let start = output.len();
+        output.reserve(input.len());
 
         #[cfg(feature = "simd")]
-        {
-            self.translate_simd(input, &mut output)?;
-        }
+        let result = self.translate_simd(input, output);
 
         #[cfg(not(feature = "simd"))]
-        {
-            self.translate_scalar(input, &mut output)?;
+        let result = self.translate_scalar(input, output);
+
+        if result.is_err() {
+            output.truncate(start);
+        }
+        result
+    }
+
+    /// Translate bytes using a specific backend
+    ///
+    /// Falls back to the scalar implementation if `backend` is not compiled in.
+    pub fn translate_with(&self, input: &[u8], backend: Backend) -> Result<Vec<u8>> {
+        let mut output = Vec::with_capacity(input.len());
+
+        match backend {
+            #[cfg(feature = "simd")]
+            Backend::Simd => self.translate_simd(input, &mut output)?,
+            _ => self.translate_scalar(input, &mut output)?,
         }
 
         Ok(output)
@@ -475,6 +1362,36 @@ impl TranslationTable {
         }
     }
 
+    /// Translate bytes on the rayon thread pool, `chunk_size` bytes per task
+    ///
+    /// Fails with the first unmappable byte in input order.
+    #[cfg(feature = "rayon")]
+    pub fn translate_parallel(&self, input: &[u8], chunk_size: usize) -> Result<Vec<u8>> {
+        let mut output = input.to_vec();
+        self.translate_in_place_parallel(&mut output, chunk_size)?;
+        Ok(output)
+    }
+
+    /// Translate in place on the rayon thread pool, `chunk_size` bytes per task
+    ///
+    /// Fails with the first unmappable byte in input order; the buffer is then
+    /// partly translated.
+    #[cfg(feature = "rayon")]
+    pub fn translate_in_place_parallel(&self, buffer: &mut [u8], chunk_size: usize) -> Result<()> {
+        use rayon::prelude::*;
+
+        let chunk_size = chunk_size.max(1);
+        buffer
+            .par_chunks_mut(chunk_size)
+            .enumerate()
+            .map(|(index, chunk)| {
+                self.translate_in_place(chunk)
+                    .map_err(|e| e.offset_by(index * chunk_size))
+            })
+            .find_first(Result::is_err)
+            .unwrap_or(Ok(()))
+    }
+
     // Scalar implementation
     fn translate_scalar(&self, input: &[u8], output: &mut Vec<u8>) -> Result<()> {
         for (pos, &byte) in input.iter().enumerate() {
@@ -502,146 +1419,82 @@ impl TranslationTable {
         Ok(())
     }
 
-    // SIMD implementations
+    /// Vector lookups for as much of `input` as they can take, the scalar loop for the rest
     #[cfg(feature = "simd")]
     fn translate_simd(&self, input: &[u8], output: &mut Vec<u8>) -> Result<()> {
-        const CHUNK_SIZE: usize = 32;
-        let chunks = input.chunks_exact(CHUNK_SIZE);
-        let remainder = chunks.remainder();
-
-        // Process 32-byte chunks with AVX2-style SIMD
-        for (chunk_idx, chunk) in chunks.enumerate() {
-            let chunk_pos = chunk_idx * CHUNK_SIZE;
-            let input_vec = u8x32::from_slice(chunk);
-
-            // Check for unmappable bytes using SIMD comparison
-            if self.has_unmappable_simd(input_vec, chunk_pos)? {
-                return Err(Error::UnmappableSource {
-                    byte: 0, // Would need more complex logic to find exact byte
-                    position: chunk_pos,
-                });
-            }
-
-            // Perform vectorized translation using gather operation
-            let translated = self.translate_chunk_simd(input_vec);
-            output.extend_from_slice(translated.as_array());
-        }
-
-        // Handle remainder with scalar code
-        let remainder_start = input.len() - remainder.len();
-        for (i, &byte) in remainder.iter().enumerate() {
-            let pos = remainder_start + i;
-            if !self.is_mappable(byte) {
-                return Err(Error::UnmappableSource {
-                    byte,
-                    position: pos,
-                });
-            }
-            output.push(self.translate_byte_unchecked(byte));
-        }
-
-        Ok(())
+        let done = simd::translate_into(self, input, output);
+        self.translate_scalar(&input[done..], output)
+            .map_err(|e| e.offset_by(done))
     }
 
     #[cfg(feature = "simd")]
     fn translate_in_place_simd(&self, buffer: &mut [u8]) -> Result<()> {
-        const CHUNK_SIZE: usize = 32;
-        let len = buffer.len();
-        let (chunks, remainder) = buffer.split_at_mut(len - (len % CHUNK_SIZE));
-
-        // Process chunks
-        for (chunk_idx, chunk) in chunks.chunks_exact_mut(CHUNK_SIZE).enumerate() {
-            let chunk_pos = chunk_idx * CHUNK_SIZE;
-            let input_vec = u8x32::from_slice(chunk);
-
-            // Check for unmappable bytes
-            if self.has_unmappable_simd(input_vec, chunk_pos)? {
-                return Err(Error::UnmappableSource {
-                    byte: 0,
-                    position: chunk_pos,
-                });
-            }
-
-            // Translate and store back
-            let translated = self.translate_chunk_simd(input_vec);
-            chunk.copy_from_slice(translated.as_array());
-        }
-
-        // Handle remainder
-        let remainder_start = len - remainder.len();
-        for (i, byte) in remainder.iter_mut().enumerate() {
-            let pos = remainder_start + i;
-            if !self.is_mappable(*byte) {
-                return Err(Error::UnmappableSource {
-                    byte: *byte,
-                    position: pos,
-                });
-            }
-            *byte = self.translate_byte_unchecked(*byte);
-        }
-
-        Ok(())
-    }
-
-    #[cfg(feature = "simd")]
-    fn has_unmappable_simd(&self, input: u8x32, _chunk_pos: usize) -> Result<bool> {
-        // This is a simplified check - in a real implementation, we'd use
-        // vectorized bit manipulation to check the unmappable_mask
-        // For now, fall back to scalar checking
-        for &byte in input.as_array() {
-            if !self.is_mappable(byte) {
-                return Ok(true);
-            }
-        }
-        Ok(false)
-    }
-
-    #[cfg(feature = "simd")]
-    fn translate_chunk_simd(&self, input: u8x32) -> u8x32 {
-        // SIMD gather operation - translate 32 bytes at once
-        // This uses the fact that our translation table is exactly 256 bytes
-        let mut result = [0u8; 32];
-
-        for (i, &byte) in input.as_array().iter().enumerate() {
-            result[i] = self.translate_byte_unchecked(byte);
-        }
-
-        u8x32::from_array(result)
+        let done = simd::translate_in_place(self, buffer);
+        self.translate_in_place_scalar(&mut buffer[done..])
+            .map_err(|e| e.offset_by(done))
     }
 }
 
 /// High-level encoding converter with streaming support
+#[derive(Clone)]
 pub struct Translator {
     table: Option<TranslationTable>,
     multibyte: Option<multibyte::MultiByte>,
     from: Encoding,
     to: Encoding,
+    /// Policy applied by [`convert`](Self::convert)
+    policy: ErrorPolicy,
+    /// Line-ending and control character rewriting (`None` leaves output as converted)
+    normalizer: Option<newline::NewlineNormalizer>,
 }
 
 impl Translator {
     /// Create a new translator between two encodings
     pub fn new(from: Encoding, to: Encoding) -> Result<Self> {
-        // Check if we need multi-byte conversion (involves UTF-8, UTF-16, or other multibyte encodings)
-        if from.is_multibyte() || to.is_multibyte() {
+        // Check if we need multi-byte conversion (involves UTF-8, UTF-16, or other multibyte encodings).
+        // Windows-1258 composes and decomposes tone marks, so it cannot be a byte-for-byte table either.
+        if from.is_multibyte()
+            || to.is_multibyte()
+            || vietnamese::composes(from)
+            || vietnamese::composes(to)
+        {
             let multibyte = multibyte::MultiByte::new(from, to);
+            telemetry::translator_created(from, to, "multibyte");
             Ok(Self {
                 table: None,
                 multibyte: Some(multibyte),
                 from,
                 to,
+                policy: ErrorPolicy::Strict,
+                normalizer: None,
             })
         } else {
             // Single-byte to single-byte conversion
             let table = TranslationTable::new(from, to)?;
+            telemetry::translator_created(from, to, "table");
             Ok(Self {
                 table: Some(table),
                 multibyte: None,
                 from,
                 to,
+                policy: ErrorPolicy::Strict,
+                normalizer: None,
             })
         }
     }
 
+    /// Configure a translator, e.g. with an error policy, before creating it
+    pub fn builder(from: Encoding, to: Encoding) -> TranslatorBuilder {
+        TranslatorBuilder {
+            from,
+            to,
+            policy: ErrorPolicy::Strict,
+            newline: NewlineMode::Preserve,
+            controls: ControlMode::Preserve,
+            surrogates: SurrogateMode::Reject,
+        }
+    }
+
     /// Get source encoding
     pub fn from_encoding(&self) -> Encoding {
         self.from
@@ -652,8 +1505,61 @@ impl Translator {
         self.to
     }
 
+    /// Policy [`convert`](Self::convert) applies to unmappable input
+    pub fn policy(&self) -> &ErrorPolicy {
+        &self.policy
+    }
+
+    /// Line endings written by [`convert`](Self::convert)
+    pub fn newline_mode(&self) -> NewlineMode {
+        self.normalizer
+            .as_ref()
+            .map_or(NewlineMode::Preserve, |normalizer| normalizer.mode())
+    }
+
+    /// Control character handling applied by [`convert`](Self::convert)
+    pub fn control_mode(&self) -> ControlMode {
+        self.normalizer
+            .as_ref()
+            .map_or(ControlMode::Preserve, |normalizer| normalizer.controls())
+    }
+
+    /// Handling of unpaired surrogates in UTF-16 or UTF-32 input
+    pub fn surrogate_mode(&self) -> SurrogateMode {
+        self.multibyte
+            .as_ref()
+            .map_or(SurrogateMode::Reject, |multibyte| multibyte.surrogates())
+    }
+
+    /// Rewrite line endings and control characters in a complete output
+    fn normalize(&self, output: Vec<u8>) -> Vec<u8> {
+        match self.normalizer {
+            Some(ref normalizer) => normalizer.clone().normalize(&output),
+            None => output,
+        }
+    }
+
+    /// Fail if the output needs rewriting that can change its length
+    fn check_in_place(&self) -> Result<()> {
+        if self.normalizer.is_some() {
+            return Err(Error::InvalidInput(
+                "In-place conversion cannot rewrite line endings or control characters".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     /// Convert data from source to target encoding
+    ///
+    /// Fails on the first unmappable byte or character unless the translator
+    /// was built with another [`policy`](TranslatorBuilder::policy).
     pub fn convert(&self, input: &[u8]) -> Result<Vec<u8>> {
+        self.convert_with_policy_counted(input, &self.policy)
+            .map(|(output, _)| self.normalize(output))
+    }
+
+    /// [`convert`](Self::convert) without reporting to telemetry, for callers that report themselves
+    fn convert_unrecorded(&self, input: &[u8]) -> Result<Vec<u8>> {
         if let Some(ref table) = self.table {
             table.translate(input)
         } else if let Some(ref multibyte) = self.multibyte {
@@ -666,13 +1572,77 @@ impl Translator {
         }
     }
 
-    /// Convert data in-place (destructive)
+    /// Convert data using a specific table lookup backend
     ///
-    /// Note: This only works for single-byte to single-byte conversions.
-    /// Multi-byte conversions (involving UTF-8) cannot be done in-place due to variable lengths.
-    pub fn convert_in_place(&self, buffer: &mut [u8]) -> Result<()> {
+    /// The backend only applies to single-byte conversions; multi-byte
+    /// conversions always take the regular path.
+    pub fn convert_with(&self, input: &[u8], backend: Backend) -> Result<Vec<u8>> {
+        let Some(ref table) = self.table else {
+            return self.convert(input);
+        };
+
+        let conversion = telemetry::Conversion::start(self.from, self.to, input.len());
+        let result = match table.translate_with(input, backend) {
+            Err(_) if self.policy != ErrorPolicy::Strict => {
+                policy::convert(self.from, self.to, input, &self.policy)
+            }
+            result => result.map(|output| (output, 0)),
+        };
+        let replacements = result.as_ref().map_or(0, |&(_, handled)| handled);
+        conversion.finish(replacements, result.as_ref().err());
+        result.map(|(output, _)| self.normalize(output))
+    }
+
+    /// Convert data, appending it to `output`
+    ///
+    /// Reusing one `output` across calls saves an allocation per conversion:
+    /// clear it between calls and it keeps its capacity. Single-byte
+    /// conversions, and decoding single-byte data to UTF-8, write straight
+    /// into `output`; other conversions go through an intermediate buffer.
+    /// On error `output` is left as it was.
+    ///
+    /// ```
+    /// use fast_encode::{Encoding, Translator};
+    ///
+    /// let translator = Translator::new(Encoding::EBCDIC_037, Encoding::UTF8).unwrap();
+    /// let mut output = Vec::with_capacity(translator.max_output_len(5));
+    /// for record in [&[0xC8, 0x85, 0x93, 0x93, 0x96][..], &[0xE6, 0x96, 0x99, 0x93, 0x84]] {
+    ///     output.clear();
+    ///     translator.convert_into(record, &mut output).unwrap();
+    /// }
+    /// assert_eq!(output, b"World");
+    /// ```
+    pub fn convert_into(&self, input: &[u8], output: &mut Vec<u8>) -> Result<()> {
+        let conversion = telemetry::Conversion::start(self.from, self.to, input.len());
+        let start = output.len();
+        let result = match self.convert_into_unrecorded(input, output) {
+            Err(_) if self.policy != ErrorPolicy::Strict => {
+                output.truncate(start);
+                policy::convert(self.from, self.to, input, &self.policy).map(
+                    |(converted, handled)| {
+                        output.extend_from_slice(&converted);
+                        handled
+                    },
+                )
+            }
+            result => result.map(|()| 0),
+        };
+
+        if result.is_err() {
+            output.truncate(start);
+        } else if self.normalizer.is_some() {
+            let converted = output.split_off(start);
+            output.extend(self.normalize(converted));
+        }
+        conversion.finish(*result.as_ref().unwrap_or(&0), result.as_ref().err());
+        result.map(|_| ())
+    }
+
+    fn convert_into_unrecorded(&self, input: &[u8], output: &mut Vec<u8>) -> Result<()> {
         if let Some(ref table) = self.table {
-            table.translate_in_place(buffer)
+            table.translate_into(input, output)
+        } else if let Some(ref multibyte) = self.multibyte {
+            multibyte.convert_into(input, output)
         } else {
             Err(Error::UnsupportedConversion {
                 from: self.from.name(),
@@ -681,54 +1651,420 @@ impl Translator {
         }
     }
 
-    /// Convert with custom error handling  
+    /// Convert data into `output`, returning the number of bytes written
     ///
-    /// Note: This only works for single-byte to single-byte conversions.
-    /// For multi-byte conversions, use `convert()` and handle errors manually.
-    pub fn convert_lossy(&self, input: &[u8], replacement: u8) -> Vec<u8> {
-        if let Some(ref table) = self.table {
-            let mut output = Vec::with_capacity(input.len());
+    /// Fails with [`Error::OutputTooSmall`] if `output` cannot hold the
+    /// result; [`max_output_len`](Self::max_output_len) gives a size that
+    /// always can. Single-byte conversions translate straight into `output`;
+    /// others are converted first and then copied.
+    pub fn convert_to_slice(&self, input: &[u8], output: &mut [u8]) -> Result<usize> {
+        if self.table.is_some() && self.policy == ErrorPolicy::Strict && self.normalizer.is_none() {
+            let Some(target) = output.get_mut(..input.len()) else {
+                return Err(Error::OutputTooSmall {
+                    needed: input.len(),
+                    available: output.len(),
+                });
+            };
+            target.copy_from_slice(input);
+            self.convert_in_place(target)?;
+            return Ok(input.len());
+        }
 
-            for &byte in input {
-                if table.is_mappable(byte) {
-                    output.push(table.translate_byte_unchecked(byte));
-                } else {
-                    output.push(replacement);
-                }
-            }
+        let converted = self.convert(input)?;
+        let Some(target) = output.get_mut(..converted.len()) else {
+            return Err(Error::OutputTooSmall {
+                needed: converted.len(),
+                available: output.len(),
+            });
+        };
+        target.copy_from_slice(&converted);
+        Ok(converted.len())
+    }
+
+    /// Largest number of bytes converting `input_len` bytes can produce
+    ///
+    /// The bound holds for strict conversion and for
+    /// [`Ignore`](ErrorPolicy::Ignore); replacement text written by other
+    /// policies, CRLF line endings and control character replacements can
+    /// need more room.
+    pub fn max_output_len(&self, input_len: usize) -> usize {
+        let is_utf16 = |encoding| matches!(encoding, Encoding::UTF16LE | Encoding::UTF16BE);
+        let is_utf32 = |encoding| matches!(encoding, Encoding::UTF32LE | Encoding::UTF32BE);
+
+        // Output bytes per input bytes in the worst case
+        let (output, input) = match (self.from, self.to) {
+            (from, to) if from == to => (1, 1),
+            // No encoding needs more than four bytes for a character
+            (from, _) if is_utf32(from) => (1, 1),
+            (_, to) if is_utf32(to) => (4, 1),
+            // A two-byte unit becomes up to three bytes; a surrogate pair four
+            (from, Encoding::UTF8) if is_utf16(from) => (3, 2),
+            (from, _) if is_utf16(from) => (1, 1),
+            (Encoding::UTF8, to) if is_utf16(to) => (2, 1),
+            (Encoding::UTF8, _) => (1, 1),
+            (_, Encoding::UTF8) => (3, 1),
+            (_, to) if is_utf16(to) => (2, 1),
+            // Single bytes that become double-byte characters or base letter plus tone mark
+            (_, to) if cjk::is_double_byte(to) || vietnamese::composes(to) => (2, 1),
+            _ => (1, 1),
+        };
+        input_len.saturating_mul(output).div_ceil(input)
+    }
 
-            output
+    /// Convert data in-place (destructive)
+    ///
+    /// Note: This only works for single-byte to single-byte conversions.
+    /// Multi-byte conversions (involving UTF-8) cannot be done in-place due to variable lengths,
+    /// and neither can conversions that rewrite line endings or control characters.
+    pub fn convert_in_place(&self, buffer: &mut [u8]) -> Result<()> {
+        self.check_in_place()?;
+        if let Some(ref table) = self.table {
+            let conversion = telemetry::Conversion::start(self.from, self.to, buffer.len());
+            let result = table.translate_in_place(buffer);
+            conversion.finish(0, result.as_ref().err());
+            result
         } else {
-            // For multi-byte conversions, try convert and fall back to replacement
-            match self.convert(input) {
-                Ok(result) => result,
-                Err(_) => vec![replacement; input.len()],
-            }
+            Err(Error::UnsupportedConversion {
+                from: self.from.name(),
+                to: self.to.name(),
+            })
+        }
+    }
+
+    /// Convert on the rayon thread pool, about `chunk_size` input bytes per task
+    ///
+    /// Multi-byte input is split only between characters, so every chunk
+    /// converts exactly as it would as part of the whole. Errors are those
+    /// [`convert`](Self::convert) would report, positions included.
+    ///
+    /// ```
+    /// use fast_encode::{Encoding, Translator};
+    ///
+    /// let translator = Translator::new(Encoding::UTF8, Encoding::UTF16LE).unwrap();
+    /// let text = "Grüße, мир! ".repeat(1000);
+    /// let parallel = translator.convert_parallel(text.as_bytes(), 1024).unwrap();
+    /// assert_eq!(parallel, translator.convert(text.as_bytes()).unwrap());
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn convert_parallel(&self, input: &[u8], chunk_size: usize) -> Result<Vec<u8>> {
+        use rayon::prelude::*;
+
+        let conversion = telemetry::Conversion::start(self.from, self.to, input.len());
+        let result = match self.table {
+            Some(ref table) if self.policy == ErrorPolicy::Strict => table
+                .translate_parallel(input, chunk_size)
+                .map(|output| (output, 0)),
+            _ => {
+                let ranges = multibyte::split_at_boundaries(self.from, input, chunk_size);
+                let chunks: Vec<_> = ranges
+                    .par_iter()
+                    .map(|range| {
+                        self.convert_with_policy_unrecorded(&input[range.clone()], &self.policy)
+                    })
+                    .collect();
+
+                let mut output = Vec::with_capacity(input.len());
+                let mut replacements = 0;
+                let mut result = Ok(());
+                for (range, chunk) in ranges.iter().zip(chunks) {
+                    match chunk {
+                        Ok((converted, handled)) => {
+                            output.extend_from_slice(&converted);
+                            replacements += handled;
+                        }
+                        // Messages that quote a position are only right for
+                        // the chunk, so let a serial pass word the error
+                        Err(e @ Error::InvalidInput(_)) => {
+                            let serial = &input[..range.end];
+                            result = Err(self
+                                .convert_with_policy_unrecorded(serial, &self.policy)
+                                .err()
+                                .unwrap_or(e));
+                            break;
+                        }
+                        Err(e) => {
+                            result = Err(e.offset_by(range.start));
+                            break;
+                        }
+                    }
+                }
+                result.map(|()| (output, replacements))
+            }
+        };
+
+        let replacements = result.as_ref().map_or(0, |&(_, handled)| handled);
+        conversion.finish(replacements, result.as_ref().err());
+        result.map(|(output, _)| self.normalize(output))
+    }
+
+    /// Convert in place on the rayon thread pool, `chunk_size` bytes per task
+    ///
+    /// Like [`convert_in_place`](Self::convert_in_place), this only works for
+    /// single-byte to single-byte conversions.
+    #[cfg(feature = "rayon")]
+    pub fn convert_in_place_parallel(&self, buffer: &mut [u8], chunk_size: usize) -> Result<()> {
+        self.check_in_place()?;
+        let Some(ref table) = self.table else {
+            return Err(Error::UnsupportedConversion {
+                from: self.from.name(),
+                to: self.to.name(),
+            });
+        };
+
+        let conversion = telemetry::Conversion::start(self.from, self.to, buffer.len());
+        let result = table.translate_in_place_parallel(buffer, chunk_size);
+        conversion.finish(0, result.as_ref().err());
+        result
+    }
+
+    /// Convert, writing `replacement` in place of each unmappable byte or character
+    ///
+    /// For targets where a single byte is not a character, such as UTF-16, use
+    /// [`convert_with_policy`](Self::convert_with_policy) instead.
+    pub fn convert_lossy(&self, input: &[u8], replacement: u8) -> Vec<u8> {
+        self.normalize(self.convert_lossy_counted(input, replacement).0)
+    }
+
+    /// Convert data, handling unmappable input according to `policy`
+    ///
+    /// Conversions that succeed take the regular fast path; only input with
+    /// errors is re-processed character by character.
+    pub fn convert_with_policy(&self, input: &[u8], policy: ErrorPolicy) -> Result<Vec<u8>> {
+        self.convert_with_policy_counted(input, &policy)
+            .map(|(output, _)| self.normalize(output))
+    }
+
+    /// Find every error a strict conversion of `input` would meet
+    ///
+    /// Unlike [`convert`](Self::convert), this does not stop at the first
+    /// problem: the report lists every undecodable byte sequence and every
+    /// character the target cannot represent, in input order. Use
+    /// [`validator`](Self::validator) to cap the number of errors or to check
+    /// a stream chunk by chunk.
+    ///
+    /// ```
+    /// use fast_encode::{Encoding, Translator};
+    ///
+    /// let translator = Translator::new(Encoding::UTF8, Encoding::ISO_8859_1).unwrap();
+    /// let report = translator.validate("5 € or \u{2122}".as_bytes());
+    /// let positions: Vec<_> = report.invalid.iter().map(|e| (e.offset, e.character)).collect();
+    /// assert_eq!(positions, [(2, Some('€')), (9, Some('™'))]);
+    /// ```
+    pub fn validate(&self, input: &[u8]) -> validation::ValidationReport {
+        self.validator().validate(input)
+    }
+
+    /// Source bytes whose characters do not survive a round trip through the target
+    ///
+    /// Checks the encoding tables rather than any data, so it answers ahead of
+    /// time which characters a conversion will fail on or change; the error
+    /// policy and line-ending settings are not applied.
+    ///
+    /// ```
+    /// use fast_encode::{Encoding, Translator};
+    ///
+    /// let translator = Translator::new(Encoding::WINDOWS_1252, Encoding::EBCDIC_037).unwrap();
+    /// let report = translator.compatibility_report();
+    /// assert!(report.unmappable.contains(&(0x80, '€')));
+    /// assert!(report.undefined.contains(&0x81));
+    /// ```
+    pub fn compatibility_report(&self) -> validation::CompatibilityReport {
+        validation::CompatibilityReport::new(self.from, self.to)
+    }
+
+    /// Streaming validator for this translator's source and target encodings
+    pub fn validator(&self) -> validation::Validator {
+        validation::Validator::new(self.from).target(self.to)
+    }
+
+    /// Policy conversion that also reports how many errors were handled
+    fn convert_with_policy_counted(
+        &self,
+        input: &[u8],
+        policy: &ErrorPolicy,
+    ) -> Result<(Vec<u8>, usize)> {
+        let conversion = telemetry::Conversion::start(self.from, self.to, input.len());
+        let result = self.convert_with_policy_unrecorded(input, policy);
+        let replacements = result.as_ref().map_or(0, |&(_, handled)| handled);
+        conversion.finish(replacements, result.as_ref().err());
+        result
+    }
+
+    fn convert_with_policy_unrecorded(
+        &self,
+        input: &[u8],
+        policy: &ErrorPolicy,
+    ) -> Result<(Vec<u8>, usize)> {
+        match self.convert_unrecorded(input) {
+            Ok(output) => Ok((output, 0)),
+            Err(e) if *policy == ErrorPolicy::Strict => Err(e),
+            Err(_) => policy::convert(self.from, self.to, input, policy),
+        }
+    }
+
+    /// Lossy conversion that also reports how many replacements were made
+    fn convert_lossy_counted(&self, input: &[u8], replacement: u8) -> (Vec<u8>, usize) {
+        let conversion = telemetry::Conversion::start(self.from, self.to, input.len());
+        let (output, replacements) = self.convert_lossy_unrecorded(input, replacement);
+        conversion.finish(replacements, None);
+        (output, replacements)
+    }
+
+    fn convert_lossy_unrecorded(&self, input: &[u8], replacement: u8) -> (Vec<u8>, usize) {
+        if let Some(ref table) = self.table {
+            let mut output = Vec::with_capacity(input.len());
+            let mut replacements = 0;
+
+            for &byte in input {
+                if table.is_mappable(byte) {
+                    output.push(table.translate_byte_unchecked(byte));
+                } else {
+                    output.push(replacement);
+                    replacements += 1;
+                }
+            }
+
+            (output, replacements)
+        } else {
+            match self.convert_unrecorded(input) {
+                Ok(result) => (result, 0),
+                Err(_) => policy::convert_lossy(self.from, self.to, input, replacement),
+            }
         }
     }
 }
 
+/// Builder for a [`Translator`] with non-default settings
+///
+/// ```
+/// use fast_encode::{Encoding, ErrorPolicy, Translator};
+///
+/// let translator = Translator::builder(Encoding::UTF8, Encoding::ISO_8859_1)
+///     .policy(ErrorPolicy::ReplaceWithChar('?'))
+///     .build()
+///     .unwrap();
+/// assert_eq!(translator.convert("5 €".as_bytes()).unwrap(), b"5 ?");
+/// ```
+#[derive(Debug, Clone)]
+pub struct TranslatorBuilder {
+    from: Encoding,
+    to: Encoding,
+    policy: ErrorPolicy,
+    newline: NewlineMode,
+    controls: ControlMode,
+    surrogates: SurrogateMode,
+}
+
+impl TranslatorBuilder {
+    /// Handle unmappable input according to `policy` instead of failing
+    pub fn policy(mut self, policy: ErrorPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Rewrite line endings in the output according to `mode`
+    pub fn newline(mut self, mode: NewlineMode) -> Self {
+        self.newline = mode;
+        self
+    }
+
+    /// Strip or replace control characters in the output according to `mode`
+    pub fn controls(mut self, mode: ControlMode) -> Self {
+        self.controls = mode;
+        self
+    }
+
+    /// Reject, replace or keep unpaired surrogates in UTF-16 or UTF-32 input
+    pub fn surrogates(mut self, mode: SurrogateMode) -> Self {
+        self.surrogates = mode;
+        self
+    }
+
+    /// Create the translator
+    ///
+    /// Fails if the target encoding cannot represent the requested newline or
+    /// control character replacement.
+    pub fn build(self) -> Result<Translator> {
+        let mut translator = Translator::new(self.from, self.to)?;
+        translator.policy = self.policy;
+        translator.multibyte = translator
+            .multibyte
+            .map(|multibyte| multibyte.with_surrogates(self.surrogates));
+        translator.normalizer =
+            newline::NewlineNormalizer::new(self.newline, self.controls, self.to)?;
+        Ok(translator)
+    }
+}
+
 /// Streaming converter for processing large datasets
+///
+/// Input may be split at arbitrary byte boundaries: a multi-byte sequence that
+/// is cut off at the end of one chunk is held back and completed by the next.
 pub struct StreamingTranslator {
     /// Internal translator
     translator: Translator,
-    /// Internal buffer for processing
-    #[allow(dead_code)]
+    /// Trailing bytes of an incomplete sequence carried over from the previous chunk
     buffer: Vec<u8>,
     /// Buffer size in bytes
-    #[allow(dead_code)]
     buffer_size: usize,
+    /// Number of input bytes converted so far (used for absolute error positions)
+    consumed: usize,
+    /// Number of replacements made by lossy processing
+    replacements: usize,
+    /// Line-ending rewriting (`None` preserves line endings)
+    newline: Option<newline::NewlineNormalizer>,
 }
 
 impl StreamingTranslator {
     /// Create a new streaming translator with specified buffer size
     pub fn new(from: Encoding, to: Encoding, buffer_size: usize) -> Result<Self> {
         let translator = Translator::new(from, to)?;
-        Ok(Self {
+        Ok(Self::from_translator(translator, buffer_size))
+    }
+
+    /// Create a streaming translator around an existing translator
+    ///
+    /// Line endings and control characters are rewritten as the translator's
+    /// [`newline_mode`](Translator::newline_mode) and
+    /// [`control_mode`](Translator::control_mode) ask.
+    pub fn from_translator(translator: Translator, buffer_size: usize) -> Self {
+        Self {
+            newline: translator.normalizer.clone(),
             translator,
-            buffer: Vec::with_capacity(buffer_size),
+            buffer: Vec::with_capacity(8),
             buffer_size,
-        })
+            consumed: 0,
+            replacements: 0,
+        }
+    }
+
+    /// Rewrite line endings in the output according to `mode`
+    ///
+    /// Fails if the target encoding cannot represent the requested newline.
+    pub fn with_newline_mode(mut self, mode: NewlineMode) -> Result<Self> {
+        let controls = self.translator.control_mode();
+        self.newline = newline::NewlineNormalizer::new(mode, controls, self.translator.to)?;
+        self.translator.normalizer = self.newline.clone();
+        Ok(self)
+    }
+
+    /// Strip or replace control characters in the output according to `mode`
+    ///
+    /// Fails if the target encoding cannot represent the replacement.
+    pub fn with_control_mode(mut self, mode: ControlMode) -> Result<Self> {
+        let newline = self.translator.newline_mode();
+        self.newline = newline::NewlineNormalizer::new(newline, mode, self.translator.to)?;
+        self.translator.normalizer = self.newline.clone();
+        Ok(self)
+    }
+
+    /// Reject, replace or keep unpaired surrogates in UTF-16 or UTF-32 input
+    pub fn with_surrogate_mode(mut self, mode: SurrogateMode) -> Self {
+        self.translator.multibyte = self
+            .translator
+            .multibyte
+            .map(|multibyte| multibyte.with_surrogates(mode));
+        self
     }
 
     /// Create with default 64KB buffer
@@ -736,9 +2072,184 @@ impl StreamingTranslator {
         Self::new(from, to, 64 * 1024)
     }
 
-    /// Process a chunk of data
+    /// Preferred size of the chunks fed to this translator
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
+    /// Get the underlying translator
+    pub fn translator(&self) -> &Translator {
+        &self.translator
+    }
+
+    /// Number of input bytes converted so far
+    pub fn bytes_consumed(&self) -> usize {
+        self.consumed
+    }
+
+    /// Number of replacements made by [`process_chunk_lossy`](Self::process_chunk_lossy) so far
+    pub fn replacements(&self) -> usize {
+        self.replacements
+    }
+
+    /// Process a chunk of data with the translator's [`policy`](Translator::policy)
+    ///
+    /// Errors report positions relative to the start of the stream.
     pub fn process_chunk(&mut self, input: &[u8]) -> Result<Vec<u8>> {
-        self.translator.convert(input)
+        let policy = self.translator.policy.clone();
+        self.process_chunk_with_policy(input, policy)
+    }
+
+    /// Process a chunk of data, replacing unmappable bytes
+    pub fn process_chunk_lossy(&mut self, input: &[u8], replacement: u8) -> Vec<u8> {
+        let mut replacements = 0;
+        let Ok(output) = self.with_complete_input(input, |translator, data| {
+            let (output, count) = translator.convert_lossy_counted(data, replacement);
+            replacements = count;
+            Ok::<_, Infallible>(output)
+        });
+        self.replacements += replacements;
+        self.normalize_newlines(output)
+    }
+
+    /// Process a chunk of data, handling errors according to `policy`
+    ///
+    /// Errors the policy recovers from are counted in [`replacements`](Self::replacements).
+    pub fn process_chunk_with_policy(
+        &mut self,
+        input: &[u8],
+        policy: ErrorPolicy,
+    ) -> Result<Vec<u8>> {
+        let offset = self.consumed;
+        let mut handled = 0;
+        let output = self
+            .with_complete_input(input, |translator, data| {
+                let (output, count) = translator.convert_with_policy_counted(data, &policy)?;
+                handled = count;
+                Ok(output)
+            })
+            .map_err(|e: Error| e.offset_by(offset))?;
+        self.replacements += handled;
+        Ok(self.normalize_newlines(output))
+    }
+
+    /// Signal the end of a stream processed with `policy`
+    ///
+    /// A truncated trailing sequence is handed to the policy like any other
+    /// undecodable input.
+    pub fn finish_with_policy(&mut self, policy: ErrorPolicy) -> Result<Vec<u8>> {
+        if policy == ErrorPolicy::Strict || self.buffer.is_empty() {
+            return self.finish();
+        }
+
+        let pending = std::mem::take(&mut self.buffer);
+        let (from, to) = (self.translator.from, self.translator.to);
+        let conversion = telemetry::Conversion::start(from, to, pending.len());
+        let result =
+            policy::convert(from, to, &pending, &policy).map_err(|e| e.offset_by(self.consumed));
+        let replacements = result.as_ref().map_or(0, |&(_, handled)| handled);
+        conversion.finish(replacements, result.as_ref().err());
+        let (output, handled) = result?;
+        self.consumed += pending.len();
+        self.replacements += handled;
+        Ok(self.normalize_newlines(output))
+    }
+
+    /// Signal the end of the stream
+    ///
+    /// Fails if the input ended in the middle of a multi-byte sequence, unless
+    /// the translator's [`policy`](Translator::policy) recovers from it.
+    pub fn finish(&mut self) -> Result<Vec<u8>> {
+        if self.buffer.is_empty() {
+            return Ok(Vec::new());
+        }
+        if self.translator.policy != ErrorPolicy::Strict {
+            let policy = self.translator.policy.clone();
+            return self.finish_with_policy(policy);
+        }
+
+        // A letter held back in case a tone mark followed is complete after all
+        if vietnamese::composes(self.translator.from) {
+            let pending = std::mem::take(&mut self.buffer);
+            let position = self.consumed;
+            let (output, _) = self
+                .translator
+                .convert_with_policy_counted(&pending, &ErrorPolicy::Strict)
+                .map_err(|e| e.offset_by(position))?;
+            self.consumed += pending.len();
+            return Ok(self.normalize_newlines(output));
+        }
+
+        let position = self.consumed;
+        let pending = self.buffer.len();
+        self.buffer.clear();
+        Err(Error::InvalidInput(format!(
+            "Truncated {} sequence at end of input ({} byte(s) at position {})",
+            self.translator.from.name(),
+            pending,
+            position
+        )))
+    }
+
+    /// Signal the end of a lossy stream, replacing a truncated trailing sequence
+    pub fn finish_lossy(&mut self, replacement: u8) -> Vec<u8> {
+        match self.finish() {
+            Ok(output) => output,
+            Err(_) => {
+                self.replacements += 1;
+                vec![replacement]
+            }
+        }
+    }
+
+    /// Apply the newline mode, if any, to converted output
+    fn normalize_newlines(&mut self, output: Vec<u8>) -> Vec<u8> {
+        match self.newline {
+            Some(ref mut normalizer) => normalizer.normalize(&output),
+            None => output,
+        }
+    }
+
+    /// Join carried-over bytes with `input`, convert every complete sequence and
+    /// keep the incomplete tail for the next call
+    ///
+    /// If `convert` fails, the carried-over bytes are kept so that the stream
+    /// is left as it was before the call.
+    fn with_complete_input<F, E>(
+        &mut self,
+        input: &[u8],
+        convert: F,
+    ) -> std::result::Result<Vec<u8>, E>
+    where
+        F: FnOnce(&Translator, &[u8]) -> std::result::Result<Vec<u8>, E>,
+    {
+        let from = self.translator.from;
+        let _span = telemetry::stream_chunk(from, self.translator.to, input.len(), self.consumed);
+
+        if self.buffer.is_empty() {
+            let split = input.len() - multibyte::incomplete_suffix_len(from, input);
+            let output = convert(&self.translator, &input[..split])?;
+            self.buffer.extend_from_slice(&input[split..]);
+            self.consumed += split;
+            return Ok(output);
+        }
+
+        let carried = self.buffer.len();
+        let mut data = std::mem::take(&mut self.buffer);
+        data.extend_from_slice(input);
+        let split = data.len() - multibyte::incomplete_suffix_len(from, &data);
+        match convert(&self.translator, &data[..split]) {
+            Ok(output) => {
+                self.buffer.extend_from_slice(&data[split..]);
+                self.consumed += split;
+                Ok(output)
+            }
+            Err(e) => {
+                data.truncate(carried);
+                self.buffer = data;
+                Err(e)
+            }
+        }
     }
 }
 
@@ -786,6 +2297,541 @@ mod tests {
         assert_eq!(std::str::from_utf8(&combined).unwrap(), "HELLO");
     }
 
+    #[test]
+    fn test_streaming_split_multibyte_sequence() {
+        let mut stream =
+            StreamingTranslator::with_default_buffer(Encoding::UTF8, Encoding::UTF16LE).unwrap();
+
+        // "é€" with both sequences split across chunk boundaries
+        let utf8 = "é€".as_bytes();
+        let mut output = stream.process_chunk(&utf8[..1]).unwrap();
+        output.extend(stream.process_chunk(&utf8[1..3]).unwrap());
+        output.extend(stream.process_chunk(&utf8[3..]).unwrap());
+        output.extend(stream.finish().unwrap());
+
+        assert_eq!(output, &[0xE9, 0x00, 0xAC, 0x20]);
+    }
+
+    #[test]
+    fn test_streaming_failed_chunk_keeps_carry() {
+        let mut stream =
+            StreamingTranslator::with_default_buffer(Encoding::UTF8, Encoding::ISO_8859_1).unwrap();
+
+        // The lead byte of "é" is carried, then the next chunk fails on "€"
+        assert_eq!(stream.process_chunk(&[0xC3]).unwrap(), b"");
+        let err = stream.process_chunk(&[0xA9, 0xE2, 0x82, 0xAC]).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::UnmappableTarget {
+                character: '€', ..
+            }
+        ));
+
+        // Retrying with the rest of "é" still completes the carried sequence
+        assert_eq!(stream.bytes_consumed(), 0);
+        assert_eq!(stream.process_chunk(&[0xA9]).unwrap(), [0xE9]);
+        assert_eq!(stream.finish().unwrap(), b"");
+    }
+
+    #[test]
+    fn test_streaming_lossy_split_sequences() {
+        let mut stream =
+            StreamingTranslator::with_default_buffer(Encoding::UTF8, Encoding::ASCII).unwrap();
+
+        // "a€b" with "€" split across chunks, then an invalid byte
+        let mut output = stream.process_chunk_lossy(&[b'a', 0xE2], b'?');
+        output.extend(stream.process_chunk_lossy(&[0x82, 0xAC, b'b', 0xFF], b'?'));
+        output.extend(stream.finish_lossy(b'?'));
+
+        assert_eq!(output, b"a?b?");
+        assert_eq!(stream.replacements(), 2);
+        assert_eq!(stream.bytes_consumed(), 6);
+    }
+
+    #[test]
+    fn test_streaming_truncated_input() {
+        let mut stream =
+            StreamingTranslator::with_default_buffer(Encoding::UTF16LE, Encoding::UTF8).unwrap();
+
+        assert_eq!(stream.process_chunk(&[b'A', 0x00, b'B']).unwrap(), b"A");
+        assert!(matches!(stream.finish(), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_streaming_error_position_is_absolute() {
+        let mut stream =
+            StreamingTranslator::with_default_buffer(Encoding::ASCII, Encoding::UTF8).unwrap();
+
+        stream.process_chunk(b"abc").unwrap();
+        let err = stream.process_chunk(&[b'd', 0x80]).unwrap_err();
+        assert_eq!(
+            err,
+            Error::UnmappableSource {
+                byte: 0x80,
+                position: 4
+            }
+        );
+    }
+
+    #[test]
+    fn test_error_policies() {
+        let to_ascii = Translator::new(Encoding::UTF8, Encoding::ASCII).unwrap();
+        let input = "a€b".as_bytes();
+
+        assert!(
+            to_ascii
+                .convert_with_policy(input, ErrorPolicy::Strict)
+                .is_err()
+        );
+        let convert = |policy| to_ascii.convert_with_policy(input, policy).unwrap();
+        assert_eq!(convert(ErrorPolicy::Replace), b"a?b");
+        assert_eq!(convert(ErrorPolicy::ReplaceWithChar('*')), b"a*b");
+        assert_eq!(convert(ErrorPolicy::ReplaceWithStr("<?>".into())), b"a<?>b");
+        assert_eq!(convert(ErrorPolicy::ReplaceWithStr("".into())), b"ab");
+        // Replacements built at runtime, with characters that take several bytes in the target
+        let replacement: String = ['[', '\u{FFFD}', ']'].iter().collect();
+        let policy = ErrorPolicy::ReplaceWithStr(replacement.into());
+        let to_utf16 = Translator::new(Encoding::UTF8, Encoding::UTF16BE).unwrap();
+        assert_eq!(
+            to_utf16
+                .convert_with_policy(b"a\xFF", policy.clone())
+                .unwrap(),
+            [0, b'a', 0, b'[', 0xFF, 0xFD, 0, b']']
+        );
+        let to_sjis = Translator::new(Encoding::UTF8, Encoding::SHIFT_JIS).unwrap();
+        let geta = ErrorPolicy::ReplaceWithStr("〓〓".into());
+        assert_eq!(
+            to_sjis.convert_with_policy("a€".as_bytes(), geta).unwrap(),
+            b"a\x81\xAC\x81\xAC"
+        );
+        // The whole replacement must be encodable
+        assert!(to_sjis.convert_with_policy("€".as_bytes(), policy).is_err());
+        assert_eq!(convert(ErrorPolicy::Ignore), b"ab");
+        assert_eq!(convert(ErrorPolicy::BackslashReplace), b"a\\u20acb");
+        assert_eq!(convert(ErrorPolicy::XmlCharRefReplace), b"a&#8364;b");
+
+        // Undecodable source bytes
+        let to_utf8 = Translator::new(Encoding::UTF8, Encoding::UTF16LE).unwrap();
+        let output = to_utf8
+            .convert_with_policy(b"a\xFFb", ErrorPolicy::BackslashReplace)
+            .unwrap();
+        let expected: Vec<u8> = "a\\xffb"
+            .encode_utf16()
+            .flat_map(|u| u.to_le_bytes())
+            .collect();
+        assert_eq!(output, expected);
+
+        // A truncated sequence at the end of a stream is replaced, not fatal
+        let mut stream =
+            StreamingTranslator::with_default_buffer(Encoding::UTF8, Encoding::UTF8).unwrap();
+        let mut output = stream
+            .process_chunk_with_policy(b"ok\xE2\x82", ErrorPolicy::Replace)
+            .unwrap();
+        output.extend(stream.finish_with_policy(ErrorPolicy::Replace).unwrap());
+        assert_eq!(output, "ok\u{FFFD}".as_bytes());
+        assert_eq!(stream.replacements(), 1);
+
+        // Errors from the tail are reported at their position in the whole stream
+        let mut stream =
+            StreamingTranslator::with_default_buffer(Encoding::UTF8, Encoding::ASCII).unwrap();
+        let policy = ErrorPolicy::ReplaceWithChar('€');
+        stream
+            .process_chunk_with_policy(b"abc", policy.clone())
+            .unwrap();
+        stream
+            .process_chunk_with_policy(b"de\xE2\x82", policy.clone())
+            .unwrap();
+        let error = stream.finish_with_policy(policy).unwrap_err();
+        assert!(
+            matches!(error, Error::UnmappableTarget { position: 5, .. }),
+            "{:?}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_transliteration() {
+        use translit::Transliterator;
+
+        let input = "“Œuvre” – 5 € naïve";
+        let convert = |to, policy| {
+            Translator::builder(Encoding::UTF8, to)
+                .policy(policy)
+                .build()
+                .unwrap()
+                .convert(input.as_bytes())
+        };
+        let default = ErrorPolicy::Transliterate(Arc::new(translit::DEFAULT.clone()));
+        assert_eq!(
+            convert(Encoding::ASCII, default.clone()).unwrap(),
+            b"\"OEuvre\" - 5 EUR naive"
+        );
+        // Characters the target has are kept
+        let latin1 = convert(Encoding::ISO_8859_1, default.clone()).unwrap();
+        assert_eq!(latin1, b"\"OEuvre\" - 5 EUR na\xEFve");
+        let ebcdic = convert(Encoding::EBCDIC_037, default.clone()).unwrap();
+        let back = Translator::new(Encoding::EBCDIC_037, Encoding::UTF8).unwrap();
+        assert_eq!(
+            back.convert(&ebcdic).unwrap(),
+            "\"OEuvre\" - 5 EUR naïve".as_bytes()
+        );
+
+        // No approximation: the fallback decides
+        let strict = Translator::builder(Encoding::UTF8, Encoding::ASCII)
+            .policy(default)
+            .build()
+            .unwrap();
+        assert_eq!(
+            strict.convert("a✓".as_bytes()).unwrap_err(),
+            Error::UnmappableTarget {
+                character: '✓',
+                position: 1
+            }
+        );
+        assert!(strict.convert(b"a\xFF").is_err());
+
+        let custom = Transliterator::new()
+            .substitute('✓', "OK")
+            .substitute('€', "E")
+            .fallback(ErrorPolicy::ReplaceWithChar('#'));
+        let custom = Translator::builder(Encoding::UTF8, Encoding::ASCII)
+            .policy(ErrorPolicy::Transliterate(Arc::new(custom)))
+            .build()
+            .unwrap();
+        assert_eq!(custom.convert("✓ 5€ ✗ é".as_bytes()).unwrap(), b"OK 5E # e");
+        assert_eq!(custom.convert(b"a\xFFb").unwrap(), b"a#b");
+
+        // Only the user's substitutions, applied by streaming translators too
+        static EMPTY: Transliterator = Transliterator::empty();
+        assert_eq!(EMPTY.get('€'), None);
+        assert_eq!(translit::builtin('—'), Some("--"));
+        let mut stream = StreamingTranslator::from_translator(custom, 4);
+        let mut output = stream.process_chunk(&"“ok” ✓".as_bytes()[..5]).unwrap();
+        output.extend(stream.process_chunk(&"“ok” ✓".as_bytes()[5..]).unwrap());
+        output.extend(stream.finish().unwrap());
+        assert_eq!(output, b"\"ok\" OK");
+    }
+
+    #[test]
+    fn test_utf8_to_utf8_validates() {
+        let translator = Translator::new(Encoding::UTF8, Encoding::UTF8).unwrap();
+        assert_eq!(
+            translator.convert("café €".as_bytes()).unwrap(),
+            "café €".as_bytes()
+        );
+        assert!(translator.convert(b"caf\xE9").is_err());
+    }
+
+    #[test]
+    fn test_streaming_newline_mode() {
+        let mut stream =
+            StreamingTranslator::with_default_buffer(Encoding::UTF8, Encoding::UTF16LE)
+                .unwrap()
+                .with_newline_mode(NewlineMode::Lf)
+                .unwrap();
+
+        // CRLF split across chunks still becomes a single LF
+        let mut output = stream.process_chunk(b"a\r\nb\r").unwrap();
+        output.extend(stream.process_chunk(b"\nc\rd\xC2\x85").unwrap());
+        output.extend(stream.finish().unwrap());
+
+        let expected: Vec<u8> = "a\nb\nc\nd\n"
+            .encode_utf16()
+            .flat_map(|u| u.to_le_bytes())
+            .collect();
+        assert_eq!(output, expected);
+
+        assert!(
+            StreamingTranslator::with_default_buffer(Encoding::UTF8, Encoding::ASCII)
+                .unwrap()
+                .with_newline_mode(NewlineMode::Nel)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_translator_newline_and_controls() {
+        // EBCDIC NEL (0x15) and RS/US (0x1E/0x1F) are real controls now
+        let records = [0xC1, 0x1E, 0xC2, 0x15, 0xC3, 0x1F, 0x05, 0xC4, 0x15];
+        let plain = Translator::new(Encoding::EBCDIC_037, Encoding::UTF8).unwrap();
+        assert_eq!(
+            plain.convert(&records).unwrap(),
+            "A\u{1E}B\u{85}C\u{1F}\tD\u{85}".as_bytes()
+        );
+
+        let to_crlf = Translator::builder(Encoding::EBCDIC_037, Encoding::UTF8)
+            .newline(NewlineMode::CrLf)
+            .controls(ControlMode::Strip)
+            .build()
+            .unwrap();
+        assert_eq!(to_crlf.newline_mode(), NewlineMode::CrLf);
+        assert_eq!(to_crlf.control_mode(), ControlMode::Strip);
+        assert_eq!(to_crlf.convert(&records).unwrap(), b"AB\r\nC\tD\r\n");
+        let mut appended = b">".to_vec();
+        to_crlf.convert_into(&records, &mut appended).unwrap();
+        assert_eq!(appended, b">AB\r\nC\tD\r\n");
+        let mut buffer = [0; 16];
+        let written = to_crlf.convert_to_slice(&records, &mut buffer).unwrap();
+        assert_eq!(&buffer[..written], b"AB\r\nC\tD\r\n");
+
+        // A line break as the replacement follows the newline mode
+        let records_as_lines = Translator::builder(Encoding::EBCDIC_037, Encoding::ISO_8859_1)
+            .newline(NewlineMode::Lf)
+            .controls(ControlMode::Replace('\n'))
+            .build()
+            .unwrap();
+        assert_eq!(
+            records_as_lines.convert(&records).unwrap(),
+            b"A\nB\nC\n\tD\n"
+        );
+        assert!(
+            records_as_lines
+                .convert_in_place(&mut records.clone())
+                .is_err()
+        );
+
+        // And back to the mainframe: LF becomes NEL, other controls a space
+        let to_ebcdic = Translator::builder(Encoding::UTF16LE, Encoding::EBCDIC_037)
+            .newline(NewlineMode::Nel)
+            .controls(ControlMode::Replace(' '))
+            .build()
+            .unwrap();
+        let utf16: Vec<u8> = "A\r\nB\u{7}C\n"
+            .encode_utf16()
+            .flat_map(|u| u.to_le_bytes())
+            .collect();
+        assert_eq!(
+            to_ebcdic.convert(&utf16).unwrap(),
+            [0xC1, 0x15, 0xC2, 0x40, 0xC3, 0x15]
+        );
+        assert!(
+            Translator::builder(Encoding::UTF8, Encoding::ASCII)
+                .controls(ControlMode::Replace('¶'))
+                .build()
+                .is_err()
+        );
+
+        // Streaming keeps the translator's settings across chunk boundaries
+        let mut stream = StreamingTranslator::from_translator(to_crlf, 4);
+        let mut output = Vec::new();
+        for chunk in records.chunks(2) {
+            output.extend(stream.process_chunk(chunk).unwrap());
+        }
+        output.extend(stream.finish().unwrap());
+        assert_eq!(output, b"AB\r\nC\tD\r\n");
+
+        let utf8 = "a\u{1}b\u{9F}c\r\n";
+        let mut stream = StreamingTranslator::new(Encoding::UTF8, Encoding::UTF16BE, 4)
+            .unwrap()
+            .with_control_mode(ControlMode::Replace('?'))
+            .unwrap()
+            .with_newline_mode(NewlineMode::Lf)
+            .unwrap();
+        let output = stream.process_chunk(utf8.as_bytes()).unwrap();
+        let expected: Vec<u8> = "a?b?c\n"
+            .encode_utf16()
+            .flat_map(|u| u.to_be_bytes())
+            .collect();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        let translator = Translator::new(Encoding::EBCDIC_037, Encoding::ISO_8859_1).unwrap();
+        let input: Vec<u8> = (0..100).map(|i| 0xC1 + (i % 9) as u8).collect();
+        let expected = translator.convert(&input).unwrap();
+
+        for &backend in Backend::available() {
+            assert_eq!(translator.convert_with(&input, backend).unwrap(), expected);
+        }
+
+        // Every byte in every lane, with lengths that leave a scalar tail
+        let all_bytes: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        for (from, to) in [
+            (Encoding::EBCDIC_037, Encoding::ISO_8859_1),
+            (Encoding::ISO_8859_1, Encoding::EBCDIC_1047),
+            (Encoding::CP_437, Encoding::CP_850),
+        ] {
+            let table = TranslationTable::new(from, to).unwrap();
+            let mappable: Vec<u8> = all_bytes
+                .iter()
+                .copied()
+                .filter(|&byte| table.is_mappable(byte))
+                .collect();
+            let expected: Vec<u8> = mappable
+                .iter()
+                .map(|&byte| table.translate_byte_unchecked(byte))
+                .collect();
+            for &backend in Backend::available() {
+                assert_eq!(table.translate_with(&mappable, backend).unwrap(), expected);
+            }
+            let mut buffer = mappable.clone();
+            table.translate_in_place(&mut buffer).unwrap();
+            assert_eq!(buffer, expected);
+        }
+
+        // An unmappable byte deep inside a vector is reported exactly
+        let translator = Translator::new(Encoding::WINDOWS_1252, Encoding::ISO_8859_1).unwrap();
+        let mut input = vec![b'a'; 100];
+        input[70] = 0x80;
+        for &backend in Backend::available() {
+            assert_eq!(
+                translator.convert_with(&input, backend),
+                Err(Error::UnmappableSource {
+                    byte: 0x80,
+                    position: 70
+                })
+            );
+        }
+        assert_eq!(
+            translator.convert_in_place(&mut input),
+            Err(Error::UnmappableSource {
+                byte: 0x80,
+                position: 70
+            })
+        );
+    }
+
+    #[test]
+    fn test_custom_code_page_from_ucm() {
+        // Site-specific EBCDIC variant: 0x4A carries a customer symbol instead of '¢'
+        let ucm = "<code_set_name> \"SITE037\"\n<mb_cur_max> 1\n\nCHARMAP\n\
+                   <U0041> \\xC1 |0\n<U0042> \\xC2 |0\n<U2318> \\x4A |0\n\
+                   <U0020> \\x40 |0\n<U00A0> \\x40 |1\nEND CHARMAP\n";
+        let page = custom::CustomCodePage::from_ucm(ucm).unwrap();
+        assert_eq!(page.name(), "SITE037");
+        assert!(!page.is_ascii_compatible());
+
+        let site = page.into_encoding();
+        assert_eq!(site.name(), "SITE037");
+
+        let to_utf8 = Translator::new(site, Encoding::UTF8).unwrap();
+        let output = to_utf8.convert(&[0xC1, 0x40, 0x4A, 0xC2]).unwrap();
+        assert_eq!(std::str::from_utf8(&output).unwrap(), "A ⌘B");
+
+        let from_utf8 = Translator::new(Encoding::UTF8, site).unwrap();
+        assert_eq!(
+            from_utf8.convert("B⌘A".as_bytes()).unwrap(),
+            &[0xC2, 0x4A, 0xC1]
+        );
+
+        let to_ebcdic = Translator::new(site, Encoding::EBCDIC_037).unwrap();
+        assert!(to_ebcdic.convert(&[0x4A]).is_err());
+    }
+
+    #[test]
+    fn test_custom_code_page_from_tsv() {
+        use custom::CustomCodePage;
+
+        let tsv = "# SITE500 customer symbols\n0x4A\tU+2318\t# PLACE OF INTEREST\n\
+                   4F\t0x2764\nC1\t0041\n40\t0020\n0x41\n";
+        let page = CustomCodePage::from_tsv("SITE500", tsv).unwrap();
+        assert_eq!(page.chars()[0x4A], Some('⌘'));
+        assert_eq!(page.chars()[0x4F], Some('❤'));
+        assert_eq!(page.chars()[0x41], None);
+        assert_eq!(CustomCodePage::parse("SITE500", tsv).unwrap(), page);
+        assert!(CustomCodePage::from_tsv("bad", "0x4A\tU+D800").is_err());
+        assert!(CustomCodePage::from_tsv("bad", "4A U+2318 extra\nzz").is_err());
+
+        // Overriding a few bytes of a built-in page keeps the rest of it
+        let derived = CustomCodePage::based_on("SITE037", Encoding::EBCDIC_037)
+            .unwrap()
+            .map(0x4A, Some('⌘'))
+            .map(0x4F, None);
+        assert_eq!(derived.chars()[0xC1], Some('A'));
+        assert!(CustomCodePage::based_on("bad", Encoding::UTF8).is_err());
+
+        let site = derived.into_encoding();
+        let table = TranslationTable::new(Encoding::UTF8, site);
+        assert!(table.is_err(), "UTF-8 has no single-byte table");
+        let table = TranslationTable::new(site, Encoding::ISO_8859_1).unwrap();
+        assert_eq!(table.translate(&[0xC1, 0x40]).unwrap(), b"A ");
+        assert!(table.translate(&[0x4A]).is_err());
+
+        let from_utf8 = Translator::new(Encoding::UTF8, site).unwrap();
+        assert_eq!(from_utf8.convert("A⌘".as_bytes()).unwrap(), [0xC1, 0x4A]);
+
+        let report = validation::Validator::new(site).validate(&[0xC1, 0x4F, 0x4A]);
+        assert_eq!(report.invalid.len(), 1);
+        assert_eq!(report.invalid[0].offset, 1);
+        // Registering the same page again reuses the leaked table
+        let again = CustomCodePage::based_on("SITE037", Encoding::EBCDIC_037)
+            .unwrap()
+            .map(0x4A, Some('⌘'))
+            .map(0x4F, None)
+            .into_encoding();
+        match (site, again) {
+            (Encoding::Custom(a), Encoding::Custom(b)) => assert!(std::ptr::eq(a, b)),
+            _ => unreachable!(),
+        }
+
+        // Borrowed tables convert without registering the page at all
+        let page = CustomCodePage::based_on("SITE037", Encoding::EBCDIC_037)
+            .unwrap()
+            .map(0x4A, Some('¤'));
+        let table = page.table_to(Encoding::ISO_8859_1).unwrap();
+        assert_eq!(table.translate(&[0xC1, 0x4A]).unwrap(), b"A\xA4");
+        let table = page.table_from(Encoding::ISO_8859_1).unwrap();
+        assert_eq!(table.translate(b"A\xA4").unwrap(), [0xC1, 0x4A]);
+        assert!(page.table_to(Encoding::UTF8).is_err());
+        assert!(page.table_from(Encoding::UTF16LE).is_err());
+    }
+
+    #[test]
+    fn test_mojibake_repair() {
+        // UTF-8 read as Windows-1252, including the undefined 0x81 in "Á"
+        let repair = mojibake::repair("cafÃ© and Ã\u{81}ngel, â€œquotedâ€\u{9d}").unwrap();
+        assert_eq!(repair.text, "café and Ángel, “quoted”");
+        assert_eq!(repair.layers, vec![Encoding::WINDOWS_1252]);
+
+        // Double-encoded twice, mixed with text that was never damaged
+        let repair = mojibake::repair("naïve ÃƒÂ©").unwrap();
+        assert_eq!(repair.text, "naïve é");
+        assert_eq!(repair.layers.len(), 2);
+
+        assert!(mojibake::repair("naïve café — fine").is_none());
+    }
+
+    #[test]
+    fn test_byte_stats() {
+        let mut stats = analysis::ByteStats::new();
+        stats.update(b"caf\xe9\r");
+        stats.update(b"\nline\rtwo\n\x00\x1b");
+
+        assert_eq!(stats.total(), 17);
+        assert_eq!(stats.non_ascii(), 1);
+        assert_eq!(stats.c0_controls(), 2);
+        assert_eq!(stats.nul(), 1);
+        // CRLF split across the two chunks is still one CRLF
+        let endings = stats.line_endings();
+        assert_eq!((endings.lf, endings.crlf, endings.cr), (1, 1, 1));
+        assert!(endings.is_mixed());
+        assert_eq!(stats.most_common(1), vec![(b'\n', 2)]);
+    }
+
+    #[test]
+    fn test_encoding_enumeration() {
+        assert!(Encoding::all().len() > 50);
+        assert!(Encoding::all().contains(&Encoding::EBCDIC_1047));
+        assert_eq!(Encoding::WINDOWS_1252.category(), "windows");
+        assert!(Encoding::WINDOWS_1252.aliases().contains(&"CP1252"));
+
+        // Names and aliases identify exactly one encoding each, even with
+        // case and punctuation ignored
+        let mut labels = std::collections::HashMap::new();
+        for &encoding in Encoding::all() {
+            for label in std::iter::once(&encoding.name()).chain(encoding.aliases()) {
+                let previous = labels.insert(normalize_label(label), encoding);
+                assert!(
+                    previous.is_none_or(|previous| previous == encoding),
+                    "duplicate {}",
+                    label
+                );
+                assert_eq!(Encoding::for_label(label), Some(encoding), "{}", label);
+            }
+        }
+    }
+
     #[test]
     fn test_encoding_properties() {
         assert_eq!(Encoding::UTF8.name(), "UTF-8");
@@ -953,6 +2999,91 @@ mod tests {
         assert_eq!(le_input, &roundtrip[..]);
     }
 
+    #[test]
+    fn test_utf32_and_surrogates() {
+        let text = "Aé\u{1F600}";
+        let utf32le: Vec<u8> = text
+            .chars()
+            .flat_map(|c| (c as u32).to_le_bytes())
+            .collect();
+        let utf32be: Vec<u8> = text
+            .chars()
+            .flat_map(|c| (c as u32).to_be_bytes())
+            .collect();
+        let to_utf32 = Translator::new(Encoding::UTF8, Encoding::UTF32LE).unwrap();
+        assert_eq!(to_utf32.convert(text.as_bytes()).unwrap(), utf32le);
+        let swap = Translator::new(Encoding::UTF32LE, Encoding::UTF32BE).unwrap();
+        assert_eq!(swap.convert(&utf32le).unwrap(), utf32be);
+        let from_latin1 = Translator::new(Encoding::ISO_8859_1, Encoding::UTF32BE).unwrap();
+        assert_eq!(from_latin1.convert(b"A\xE9").unwrap(), utf32be[..8]);
+
+        assert_eq!(Encoding::for_label("utf-32"), Some(Encoding::UTF32LE));
+        assert_eq!(Encoding::from_code_page(12001), Some(Encoding::UTF32BE));
+        let detected =
+            detection::EncodingDetector::new().detect(&[0xFF, 0xFE, 0, 0, b'A', 0, 0, 0]);
+        assert_eq!(detected.encoding, Encoding::UTF32LE);
+
+        // A character split across chunks is held back until it is complete
+        let mut stream = StreamingTranslator::new(Encoding::UTF32BE, Encoding::UTF8, 64).unwrap();
+        let mut output = stream.process_chunk(&utf32be[..6]).unwrap();
+        output.extend(stream.process_chunk(&utf32be[6..]).unwrap());
+        output.extend(stream.finish().unwrap());
+        assert_eq!(output, text.as_bytes());
+
+        let bad_utf32 = [0x41, 0, 0, 0, 0, 0, 0x11, 0];
+        let from_utf32 = Translator::new(Encoding::UTF32LE, Encoding::UTF8).unwrap();
+        let message = from_utf32.convert(&bad_utf32).unwrap_err().to_string();
+        assert!(message.contains("0x110000 at position 4"), "{message}");
+
+        // "A", lone high surrogate, "B" in UTF-16LE
+        let lone = [0x41, 0x00, 0x00, 0xD8, 0x42, 0x00];
+        let strict = Translator::new(Encoding::UTF16LE, Encoding::UTF8).unwrap();
+        let message = strict.convert(&lone).unwrap_err().to_string();
+        assert!(
+            message.contains("0xD800") && message.contains("position 2"),
+            "{message}"
+        );
+        let message = strict.convert(&lone[..3]).unwrap_err().to_string();
+        assert!(
+            message.contains("Incomplete UTF-16LE code unit at position 2"),
+            "{message}"
+        );
+
+        let with = |to, mode| {
+            Translator::builder(Encoding::UTF16LE, to)
+                .surrogates(mode)
+                .build()
+                .unwrap()
+        };
+        assert_eq!(
+            with(Encoding::UTF8, SurrogateMode::Replace)
+                .convert(&lone)
+                .unwrap(),
+            "A\u{FFFD}B".as_bytes()
+        );
+        assert_eq!(
+            with(Encoding::UTF8, SurrogateMode::Preserve)
+                .convert(&lone)
+                .unwrap(),
+            [0x41, 0xED, 0xA0, 0x80, 0x42]
+        );
+        assert_eq!(
+            with(Encoding::UTF32BE, SurrogateMode::Preserve)
+                .convert(&lone)
+                .unwrap(),
+            [0, 0, 0, 0x41, 0, 0, 0xD8, 0x00, 0, 0, 0, 0x42]
+        );
+        assert!(
+            with(Encoding::ISO_8859_1, SurrogateMode::Preserve)
+                .convert(&lone)
+                .is_err()
+        );
+        assert_eq!(
+            with(Encoding::UTF8, SurrogateMode::Preserve).surrogate_mode(),
+            SurrogateMode::Preserve
+        );
+    }
+
     #[test]
     fn test_single_byte_to_utf16() {
         // Test Windows-1252 to UTF-16LE conversion
@@ -1019,4 +3150,1916 @@ mod tests {
             assert!(result2.confidence >= result1.confidence);
         }
     }
+
+    #[cfg(feature = "encoding_rs")]
+    #[test]
+    fn test_encoding_rs_compat() {
+        use compat::EncodingRsTranslator;
+
+        let rs: &'static encoding_rs::Encoding = Encoding::WINDOWS_1252.try_into().unwrap();
+        assert_eq!(rs, encoding_rs::WINDOWS_1252);
+        assert_eq!(Encoding::try_from(rs).unwrap(), Encoding::WINDOWS_1252);
+        assert_eq!(
+            Encoding::try_from(encoding_rs::ISO_8859_8_I).unwrap(),
+            Encoding::ISO_8859_8
+        );
+        // WHATWG maps the ISO-8859-1 label to windows-1252, so there is no equivalent
+        assert!(<&encoding_rs::Encoding>::try_from(Encoding::ISO_8859_1).is_err());
+        assert!(<&encoding_rs::Encoding>::try_from(Encoding::EBCDIC_037).is_err());
+        assert!(Encoding::try_from(encoding_rs::GBK).is_err());
+
+        // GBK is only available through encoding_rs
+        let gbk = EncodingRsTranslator::new(encoding_rs::GBK, encoding_rs::UTF_16LE).unwrap();
+        assert_eq!(
+            gbk.convert(&[0xC4, 0xE3, b'!']).unwrap(),
+            [0x60, 0x4F, b'!', 0x00]
+        );
+
+        let latin =
+            EncodingRsTranslator::new(encoding_rs::UTF_8, encoding_rs::WINDOWS_1252).unwrap();
+        assert_eq!(latin.convert("a€b".as_bytes()).unwrap(), [b'a', 0x80, b'b']);
+        assert_eq!(
+            latin.convert("aпb".as_bytes()),
+            Err(Error::UnmappableTarget {
+                character: 'п',
+                position: 1
+            })
+        );
+        assert_eq!(
+            latin.convert(b"ab\xFFc"),
+            Err(Error::UnmappableSource {
+                byte: 0xFF,
+                position: 2
+            })
+        );
+        assert_eq!(
+            latin
+                .convert_with_policy(b"a\xFF\xD0\xBF", ErrorPolicy::BackslashReplace)
+                .unwrap(),
+            b"a\\xff\\u043f"
+        );
+        assert_eq!(
+            latin
+                .convert_with_policy("п!".as_bytes(), ErrorPolicy::ReplaceWithStr("<?>".into()))
+                .unwrap(),
+            b"<?>!"
+        );
+        assert!(EncodingRsTranslator::new(encoding_rs::UTF_8, encoding_rs::REPLACEMENT).is_err());
+    }
+
+    #[test]
+    fn test_iconv_shim() {
+        use iconv::{IconvError, iconv_open};
+
+        let mut cd = iconv_open("UTF-8", "windows-1252").unwrap();
+        let mut input: &[u8] = b"\x80 caf\xE9";
+        let mut buffer = [0u8; 16];
+        let mut output: &mut [u8] = &mut buffer;
+        assert_eq!(cd.iconv(&mut input, &mut output), Ok(0));
+        assert!(input.is_empty());
+        let written = 16 - output.len();
+        assert_eq!(&buffer[..written], "€ café".as_bytes());
+
+        // A character is never split across calls
+        let mut input: &[u8] = "aé".as_bytes();
+        let mut buffer = [0u8; 2];
+        let mut output: &mut [u8] = &mut buffer;
+        let mut cd = iconv_open("UTF-16LE", "UTF-8").unwrap();
+        assert_eq!(cd.iconv(&mut input, &mut output), Err(IconvError::TooBig));
+        assert_eq!(input, "é".as_bytes());
+        assert_eq!(output.len(), 0);
+
+        // Truncated input is left for the next call
+        let mut cd = iconv_open("ISO-8859-1", "UTF-8").unwrap();
+        let mut input: &[u8] = b"a\xC3";
+        let mut buffer = [0u8; 4];
+        let mut output: &mut [u8] = &mut buffer;
+        assert_eq!(
+            cd.iconv(&mut input, &mut output),
+            Err(IconvError::Incomplete)
+        );
+        assert_eq!(input, b"\xC3");
+        assert_eq!(output.len(), 3);
+
+        let mut input: &[u8] = b"a\xFFb";
+        let mut output: &mut [u8] = &mut buffer;
+        assert_eq!(
+            cd.iconv(&mut input, &mut output),
+            Err(IconvError::IllegalSequence)
+        );
+        assert_eq!(input, b"\xFFb");
+
+        let mut cd = iconv_open("ISO-8859-1//TRANSLIT", "UTF-8").unwrap();
+        let mut input: &[u8] = "a€b".as_bytes();
+        let mut output: &mut [u8] = &mut buffer;
+        assert_eq!(cd.iconv(&mut input, &mut output), Ok(1));
+        assert_eq!(&buffer[..3], b"a?b");
+
+        let mut cd = iconv_open("ISO-8859-1//IGNORE", "UTF-8").unwrap();
+        let mut input: &[u8] = b"a\xFF\xE2\x82\xACb";
+        let mut output: &mut [u8] = &mut buffer;
+        assert_eq!(cd.iconv(&mut input, &mut output), Ok(2));
+        assert_eq!(&buffer[..2], b"ab");
+
+        assert!(iconv_open("UTF-8", "no-such-encoding").is_err());
+        assert!(iconv_open("UTF-8//BOGUS", "UTF-8").is_err());
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_arrow_columns() {
+        use arrow_array::{Array, BinaryArray, LargeStringArray};
+
+        let column = BinaryArray::from(vec![
+            Some(&[0xC1, 0xC2][..]),
+            None,
+            Some(&[0xC3][..]),
+            Some(&[][..]),
+        ]);
+
+        // Same-length conversion shares the offsets buffer
+        let ebcdic_to_latin = Translator::new(Encoding::EBCDIC_037, Encoding::ISO_8859_1).unwrap();
+        let latin =
+            arrow::transcode_binary(&column, &ebcdic_to_latin, ErrorPolicy::Strict).unwrap();
+        assert_eq!(latin.value(0), b"AB");
+        assert!(latin.is_null(1));
+        assert_eq!(latin.value(2), b"C");
+        assert_eq!(latin.value(3), b"");
+        assert_eq!(latin.offsets(), column.offsets());
+
+        let sliced = column.slice(2, 2);
+        let latin =
+            arrow::transcode_binary(&sliced, &ebcdic_to_latin, ErrorPolicy::Strict).unwrap();
+        assert_eq!(latin.value(0), b"C");
+        assert_eq!(latin.value_offsets()[0], 0);
+
+        let to_utf8 = Translator::new(Encoding::ISO_8859_1, Encoding::UTF8).unwrap();
+        let bytes = BinaryArray::from(vec![Some(&b"caf\xE9"[..]), None]);
+        let text = arrow::decode_binary(&bytes, &to_utf8, ErrorPolicy::Strict).unwrap();
+        assert_eq!(text.value(0), "café");
+        assert!(text.is_null(1));
+
+        let strings = LargeStringArray::from(vec![Some("a€"), Some("ok"), None]);
+        let from_utf8 = Translator::new(Encoding::UTF8, Encoding::ISO_8859_1).unwrap();
+        assert_eq!(
+            arrow::encode_strings(&strings, &from_utf8, ErrorPolicy::Strict).unwrap_err(),
+            Error::UnmappableTarget {
+                character: '€',
+                position: 1
+            }
+        );
+        let encoded = arrow::encode_strings(&strings, &from_utf8, ErrorPolicy::Replace).unwrap();
+        assert_eq!(encoded.value(0), b"a?");
+        assert_eq!(encoded.value(1), b"ok");
+        assert_eq!(encoded.null_count(), 1);
+
+        assert!(arrow::decode_binary(&column, &ebcdic_to_latin, ErrorPolicy::Strict).is_err());
+    }
+
+    #[test]
+    fn test_fixed_width_records() {
+        use fixed_width::{FieldKind, Layout, from_bytes, from_records};
+        use serde::Deserialize;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        enum Status {
+            A,
+            C,
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Account {
+            acct_no: u32,
+            status: Status,
+            owner: Option<String>,
+            balance: String,
+            delta: i64,
+            active: bool,
+        }
+
+        let layout = Layout::from_copybook(
+            "      * Account master record\n\
+             000100 01  ACCOUNT-REC.\n\
+             000200     05  ACCT-NO      PIC 9(5).\n\
+             000300     05  STATUS       PIC X.\n\
+             000310         88  OPEN     VALUE 'A'.\n\
+             000400     05  OWNER        PIC X(8).\n\
+             000500     05  FILLER       PIC X(2).\n\
+             000600     05  BALANCE      PIC S9(5)V99 USAGE IS COMP-3.\n\
+             000700     05  DELTA        PIC S9(3).\n\
+             000800     05  ACTIVE       PIC X VALUE 'N'.",
+        )
+        .unwrap();
+        assert_eq!(layout.record_length(), 5 + 1 + 8 + 2 + 4 + 3 + 1);
+        assert_eq!(layout.fields()[3].kind, FieldKind::Filler);
+        assert_eq!(layout.fields()[4].kind, FieldKind::Packed { scale: 2 });
+
+        let to_utf8 = Translator::new(Encoding::EBCDIC_037, Encoding::UTF8).unwrap();
+        let from_utf8 = Translator::new(Encoding::UTF8, Encoding::EBCDIC_037).unwrap();
+        let mut record = from_utf8.convert(b"00042ABOB     --").unwrap();
+        record.extend([0x00, 0x12, 0x34, 0x5C]);
+        record.extend(from_utf8.convert(b"01JY").unwrap());
+
+        let account: Account = from_bytes(&record, &layout, &to_utf8).unwrap();
+        assert_eq!(
+            account,
+            Account {
+                acct_no: 42,
+                status: Status::A,
+                owner: Some("BOB".to_string()),
+                balance: "123.45".to_string(),
+                delta: -11,
+                active: true,
+            }
+        );
+
+        // Blank text becomes None; errors name the field
+        let mut second = record.clone();
+        second[6..14].copy_from_slice(&[0x40; 8]);
+        second[1] = 0xC1;
+        let mut both = record.clone();
+        both.extend(&second);
+        let accounts: Vec<Result<Account>> = from_records(&both, &layout, &to_utf8).collect();
+        assert!(accounts[0].is_ok());
+        let message = accounts[1].as_ref().unwrap_err().to_string();
+        assert!(message.contains("acct_no"), "{}", message);
+
+        second[1] = 0xF0;
+        let account: Account = from_bytes(&second, &layout, &to_utf8).unwrap();
+        assert_eq!(account.owner, None);
+
+        let simple = Layout::new().text("code", 3).filler(1).field(
+            "amount",
+            2,
+            FieldKind::Binary {
+                signed: true,
+                scale: 0,
+            },
+        );
+        let (code, amount): (String, i16) =
+            from_bytes(&[0xC1, 0xC2, 0x40, 0x00, 0xFF, 0xFE], &simple, &to_utf8).unwrap();
+        assert_eq!((code.as_str(), amount), ("AB", -2));
+
+        assert!(Layout::from_copybook("01 R. 05 X PIC X(2) OCCURS 3 TIMES.").is_err());
+    }
+
+    #[test]
+    fn test_os_str_helpers() {
+        use std::ffi::OsStr;
+
+        let wide =
+            os_str::decode_to_wide("€1".as_bytes(), Encoding::UTF8, ErrorPolicy::Strict).unwrap();
+        assert_eq!(wide, [0x20AC, 0x31]);
+        assert_eq!(
+            os_str::encode_from_wide(&wide, Encoding::WINDOWS_1252, ErrorPolicy::Strict).unwrap(),
+            [0x80, b'1']
+        );
+
+        // An unpaired surrogate, as found in some Windows file names
+        let broken = [0x61, 0xD800, 0x62];
+        assert!(os_str::encode_from_wide(&broken, Encoding::UTF8, ErrorPolicy::Strict).is_err());
+        assert_eq!(
+            os_str::encode_from_wide(&broken, Encoding::UTF8, ErrorPolicy::Replace).unwrap(),
+            "a\u{FFFD}b".as_bytes()
+        );
+
+        let name =
+            os_str::decode_to_os_string(&[0xC8, 0xC9], Encoding::EBCDIC_037, ErrorPolicy::Strict)
+                .unwrap();
+        assert_eq!(name, "HI");
+        assert_eq!(
+            os_str::encode_os_str(
+                OsStr::new("café"),
+                Encoding::ISO_8859_1,
+                ErrorPolicy::Strict
+            )
+            .unwrap(),
+            b"caf\xE9"
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let invalid = OsStr::from_bytes(b"a\xFFb");
+            assert!(
+                os_str::encode_os_str(invalid, Encoding::ISO_8859_1, ErrorPolicy::Strict).is_err()
+            );
+            assert_eq!(
+                os_str::encode_os_str(invalid, Encoding::ISO_8859_1, ErrorPolicy::Ignore).unwrap(),
+                b"ab"
+            );
+        }
+    }
+
+    #[test]
+    fn test_mime_encoded_words() {
+        let decode = |header| mime::decode_header(header, ErrorPolicy::Strict).unwrap();
+
+        assert_eq!(
+            decode("=?iso-8859-1?q?Andr=E9?= <andre@example.org>"),
+            "André <andre@example.org>"
+        );
+        assert_eq!(decode("=?KOI8-R?B?8NLJ18XU?="), "Привет");
+        assert_eq!(
+            decode("=?X-UNKNOWN?B?8NLJ18XU?= plain =?bogus"),
+            "=?X-UNKNOWN?B?8NLJ18XU?= plain =?bogus"
+        );
+
+        // Folded whitespace between words disappears, and a UTF-8 character
+        // split across two words is reassembled
+        assert_eq!(
+            decode("=?UTF-8?B?4oI=?=\r\n =?UTF-8?B?rA==?= =?ISO-8859-15?Q?_=A4?="),
+            "€ €"
+        );
+        assert_eq!(
+            decode("Re: =?UTF-8*en?Q?caf=C3=A9?= again"),
+            "Re: café again"
+        );
+
+        // Text between words is kept and malformed payloads stay verbatim
+        assert_eq!(
+            decode("=?US-ASCII?Q?a?= - =?US-ASCII?Q?b=G1?="),
+            "a - =?US-ASCII?Q?b=G1?="
+        );
+
+        assert!(mime::decode_header("=?US-ASCII?Q?=FF?=", ErrorPolicy::Strict).is_err());
+        assert_eq!(
+            mime::decode_header("=?US-ASCII?Q?=FF?=", ErrorPolicy::Replace).unwrap(),
+            "\u{FFFD}"
+        );
+    }
+
+    #[test]
+    fn test_pipeline_stages() {
+        use pipeline::{Newlines, Pipeline, Substitute, Transform};
+
+        // A stage that holds back a trailing backslash until it sees what follows
+        struct JoinContinuations {
+            pending: bool,
+        }
+
+        impl Transform for JoinContinuations {
+            fn process(&mut self, input: &str, output: &mut String) {
+                for ch in input.chars() {
+                    match (std::mem::take(&mut self.pending), ch) {
+                        (true, '\n') => {}
+                        (true, ch) => {
+                            output.push('\\');
+                            if ch == '\\' {
+                                self.pending = true;
+                            } else {
+                                output.push(ch);
+                            }
+                        }
+                        (false, '\\') => self.pending = true,
+                        (false, ch) => output.push(ch),
+                    }
+                }
+            }
+
+            fn finish(&mut self, output: &mut String) {
+                if std::mem::take(&mut self.pending) {
+                    output.push('\\');
+                }
+            }
+        }
+
+        let mut pipeline = Pipeline::new(Encoding::WINDOWS_1252, Encoding::ISO_8859_1)
+            .unwrap()
+            .stage(JoinContinuations { pending: false })
+            .stage(
+                Substitute::new()
+                    .with('\u{201C}', "\"")
+                    .with('\u{201D}', "\""),
+            )
+            .stage(Newlines::new(NewlineMode::Lf));
+
+        let mut output = Vec::new();
+        for chunk in [&b"\x93caf\xE9\x94 \\"[..], b"\n\r", b"\nend\\"] {
+            output.extend(pipeline.process_chunk(chunk).unwrap());
+        }
+        output.extend(pipeline.finish().unwrap());
+        assert_eq!(output, b"\"caf\xE9\" \nend\\");
+
+        // Encode errors go through the policy after the stages have run
+        let mut strict = Pipeline::new(Encoding::UTF8, Encoding::ASCII).unwrap();
+        assert_eq!(
+            strict.convert("ab\u{2014}".as_bytes()),
+            Err(Error::UnmappableTarget {
+                character: '\u{2014}',
+                position: 2
+            })
+        );
+        let mut lenient = Pipeline::new(Encoding::UTF8, Encoding::ASCII)
+            .unwrap()
+            .stage(Substitute::new().with('\u{2014}', "--"))
+            .policy(ErrorPolicy::Replace);
+        assert_eq!(
+            lenient.convert("a\u{2014}b\u{e9}".as_bytes()).unwrap(),
+            b"a--b?"
+        );
+        assert_eq!(lenient.replacements(), 1);
+    }
+
+    #[test]
+    fn test_case_map_stage() {
+        use pipeline::{Case, CaseMap, Pipeline};
+
+        let mut upper = Pipeline::new(Encoding::ISO_8859_1, Encoding::EBCDIC_037)
+            .unwrap()
+            .stage(CaseMap::new(Case::Upper))
+            .policy(ErrorPolicy::Replace);
+        let output = upper.convert(b"Stra\xDFe no. 5").unwrap();
+        let decoded = Translator::new(Encoding::EBCDIC_037, Encoding::UTF8)
+            .unwrap()
+            .convert(&output)
+            .unwrap();
+        assert_eq!(decoded, b"STRASSE NO. 5");
+
+        // Final sigma is decided across chunk boundaries
+        let mut lower = Pipeline::new(Encoding::UTF8, Encoding::UTF8)
+            .unwrap()
+            .stage(CaseMap::new(Case::Lower));
+        let text = "ΟΔΟΣ ΣΟΦΙΑΣ' ΌΣΑ".as_bytes();
+        let mut output = Vec::new();
+        for chunk in text.chunks(6) {
+            output.extend(lower.process_chunk(chunk).unwrap());
+        }
+        output.extend(lower.finish().unwrap());
+        assert_eq!(String::from_utf8(output).unwrap(), "οδος σοφιας' όσα");
+
+        let mut lower = Pipeline::new(Encoding::UTF8, Encoding::UTF8)
+            .unwrap()
+            .stage(CaseMap::new(Case::Lower));
+        assert_eq!(lower.convert("ΧΑΟΣ".as_bytes()).unwrap(), "χαος".as_bytes());
+    }
+
+    #[test]
+    fn test_asciify_profile() {
+        use pipeline::{Pipeline, Substitute};
+
+        // Every one of these is encodable in Windows-1252, but the consumer wants ASCII
+        let mut pipeline = Pipeline::new(Encoding::WINDOWS_1252, Encoding::WINDOWS_1252)
+            .unwrap()
+            .stage(Substitute::asciify().with('\u{2122}', "(TM)"));
+        let input = b"\x93Caf\xE9\x94 \x96 \x85\xA0\x97 \x8Cuvre\x99\xAD";
+        assert_eq!(
+            pipeline.convert(input).unwrap(),
+            b"\"Caf\xE9\" - ... -- OEuvre(TM)"
+        );
+
+        let mut ascii = Pipeline::new(Encoding::UTF8, Encoding::ASCII)
+            .unwrap()
+            .stage(Substitute::asciify());
+        assert_eq!(
+            ascii
+                .convert("\u{FB01}nal \u{2018}draft\u{2019}\u{200B}".as_bytes())
+                .unwrap(),
+            b"final 'draft'"
+        );
+    }
+
+    #[test]
+    fn test_bidi_reorder_stage() {
+        use pipeline::{BidiReorder, Direction, Pipeline};
+
+        let logical = "abc שלום 123\nבית 42 (מספר) ד x\n";
+        let visual = "abc םולש 123\nד (רפסמ) 42 תיב x\n";
+
+        let hebrew = Translator::new(Encoding::UTF8, Encoding::ISO_8859_8)
+            .unwrap()
+            .convert(visual.as_bytes())
+            .unwrap();
+        let mut pipeline = Pipeline::new(Encoding::ISO_8859_8, Encoding::UTF8)
+            .unwrap()
+            .stage(BidiReorder::new(Direction::LeftToRight));
+        let mut output = Vec::new();
+        // Lines are held back until they are complete
+        for chunk in hebrew.chunks(5) {
+            output.extend(pipeline.process_chunk(chunk).unwrap());
+        }
+        output.extend(pipeline.finish().unwrap());
+        assert_eq!(String::from_utf8(output).unwrap(), logical);
+
+        // Right-to-left paragraphs reverse the whole line around Latin runs
+        let mut rtl = Pipeline::new(Encoding::UTF8, Encoding::UTF8)
+            .unwrap()
+            .stage(BidiReorder::new(Direction::RightToLeft));
+        let reordered = rtl.convert("שלום abc 12!".as_bytes()).unwrap();
+        assert_eq!(String::from_utf8(reordered).unwrap(), "!abc 12 םולש");
+    }
+
+    #[test]
+    fn test_windows_1258_tone_marks() {
+        let decode = Translator::new(Encoding::WINDOWS_1258, Encoding::UTF8).unwrap();
+        let encode = Translator::new(Encoding::UTF8, Encoding::WINDOWS_1258).unwrap();
+
+        // "Tiếng Việt": ê + acute and ê + dot below are written with combining marks
+        let bytes = b"Ti\xEA\xECng Vi\xEA\xF2t";
+        assert_eq!(decode.convert(bytes).unwrap(), "Tiếng Việt".as_bytes());
+        assert_eq!(encode.convert("Tiếng Việt".as_bytes()).unwrap(), bytes);
+
+        // Tone marks already in combining form pass through, but the code page
+        // has no combining circumflex, so fully decomposed (NFD) text needs
+        // composing first
+        assert_eq!(
+            encode
+                .convert("Vie\u{302}\u{323}t à".as_bytes())
+                .unwrap_err(),
+            Error::UnmappableTarget {
+                character: '\u{302}',
+                position: 3
+            }
+        );
+        assert_eq!(
+            encode.convert("Tie\u{301}ng à".as_bytes()).unwrap(),
+            b"Tie\xECng \xE0"
+        );
+
+        // The error-policy path and other targets compose the same way
+        let to_utf16 = Translator::new(Encoding::WINDOWS_1258, Encoding::UTF16LE).unwrap();
+        assert_eq!(to_utf16.convert(b"\xD5\xCC").unwrap(), [0xDC, 0x1E]);
+        assert_eq!(
+            decode
+                .convert_with_policy(b"\x8Aa\xD2", ErrorPolicy::Replace)
+                .unwrap(),
+            "\u{FFFD}ả".as_bytes()
+        );
+        let to_latin = Translator::new(Encoding::WINDOWS_1258, Encoding::ISO_8859_1).unwrap();
+        assert_eq!(to_latin.convert(b"a\xEC").unwrap(), b"\xE1");
+
+        // A vowel at the end of one chunk composes with a tone mark at the start of the next
+        let mut streaming =
+            StreamingTranslator::new(Encoding::WINDOWS_1258, Encoding::UTF8, 16).unwrap();
+        let mut output = streaming.process_chunk(b"Vi\xEA").unwrap();
+        output.extend(streaming.process_chunk(b"\xF2t o").unwrap());
+        output.extend(streaming.finish().unwrap());
+        assert_eq!(output, "Việt o".as_bytes());
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_conversion_metrics() {
+        use metrics::{
+            Counter, CounterFn, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString,
+            Unit,
+        };
+        use std::collections::HashMap;
+        use std::sync::{Arc, Mutex};
+
+        /// Sums every counter by its name and labels
+        #[derive(Default)]
+        struct Totals(Mutex<HashMap<String, u64>>);
+
+        struct Entry(Arc<Totals>, String);
+
+        impl CounterFn for Entry {
+            fn increment(&self, value: u64) {
+                *self.0.0.lock().unwrap().entry(self.1.clone()).or_default() += value;
+            }
+
+            fn absolute(&self, value: u64) {
+                self.0.0.lock().unwrap().insert(self.1.clone(), value);
+            }
+        }
+
+        struct TestRecorder(Arc<Totals>);
+
+        impl Recorder for TestRecorder {
+            fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+            fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+            fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+            fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+                let labels: Vec<String> = key.labels().map(|l| l.value().to_string()).collect();
+                let name = format!("{} {}", key.name(), labels.join(" "));
+                Counter::from_arc(Arc::new(Entry(self.0.clone(), name)))
+            }
+
+            fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+                Gauge::noop()
+            }
+
+            fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+                Histogram::noop()
+            }
+        }
+
+        let totals = Arc::new(Totals::default());
+        metrics::with_local_recorder(&TestRecorder(totals.clone()), || {
+            let translator = Translator::new(Encoding::UTF8, Encoding::ISO_8859_1).unwrap();
+            translator.convert("café".as_bytes()).unwrap();
+            translator
+                .convert_with_policy("€1".as_bytes(), ErrorPolicy::Replace)
+                .unwrap();
+            assert!(translator.convert("€".as_bytes()).is_err());
+
+            let mut streaming =
+                StreamingTranslator::new(Encoding::UTF8, Encoding::ISO_8859_1, 8).unwrap();
+            streaming.process_chunk(b"ab").unwrap();
+        });
+
+        let totals = totals.0.lock().unwrap();
+        assert_eq!(
+            totals["fast_encode_bytes_converted_total UTF-8 ISO-8859-1"],
+            5 + 4 + 2
+        );
+        assert_eq!(totals["fast_encode_replacements_total UTF-8 ISO-8859-1"], 1);
+        assert_eq!(
+            totals["fast_encode_errors_total UTF-8 ISO-8859-1 unmappable_target"],
+            1
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing_spans() {
+        use std::fmt::Debug;
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        /// Writes every span and event as its name followed by its fields
+        struct Fields(String);
+
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                self.0 += &format!(" {}={:?}", field.name(), value);
+            }
+        }
+
+        #[derive(Clone, Default)]
+        struct TestSubscriber(Arc<Mutex<Vec<String>>>);
+
+        impl Subscriber for TestSubscriber {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let mut fields = Fields(span.metadata().name().to_string());
+                span.record(&mut fields);
+                let mut log = self.0.lock().unwrap();
+                log.push(fields.0);
+                Id::from_u64(log.len() as u64)
+            }
+
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, event: &Event<'_>) {
+                let mut fields = Fields(String::from("event"));
+                event.record(&mut fields);
+                self.0.lock().unwrap().push(fields.0);
+            }
+
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let subscriber = TestSubscriber::default();
+        tracing::subscriber::with_default(subscriber.clone(), || {
+            let translator = Translator::new(Encoding::EBCDIC_037, Encoding::UTF8).unwrap();
+            translator.convert(&[0xC1; 16]).unwrap();
+            translator
+                .convert(&vec![0xC1; telemetry::LARGE_CONVERSION])
+                .unwrap();
+
+            let mut streaming = StreamingTranslator::from_translator(translator, 8);
+            streaming.process_chunk(&[0xC1; 3]).unwrap();
+
+            detection::EncodingDetector::new().detect("naïve".as_bytes());
+        });
+
+        let log = subscriber.0.lock().unwrap();
+        let find = |prefix: &str| {
+            log.iter()
+                .find(|line| line.starts_with(prefix))
+                .unwrap_or_else(|| panic!("no {prefix:?} in {log:?}"))
+        };
+        assert!(find("event message=translator created").contains("path=\"multibyte\""));
+        assert_eq!(
+            find("convert "),
+            &format!(
+                "convert from=\"IBM037\" to=\"UTF-8\" bytes={}",
+                telemetry::LARGE_CONVERSION
+            )
+        );
+        // Only the large conversion gets a span of its own
+        assert_eq!(log.iter().filter(|l| l.starts_with("convert ")).count(), 1);
+        assert!(find("stream_chunk").ends_with("bytes=3 offset=0"));
+        assert_eq!(find("detect"), "detect bytes=6");
+        assert!(find("event message=encoding detected").contains("encoding=\"UTF-8\""));
+    }
+
+    #[cfg(feature = "normalization")]
+    #[test]
+    fn test_normalization_stage() {
+        use pipeline::{NormalizationForm, Normalize, Pipeline};
+
+        // MacRoman has no precomposed-only bytes, but decomposed text arrives
+        // from Mac file systems as a base letter followed by a combining mark
+        let decomposed = "Cafe\u{301} \u{1100}\u{1161}\u{11A8}";
+        let mut nfc = Pipeline::new(Encoding::UTF8, Encoding::UTF16LE)
+            .unwrap()
+            .stage(Normalize::new(NormalizationForm::Nfc));
+
+        // Split inside both the e + accent and the Hangul jamo sequence
+        let bytes = decomposed.as_bytes();
+        let mut output = Vec::new();
+        for chunk in [&bytes[..4], &bytes[4..9], &bytes[9..], &[][..]] {
+            output.extend(nfc.process_chunk(chunk).unwrap());
+        }
+        output.extend(nfc.finish().unwrap());
+        let expected: Vec<u8> = "Café \u{AC01}"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert_eq!(output, expected);
+
+        let mut nfd = Pipeline::new(Encoding::ISO_8859_1, Encoding::UTF8)
+            .unwrap()
+            .stage(Normalize::new(NormalizationForm::Nfd));
+        assert_eq!(
+            nfd.convert(b"\xE9t\xE9").unwrap(),
+            "e\u{301}te\u{301}".as_bytes()
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_io() {
+        use async_io::{AsyncTranslatingReader, AsyncTranslatingWriter};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            // A tiny buffer splits characters between reads
+            let text = "Grüße aus Köln – 東京";
+            let stream = StreamingTranslator::new(Encoding::UTF8, Encoding::UTF16BE, 3).unwrap();
+            let mut reader = AsyncTranslatingReader::with_translator(text.as_bytes(), stream);
+            let mut utf16 = Vec::new();
+            reader.read_to_end(&mut utf16).await.unwrap();
+            let expected: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+            assert_eq!(utf16, expected);
+
+            let mut writer =
+                AsyncTranslatingWriter::new(Vec::new(), Encoding::UTF16BE, Encoding::UTF8).unwrap();
+            for chunk in utf16.chunks(5) {
+                writer.write_all(chunk).await.unwrap();
+            }
+            writer.shutdown().await.unwrap();
+            assert_eq!(writer.into_inner(), text.as_bytes());
+
+            // Errors and truncated input surface as InvalidData
+            let mut reader =
+                AsyncTranslatingReader::new("5 €".as_bytes(), Encoding::UTF8, Encoding::ISO_8859_1)
+                    .unwrap();
+            let error = reader.read_to_end(&mut Vec::new()).await.unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+            let mut lossy =
+                AsyncTranslatingReader::new("5 €".as_bytes(), Encoding::UTF8, Encoding::ISO_8859_1)
+                    .unwrap()
+                    .policy(ErrorPolicy::ReplaceWithChar('?'));
+            let mut latin1 = Vec::new();
+            lossy.read_to_end(&mut latin1).await.unwrap();
+            assert_eq!(latin1, b"5 ?");
+
+            let mut writer =
+                AsyncTranslatingWriter::new(Vec::new(), Encoding::UTF8, Encoding::UTF16LE).unwrap();
+            writer.write_all(&"é".as_bytes()[..1]).await.unwrap();
+            let error = writer.shutdown().await.unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        });
+    }
+
+    #[cfg(feature = "capi")]
+    #[test]
+    fn test_c_api() {
+        use ffi::*;
+        use std::ffi::{CStr, c_char};
+
+        let mut handle = std::ptr::null_mut();
+        let status = unsafe {
+            fe_translator_new(
+                c"IBM037".as_ptr(),
+                c"utf-8".as_ptr(),
+                FE_ERRORS_STRICT,
+                &mut handle,
+            )
+        };
+        assert_eq!(status, FE_OK);
+
+        // Too small: the needed size comes back, then the conversion fits
+        let input = [0xC8, 0x85, 0x93, 0x93, 0x96];
+        let mut output = [0u8; 16];
+        let mut len = 0;
+        let status = unsafe {
+            fe_convert(
+                handle,
+                input.as_ptr(),
+                input.len(),
+                output.as_mut_ptr(),
+                2,
+                &mut len,
+            )
+        };
+        assert_eq!((status, len), (FE_ERR_BUFFER_TOO_SMALL, 5));
+        let status = unsafe {
+            fe_convert(
+                handle,
+                input.as_ptr(),
+                5,
+                output.as_mut_ptr(),
+                output.len(),
+                &mut len,
+            )
+        };
+        assert_eq!((status, &output[..len]), (FE_OK, &b"Hello"[..]));
+        let status = unsafe {
+            fe_convert_into(
+                handle,
+                input.as_ptr(),
+                1,
+                output.as_mut_ptr(),
+                output.len(),
+                &mut len,
+            )
+        };
+        assert_eq!((status, &output[..len]), (FE_OK, &b"HelloH"[..]));
+
+        let mut ascii = std::ptr::null_mut();
+        unsafe { fe_translator_new(c"utf-8".as_ptr(), c"ascii".as_ptr(), 0, &mut ascii) };
+        let status = unsafe {
+            fe_convert(
+                ascii,
+                "é".as_ptr(),
+                2,
+                output.as_mut_ptr(),
+                output.len(),
+                &mut len,
+            )
+        };
+        assert_eq!(status, FE_ERR_UNMAPPABLE);
+        let message = unsafe { CStr::from_ptr(fe_error_message()) };
+        assert!(message.to_str().unwrap().contains("position 0"));
+
+        let status =
+            unsafe { fe_translator_new(c"klingon".as_ptr(), c"utf-8".as_ptr(), 0, &mut ascii) };
+        assert_eq!(status, FE_ERR_UNKNOWN_ENCODING);
+        unsafe {
+            fe_translator_free(handle);
+            fe_translator_free(ascii);
+        }
+
+        let mut name = [0 as c_char; 32];
+        let mut confidence = 0.0;
+        let data = [0xEF, 0xBB, 0xBF, b'h', b'i'];
+        let status = unsafe {
+            fe_detect(
+                data.as_ptr(),
+                data.len(),
+                name.as_mut_ptr(),
+                name.len(),
+                &mut confidence,
+            )
+        };
+        assert_eq!(status, FE_OK);
+        assert_eq!(unsafe { CStr::from_ptr(name.as_ptr()) }, c"UTF-8");
+        assert!(confidence > 0.9);
+    }
+
+    #[cfg(feature = "conformance")]
+    #[test]
+    fn test_conformance_harness() {
+        let reports = conformance::verify_all();
+        assert_eq!(reports.len(), conformance::encodings().count());
+
+        for report in &reports {
+            assert!(report.is_conformant(), "{report}");
+        }
+
+        // Every single-byte encoding with a built-in table has a reference
+        for &encoding in Encoding::all() {
+            if encoding.is_multibyte() || encoding == Encoding::ASCII {
+                continue;
+            }
+            assert!(conformance::verify(encoding).is_ok(), "{}", encoding.name());
+        }
+        assert!(conformance::verify(Encoding::SHIFT_JIS).is_err());
+
+        // A reference that disagrees with the built-in table is reported byte by byte
+        let mut mapping = String::new();
+        for byte in 0..=0xFFu32 {
+            let code_point = if byte == 0x80 { 0x20AC } else { byte };
+            mapping += &format!("0x{:02X}\t0x{:04X}\t# comment\n", byte, code_point);
+        }
+        let reference = conformance::parse_mapping("Latin-1 with Euro", &mapping).unwrap();
+        let report = conformance::verify_against(Encoding::ISO_8859_1, &reference);
+        assert_eq!(
+            report.mismatches[0],
+            conformance::Mismatch::Table {
+                byte: 0x80,
+                expected: Some('€'),
+                actual: Some('\u{80}'),
+            }
+        );
+        assert!(matches!(
+            report.mismatches[2],
+            conformance::Mismatch::Encode {
+                ch: '€',
+                expected: 0x80,
+                actual: None,
+            }
+        ));
+        assert_eq!(report.mismatches.len(), 3);
+
+        assert!(conformance::parse_mapping("bad", "0x100\t0x0041").is_err());
+    }
+
+    #[test]
+    fn test_compatibility_report() {
+        assert_eq!(Encoding::EBCDIC_037.decode_byte(0x40), Some(' '));
+        assert_eq!(Encoding::EBCDIC_037.encode_char(' '), Some(0x40));
+        assert_eq!(Encoding::UTF8.decode_byte(b'A'), Some('A'));
+        assert_eq!(Encoding::UTF8.decode_byte(0xC3), None);
+        assert_eq!(Encoding::UTF16LE.decode_byte(b'A'), None);
+        assert_eq!(Encoding::UTF8.encode_char('é'), None);
+        for byte in 0..=u8::MAX {
+            if let Some(ch) = Encoding::CP_437.decode_byte(byte) {
+                assert_eq!(Encoding::CP_437.encode_char(ch), Some(byte));
+            }
+        }
+
+        // Windows-1252 typography has no place in EBCDIC 037
+        let report = Translator::new(Encoding::WINDOWS_1252, Encoding::EBCDIC_037)
+            .unwrap()
+            .compatibility_report();
+        assert!(report.exhaustive);
+        assert!(!report.is_lossless());
+        assert_eq!(report.undefined, [0x81, 0x8D, 0x8F, 0x90, 0x9D]);
+        assert!(report.unmappable.contains(&(0x80, '€')));
+        assert!(report.unmappable.contains(&(0x99, '™')));
+        assert!(!report.unmappable.iter().any(|&(_, ch)| ch.is_ascii()));
+        assert!(report.lossy.is_empty());
+
+        // Latin-1 and EBCDIC 037 share a repertoire
+        let report = Translator::new(Encoding::ISO_8859_1, Encoding::EBCDIC_037)
+            .unwrap()
+            .compatibility_report();
+        assert!(report.is_lossless(), "{:?}", report);
+
+        // Only the single-byte characters of a multi-byte source are checked
+        let report = Translator::new(Encoding::UTF8, Encoding::EBCDIC_037)
+            .unwrap()
+            .compatibility_report();
+        assert!(!report.exhaustive);
+        assert!(report.undefined.is_empty());
+        assert!(report.is_lossless());
+    }
+
+    #[test]
+    fn test_validator_report() {
+        use validation::Validator;
+
+        let data = b"na\xEFve caf\xE9 \xF0\x9F";
+        let report = Validator::new(Encoding::UTF8).validate(data);
+        assert!(!report.is_valid());
+        assert!(!report.truncated);
+        assert_eq!(report.bytes_checked, data.len());
+        let found: Vec<_> = report
+            .invalid
+            .iter()
+            .map(|e| (e.offset, e.bytes.clone(), e.reason.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (2, vec![0xEF], "invalid UTF-8 sequence"),
+                (9, vec![0xE9], "invalid UTF-8 sequence"),
+                (11, vec![0xF0, 0x9F], "truncated UTF-8 sequence"),
+            ]
+        );
+        // Only encodings the whole sample decodes in are suggested
+        assert!(
+            report
+                .suggestions
+                .iter()
+                .any(|&(encoding, _)| encoding == Encoding::WINDOWS_1252)
+        );
+        for &(encoding, _) in &report.suggestions {
+            let translator = Translator::new(encoding, Encoding::UTF8).unwrap();
+            assert!(translator.convert(data).is_ok(), "{encoding:?}");
+        }
+        assert!(
+            report
+                .suggestions
+                .iter()
+                .all(|&(encoding, _)| encoding != Encoding::UTF8)
+        );
+
+        // Chunk boundaries inside a sequence change nothing
+        let mut streaming = Validator::new(Encoding::UTF8);
+        for chunk in "ÿ€ok".as_bytes().chunks(1) {
+            streaming.update(chunk);
+        }
+        let report = streaming.finish();
+        assert!(report.is_valid());
+        assert!(report.suggestions.is_empty());
+
+        let report = Validator::new(Encoding::UTF16LE).validate(&[0x41, 0x00, 0x00, 0xD8, 0x42]);
+        assert_eq!(report.invalid[0].reason, "unpaired surrogate 0xD800");
+        assert_eq!(report.invalid[1].offset, 4);
+
+        let report = Validator::new(Encoding::WINDOWS_1252)
+            .max_errors(1)
+            .validate(&[b'a', 0x81, 0x8D]);
+        assert_eq!(report.invalid.len(), 1);
+        assert_eq!(report.invalid[0].reason, "unmappable byte 0x81");
+        assert!(report.truncated);
+    }
+
+    #[test]
+    fn test_translator_validate() {
+        let translator = Translator::new(Encoding::UTF8, Encoding::EBCDIC_037).unwrap();
+        // A stray lead byte between two characters EBCDIC lacks
+        let input = b"\xE2\x82\xAC1 \xC3\xE2\x80\x93 ok \xE2\x84\xA2\x00 \xC3\xBF";
+
+        let report = translator.validate(input);
+        assert_eq!(report.target, Some(Encoding::EBCDIC_037));
+        let found: Vec<_> = report
+            .invalid
+            .iter()
+            .map(|e| (e.offset, e.bytes.clone(), e.character))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (0, vec![0xE2, 0x82, 0xAC], Some('€')),
+                (5, vec![0xC3], None),
+                (6, vec![0xE2, 0x80, 0x93], Some('–')),
+                (13, vec![0xE2, 0x84, 0xA2], Some('™')),
+            ]
+        );
+        assert_eq!(
+            report.invalid[0].reason,
+            "U+20AC '€' cannot be encoded in IBM037"
+        );
+
+        // The cap counts both kinds in input order
+        let capped = translator.validator().max_errors(2).validate(input);
+        assert!(capped.truncated);
+        assert_eq!(capped.invalid.len(), 2);
+        assert_eq!(capped.invalid[1].offset, 5);
+
+        // Streaming with characters split across chunks
+        let mut validator = translator.validator();
+        for chunk in input.chunks(3) {
+            validator.update(chunk);
+        }
+        let streamed = validator.finish();
+        assert_eq!(streamed.invalid, report.invalid);
+        assert_eq!(streamed.bytes_checked, input.len());
+
+        // Only target errors: no other source encoding is suggested
+        let clean = translator.validate("a€".as_bytes());
+        assert_eq!(clean.invalid.len(), 1);
+        assert!(clean.suggestions.is_empty());
+        assert!(translator.validate(b"plain text").is_valid());
+    }
+
+    #[test]
+    fn test_double_byte_encodings() {
+        let text = "日本語 ｶﾀｶﾅ abc";
+        let cases: [(Encoding, &str, &[u8]); 5] = [
+            (Encoding::SHIFT_JIS, "日本", &[0x93, 0xFA, 0x96, 0x7B]),
+            (Encoding::EUC_JP, "日本", &[0xC6, 0xFC, 0xCB, 0xDC]),
+            (Encoding::GB2312, "中文", &[0xD6, 0xD0, 0xCE, 0xC4]),
+            (Encoding::BIG5, "中文", &[0xA4, 0xA4, 0xA4, 0xE5]),
+            (Encoding::EUC_KR, "한국", &[0xC7, 0xD1, 0xB1, 0xB9]),
+        ];
+
+        for (encoding, sample, bytes) in cases {
+            let encoder = Translator::new(Encoding::UTF8, encoding).unwrap();
+            let decoder = Translator::new(encoding, Encoding::UTF8).unwrap();
+            assert_eq!(encoder.convert(sample.as_bytes()).unwrap(), bytes);
+            assert_eq!(decoder.convert(bytes).unwrap(), sample.as_bytes());
+
+            // A character split across chunks is completed by the next one
+            let mut streaming = StreamingTranslator::new(encoding, Encoding::UTF8, 4).unwrap();
+            let mut output = Vec::new();
+            for chunk in [b"a".as_slice(), &bytes[..1], &bytes[1..3], &bytes[3..]] {
+                output.extend(streaming.process_chunk(chunk).unwrap());
+            }
+            output.extend(streaming.finish().unwrap());
+            assert_eq!(output, format!("a{}", sample).as_bytes());
+        }
+
+        // Half-width katakana are single bytes in Shift_JIS and 0x8E pairs in EUC-JP
+        let sjis = Translator::new(Encoding::UTF8, Encoding::SHIFT_JIS).unwrap();
+        let eucjp = Translator::new(Encoding::UTF8, Encoding::EUC_JP).unwrap();
+        assert_eq!(sjis.convert(text.as_bytes()).unwrap().len(), 6 + 1 + 4 + 4);
+        assert_eq!(eucjp.convert(text.as_bytes()).unwrap().len(), 6 + 1 + 8 + 4);
+        let japanese = Translator::new(Encoding::SHIFT_JIS, Encoding::EUC_JP).unwrap();
+        let back = Translator::new(Encoding::EUC_JP, Encoding::UTF8).unwrap();
+        let converted = japanese
+            .convert(&sjis.convert(text.as_bytes()).unwrap())
+            .unwrap();
+        assert_eq!(back.convert(&converted).unwrap(), text.as_bytes());
+
+        // Errors point at the lead byte of the bad sequence
+        let decoder = Translator::new(Encoding::SHIFT_JIS, Encoding::UTF8).unwrap();
+        assert_eq!(
+            decoder.convert(&[b'a', 0x93, 0xFA, 0x85, 0x40]),
+            Err(Error::UnmappableSource {
+                byte: 0x85,
+                position: 3
+            })
+        );
+        assert_eq!(
+            decoder.convert(&[b'a', 0x93]),
+            Err(Error::UnmappableSource {
+                byte: 0x93,
+                position: 1
+            })
+        );
+        assert_eq!(
+            Translator::new(Encoding::UTF8, Encoding::GB2312)
+                .unwrap()
+                .convert("中€".as_bytes()),
+            Err(Error::UnmappableTarget {
+                character: '€',
+                position: 3
+            })
+        );
+        let lossy = decoder
+            .convert_with_policy(&[0x93, 0xFA, 0x85, 0x40, b'!'], ErrorPolicy::Replace)
+            .unwrap();
+        // An ASCII trail byte is not swallowed by an invalid lead byte
+        assert_eq!(lossy, "日\u{FFFD}@!".as_bytes());
+    }
+
+    #[test]
+    fn test_io_adapters() {
+        use std::io::{ErrorKind, Read, Write};
+
+        /// Hands out its data one byte per read
+        struct Trickle<'a>(&'a [u8]);
+
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let count = self.0.len().min(buf.len()).min(1);
+                buf[..count].copy_from_slice(&self.0[..count]);
+                self.0 = &self.0[count..];
+                Ok(count)
+            }
+        }
+
+        let text = "Grüße, 日本 😀";
+        let mut reader =
+            io::TranslatingReader::new(Trickle(text.as_bytes()), Encoding::UTF8, Encoding::UTF16BE)
+                .unwrap();
+        let mut utf16 = Vec::new();
+        reader.read_to_end(&mut utf16).unwrap();
+        let expected: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(utf16, expected);
+
+        // Input that stops inside a sequence is reported at the end
+        let truncated = &"é".as_bytes()[..1];
+        let mut reader =
+            io::TranslatingReader::new(truncated, Encoding::UTF8, Encoding::ISO_8859_1).unwrap();
+        let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        let stream = StreamingTranslator::new(Encoding::UTF16BE, Encoding::UTF8, 3).unwrap();
+        let mut writer = io::TranslatingWriter::with_translator(Vec::new(), stream);
+        for byte in &expected {
+            writer.write_all(std::slice::from_ref(byte)).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), text.as_bytes());
+
+        let mut writer = io::TranslatingWriter::new(Vec::new(), Encoding::UTF8, Encoding::ASCII)
+            .unwrap()
+            .policy(ErrorPolicy::ReplaceWithChar('?'));
+        writer.write_all("naïve".as_bytes()).unwrap();
+        assert_eq!(writer.translator().replacements(), 1);
+        assert_eq!(writer.finish().unwrap(), b"na?ve");
+
+        let mut writer =
+            io::TranslatingWriter::new(Vec::new(), Encoding::UTF8, Encoding::ASCII).unwrap();
+        let error = writer.write_all("€".as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_translator_builder_policy() {
+        // The configured policy applies to convert(), on both paths
+        let table = Translator::builder(Encoding::WINDOWS_1252, Encoding::ASCII)
+            .policy(ErrorPolicy::ReplaceWithChar('?'))
+            .build()
+            .unwrap();
+        assert_eq!(*table.policy(), ErrorPolicy::ReplaceWithChar('?'));
+        assert_eq!(table.convert(b"caf\xE9").unwrap(), b"caf?");
+
+        let multibyte = Translator::builder(Encoding::UTF8, Encoding::UTF16LE)
+            .policy(ErrorPolicy::Replace)
+            .build()
+            .unwrap();
+        assert_eq!(multibyte.convert(b"a\xFF").unwrap(), [b'a', 0, 0xFD, 0xFF]);
+        assert_eq!(
+            Translator::new(Encoding::UTF8, Encoding::UTF16LE)
+                .unwrap()
+                .policy(),
+            &ErrorPolicy::Strict
+        );
+
+        // A custom handler sees every error and may skip it with an empty string
+        fn describe(error: Unmappable<'_>) -> String {
+            match error {
+                Unmappable::Source { bytes, .. } => format!("[{:02X?}]", bytes),
+                Unmappable::Target { character: 'x', .. } => String::new(),
+                Unmappable::Target {
+                    character,
+                    position,
+                } => {
+                    format!("[U+{:04X}@{}]", character as u32, position)
+                }
+            }
+        }
+        let custom = Translator::builder(Encoding::UTF8, Encoding::ISO_8859_1)
+            .policy(ErrorPolicy::Custom(describe))
+            .build()
+            .unwrap();
+        assert_eq!(
+            custom.convert("é€\u{FF}".as_bytes()).unwrap(),
+            b"\xE9[U+20AC@2]\xFF"
+        );
+        assert_eq!(custom.convert(b"a\xC3").unwrap(), b"a[[C3]]");
+
+        // Streams use the policy too, counting what it handled
+        let mut stream = StreamingTranslator::from_translator(custom, 16);
+        assert_eq!(stream.process_chunk("€".as_bytes()).unwrap(), b"[U+20AC@0]");
+        assert_eq!(stream.process_chunk(b"\xE2\x82").unwrap(), b"");
+        assert_eq!(stream.finish().unwrap(), b"[[E2, 82]]");
+        assert_eq!(stream.replacements(), 2);
+
+        // Lossy conversion to a multi-byte target replaces only the bad input
+        let lossy = Translator::new(Encoding::UTF8, Encoding::SHIFT_JIS).unwrap();
+        assert_eq!(
+            lossy.convert_lossy("日€本".as_bytes(), b'?'),
+            b"\x93\xfa?\x96\x7b"
+        );
+        assert_eq!(lossy.convert_lossy(b"\xFFa", b'?'), b"?a");
+    }
+
+    #[test]
+    fn test_incremental_detection() {
+        use detection::EncodingDetector;
+
+        let encode = |text: &str, encoding| {
+            Translator::new(Encoding::UTF8, encoding)
+                .unwrap()
+                .convert(text.as_bytes())
+                .unwrap()
+        };
+
+        // Feeding chunks reaches the same result as detecting the whole sample
+        let polish = encode(
+            "Zażółć gęślą jaźń. Właściciel sklepu powiedział, że dostawa będzie jutro.",
+            Encoding::WINDOWS_1250,
+        );
+        let whole = EncodingDetector::new().detect(&polish);
+        assert_eq!(whole.encoding, Encoding::WINDOWS_1250);
+
+        let mut detector = EncodingDetector::new();
+        for chunk in polish.chunks(7) {
+            assert!(detector.feed(chunk));
+        }
+        assert_eq!(detector.bytes_seen(), polish.len());
+        let fed = detector.conclude();
+        assert_eq!(fed.encoding, whole.encoding);
+        assert_eq!(fed.confidence, whole.confidence);
+
+        // The sample limit stops feeding, and reset starts over
+        let mut limited = EncodingDetector::with_sample_size(10);
+        assert!(limited.feed(b"hello"));
+        assert!(!limited.feed(b" world, and more"));
+        assert_eq!(limited.bytes_seen(), 10);
+        limited.reset();
+        assert_eq!(limited.bytes_seen(), 0);
+
+        // EBCDIC and DOS pages are scored rather than falling back to ASCII
+        let ebcdic = encode(
+            "CUSTOMER NAME AND ADDRESS 12345 MAIN STREET",
+            Encoding::EBCDIC_037,
+        );
+        assert_eq!(
+            EncodingDetector::new().detect(&ebcdic).encoding,
+            Encoding::EBCDIC_037
+        );
+        let french = encode("Le café est très différent à Noël", Encoding::CP_437);
+        assert_eq!(
+            EncodingDetector::new().detect(&french).encoding,
+            Encoding::CP_437
+        );
+    }
+
+    #[test]
+    fn test_document_hints() {
+        use detection::{DetectionSource, EncodingDetector, declared_encoding};
+
+        assert_eq!(
+            declared_encoding(
+                b"<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=KOI8-R\">"
+            ),
+            Some(Encoding::KOI8_R)
+        );
+        assert_eq!(
+            declared_encoding(b"Subject: hi\r\nContent-Type: text/plain; charset=\"cp1250\"\r\n"),
+            Some(Encoding::WINDOWS_1250)
+        );
+        assert_eq!(
+            declared_encoding(b"<?xml version=\"1.0\"?>"),
+            Some(Encoding::UTF8)
+        );
+        assert_eq!(
+            declared_encoding(b"<meta charset=\"utf-16\">"),
+            Some(Encoding::UTF8)
+        );
+        assert_eq!(declared_encoding(b"<meta charset=\"x-unknown\">"), None);
+        assert_eq!(declared_encoding(b"plain text with charset=latin1"), None);
+
+        let detector = EncodingDetector::new();
+
+        // The declaration decides when the data decodes in it
+        let page = b"<meta charset=\"windows-1250\"><p>Za\xBF\xF3\xB3\xE6</p>";
+        let result = detector.detect_with_document_hints(page);
+        assert_eq!(result.encoding, Encoding::WINDOWS_1250);
+        assert_eq!(result.source, DetectionSource::Declaration);
+        assert_eq!(result.declared, Some(Encoding::WINDOWS_1250));
+        assert_eq!(result.candidates[0].0, Encoding::WINDOWS_1250);
+        assert_eq!(detector.detect(page).source, DetectionSource::Statistics);
+
+        // A stale declaration in a page re-saved as UTF-8 is overruled
+        let result = detector.detect_with_document_hints(
+            "<meta charset=\"us-ascii\"><p>Grüße aus Köln</p>".as_bytes(),
+        );
+        assert_eq!(result.encoding, Encoding::UTF8);
+        assert_eq!(result.source, DetectionSource::Statistics);
+        assert_eq!(result.declared, Some(Encoding::ASCII));
+
+        // Latin-1 pages with C1 bytes are Windows-1252, and a BOM beats any declaration
+        let result = detector.detect_with_document_hints(b"<meta charset=latin1>\x93quoted\x94");
+        assert_eq!(result.encoding, Encoding::WINDOWS_1252);
+        assert_eq!(result.declared, Some(Encoding::ISO_8859_1));
+        let result = detector.detect_with_document_hints(b"\xEF\xBB\xBF<meta charset=\"koi8-r\">");
+        assert_eq!(result.encoding, Encoding::UTF8);
+        assert_eq!(result.source, DetectionSource::Bom);
+    }
+
+    #[test]
+    fn test_language_detection() {
+        use detection::EncodingDetector;
+
+        let detector = EncodingDetector::new();
+        let detect = |text: &str, encoding| {
+            let bytes = Translator::new(Encoding::UTF8, encoding)
+                .unwrap()
+                .convert(text.as_bytes())
+                .unwrap();
+            detector.detect(&bytes)
+        };
+
+        // Each Cyrillic code page is told apart from the others by letter frequencies
+        let russian = "В последние годы город заметно изменился: появились новые парки, \
+                       отремонтировали старые улицы, а в центре открылось несколько музеев.";
+        for encoding in [
+            Encoding::WINDOWS_1251,
+            Encoding::KOI8_R,
+            Encoding::CP_866,
+            Encoding::ISO_8859_5,
+        ] {
+            let result = detect(russian, encoding);
+            assert_eq!(result.encoding, encoding);
+            assert!(result.confidence > 0.8, "{:?}", result.candidates);
+        }
+        assert_eq!(
+            detect("Привет, мир", Encoding::KOI8_R).encoding,
+            Encoding::KOI8_R
+        );
+
+        // Greek and Hebrew text without code-page-specific punctuation fits both
+        let greek = detect(
+            "Η Αθήνα είναι η πρωτεύουσα της Ελλάδας και μία από τις αρχαιότερες πόλεις.",
+            Encoding::ISO_8859_7,
+        );
+        assert!(matches!(
+            greek.encoding,
+            Encoding::WINDOWS_1253 | Encoding::ISO_8859_7
+        ));
+        assert!(
+            greek
+                .candidates
+                .iter()
+                .any(|&(e, _)| e == Encoding::ISO_8859_7)
+        );
+        let hebrew = detect(
+            "ירושלים היא בירת ישראל והעיר הגדולה ביותר במדינה.",
+            Encoding::WINDOWS_1255,
+        );
+        assert_eq!(hebrew.encoding, Encoding::WINDOWS_1255);
+
+        let arabic = "القاهرة هي عاصمة جمهورية مصر العربية وأكبر مدنها.";
+        assert_eq!(
+            detect(arabic, Encoding::WINDOWS_1256).encoding,
+            Encoding::WINDOWS_1256
+        );
+        assert_eq!(
+            detect(arabic, Encoding::ISO_8859_6).encoding,
+            Encoding::ISO_8859_6
+        );
+
+        // Latin text is not mistaken for another script, nor UTF-8 for a code page
+        let french = detect(
+            "Le cœur déçu mais l'âme plutôt naïve.",
+            Encoding::WINDOWS_1252,
+        );
+        assert!(french.candidates.iter().all(|&(e, confidence)| {
+            !matches!(e, Encoding::WINDOWS_1251 | Encoding::KOI8_R) || confidence < 0.2
+        }));
+        assert_eq!(detector.detect(russian.as_bytes()).encoding, Encoding::UTF8);
+    }
+
+    #[test]
+    fn test_code_page_families() {
+        use detection::EncodingDetector;
+
+        let detector = EncodingDetector::new();
+        let detect = |text: &str, encoding| {
+            let bytes = Translator::new(Encoding::UTF8, encoding)
+                .unwrap()
+                .convert(text.as_bytes())
+                .unwrap();
+            detector.detect(&bytes)
+        };
+        // Closely related code pages may score the same; the right one must be among the best
+        let best = |text: &str, encoding| {
+            let result = detect(text, encoding);
+            assert!(
+                result
+                    .candidates
+                    .iter()
+                    .any(|&(e, confidence)| e == encoding && confidence == result.confidence),
+                "{}: {:?}",
+                encoding.name(),
+                result.candidates
+            );
+        };
+
+        // ISO-8859 and Windows Latin variants
+        let polish = "Zażółć gęślą jaźń, powiedział Łukasz i poszedł do sklepu po chleb.";
+        let turkish = "Bugün İstanbul'da hava çok güzel, şehirde yürüyüş yaptık ve çay içtik.";
+        let baltic = "Rīgā šodien līst, bet Vilniuje saulėta ir šilta, sakė žmonės.";
+        assert_eq!(
+            detect(polish, Encoding::ISO_8859_2).encoding,
+            Encoding::ISO_8859_2
+        );
+        best(turkish, Encoding::ISO_8859_9);
+        best(turkish, Encoding::WINDOWS_1254);
+        best(baltic, Encoding::ISO_8859_13);
+        best(baltic, Encoding::WINDOWS_1257);
+
+        // Thai, which has combining vowel signs and no spaces between words
+        let thai = detect("กรุงเทพมหานครเป็นเมืองหลวงของประเทศไทย", Encoding::WINDOWS_874);
+        assert!(matches!(
+            thai.encoding,
+            Encoding::WINDOWS_874 | Encoding::ISO_8859_11
+        ));
+
+        // DOS code pages
+        assert_eq!(detect(polish, Encoding::CP_852).encoding, Encoding::CP_852);
+        best(turkish, Encoding::CP_857);
+        let russian = "В последние годы город заметно изменился: появились новые парки.";
+        assert_eq!(detect(russian, Encoding::CP_855).encoding, Encoding::CP_855);
+        let hebrew = "ירושלים היא בירת ישראל והעיר הגדולה ביותר במדינה.";
+        assert_eq!(detect(hebrew, Encoding::CP_862).encoding, Encoding::CP_862);
+
+        // Mac encodings
+        let french = "Ça coûte très cher à Noël, déclara l'élève français.";
+        assert_eq!(
+            detect(french, Encoding::MAC_ROMAN).encoding,
+            Encoding::MAC_ROMAN
+        );
+        assert_eq!(
+            detect(russian, Encoding::MAC_CYRILLIC).encoding,
+            Encoding::MAC_CYRILLIC
+        );
+
+        // EBCDIC national variants
+        best(
+            "Grüße aus München, schöne Äpfel und Öl.",
+            Encoding::EBCDIC_273,
+        );
+        best(french, Encoding::EBCDIC_297);
+        best(french, Encoding::EBCDIC_500);
+        for encoding in [
+            Encoding::EBCDIC_277,
+            Encoding::EBCDIC_278,
+            Encoding::EBCDIC_280,
+            Encoding::EBCDIC_284,
+            Encoding::EBCDIC_285,
+            Encoding::EBCDIC_1047,
+        ] {
+            best("Hello from the mainframe, the report is ready.", encoding);
+        }
+    }
+
+    #[test]
+    fn test_single_byte_tables_are_complete() {
+        // Everything `Encoding::all` lists, and so `fast-encode list` shows, has its own table
+        let single_byte: Vec<Encoding> = Encoding::all()
+            .iter()
+            .copied()
+            .filter(|&e| !e.is_multibyte() && e != Encoding::ASCII)
+            .collect();
+        for (i, &encoding) in single_byte.iter().enumerate() {
+            let table = tables::get_encoding_chars(encoding);
+            assert_ne!(table, &tables::ASCII_CHARS, "{}", encoding.name());
+            for &other in &single_byte[i + 1..] {
+                assert_ne!(
+                    table,
+                    tables::get_encoding_chars(other),
+                    "{} and {}",
+                    encoding.name(),
+                    other.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_labels_and_code_pages() {
+        for (label, encoding) in [
+            ("utf-8", Encoding::UTF8),
+            ("UTF8", Encoding::UTF8),
+            ("unicode-1-1-utf-8", Encoding::UTF8),
+            ("\tlatin1 ", Encoding::ISO_8859_1),
+            ("ISO_8859-1:1987", Encoding::ISO_8859_1),
+            ("iso8859-15", Encoding::ISO_8859_15),
+            ("x-cp1251", Encoding::WINDOWS_1251),
+            ("koi8_r", Encoding::KOI8_R),
+            ("ms_kanji", Encoding::SHIFT_JIS),
+            ("ks_c_5601-1987", Encoding::EUC_KR),
+            ("ebcdic-cp-be", Encoding::EBCDIC_500),
+            ("CP037", Encoding::EBCDIC_037),
+            ("ibm-1047", Encoding::EBCDIC_1047),
+            ("CCSID 00500", Encoding::EBCDIC_500),
+            ("866", Encoding::CP_866),
+            ("ibm819", Encoding::ISO_8859_1),
+        ] {
+            assert_eq!(Encoding::for_label(label), Some(encoding), "{}", label);
+        }
+        for label in ["", " ", "utf-7", "cp99999", "cp", "x-user-defined"] {
+            assert_eq!(Encoding::for_label(label), None, "{}", label);
+        }
+
+        // Every code page number leads back to its encoding
+        for &encoding in Encoding::all() {
+            if let Some(code_page) = encoding.code_page() {
+                assert_eq!(Encoding::from_code_page(code_page), Some(encoding));
+            }
+        }
+        assert_eq!(Encoding::WINDOWS_1252.code_page(), Some(1252));
+        assert_eq!(Encoding::EBCDIC_037.code_page(), Some(37));
+        assert_eq!(Encoding::from_code_page(1208), Some(Encoding::UTF8));
+        assert_eq!(Encoding::from_code_page(1), None);
+        assert_eq!(Encoding::ISO_8859_16.code_page(), None);
+
+        // Labels lead to working tables, not just to a name
+        for (label, byte, expected) in [
+            ("latin2", 0xB1, 'ą'),
+            ("iso-ir-109", 0xB1, 'ħ'),
+            ("ISO-8859-9", 0xFD, 'ı'),
+            ("cp1254", 0xF0, 'ğ'),
+            ("windows-1257", 0xE8, 'č'),
+            ("TIS-620", 0xA1, 'ก'),
+            ("ibm852", 0xA5, 'ą'),
+            ("cp862", 0x80, 'א'),
+            ("macintosh", 0x8E, 'é'),
+            ("x-mac-cyrillic", 0x80, 'А'),
+            ("IBM273", 0x4A, 'Ä'),
+            ("CCSID 00285", 0x5B, '£'),
+            ("cp500", 0x4A, '['),
+            ("ibm-1047", 0xAD, '['),
+        ] {
+            let encoding = Encoding::for_label(label).expect(label);
+            assert_eq!(encoding.decode_byte(byte), Some(expected), "{}", label);
+        }
+    }
+
+    #[test]
+    fn test_convert_into_buffers() {
+        let ebcdic = Translator::new(Encoding::EBCDIC_037, Encoding::UTF8).unwrap();
+        let mut output = b"> ".to_vec();
+        ebcdic.convert_into(&[0xC8, 0x89], &mut output).unwrap();
+        assert_eq!(output, b"> Hi");
+
+        // A failed conversion leaves the buffer as it was
+        let latin = Translator::new(Encoding::UTF8, Encoding::ISO_8859_1).unwrap();
+        assert!(latin.convert_into("€".as_bytes(), &mut output).is_err());
+        assert_eq!(output, b"> Hi");
+
+        // Every path agrees with convert, and max_output_len bounds it
+        let samples: [&[u8]; 3] = [
+            b"plain",
+            "caf\u{e9} \u{20ac}".as_bytes(),
+            &[0xA4, 0xE9, 0x41],
+        ];
+        for &from in Encoding::all() {
+            for &to in Encoding::all() {
+                let translator = Translator::new(from, to).unwrap();
+                for &input in &samples {
+                    let Ok(expected) = translator.convert(input) else {
+                        continue;
+                    };
+                    let mut appended = vec![0];
+                    translator.convert_into(input, &mut appended).unwrap();
+                    assert_eq!(appended[1..], expected, "{} -> {}", from.name(), to.name());
+
+                    let mut buffer = vec![0; translator.max_output_len(input.len())];
+                    let written = translator.convert_to_slice(input, &mut buffer).unwrap();
+                    assert_eq!(
+                        buffer[..written],
+                        expected,
+                        "{} -> {}",
+                        from.name(),
+                        to.name()
+                    );
+                }
+            }
+        }
+
+        let mut small = [0; 3];
+        assert_eq!(
+            ebcdic.convert_to_slice(&[0xC8, 0x89, 0x5A, 0x5A], &mut small),
+            Err(Error::OutputTooSmall {
+                needed: 4,
+                available: 3
+            })
+        );
+        let table = Translator::new(Encoding::EBCDIC_037, Encoding::ISO_8859_1).unwrap();
+        assert_eq!(table.convert_to_slice(&[0xC8, 0x89], &mut small), Ok(2));
+        assert_eq!(small[..2], *b"Hi");
+        assert_eq!(ebcdic.max_output_len(10), 30);
+        assert_eq!(table.max_output_len(10), 10);
+    }
+
+    #[test]
+    fn test_record_formats() {
+        use crate::fixed_width::{FieldKind, Layout};
+        use crate::records::{RecordFormat, RecordReader, RecordWriter};
+
+        let layout = Layout::new()
+            .text("id", 2)
+            .field(
+                "count",
+                2,
+                FieldKind::Binary {
+                    signed: false,
+                    scale: 0,
+                },
+            )
+            .text("name", 4);
+        let records: [&[u8]; 3] = [b"AB\x00\x2AJOHN", b"CD\xC1\xF0ANN ", b"EF\x00\x00"];
+
+        // ASCII out to EBCDIC and back: the binary field is never translated
+        for format in [
+            RecordFormat::Variable,
+            RecordFormat::VariableBlocked,
+            RecordFormat::Fixed(8),
+        ] {
+            let to_ebcdic = Translator::new(Encoding::ISO_8859_1, Encoding::EBCDIC_037).unwrap();
+            let mut writer = RecordWriter::new(Vec::new(), format, to_ebcdic)
+                .layout(layout.clone())
+                .block_size(24);
+            let mut expected = Vec::new();
+            for record in records {
+                let result = writer.write_record(record);
+                if format == RecordFormat::Fixed(8) && record.len() != 8 {
+                    assert!(result.is_err());
+                    continue;
+                }
+                result.unwrap();
+                expected.push(record.to_vec());
+            }
+            let dataset = writer.finish().unwrap();
+            assert_eq!(dataset.windows(2).filter(|w| *w == [0x00, 0x2A]).count(), 1);
+
+            let to_latin = Translator::new(Encoding::EBCDIC_037, Encoding::ISO_8859_1).unwrap();
+            let reader = RecordReader::new(&dataset[..], format, to_latin).layout(layout.clone());
+            let read: Vec<Vec<u8>> = reader.map(|record| record.unwrap()).collect();
+            assert_eq!(read, expected, "{:?}", format);
+        }
+
+        // Two records do not fit a 24-byte block, so each gets its own BDW
+        let plain = Translator::new(Encoding::EBCDIC_037, Encoding::EBCDIC_037).unwrap();
+        let mut writer =
+            RecordWriter::new(Vec::new(), RecordFormat::VariableBlocked, plain).block_size(24);
+        writer.write_record(&[0xC1; 10]).unwrap();
+        writer.write_record(&[0xC2; 10]).unwrap();
+        let blocked = writer.finish().unwrap();
+        assert_eq!(blocked.len(), 2 * (4 + 4 + 10));
+        assert_eq!(
+            blocked[..8],
+            [0x00, 0x12, 0x00, 0x00, 0x00, 0x0E, 0x00, 0x00]
+        );
+
+        // Errors carry offsets from the start of the dataset
+        let translator = Translator::new(Encoding::EBCDIC_037, Encoding::UTF8).unwrap();
+        let mut truncated = RecordReader::new(
+            &[0x00, 0x08, 0x00, 0x00, 0xC1][..],
+            RecordFormat::Variable,
+            translator.clone(),
+        );
+        let error = truncated.read_record().unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        let to_ascii = Translator::new(Encoding::EBCDIC_037, Encoding::WINDOWS_1252).unwrap();
+        let unmappable = [
+            0x00, 0x05, 0x00, 0x00, 0xC1, 0x00, 0x06, 0x00, 0x00, 0xC1, 0xFF,
+        ];
+        let mut reader = RecordReader::new(&unmappable[..], RecordFormat::Variable, to_ascii);
+        assert_eq!(reader.read_record().unwrap().unwrap(), b"A");
+        let error = reader.read_record().unwrap_err();
+        assert_eq!(
+            error
+                .into_inner()
+                .unwrap()
+                .downcast::<Error>()
+                .unwrap()
+                .as_ref(),
+            &Error::UnmappableSource {
+                byte: 0xFF,
+                position: 10
+            }
+        );
+
+        let spanned = [0x00, 0x05, 0x01, 0x00, 0xC1];
+        let mut reader = RecordReader::new(&spanned[..], RecordFormat::Variable, translator);
+        assert!(reader.read_record().is_err());
+    }
+
+    #[test]
+    fn test_numeric_codecs() {
+        use crate::numeric::{Decimal, PackedDecimal, ZonedDecimal};
+
+        // PIC S9(4) COMP-3: an even digit count leaves a zero pad nibble
+        let packed = PackedDecimal::new(4, 0);
+        assert_eq!(packed.len(), 3);
+        assert_eq!(
+            packed.encode(Decimal::from(-1234)).unwrap(),
+            [0x01, 0x23, 0x4D]
+        );
+        assert_eq!(
+            packed.unsigned().encode(Decimal::from(7)).unwrap(),
+            [0x00, 0x00, 0x7F]
+        );
+        assert_eq!(
+            packed.decode(&[0x00, 0x00, 0x5B]).unwrap(),
+            Decimal::from(-5)
+        );
+        assert!(packed.unsigned().decode(&[0x00, 0x00, 0x5D]).is_err());
+        assert!(packed.unsigned().encode(Decimal::from(-1)).is_err());
+        assert!(packed.decode(&[0x00, 0x0A, 0x0C]).is_err());
+        assert!(packed.decode(&[0x00, 0x01, 0x02]).is_err());
+        assert!(packed.decode(&[0x01, 0x2C]).is_err());
+        assert!(packed.encode(Decimal::from(12345)).is_err());
+
+        // Scale: values are rescaled, but digits are never dropped
+        let money = PackedDecimal::new(5, 2);
+        let amount: Decimal = "-12.3".parse().unwrap();
+        assert_eq!(money.encode(amount).unwrap(), [0x01, 0x23, 0x0D]);
+        assert_eq!(
+            money.decode(&[0x01, 0x23, 0x0D]).unwrap().to_string(),
+            "-12.30"
+        );
+        assert!(money.encode("1.005".parse().unwrap()).is_err());
+
+        for value in [-99_999, -100, -1, 0, 1, 42, 99_999] {
+            let decimal = Decimal::new(value, 2);
+            assert_eq!(
+                money.decode(&money.encode(decimal).unwrap()).unwrap(),
+                decimal
+            );
+            let zoned = ZonedDecimal::new(5, 2);
+            assert_eq!(
+                zoned.decode(&zoned.encode(decimal).unwrap()).unwrap(),
+                decimal
+            );
+            let ascii = zoned.ascii();
+            assert_eq!(
+                ascii.decode(&ascii.encode(decimal).unwrap()).unwrap(),
+                decimal
+            );
+        }
+
+        // Zoned: the sign lives in the last digit's zone
+        let zoned = ZonedDecimal::new(3, 1);
+        assert_eq!(
+            zoned.encode("-4.2".parse().unwrap()).unwrap(),
+            [0xF0, 0xF4, 0xD2]
+        );
+        assert_eq!(
+            zoned.unsigned().encode("4.2".parse().unwrap()).unwrap(),
+            [0xF0, 0xF4, 0xF2]
+        );
+        assert_eq!(
+            zoned.ascii().encode("-4.2".parse().unwrap()).unwrap(),
+            b"04r"
+        );
+        assert_eq!(zoned.ascii().decode(b"04K").unwrap().to_string(), "-4.2");
+        assert_eq!(zoned.ascii().decode(b"04B").unwrap().to_string(), "4.2");
+        assert!(zoned.decode(&[0xF0, 0x40, 0xC2]).is_err());
+        assert!(zoned.encode(Decimal::from(100)).is_err());
+
+        assert_eq!("+0.50".parse::<Decimal>().unwrap(), Decimal::new(50, 2));
+        assert_eq!(Decimal::new(-5, 3).to_string(), "-0.005");
+        assert!("1.2.3".parse::<Decimal>().is_err());
+        assert!("".parse::<Decimal>().is_err());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_conversion() {
+        let text = "Grüße, мир! 𝄞 Tiếng Việt ｶﾀｶﾅ 漢字 ".repeat(50);
+        for (from, to) in [
+            (Encoding::UTF8, Encoding::UTF16LE),
+            (Encoding::UTF16BE, Encoding::UTF8),
+            (Encoding::SHIFT_JIS, Encoding::EUC_JP),
+            (Encoding::WINDOWS_1258, Encoding::UTF8),
+            (Encoding::EBCDIC_037, Encoding::ISO_8859_1),
+        ] {
+            let input = Translator::builder(Encoding::UTF8, from)
+                .policy(ErrorPolicy::Ignore)
+                .build()
+                .unwrap()
+                .convert(text.as_bytes())
+                .unwrap();
+            let translator = Translator::builder(from, to)
+                .policy(ErrorPolicy::Replace)
+                .build()
+                .unwrap();
+            let expected = translator.convert(&input).unwrap();
+            for chunk_size in [0, 1, 3, 64, 1 << 20] {
+                assert_eq!(
+                    translator.convert_parallel(&input, chunk_size).unwrap(),
+                    expected,
+                    "{} -> {} in {} byte chunks",
+                    from.name(),
+                    to.name(),
+                    chunk_size
+                );
+            }
+        }
+
+        // Errors match the serial ones, positions included
+        let mut input = "añb".repeat(200).into_bytes();
+        input[450] = 0xFF;
+        let utf8 = Translator::new(Encoding::UTF8, Encoding::UTF16LE).unwrap();
+        let latin = Translator::new(Encoding::UTF8, Encoding::WINDOWS_1252).unwrap();
+        for translator in [&utf8, &latin] {
+            assert_eq!(
+                translator.convert_parallel(&input, 100),
+                translator.convert(&input)
+            );
+        }
+        let euro = "€ ".repeat(100);
+        let latin1 = Translator::new(Encoding::UTF8, Encoding::ISO_8859_1).unwrap();
+        assert_eq!(
+            latin1.convert_parallel(euro.as_bytes(), 16),
+            Err(Error::UnmappableTarget {
+                character: '€',
+                position: 0
+            })
+        );
+
+        let table = Translator::new(Encoding::WINDOWS_1252, Encoding::ISO_8859_1).unwrap();
+        let mut buffer = vec![b'x'; 1000];
+        buffer[777] = 0x80;
+        buffer[900] = 0x80;
+        let expected = Error::UnmappableSource {
+            byte: 0x80,
+            position: 777,
+        };
+        assert_eq!(table.convert_parallel(&buffer, 64), Err(expected.clone()));
+        assert_eq!(
+            table.convert_in_place_parallel(&mut buffer, 64),
+            Err(expected)
+        );
+        assert!(utf8.convert_in_place_parallel(&mut buffer, 64).is_err());
+    }
 }
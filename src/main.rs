@@ -4,21 +4,37 @@
 //! supporting mainframe data processing and enterprise file formats.
 
 #[cfg(feature = "cli")]
-use std::fs;
+use std::collections::{HashMap, hash_map::Entry};
 #[cfg(feature = "cli")]
-use std::io::{self, Read, Write};
+use std::fs::{self, File};
 #[cfg(feature = "cli")]
-use std::path::PathBuf;
+use std::io::{self, BufWriter, IsTerminal, Read, Write};
+#[cfg(feature = "cli")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "cli")]
+use std::sync::Arc;
 
 #[cfg(feature = "cli")]
 use anyhow::{Context, Result};
 #[cfg(feature = "cli")]
-use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
 #[cfg(feature = "cli")]
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use fast_encode::detection::EncodingDetector;
-use fast_encode::{Encoding, Error as EncodeError, Translator};
+use fast_encode::analysis::ByteStats;
+use fast_encode::custom::CustomCodePage;
+use fast_encode::detection::{DetectionResult, DetectionSource, EncodingDetector};
+use fast_encode::mojibake;
+#[cfg(feature = "cli")]
+use fast_encode::pipeline::{self, Transform};
+#[cfg(feature = "cli")]
+use fast_encode::translit::Transliterator;
+#[cfg(feature = "cli")]
+use fast_encode::validation::{InvalidSequence, Validator};
+use fast_encode::{
+    Backend, ControlMode, Encoding, ErrorPolicy, NewlineMode, StreamingTranslator, SurrogateMode,
+    Translator,
+};
 
 #[cfg(not(feature = "cli"))]
 fn main() {
@@ -40,9 +56,70 @@ struct Cli {
     #[arg(short, long, global = true)]
     verbose: bool,
 
-    /// Output format (text, json)
-    #[arg(long, global = true, default_value = "text")]
+    /// Output format: text (default) or json; export writes json (default), csv or ucm tables
+    #[arg(long = "format", global = true, value_name = "FORMAT")]
+    format_arg: Option<FormatArg>,
+
+    /// Output format of every command except export, resolved from --format
+    #[arg(skip = OutputFormat::Text)]
     format: OutputFormat,
+
+    /// Treat warnings (e.g. replacements in lossy output) as failures
+    #[arg(long, global = true)]
+    strict: bool,
+}
+
+/// Process exit codes, stable for use in scripts
+#[cfg(feature = "cli")]
+mod exit_code {
+    use fast_encode::Error as EncodeError;
+
+    /// Everything succeeded
+    pub const SUCCESS: i32 = 0;
+    /// Generic failure, including failed validation or batch jobs
+    pub const FAILURE: i32 = 1;
+    // 2 is used by the argument parser for invalid command lines
+    /// Input could not be converted (unmappable or malformed data)
+    pub const CONVERSION_ERROR: i32 = 3;
+    /// Detection confidence was below `--min-confidence`
+    pub const LOW_CONFIDENCE: i32 = 4;
+    /// Output was written but is lossy (replacements made under `--strict`)
+    pub const LOSSY_OUTPUT: i32 = 5;
+    /// A file could not be read or written
+    pub const IO_ERROR: i32 = 6;
+
+    /// Non-zero status of a command that has already reported why it failed
+    #[derive(Debug)]
+    pub struct ExitStatus(pub i32);
+
+    impl std::fmt::Display for ExitStatus {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "exit status {}", self.0)
+        }
+    }
+
+    impl std::error::Error for ExitStatus {}
+
+    /// `Ok` for [`SUCCESS`], otherwise an [`ExitStatus`] error
+    pub fn status(code: i32) -> anyhow::Result<()> {
+        match code {
+            SUCCESS => Ok(()),
+            code => Err(ExitStatus(code).into()),
+        }
+    }
+
+    /// Exit code for an error that aborted a command
+    pub fn for_error(error: &anyhow::Error) -> i32 {
+        if let Some(ExitStatus(code)) = error.downcast_ref() {
+            *code
+        } else if error.chain().any(|cause| cause.is::<EncodeError>()) {
+            CONVERSION_ERROR
+        } else if error.chain().any(|cause| cause.is::<std::io::Error>()) {
+            IO_ERROR
+        } else {
+            FAILURE
+        }
+    }
 }
 
 #[cfg(feature = "cli")]
@@ -62,16 +139,46 @@ enum Commands {
 
     /// Display detailed information about an encoding
     Info(InfoArgs),
+
+    /// Run the conversion jobs listed in a TOML manifest
+    Batch(BatchArgs),
+
+    /// Measure conversion throughput on this machine
+    Bench(BenchArgs),
+
+    /// Print the full 256-position table of a single-byte code page
+    Map(MapArgs),
+
+    /// Show byte positions where two code pages map to different characters
+    Compare(CompareArgs),
+
+    /// Export a code page mapping table for other tools
+    Export(ExportArgs),
+
+    /// Hex dump a file alongside its decoding in one or more encodings
+    Inspect(InspectArgs),
+
+    /// Detect and reverse double-encoded UTF-8 (e.g. "cafÃ©" -> "café")
+    FixMojibake(FixMojibakeArgs),
+
+    /// List the files in a tree that are not in the expected encoding
+    Audit(AuditArgs),
+
+    /// Summarize the bytes of a file: histogram, controls, line endings, candidates
+    Stats(StatsArgs),
+
+    /// Run an HTTP conversion service (POST /convert?from=&to=, POST /detect)
+    Serve(ServeArgs),
 }
 
 #[cfg(feature = "cli")]
 #[derive(Args)]
 struct ConvertArgs {
-    /// Source encoding
+    /// Source encoding (or custom:PATH to a .ucm or TSV code page file)
     #[arg(short = 'f', long = "from")]
     from: EncodingArg,
 
-    /// Target encoding  
+    /// Target encoding (or custom:PATH to a .ucm or TSV code page file)
     #[arg(short = 't', long = "to")]
     to: EncodingArg,
 
@@ -87,13 +194,38 @@ struct ConvertArgs {
     #[arg(long, conflicts_with = "output")]
     in_place: bool,
 
-    /// Use lossy conversion with replacement character
+    /// Write converted files under this directory, mirroring the input tree
+    #[arg(long, conflicts_with_all = ["output", "in_place"])]
+    out_dir: Option<PathBuf>,
+
+    /// Read the files to convert from this list, one per line ("-" for stdin)
+    #[arg(short = '@', long, value_name = "LIST", conflicts_with_all = ["input", "output"])]
+    files_from: Option<PathBuf>,
+
+    /// The --files-from list is NUL-delimited (as written by `find -print0`)
+    #[arg(short = '0', long = "null", requires = "files_from")]
+    null_delimited: bool,
+
+    /// Skip files that already appear to be in the target encoding
     #[arg(long)]
+    skip_if_target: bool,
+
+    /// Convert the output back and compare it with the input; in-place files are left unchanged if it differs
+    #[arg(long, conflicts_with_all = ["strip_bom", "add_bom", "record_length", "newline", "controls", "normalize"])]
+    verify: bool,
+
+    /// How to handle unmappable input
+    #[arg(long, default_value = "strict")]
+    encoding_errors: ErrorMode,
+
+    /// Same as --encoding-errors replace with '?' (kept for compatibility)
+    #[arg(long, hide = true, conflicts_with = "encoding_errors")]
     lossy: bool,
 
-    /// Replacement character for lossy conversion (default: ?)
-    #[arg(long, default_value = "?")]
-    replacement: String,
+    /// Replacement for --encoding-errors replace and translit; any string, with escapes such as \uFFFD
+    /// (default: U+FFFD, or ? if the target cannot encode it)
+    #[arg(long, value_parser = parse_replacement)]
+    replacement: Option<String>,
 
     /// Strip BOM from input
     #[arg(long)]
@@ -107,18 +239,75 @@ struct ConvertArgs {
     #[arg(short, long)]
     recursive: bool,
 
+    /// Only convert files matching GLOB: the file name, or the path below the input
+    /// directory if GLOB contains '/' (repeatable)
+    #[arg(long, value_name = "GLOB", requires = "recursive")]
+    include: Vec<glob::Pattern>,
+
+    /// Leave out files matching GLOB, as for --include (repeatable)
+    #[arg(long, value_name = "GLOB", requires = "recursive")]
+    exclude: Vec<glob::Pattern>,
+
+    /// Write each converted file next to its source (or under --out-dir) with SUFFIX appended to its name
+    #[arg(long, value_name = "SUFFIX", conflicts_with_all = ["output", "in_place"])]
+    suffix: Option<String>,
+
+    /// Worker threads for directory and --files-from conversions (0 = one per CPU)
+    #[arg(short, long, default_value = "1")]
+    jobs: usize,
+
+    /// List what a directory or --files-from conversion would write, converting without writing anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Report progress on stderr: bytes read, rate and time remaining, or files done for several files
+    #[arg(long)]
+    progress: bool,
+
     /// Buffer size for large files (KB)
     #[arg(long, default_value = "64")]
     buffer_size: usize,
+
+    /// Split fixed-length input records (bytes) into lines
+    #[arg(long, value_name = "N")]
+    record_length: Option<usize>,
+
+    /// Remove trailing spaces from each record
+    #[arg(long, requires = "record_length")]
+    trim_trailing: bool,
+
+    /// Line ending written after each record
+    #[arg(long, default_value = "lf", requires = "record_length")]
+    line_ending: LineEnding,
+
+    /// Rewrite every line break in the output
+    #[arg(long, default_value = "keep")]
+    newline: NewlineArg,
+
+    /// Handle control characters other than tab and line breaks in the output
+    #[arg(long, default_value = "keep")]
+    controls: ControlArg,
+
+    /// Unicode normalization form for UTF-8/UTF-16/UTF-32 output
+    #[arg(long)]
+    normalize: Option<NormalizationForm>,
+
+    /// Handle unpaired surrogates in UTF-16 or UTF-32 input
+    #[arg(long, default_value = "reject")]
+    surrogates: SurrogateArg,
 }
 
 #[cfg(feature = "cli")]
 #[derive(Args)]
 struct DetectArgs {
-    /// Input file (stdin if not specified)
+    /// Input file or directory (stdin if not specified)
     #[arg(short, long)]
     input: Option<PathBuf>,
 
+    /// Worker threads for directory scans (0 = one per CPU)
+    #[arg(short, long, default_value = "0")]
+    jobs: usize,
+
     /// Show confidence scores
     #[arg(long)]
     confidence: bool,
@@ -127,17 +316,87 @@ struct DetectArgs {
     #[arg(long, default_value = "8192")]
     sample_size: usize,
 
+    /// Which part of the input to sample: the start, evenly spaced windows, or the end
+    #[arg(long, value_enum, default_value = "head")]
+    sample_strategy: SampleStrategy,
+
     /// Language hint for better detection accuracy
     #[arg(long)]
     language: Option<String>,
+
+    /// Use a charset declared in HTML, XML or Content-Type headers when the data agrees with it
+    #[arg(long, conflicts_with = "language")]
+    document_hints: bool,
+
+    /// Exit with a distinct code if confidence is below this value (0.0-1.0)
+    #[arg(long)]
+    min_confidence: Option<f64>,
+
+    /// Show the first N characters decoded under the top candidates
+    #[arg(long, value_name = "N")]
+    preview: Option<usize>,
+}
+
+/// Region of the input used for encoding detection
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum SampleStrategy {
+    /// The first bytes of the input
+    Head,
+    /// Windows spread evenly across the whole input
+    Spread,
+    /// The last bytes of the input
+    Tail,
+}
+
+/// Encoding families accepted by `list --category`, named as in [`Encoding::category`]
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Category {
+    /// UTF-8, UTF-16 and UTF-32
+    Unicode,
+    /// US-ASCII
+    Ascii,
+    /// ISO-8859 parts
+    Iso,
+    /// Windows code pages
+    Windows,
+    /// IBM EBCDIC code pages
+    Ebcdic,
+    /// DOS code pages
+    Dos,
+    /// KOI8-R
+    Koi,
+    /// Classic Mac OS code pages
+    Mac,
+    /// CJK multibyte encodings
+    Asian,
+}
+
+#[cfg(feature = "cli")]
+impl Category {
+    /// Name as returned by [`Encoding::category`]
+    fn name(self) -> &'static str {
+        match self {
+            Category::Unicode => "unicode",
+            Category::Ascii => "ascii",
+            Category::Iso => "iso",
+            Category::Windows => "windows",
+            Category::Ebcdic => "ebcdic",
+            Category::Dos => "dos",
+            Category::Koi => "koi",
+            Category::Mac => "mac",
+            Category::Asian => "asian",
+        }
+    }
 }
 
 #[cfg(feature = "cli")]
 #[derive(Args)]
 struct ListArgs {
-    /// Filter by category (unicode, windows, iso, ebcdic, dos, mac, asian)
+    /// Filter by category
     #[arg(short, long)]
-    category: Option<String>,
+    category: Option<Category>,
 
     /// Show only ASCII-compatible encodings
     #[arg(long)]
@@ -155,7 +414,7 @@ struct ListArgs {
 #[cfg(feature = "cli")]
 #[derive(Args)]
 struct ValidateArgs {
-    /// Input file (stdin if not specified)
+    /// Input file or directory (stdin if not specified)
     #[arg(short, long)]
     input: Option<PathBuf>,
 
@@ -163,9 +422,29 @@ struct ValidateArgs {
     #[arg(short, long)]
     encoding: EncodingArg,
 
+    /// Also report characters this encoding cannot represent
+    #[arg(long)]
+    to: Option<EncodingArg>,
+
     /// Show position of first error
     #[arg(long)]
     show_errors: bool,
+
+    /// List every invalid position with surrounding context
+    #[arg(long)]
+    all_errors: bool,
+
+    /// Stop after this many errors with --all-errors
+    #[arg(long, requires = "all_errors")]
+    max_errors: Option<usize>,
+
+    /// Bytes of context shown on each side of an error
+    #[arg(long, default_value = "8")]
+    context: usize,
+
+    /// Worker threads for directory scans (0 = one per CPU)
+    #[arg(short, long, default_value = "0")]
+    jobs: usize,
 }
 
 #[cfg(feature = "cli")]
@@ -180,248 +459,618 @@ struct InfoArgs {
 }
 
 #[cfg(feature = "cli")]
-#[derive(Clone, Debug, ValueEnum)]
-enum OutputFormat {
-    Text,
-    Json,
+#[derive(Args)]
+struct BatchArgs {
+    /// Job manifest (TOML)
+    manifest: PathBuf,
+
+    /// Stop at the first failing job
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// Buffer size for large files (KB)
+    #[arg(long, default_value = "64")]
+    buffer_size: usize,
+
+    /// Write a per-file summary report to this path
+    #[arg(long)]
+    report: Option<PathBuf>,
+
+    /// Format of the per-file report
+    #[arg(long, default_value = "json", requires = "report")]
+    report_format: ReportFormat,
 }
 
 #[cfg(feature = "cli")]
-#[derive(Clone, Debug)]
-enum EncodingArg {
-    Encoding(Encoding),
+#[derive(Args)]
+struct BenchArgs {
+    /// Encoding pair to measure as FROM:TO (repeatable; defaults to a representative set)
+    #[arg(long = "encoding-pair", value_name = "FROM:TO")]
+    pairs: Vec<EncodingPairArg>,
+
+    /// Input size per run (bytes, or with K/M/G suffix)
+    #[arg(long, default_value = "16M", value_parser = parse_size)]
+    size: usize,
+
+    /// Timed runs per pair and backend
+    #[arg(long, default_value = "5")]
+    iterations: usize,
 }
 
-impl std::str::FromStr for EncodingArg {
-    type Err = anyhow::Error;
+#[cfg(feature = "cli")]
+#[derive(Args)]
+struct MapArgs {
+    /// Encoding to dump
+    encoding: EncodingArg,
 
-    fn from_str(s: &str) -> Result<Self> {
-        let encoding = match s.to_uppercase().as_str() {
-            "UTF8" | "UTF-8" => Encoding::UTF8,
-            "UTF16LE" | "UTF-16LE" => Encoding::UTF16LE,
-            "UTF16BE" | "UTF-16BE" => Encoding::UTF16BE,
-            "ASCII" | "US-ASCII" => Encoding::ASCII,
-
-            // ISO-8859 series
-            "ISO88591" | "ISO-8859-1" | "LATIN1" => Encoding::ISO_8859_1,
-            "ISO88592" | "ISO-8859-2" | "LATIN2" => Encoding::ISO_8859_2,
-            "ISO88593" | "ISO-8859-3" | "LATIN3" => Encoding::ISO_8859_3,
-            "ISO88594" | "ISO-8859-4" | "LATIN4" => Encoding::ISO_8859_4,
-            "ISO88595" | "ISO-8859-5" => Encoding::ISO_8859_5,
-            "ISO88596" | "ISO-8859-6" => Encoding::ISO_8859_6,
-            "ISO88597" | "ISO-8859-7" => Encoding::ISO_8859_7,
-            "ISO88598" | "ISO-8859-8" => Encoding::ISO_8859_8,
-            "ISO88599" | "ISO-8859-9" | "LATIN5" => Encoding::ISO_8859_9,
-            "ISO885910" | "ISO-8859-10" | "LATIN6" => Encoding::ISO_8859_10,
-            "ISO885911" | "ISO-8859-11" => Encoding::ISO_8859_11,
-            "ISO885913" | "ISO-8859-13" | "LATIN7" => Encoding::ISO_8859_13,
-            "ISO885914" | "ISO-8859-14" | "LATIN8" => Encoding::ISO_8859_14,
-            "ISO885915" | "ISO-8859-15" | "LATIN9" => Encoding::ISO_8859_15,
-            "ISO885916" | "ISO-8859-16" | "LATIN10" => Encoding::ISO_8859_16,
-
-            // Windows code pages
-            "WINDOWS1250" | "WIN1250" | "CP1250" => Encoding::WINDOWS_1250,
-            "WINDOWS1251" | "WIN1251" | "CP1251" => Encoding::WINDOWS_1251,
-            "WINDOWS1252" | "WIN1252" | "CP1252" => Encoding::WINDOWS_1252,
-            "WINDOWS1253" | "WIN1253" | "CP1253" => Encoding::WINDOWS_1253,
-            "WINDOWS1254" | "WIN1254" | "CP1254" => Encoding::WINDOWS_1254,
-            "WINDOWS1255" | "WIN1255" | "CP1255" => Encoding::WINDOWS_1255,
-            "WINDOWS1256" | "WIN1256" | "CP1256" => Encoding::WINDOWS_1256,
-            "WINDOWS1257" | "WIN1257" | "CP1257" => Encoding::WINDOWS_1257,
-            "WINDOWS1258" | "WIN1258" | "CP1258" => Encoding::WINDOWS_1258,
-            "WINDOWS874" | "WIN874" | "CP874" => Encoding::WINDOWS_874,
-
-            // EBCDIC
-            "EBCDIC037" | "IBM037" | "CP037" => Encoding::EBCDIC_037,
-            "EBCDIC273" | "IBM273" | "CP273" => Encoding::EBCDIC_273,
-            "EBCDIC277" | "IBM277" | "CP277" => Encoding::EBCDIC_277,
-            "EBCDIC278" | "IBM278" | "CP278" => Encoding::EBCDIC_278,
-            "EBCDIC280" | "IBM280" | "CP280" => Encoding::EBCDIC_280,
-            "EBCDIC284" | "IBM284" | "CP284" => Encoding::EBCDIC_284,
-            "EBCDIC285" | "IBM285" | "CP285" => Encoding::EBCDIC_285,
-            "EBCDIC297" | "IBM297" | "CP297" => Encoding::EBCDIC_297,
-            "EBCDIC500" | "IBM500" | "CP500" => Encoding::EBCDIC_500,
-            "EBCDIC1047" | "IBM1047" | "CP1047" => Encoding::EBCDIC_1047,
-
-            // DOS/OEM
-            "CP437" | "DOS437" => Encoding::CP_437,
-            "CP850" | "DOS850" => Encoding::CP_850,
-            "CP852" | "DOS852" => Encoding::CP_852,
-            "CP855" | "DOS855" => Encoding::CP_855,
-            "CP857" | "DOS857" => Encoding::CP_857,
-            "CP860" | "DOS860" => Encoding::CP_860,
-            "CP861" | "DOS861" => Encoding::CP_861,
-            "CP862" | "DOS862" => Encoding::CP_862,
-            "CP863" | "DOS863" => Encoding::CP_863,
-            "CP865" | "DOS865" => Encoding::CP_865,
-            "CP866" | "DOS866" => Encoding::CP_866,
-
-            // Mac
-            "MACROMAN" | "MAC-ROMAN" => Encoding::MAC_ROMAN,
-            "MACCYRILLIC" | "MAC-CYRILLIC" => Encoding::MAC_CYRILLIC,
-
-            // Asian (placeholders)
-            "SHIFTJIS" | "SHIFT-JIS" | "SHIFT_JIS" => Encoding::SHIFT_JIS,
-            "EUCJP" | "EUC-JP" | "EUC_JP" => Encoding::EUC_JP,
-            "GB2312" => Encoding::GB2312,
-            "BIG5" => Encoding::BIG5,
-            "EUCKR" | "EUC-KR" | "EUC_KR" => Encoding::EUC_KR,
-
-            _ => anyhow::bail!("Unknown encoding: {}", s),
-        };
+    /// Omit positions that have no mapping
+    #[arg(long)]
+    mapped_only: bool,
+}
 
-        Ok(EncodingArg::Encoding(encoding))
-    }
+#[cfg(feature = "cli")]
+#[derive(Args)]
+struct CompareArgs {
+    /// First encoding
+    first: EncodingArg,
+
+    /// Second encoding
+    second: EncodingArg,
 }
 
 #[cfg(feature = "cli")]
-#[derive(Serialize)]
-struct ConversionResult {
-    success: bool,
-    bytes_processed: usize,
-    bytes_written: usize,
-    errors: Vec<String>,
-    processing_time_ms: u64,
+#[derive(Args)]
+struct ExportArgs {
+    /// Encoding to export
+    #[arg(short, long)]
+    encoding: EncodingArg,
+
+    /// Output file (stdout if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
 }
 
-#[derive(Serialize)]
-struct DetectionResult {
-    detected_encoding: Option<String>,
-    confidence: f64,
-    bom_detected: bool,
+#[cfg(feature = "cli")]
+#[derive(Args)]
+struct InspectArgs {
+    /// File to inspect
+    file: PathBuf,
+
+    /// Encoding to decode with (repeat for parallel columns)
+    #[arg(long = "as", value_name = "ENCODING", required = true)]
+    encodings: Vec<EncodingArg>,
+
+    /// Byte offset to start the dump at
+    #[arg(long, default_value = "0")]
+    offset: usize,
+
+    /// Number of bytes to dump (0 = to end of file, up to 1 MiB)
+    #[arg(short = 'n', long, default_value = "512")]
+    length: usize,
+
+    /// Bytes per row
+    #[arg(long, default_value = "16")]
+    width: usize,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Args)]
+struct FixMojibakeArgs {
+    /// Input UTF-8 file (stdin if not specified)
+    #[arg(short, long)]
+    input: Option<PathBuf>,
+
+    /// Output file (stdout if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Repair in-place (overwrite input file)
+    #[arg(long, conflicts_with = "output")]
+    in_place: bool,
+
+    /// Only report damage; exit non-zero if any is found
+    #[arg(long, conflicts_with_all = ["output", "in_place"])]
+    check: bool,
+
+    /// Code page the text was misread as (repeatable; default: common Western pages)
+    #[arg(long = "candidate", value_name = "ENCODING")]
+    candidates: Vec<EncodingArg>,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Args)]
+struct AuditArgs {
+    /// Directory to scan
+    dir: PathBuf,
+
+    /// Encoding every file is expected to be in
+    #[arg(short, long)]
+    expect: EncodingArg,
+
+    /// Minimum detector confidence for flagging a file that decodes without errors
+    #[arg(long, default_value = "0.5")]
+    min_confidence: f64,
+
+    /// Bytes sampled for encoding detection
+    #[arg(long, default_value = "65536")]
     sample_size: usize,
+
+    /// Worker threads (0 = one per CPU)
+    #[arg(short, long, default_value = "0")]
+    jobs: usize,
 }
 
 #[cfg(feature = "cli")]
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+#[derive(Args)]
+struct StatsArgs {
+    /// File to analyze (stdin if not specified)
+    file: Option<PathBuf>,
 
-    match cli.command {
-        Commands::Convert(ref args) => convert_command(args, &cli)?,
-        Commands::Detect(ref args) => detect_command(args, &cli)?,
-        Commands::List(ref args) => list_command(args, &cli)?,
-        Commands::Validate(ref args) => validate_command(args, &cli)?,
-        Commands::Info(ref args) => info_command(args, &cli)?,
-    }
+    /// Number of most frequent bytes to list
+    #[arg(long, default_value = "10")]
+    top: usize,
 
-    Ok(())
+    /// Bytes sampled for encoding detection
+    #[arg(long, default_value = "65536")]
+    sample_size: usize,
 }
 
 #[cfg(feature = "cli")]
-fn convert_command(args: &ConvertArgs, cli: &Cli) -> Result<()> {
-    let start_time = std::time::Instant::now();
+#[derive(Args)]
+struct ServeArgs {
+    /// Address to listen on (":PORT" listens on all interfaces)
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: String,
 
-    let EncodingArg::Encoding(from_encoding) = &args.from;
-    let EncodingArg::Encoding(to_encoding) = &args.to;
+    /// Connections handled concurrently
+    #[arg(long, default_value = "4")]
+    workers: usize,
 
-    if cli.verbose {
-        eprintln!(
-            "Converting from {} to {}",
-            from_encoding.name(),
-            to_encoding.name()
-        );
-    }
+    /// Conversion buffer size in KB
+    #[arg(long, default_value = "64")]
+    buffer_size: usize,
 
-    let translator = Translator::new(*from_encoding, *to_encoding).with_context(|| {
-        format!(
-            "Failed to create translator from {} to {}",
-            from_encoding.name(),
-            to_encoding.name()
-        )
-    })?;
+    /// Seconds a connection may stall on a read or write before it is dropped
+    #[arg(long, default_value = "30")]
+    timeout: u64,
+}
 
-    // Read input
-    let input_data = if let Some(ref input_path) = args.input {
-        if cli.verbose {
-            eprintln!("Reading from: {}", input_path.display());
-        }
-        fs::read(input_path)
-            .with_context(|| format!("Failed to read input file: {}", input_path.display()))?
-    } else {
-        if cli.verbose {
-            eprintln!("Reading from stdin");
-        }
-        let mut buffer = Vec::new();
-        io::stdin()
-            .read_to_end(&mut buffer)
-            .context("Failed to read from stdin")?;
-        buffer
-    };
+/// Values of the global --format option
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum FormatArg {
+    /// Human-readable text
+    Text,
+    /// JSON (for export, an object with one entry per mapped byte)
+    Json,
+    /// CSV with byte, code point, character and name columns (export only)
+    Csv,
+    /// ICU mapping table, .ucm (export only)
+    Ucm,
+}
 
-    let mut processed_data = input_data;
+#[cfg(feature = "cli")]
+#[derive(Clone, Debug, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
 
-    // Handle BOM stripping
-    if args.strip_bom {
-        if let Some(bom) = from_encoding.bom() {
-            if processed_data.starts_with(bom) {
-                processed_data = processed_data[bom.len()..].to_vec();
-                if cli.verbose {
-                    eprintln!("Stripped BOM ({} bytes)", bom.len());
-                }
-            }
-        }
-    }
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum LineEnding {
+    Lf,
+    Crlf,
+}
 
-    // Convert
-    let output_data = if args.lossy {
-        let replacement_byte = args
-            .replacement
-            .chars()
-            .next()
-            .context("Invalid replacement character")? as u8;
-        translator.convert_lossy(&processed_data, replacement_byte)
-    } else {
-        translator
-            .convert(&processed_data)
-            .context("Conversion failed")?
-    };
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum NewlineArg {
+    Lf,
+    Crlf,
+    Cr,
+    Nel,
+    Keep,
+}
 
-    // Handle BOM addition
-    let final_data = if args.add_bom {
-        if let Some(bom) = to_encoding.bom() {
-            let mut result = bom.to_vec();
-            result.extend(output_data);
-            result
-        } else {
-            output_data
+#[cfg(feature = "cli")]
+impl From<NewlineArg> for NewlineMode {
+    fn from(arg: NewlineArg) -> Self {
+        match arg {
+            NewlineArg::Lf => NewlineMode::Lf,
+            NewlineArg::Crlf => NewlineMode::CrLf,
+            NewlineArg::Cr => NewlineMode::Cr,
+            NewlineArg::Nel => NewlineMode::Nel,
+            NewlineArg::Keep => NewlineMode::Preserve,
         }
-    } else {
-        output_data
-    };
+    }
+}
 
-    // Write output
-    if args.in_place {
-        if let Some(ref input_path) = args.input {
-            fs::write(input_path, &final_data).with_context(|| {
-                format!("Failed to write to input file: {}", input_path.display())
-            })?;
-            if cli.verbose {
-                eprintln!("Updated file in-place: {}", input_path.display());
-            }
-        } else {
-            anyhow::bail!("Cannot use --in-place without input file");
-        }
-    } else if let Some(ref output_path) = args.output {
-        fs::write(output_path, &final_data)
-            .with_context(|| format!("Failed to write output file: {}", output_path.display()))?;
-        if cli.verbose {
-            eprintln!("Wrote to: {}", output_path.display());
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ControlArg {
+    /// Leave control characters as they are
+    Keep,
+    /// Drop control characters
+    Strip,
+    /// Replace each control character with a space
+    Space,
+    /// Replace each control character with a line break (per --newline)
+    Newline,
+}
+
+#[cfg(feature = "cli")]
+impl From<ControlArg> for ControlMode {
+    fn from(arg: ControlArg) -> Self {
+        match arg {
+            ControlArg::Keep => ControlMode::Preserve,
+            ControlArg::Strip => ControlMode::Strip,
+            ControlArg::Space => ControlMode::Replace(' '),
+            ControlArg::Newline => ControlMode::Replace('\n'),
         }
-    } else {
-        io::stdout()
-            .write_all(&final_data)
-            .context("Failed to write to stdout")?;
     }
+}
 
-    let processing_time = start_time.elapsed();
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum SurrogateArg {
+    /// Fail on the first unpaired surrogate
+    Reject,
+    /// Replace each unpaired surrogate with U+FFFD
+    Replace,
+    /// Keep unpaired surrogates in UTF-16 and UTF-32 output (WTF-8 in UTF-8 output)
+    Keep,
+}
 
-    if cli.verbose {
-        eprintln!(
-            "Processed {} bytes -> {} bytes in {:?}",
-            processed_data.len(),
-            final_data.len(),
-            processing_time
-        );
+#[cfg(feature = "cli")]
+impl From<SurrogateArg> for SurrogateMode {
+    fn from(arg: SurrogateArg) -> Self {
+        match arg {
+            SurrogateArg::Reject => SurrogateMode::Reject,
+            SurrogateArg::Replace => SurrogateMode::Replace,
+            SurrogateArg::Keep => SurrogateMode::Preserve,
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum NormalizationForm {
+    /// Canonical composition
+    Nfc,
+    /// Canonical decomposition
+    Nfd,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clone, Debug, ValueEnum)]
+enum ReportFormat {
+    Json,
+    Csv,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clone, Debug)]
+enum EncodingArg {
+    Encoding(Encoding),
+}
+
+impl std::str::FromStr for EncodingArg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // User-supplied code page file: custom:PATH
+        if let Some(path) = s.strip_prefix("custom:") {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read code page file: {}", path))?;
+            let name = Path::new(path)
+                .file_stem()
+                .map_or("custom".into(), |stem| stem.to_string_lossy());
+            let page = CustomCodePage::parse(name, &text)
+                .with_context(|| format!("Invalid code page file: {}", path))?;
+            return Ok(EncodingArg::Encoding(page.into_encoding()));
+        }
+
+        let encoding =
+            Encoding::for_label(s).ok_or_else(|| anyhow::anyhow!("Unknown encoding: {}", s))?;
+
+        Ok(EncodingArg::Encoding(encoding))
+    }
+}
+
+/// Parse a `--replacement` value, expanding `\n`, `\t`, `\r`, `\0`, `\\`,
+/// `\xNN`, `\uXXXX`, `\u{X..}` and `\UXXXXXXXX` escapes
+#[cfg(feature = "cli")]
+fn parse_replacement(value: &str) -> std::result::Result<String, String> {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            text.push(ch);
+            continue;
+        }
+
+        let escape = chars.next().ok_or("trailing backslash")?;
+        let literal = match escape {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '\\' => Some('\\'),
+            _ => None,
+        };
+        if let Some(literal) = literal {
+            text.push(literal);
+            continue;
+        }
+
+        let hex: String = match escape {
+            'x' => chars.by_ref().take(2).collect(),
+            'u' if chars.as_str().starts_with('{') => {
+                let rest = chars.as_str();
+                let end = rest.find('}').ok_or("unterminated \\u{...} escape")?;
+                let hex = rest[1..end].to_string();
+                chars = rest[end + 1..].chars();
+                hex
+            }
+            'u' => chars.by_ref().take(4).collect(),
+            'U' => chars.by_ref().take(8).collect(),
+            other => return Err(format!("unknown escape \\{}", other)),
+        };
+
+        let ch = u32::from_str_radix(&hex, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("invalid escape \\{}{}", escape, hex))?;
+        text.push(ch);
+    }
+
+    Ok(text)
+}
+
+/// A `FROM:TO` encoding pair
+#[cfg(feature = "cli")]
+#[derive(Clone, Debug)]
+struct EncodingPairArg {
+    from: Encoding,
+    to: Encoding,
+}
+
+#[cfg(feature = "cli")]
+impl std::str::FromStr for EncodingPairArg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (from, to) = s
+            .split_once(':')
+            .with_context(|| format!("Expected FROM:TO, got: {}", s))?;
+        let EncodingArg::Encoding(from) = from.parse()?;
+        let EncodingArg::Encoding(to) = to.parse()?;
+        Ok(Self { from, to })
+    }
+}
+
+/// Parse a byte count with an optional K/M/G suffix
+#[cfg(feature = "cli")]
+fn parse_size(s: &str) -> Result<usize> {
+    let s = s.trim();
+    let (digits, multiplier) = match s.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&s[..s.len() - 1], 1024),
+        Some('M') => (&s[..s.len() - 1], 1024 * 1024),
+        Some('G') => (&s[..s.len() - 1], 1024 * 1024 * 1024),
+        _ => (s, 1),
+    };
+    let value: usize = digits
+        .parse()
+        .with_context(|| format!("Invalid size: {}", s))?;
+    Ok(value * multiplier)
+}
+
+#[cfg(feature = "cli")]
+#[derive(Serialize)]
+struct ConversionResult {
+    success: bool,
+    bytes_processed: usize,
+    bytes_written: usize,
+    errors: Vec<String>,
+    processing_time_ms: u64,
+}
+
+#[cfg(feature = "cli")]
+fn main() {
+    let mut cli = Cli::parse();
+    cli.format = match (cli.format_arg, &cli.command) {
+        (None | Some(FormatArg::Text), _) => OutputFormat::Text,
+        (Some(FormatArg::Json), _) => OutputFormat::Json,
+        // Table formats are read by export itself
+        (Some(FormatArg::Csv | FormatArg::Ucm), Commands::Export(_)) => OutputFormat::Text,
+        (Some(_), _) => Cli::command()
+            .error(
+                clap::error::ErrorKind::InvalidValue,
+                "--format csv and ucm only apply to export; use text or json",
+            )
+            .exit(),
+    };
+
+    let result = match cli.command {
+        Commands::Convert(ref args) => convert_command(args, &cli),
+        Commands::Detect(ref args) => detect_command(args, &cli),
+        Commands::List(ref args) => list_command(args, &cli),
+        Commands::Validate(ref args) => validate_command(args, &cli),
+        Commands::Info(ref args) => info_command(args, &cli),
+        Commands::Batch(ref args) => batch_command(args, &cli),
+        Commands::Bench(ref args) => bench_command(args, &cli),
+        Commands::Map(ref args) => map_command(args, &cli),
+        Commands::Compare(ref args) => compare_command(args, &cli),
+        Commands::Export(ref args) => export_command(args, &cli),
+        Commands::Inspect(ref args) => inspect_command(args, &cli),
+        Commands::FixMojibake(ref args) => fix_mojibake_command(args, &cli),
+        Commands::Audit(ref args) => audit_command(args, &cli),
+        Commands::Stats(ref args) => stats_command(args, &cli),
+        Commands::Serve(ref args) => serve_command(args, &cli),
+    };
+
+    if let Err(error) = result {
+        if !error.is::<exit_code::ExitStatus>() {
+            eprintln!("Error: {:?}", error);
+        }
+        std::process::exit(exit_code::for_error(&error));
+    }
+}
+
+#[cfg(feature = "cli")]
+fn convert_command(args: &ConvertArgs, cli: &Cli) -> Result<()> {
+    let start_time = std::time::Instant::now();
+
+    let EncodingArg::Encoding(from_encoding) = &args.from;
+    let EncodingArg::Encoding(to_encoding) = &args.to;
+
+    if cli.verbose {
+        eprintln!(
+            "Converting from {} to {}",
+            from_encoding.name(),
+            to_encoding.name()
+        );
+    }
+
+    // clap drops `requires` when the required argument conflicts with one given (here --input)
+    if args.null_delimited && args.files_from.is_none() {
+        anyhow::bail!("-0/--null requires --files-from");
+    }
+    if let Some(ref list) = args.files_from {
+        return convert_file_list(args, list, cli);
+    }
+    if let Some(ref input_path) = args.input
+        && input_path.is_dir()
+    {
+        return convert_directory(args, input_path, cli);
+    }
+    if args.out_dir.is_some() {
+        anyhow::bail!("--out-dir requires a directory input or --files-from");
+    }
+    if args.suffix.is_some() || args.dry_run {
+        anyhow::bail!("--suffix and --dry-run require a directory input or --files-from");
+    }
+    if args.skip_if_target && !args.in_place {
+        anyhow::bail!("--skip-if-target requires --in-place, a directory input or --files-from");
+    }
+
+    let mut stream = conversion_stream(args, *from_encoding, *to_encoding)?;
+    let options = conversion_options(args, *to_encoding)?;
+
+    if args.in_place && args.input.is_none() {
+        anyhow::bail!("Cannot use --in-place without input file");
+    }
+
+    if let Some(input_path) = args.input.as_ref().filter(|_| args.skip_if_target)
+        && already_in_target(input_path, *from_encoding, *to_encoding)?.is_some()
+    {
+        match cli.format {
+            OutputFormat::Json => {
+                let result = ConversionResult {
+                    success: true,
+                    bytes_processed: 0,
+                    bytes_written: 0,
+                    errors: Vec::new(),
+                    processing_time_ms: start_time.elapsed().as_millis() as u64,
+                };
+                println!("{}", serde_json::to_string_pretty(&result)?);
+            }
+            OutputFormat::Text => eprintln!(
+                "- {} is already {}, skipped",
+                input_path.display(),
+                to_encoding.name()
+            ),
+        }
+        return Ok(());
+    }
+
+    // Open input
+    let mut reader: Box<dyn Read> = if let Some(ref input_path) = args.input {
+        if cli.verbose {
+            eprintln!("Reading from: {}", input_path.display());
+        }
+        let file = File::open(input_path)
+            .with_context(|| format!("Failed to read input file: {}", input_path.display()))?;
+        if args.progress {
+            let total = file.metadata().ok().map(|metadata| metadata.len());
+            Box::new(ProgressReader::new(file, total))
+        } else {
+            Box::new(file)
+        }
+    } else if args.progress {
+        if cli.verbose {
+            eprintln!("Reading from stdin");
+        }
+        Box::new(ProgressReader::new(io::stdin().lock(), None))
+    } else {
+        if cli.verbose {
+            eprintln!("Reading from stdin");
+        }
+        Box::new(io::stdin().lock())
+    };
+
+    // Open output. In-place output goes to a temporary file that replaces the input once complete.
+    let mut in_place = match args.input {
+        Some(ref input_path) if args.in_place => Some(AtomicFile::create(input_path)?),
+        _ => None,
+    };
+    let mut writer: Box<dyn Write> = if let Some(ref mut file) = in_place {
+        Box::new(file)
+    } else if let Some(ref output_path) = args.output {
+        Box::new(BufWriter::new(File::create(output_path).with_context(
+            || format!("Failed to write output file: {}", output_path.display()),
+        )?))
+    } else {
+        Box::new(BufWriter::new(io::stdout().lock()))
+    };
+
+    let StreamStats {
+        bytes_processed,
+        bytes_written,
+        replacements,
+        losses,
+        ..
+    } = stream_convert(&mut stream, &mut reader, &mut writer, options, cli.verbose)?;
+    if !losses.is_empty() {
+        if args.in_place {
+            eprintln!("error: {}; input left unchanged", describe_losses(&losses));
+            // Dropping the temporary file discards the conversion
+            drop(writer);
+            drop(in_place);
+            return exit_code::status(exit_code::LOSSY_OUTPUT);
+        }
+        eprintln!(
+            "{}: {}",
+            if cli.strict { "error" } else { "warning" },
+            describe_losses(&losses)
+        );
+        if cli.strict {
+            return exit_code::status(exit_code::LOSSY_OUTPUT);
+        }
+    }
+    drop(writer);
+
+    // Write output
+    if let (Some(file), Some(input_path)) = (in_place, args.input.as_ref()) {
+        file.commit()?;
+        if cli.verbose {
+            eprintln!("Updated file in-place: {}", input_path.display());
+        }
+    } else if let Some(ref output_path) = args.output
+        && cli.verbose
+    {
+        eprintln!("Wrote to: {}", output_path.display());
+    }
+
+    let processing_time = start_time.elapsed();
+
+    if cli.verbose {
+        eprintln!(
+            "Processed {} bytes -> {} bytes in {:?}",
+            bytes_processed, bytes_written, processing_time
+        );
     }
 
     // Output result in requested format
@@ -429,8 +1078,8 @@ fn convert_command(args: &ConvertArgs, cli: &Cli) -> Result<()> {
         OutputFormat::Json => {
             let result = ConversionResult {
                 success: true,
-                bytes_processed: processed_data.len(),
-                bytes_written: final_data.len(),
+                bytes_processed,
+                bytes_written,
                 errors: Vec::new(),
                 processing_time_ms: processing_time.as_millis() as u64,
             };
@@ -443,32 +1092,2414 @@ fn convert_command(args: &ConvertArgs, cli: &Cli) -> Result<()> {
         }
     }
 
+    if replacements > 0 {
+        eprintln!(
+            "{}: {} unmappable sequence(s) in lossy output",
+            if cli.strict { "error" } else { "warning" },
+            replacements
+        );
+        if cli.strict {
+            return exit_code::status(exit_code::LOSSY_OUTPUT);
+        }
+    }
+
+    Ok(())
+}
+
+/// Streaming translator configured from the convert arguments
+#[cfg(feature = "cli")]
+fn conversion_stream(
+    args: &ConvertArgs,
+    from: Encoding,
+    to: Encoding,
+) -> Result<StreamingTranslator> {
+    let stream =
+        StreamingTranslator::new(from, to, args.buffer_size.max(1) * 1024).with_context(|| {
+            format!(
+                "Failed to create translator from {} to {}",
+                from.name(),
+                to.name()
+            )
+        })?;
+    Ok(stream
+        .with_newline_mode(args.newline.into())?
+        .with_control_mode(args.controls.into())?
+        .with_surrogate_mode(args.surrogates.into()))
+}
+
+/// Per-file stream options configured from the convert arguments
+#[cfg(feature = "cli")]
+fn conversion_options(args: &ConvertArgs, to: Encoding) -> Result<StreamOptions> {
+    let policy = if args.lossy {
+        ErrorMode::Replace.policy(Some(args.replacement.as_deref().unwrap_or("?")))
+    } else {
+        args.encoding_errors.policy(args.replacement.as_deref())
+    };
+
+    let records = match args.record_length {
+        Some(0) => anyhow::bail!("--record-length must be at least 1"),
+        Some(length) => Some(RecordOptions::new(
+            length,
+            args.trim_trailing,
+            args.line_ending,
+            to,
+        )?),
+        None => None,
+    };
+
+    let normalizer = match args.normalize {
+        Some(form) => Some(UnicodeNormalizer::new(form, to)?),
+        None => None,
+    };
+
+    Ok(StreamOptions {
+        strip_bom: args.strip_bom,
+        add_bom: args.add_bom,
+        policy,
+        detect: false,
+        records,
+        normalizer,
+        verify: args.verify,
+    })
+}
+
+/// Summary of a directory conversion
+#[cfg(feature = "cli")]
+#[derive(Serialize)]
+struct DirectoryReport {
+    input: String,
+    out_dir: Option<String>,
+    dry_run: bool,
+    succeeded: usize,
+    skipped: usize,
+    failed: usize,
+    processing_time_ms: u64,
+    files: Vec<FileReport>,
+}
+
+/// Convert every file below `root`, into `--out-dir` or in place
+#[cfg(feature = "cli")]
+fn convert_directory(args: &ConvertArgs, root: &Path, cli: &Cli) -> Result<()> {
+    let start_time = std::time::Instant::now();
+
+    if !args.recursive {
+        anyhow::bail!(
+            "{} is a directory; use --recursive to convert every file in it",
+            root.display()
+        );
+    }
+    check_output_mapping(args)?;
+
+    // Output nested inside the input tree must not be picked up as input
+    let out_root = match args.out_dir {
+        // A dry run creates nothing, and a missing output directory holds no input
+        Some(ref out_dir) if args.dry_run => fs::canonicalize(out_dir).ok(),
+        Some(ref out_dir) => {
+            fs::create_dir_all(out_dir).with_context(|| {
+                format!("Failed to create output directory: {}", out_dir.display())
+            })?;
+            Some(fs::canonicalize(out_dir)?)
+        }
+        None => None,
+    };
+    let jobs = walk_files(root)?
+        .into_iter()
+        .filter(|path| match out_root {
+            Some(ref out_root) => !fs::canonicalize(path).is_ok_and(|p| p.starts_with(out_root)),
+            None => true,
+        })
+        .filter(|path| {
+            // Nor must the output of an earlier --suffix run next to its source
+            args.out_dir.is_some()
+                || !args.suffix.as_ref().is_some_and(|suffix| {
+                    path.file_name()
+                        .is_some_and(|name| name.to_string_lossy().ends_with(suffix.as_str()))
+                })
+        })
+        .filter_map(|path| {
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            if !selected(args, relative) {
+                return None;
+            }
+            let target = output_path(args, &path, relative);
+            Some((path, target))
+        })
+        .collect();
+
+    convert_many(args, root.display().to_string(), jobs, start_time, cli)
+}
+
+/// Convert every path listed in a `--files-from` list, into `--out-dir` or in place
+#[cfg(feature = "cli")]
+fn convert_file_list(args: &ConvertArgs, list: &Path, cli: &Cli) -> Result<()> {
+    let start_time = std::time::Instant::now();
+    check_output_mapping(args)?;
+
+    let contents = if list == Path::new("-") {
+        let mut contents = Vec::new();
+        io::stdin()
+            .lock()
+            .read_to_end(&mut contents)
+            .context("Failed to read file list from stdin")?;
+        contents
+    } else {
+        fs::read(list).with_context(|| format!("Failed to read file list: {}", list.display()))?
+    };
+
+    let delimiter = if args.null_delimited { b'\0' } else { b'\n' };
+    let jobs = contents
+        .split(|&b| b == delimiter)
+        .map(|entry| {
+            if args.null_delimited {
+                entry
+            } else {
+                entry.strip_suffix(b"\r").unwrap_or(entry)
+            }
+        })
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let path = path_from_bytes(entry);
+            // Listed paths may be absolute; only their normal components are mirrored
+            let relative = path
+                .components()
+                .filter(|c| matches!(c, std::path::Component::Normal(_)))
+                .collect::<PathBuf>();
+            let target = output_path(args, &path, &relative);
+            (path, target)
+        })
+        .collect();
+
+    convert_many(args, list.display().to_string(), jobs, start_time, cli)
+}
+
+/// Fail unless the arguments say where the converted files go
+#[cfg(feature = "cli")]
+fn check_output_mapping(args: &ConvertArgs) -> Result<()> {
+    if args.out_dir.is_none() && !args.in_place && args.suffix.is_none() {
+        anyhow::bail!("Converting several files requires --out-dir, --suffix or --in-place");
+    }
+    if args.suffix.as_deref() == Some("") {
+        anyhow::bail!("--suffix must not be empty; use --in-place to overwrite the input");
+    }
     Ok(())
 }
 
+/// Where the conversion of `source`, at `relative` below the input, is written
+#[cfg(feature = "cli")]
+fn output_path(args: &ConvertArgs, source: &Path, relative: &Path) -> PathBuf {
+    let mut target = match args.out_dir {
+        Some(ref out_dir) => out_dir.join(relative),
+        None => source.to_path_buf(),
+    };
+    if let Some(ref suffix) = args.suffix {
+        let mut name = target.file_name().unwrap_or_default().to_os_string();
+        name.push(suffix);
+        target.set_file_name(name);
+    }
+    target
+}
+
+/// Whether `relative`, a path below the input directory, passes --include and --exclude
+#[cfg(feature = "cli")]
+fn selected(args: &ConvertArgs, relative: &Path) -> bool {
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    let matches = |pattern: &glob::Pattern| {
+        if pattern.as_str().contains('/') {
+            pattern.matches_path_with(relative, options)
+        } else {
+            relative
+                .file_name()
+                .is_some_and(|name| pattern.matches_with(&name.to_string_lossy(), options))
+        }
+    };
+
+    (args.include.is_empty() || args.include.iter().any(matches))
+        && !args.exclude.iter().any(matches)
+}
+
+/// Path named by raw bytes from a file list
+#[cfg(all(feature = "cli", unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+/// Path named by raw bytes from a file list
+#[cfg(all(feature = "cli", not(unix)))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// Convert each `(source, target)` pair and print the combined report
+#[cfg(feature = "cli")]
+fn convert_many(
+    args: &ConvertArgs,
+    input: String,
+    jobs: Vec<(PathBuf, PathBuf)>,
+    start_time: std::time::Instant,
+    cli: &Cli,
+) -> Result<()> {
+    let EncodingArg::Encoding(from) = &args.from;
+    let EncodingArg::Encoding(to) = &args.to;
+
+    let done = std::sync::atomic::AtomicUsize::new(0);
+    let progress = |report: FileReport| {
+        if args.progress {
+            let done = done.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+            eprintln!("[{}/{}] {}", done, jobs.len(), report.source);
+        }
+        report
+    };
+    let results = parallel_map(&jobs, args.jobs, |(path, target)| {
+        let mut report = FileReport::new(
+            path.display().to_string(),
+            target.display().to_string(),
+            Some(from.name().to_string()),
+            Some(to.name().to_string()),
+        );
+        if cli.verbose {
+            eprintln!("{} -> {}", report.source, report.output);
+        }
+
+        let file_start = std::time::Instant::now();
+        if args.skip_if_target {
+            match already_in_target(path, *from, *to) {
+                Ok(Some(detected)) => {
+                    let outcome = copy_unchanged(path, target, args.dry_run);
+                    report.finish(outcome, file_start.elapsed());
+                    report.skipped = report.success;
+                    report.detected_encoding = Some(detected.name().to_string());
+                    return progress(report);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    report.finish(Err(e), file_start.elapsed());
+                    return progress(report);
+                }
+            }
+        }
+        let outcome = convert_file(args, *from, *to, path, target, cli.verbose);
+        report.finish(outcome, file_start.elapsed());
+        progress(report)
+    });
+
+    let failed = results.iter().filter(|r| !r.success).count();
+    let skipped = results.iter().filter(|r| r.skipped).count();
+    let replacements: usize = results.iter().map(|r| r.replacements).sum();
+    let report = DirectoryReport {
+        input,
+        out_dir: args.out_dir.as_ref().map(|d| d.display().to_string()),
+        dry_run: args.dry_run,
+        succeeded: results.len() - failed - skipped,
+        skipped,
+        failed,
+        processing_time_ms: start_time.elapsed().as_millis() as u64,
+        files: results,
+    };
+
+    match cli.format {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        OutputFormat::Text => {
+            let skipped = if report.dry_run {
+                "would skip"
+            } else {
+                "skipped"
+            };
+            for file in &report.files {
+                match file.error {
+                    None if file.skipped => {
+                        println!("- {} (already {}, {})", file.source, to.name(), skipped)
+                    }
+                    None => println!(
+                        "✓ {} -> {} ({} bytes -> {} bytes)",
+                        file.source, file.output, file.bytes_processed, file.bytes_written
+                    ),
+                    Some(ref error) => println!("✗ {}: {}", file.source, error),
+                }
+            }
+            if report.dry_run {
+                println!(
+                    "\nDry run over {} files: {} would be converted, {} skipped, {} would fail in {} ms",
+                    report.files.len(),
+                    report.succeeded,
+                    report.skipped,
+                    report.failed,
+                    report.processing_time_ms
+                );
+            } else {
+                println!(
+                    "\nConverted {} files: {} succeeded, {} skipped, {} failed in {} ms",
+                    report.files.len(),
+                    report.succeeded,
+                    report.skipped,
+                    report.failed,
+                    report.processing_time_ms
+                );
+            }
+        }
+    }
+
+    if report.failed > 0 {
+        return exit_code::status(exit_code::FAILURE);
+    }
+    if replacements > 0 {
+        eprintln!(
+            "{}: {} unmappable sequence(s) in lossy output",
+            if cli.strict { "error" } else { "warning" },
+            replacements
+        );
+        if cli.strict {
+            return exit_code::status(exit_code::LOSSY_OUTPUT);
+        }
+    }
+
+    Ok(())
+}
+
+/// Check whether a file already looks like it is in `to`, returning the detected encoding
+///
+/// UTF-8 targets require the whole file to be valid UTF-8. Other targets rely on
+/// detection over the first 64 KiB, except that a file which is not valid in
+/// `from` but decodes completely in a single-byte `to` also counts: related code
+/// pages such as ISO-8859-1 and Windows-1252 are easily detected as one another.
+#[cfg(feature = "cli")]
+fn already_in_target(path: &Path, from: Encoding, to: Encoding) -> Result<Option<Encoding>> {
+    let sample = read_sample(path, 64 * 1024, SampleStrategy::Head)
+        .with_context(|| format!("Failed to read input file: {}", path.display()))?;
+    let detected = EncodingDetector::new().detect(&sample).encoding;
+
+    let in_target = if to == Encoding::UTF8 {
+        is_valid_file(path, to)?
+    } else if detected == to {
+        true
+    } else {
+        !to.is_multibyte() && !is_valid_file(path, from)? && is_valid_file(path, to)?
+    };
+    Ok(in_target.then_some(detected))
+}
+
+/// Validate a file in `encoding` without reading it into memory at once
+#[cfg(feature = "cli")]
+fn is_valid_file(path: &Path, encoding: Encoding) -> Result<bool> {
+    let mut file = File::open(path)
+        .with_context(|| format!("Failed to read input file: {}", path.display()))?;
+    let mut validator = Validator::new(encoding).max_errors(1);
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    loop {
+        let read = read_chunk(&mut file, &mut buffer)
+            .with_context(|| format!("Failed to read input file: {}", path.display()))?;
+        if read == 0 {
+            return Ok(validator.finish().is_valid());
+        }
+        validator.update(&buffer[..read]);
+    }
+}
+
+/// Copy a skipped file to its output path unchanged (nothing to do in place or in a dry run)
+#[cfg(feature = "cli")]
+fn copy_unchanged(input: &Path, output: &Path, dry_run: bool) -> Result<StreamStats> {
+    let size = fs::metadata(input)
+        .with_context(|| format!("Failed to read input file: {}", input.display()))?
+        .len() as usize;
+
+    if input != output && !dry_run {
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create output directory: {}", parent.display())
+            })?;
+        }
+        fs::copy(input, output)
+            .with_context(|| format!("Failed to write output file: {}", output.display()))?;
+    }
+
+    Ok(StreamStats {
+        bytes_processed: size,
+        bytes_written: if input == output { 0 } else { size },
+        replacements: 0,
+        detected_encoding: None,
+        losses: Vec::new(),
+    })
+}
+
+/// Convert one file to `output`, which may be the input itself
+#[cfg(feature = "cli")]
+fn convert_file(
+    args: &ConvertArgs,
+    from: Encoding,
+    to: Encoding,
+    input: &Path,
+    output: &Path,
+    verbose: bool,
+) -> Result<StreamStats> {
+    let mut stream = conversion_stream(args, from, to)?;
+    let options = conversion_options(args, to)?;
+
+    if args.dry_run {
+        let mut reader = File::open(input)
+            .with_context(|| format!("Failed to read input file: {}", input.display()))?;
+        return stream_convert(&mut stream, &mut reader, &mut io::sink(), options, verbose);
+    }
+
+    let mut reader = File::open(input)
+        .with_context(|| format!("Failed to read input file: {}", input.display()))?;
+
+    if input == output {
+        let mut file = AtomicFile::create(output)?;
+        let stats = stream_convert(&mut stream, &mut reader, &mut file, options, verbose)?;
+        if !stats.losses.is_empty() {
+            // Dropping the temporary file discards the conversion
+            anyhow::bail!("{}; file left unchanged", describe_losses(&stats.losses));
+        }
+        file.commit()?;
+        return Ok(stats);
+    }
+
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create output directory: {}", parent.display()))?;
+    }
+    let mut writer = BufWriter::new(
+        File::create(output)
+            .with_context(|| format!("Failed to write output file: {}", output.display()))?,
+    );
+
+    let stats = stream_convert(&mut stream, &mut reader, &mut writer, options, verbose);
+    match stats {
+        Err(_) => {
+            // Do not leave a half-converted file behind
+            drop(writer);
+            let _ = fs::remove_file(output);
+        }
+        Ok(ref stats) if !stats.losses.is_empty() => {
+            eprintln!(
+                "warning: {}: {}",
+                input.display(),
+                describe_losses(&stats.losses)
+            );
+        }
+        Ok(_) => {}
+    }
+    stats
+}
+
+/// Source offsets of characters that do not survive conversion to `to` and back
+///
+/// Returns an empty list when converting `converted` back reproduces `source`
+/// byte for byte.
+#[cfg(feature = "cli")]
+fn round_trip_losses(
+    from: Encoding,
+    to: Encoding,
+    source: &[u8],
+    converted: &[u8],
+    policy: &ErrorPolicy,
+) -> Result<Vec<usize>> {
+    let backward = Translator::new(to, from)?;
+    if backward.convert(converted).is_ok_and(|back| back == source) {
+        return Ok(Vec::new());
+    }
+
+    let forward = Translator::new(from, to)?;
+    let mut survives: HashMap<&[u8], bool> = HashMap::new();
+    let mut losses = Vec::new();
+    for (offset, unit) in source_units(from, source) {
+        let ok = *survives.entry(unit).or_insert_with(|| {
+            forward
+                .convert_with_policy(unit, policy.clone())
+                .and_then(|encoded| backward.convert(&encoded))
+                .is_ok_and(|back| back == unit)
+        });
+        if !ok {
+            losses.push(offset);
+        }
+    }
+
+    // The round trip differs even though every character survives on its own
+    if losses.is_empty() {
+        let back = backward.convert(converted).unwrap_or_default();
+        let first = source
+            .iter()
+            .zip(&back)
+            .position(|(a, b)| a != b)
+            .unwrap_or(source.len().min(back.len()));
+        losses.push(first);
+    }
+    Ok(losses)
+}
+
+/// Split `data` into the byte sequences of individual source characters
+#[cfg(feature = "cli")]
+fn source_units(encoding: Encoding, data: &[u8]) -> Vec<(usize, &[u8])> {
+    let mut units = Vec::new();
+    match encoding {
+        Encoding::UTF8 => {
+            let mut offset = 0;
+            for chunk in data.utf8_chunks() {
+                for ch in chunk.valid().chars() {
+                    units.push((offset, &data[offset..offset + ch.len_utf8()]));
+                    offset += ch.len_utf8();
+                }
+                if !chunk.invalid().is_empty() {
+                    let len = chunk.invalid().len();
+                    units.push((offset, &data[offset..offset + len]));
+                    offset += len;
+                }
+            }
+        }
+        Encoding::UTF16LE | Encoding::UTF16BE => {
+            let mut offset = 0;
+            while offset < data.len() {
+                let high_byte = match encoding {
+                    Encoding::UTF16LE => data.get(offset + 1),
+                    _ => data.get(offset),
+                };
+                // A high surrogate and its partner travel together
+                let len = match high_byte {
+                    Some(0xD8..=0xDB) => 4,
+                    _ => 2,
+                };
+                let end = (offset + len).min(data.len());
+                units.push((offset, &data[offset..end]));
+                offset = end;
+            }
+        }
+        Encoding::UTF32LE | Encoding::UTF32BE => {
+            units.extend(data.chunks(4).enumerate().map(|(i, unit)| (i * 4, unit)));
+        }
+        _ => units.extend((0..data.len()).map(|i| (i, &data[i..i + 1]))),
+    }
+    units
+}
+
+/// Human-readable summary of round-trip losses
+#[cfg(feature = "cli")]
+fn describe_losses(losses: &[usize]) -> String {
+    const SHOWN: usize = 10;
+    let offsets: Vec<String> = losses.iter().take(SHOWN).map(|o| o.to_string()).collect();
+    format!(
+        "round trip is not byte-identical: {} lossy position(s) at byte offset(s) {}{}",
+        losses.len(),
+        offsets.join(", "),
+        if losses.len() > SHOWN { ", ..." } else { "" }
+    )
+}
+
+/// Options shared by every streamed conversion
+#[cfg(feature = "cli")]
+struct StreamOptions {
+    strip_bom: bool,
+    add_bom: bool,
+    /// Handling of unmappable input
+    policy: ErrorPolicy,
+    /// Run encoding detection on the first chunk (for reports)
+    detect: bool,
+    /// Split fixed-length records into lines
+    records: Option<RecordOptions>,
+    /// Unicode normalization of the converted text
+    normalizer: Option<UnicodeNormalizer>,
+    /// Convert each converted piece back and record where it differs from the source
+    verify: bool,
+}
+
+/// Round-trip check of a streamed conversion, one converted piece at a time
+#[cfg(feature = "cli")]
+struct RoundTrip {
+    from: Encoding,
+    to: Encoding,
+    policy: ErrorPolicy,
+    /// Source bytes read but not yet consumed by the translator
+    pending: Vec<u8>,
+    /// Stream offset of the first pending byte
+    offset: usize,
+    losses: Vec<usize>,
+}
+
+#[cfg(feature = "cli")]
+impl RoundTrip {
+    /// Queue `source`, then check `converted`, the output for the next `consumed` source bytes
+    fn check(&mut self, source: &[u8], consumed: usize, converted: &[u8]) -> Result<()> {
+        self.pending.extend_from_slice(source);
+        let piece: Vec<u8> = self.pending.drain(..consumed).collect();
+        let losses = round_trip_losses(self.from, self.to, &piece, converted, &self.policy)?;
+        self.losses
+            .extend(losses.into_iter().map(|offset| self.offset + offset));
+        self.offset += consumed;
+        Ok(())
+    }
+}
+
+/// Fixed-length record handling, with separators pre-encoded in the target encoding
+#[cfg(feature = "cli")]
+struct RecordOptions {
+    length: usize,
+    trim_trailing: bool,
+    space: Vec<u8>,
+    line_ending: Vec<u8>,
+}
+
+#[cfg(feature = "cli")]
+impl RecordOptions {
+    fn new(
+        length: usize,
+        trim_trailing: bool,
+        line_ending: LineEnding,
+        to_encoding: Encoding,
+    ) -> Result<Self> {
+        let encoder = Translator::new(Encoding::UTF8, to_encoding)?;
+        let line_ending = match line_ending {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        };
+
+        Ok(Self {
+            length,
+            trim_trailing,
+            space: encoder.convert(b" ")?,
+            line_ending: encoder
+                .convert(line_ending.as_bytes())
+                .with_context(|| format!("{} has no line ending characters", to_encoding.name()))?,
+        })
+    }
+
+    /// Append a converted record and its line ending to `output`
+    fn push_record(&self, output: &mut Vec<u8>, mut record: &[u8]) {
+        if self.trim_trailing && !self.space.is_empty() {
+            while let Some(rest) = record.strip_suffix(self.space.as_slice()) {
+                record = rest;
+            }
+        }
+        output.extend_from_slice(record);
+        output.extend_from_slice(&self.line_ending);
+    }
+}
+
+/// Streaming Unicode normalization of UTF-8 or UTF-16 output
+///
+/// Wraps the library's [`pipeline::Normalize`] stage, decoding and re-encoding
+/// the converted output around it.
+#[cfg(feature = "cli")]
+struct UnicodeNormalizer {
+    stage: pipeline::Normalize,
+    encoding: Encoding,
+}
+
+#[cfg(feature = "cli")]
+impl UnicodeNormalizer {
+    fn new(form: NormalizationForm, encoding: Encoding) -> Result<Self> {
+        if !matches!(
+            encoding,
+            Encoding::UTF8
+                | Encoding::UTF16LE
+                | Encoding::UTF16BE
+                | Encoding::UTF32LE
+                | Encoding::UTF32BE
+        ) {
+            anyhow::bail!(
+                "--normalize requires a UTF-8, UTF-16 or UTF-32 target, not {}",
+                encoding.name()
+            );
+        }
+
+        let form = match form {
+            NormalizationForm::Nfc => pipeline::NormalizationForm::Nfc,
+            NormalizationForm::Nfd => pipeline::NormalizationForm::Nfd,
+        };
+        Ok(Self {
+            stage: pipeline::Normalize::new(form),
+            encoding,
+        })
+    }
+
+    /// Normalize a chunk of converted output, holding back an unfinished tail
+    fn process(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut normalized = String::new();
+        self.stage.process(&self.decode(data), &mut normalized);
+        self.encode(normalized)
+    }
+
+    /// Normalize whatever is still held back at the end of the stream
+    fn finish(&mut self) -> Vec<u8> {
+        let mut normalized = String::new();
+        self.stage.finish(&mut normalized);
+        self.encode(normalized)
+    }
+
+    fn encode(&self, normalized: String) -> Vec<u8> {
+        match self.encoding {
+            Encoding::UTF16LE => normalized
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes)
+                .collect(),
+            Encoding::UTF16BE => normalized
+                .encode_utf16()
+                .flat_map(u16::to_be_bytes)
+                .collect(),
+            Encoding::UTF32LE => normalized
+                .chars()
+                .flat_map(|ch| (ch as u32).to_le_bytes())
+                .collect(),
+            Encoding::UTF32BE => normalized
+                .chars()
+                .flat_map(|ch| (ch as u32).to_be_bytes())
+                .collect(),
+            _ => normalized.into_bytes(),
+        }
+    }
+
+    fn decode(&self, data: &[u8]) -> String {
+        let units = data.chunks_exact(2);
+        match self.encoding {
+            Encoding::UTF16LE => {
+                char::decode_utf16(units.map(|u| u16::from_le_bytes([u[0], u[1]])))
+                    .map(|ch| ch.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect()
+            }
+            Encoding::UTF16BE => {
+                char::decode_utf16(units.map(|u| u16::from_be_bytes([u[0], u[1]])))
+                    .map(|ch| ch.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect()
+            }
+            Encoding::UTF32LE | Encoding::UTF32BE => decode_utf32_lossy(self.encoding, data),
+            _ => String::from_utf8_lossy(data).into_owned(),
+        }
+    }
+}
+
+/// Output written to a temporary file beside its destination, then renamed over it
+///
+/// An interrupted or failed conversion leaves the destination untouched: the
+/// temporary file is removed unless [`commit`](Self::commit) succeeds.
+#[cfg(feature = "cli")]
+struct AtomicFile {
+    path: PathBuf,
+    temp: PathBuf,
+    /// Always `Some` until [`commit`](Self::commit) takes it
+    writer: Option<BufWriter<File>>,
+}
+
+#[cfg(feature = "cli")]
+impl AtomicFile {
+    fn create(path: &Path) -> Result<Self> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let temp = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+        let file = File::create(&temp)
+            .with_context(|| format!("Failed to create temporary file: {}", temp.display()))?;
+        // The replacement keeps the permissions of the file it replaces
+        if let Ok(metadata) = fs::metadata(path)
+            && let Err(e) = file.set_permissions(metadata.permissions())
+        {
+            drop(file);
+            let _ = fs::remove_file(&temp);
+            return Err(e).with_context(|| {
+                format!(
+                    "Failed to copy permissions to temporary file: {}",
+                    temp.display()
+                )
+            });
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            temp,
+            writer: Some(BufWriter::new(file)),
+        })
+    }
+
+    /// Flush everything to disk and replace the destination
+    fn commit(mut self) -> Result<()> {
+        let writer = self.writer.take().expect("writer present until committed");
+        let result = writer
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)
+            .and_then(|file| file.sync_all())
+            .and_then(|()| fs::rename(&self.temp, &self.path));
+        if result.is_err() {
+            let _ = fs::remove_file(&self.temp);
+        }
+        result
+            .and_then(|()| sync_parent(&self.path))
+            .with_context(|| format!("Failed to write output file: {}", self.path.display()))
+    }
+
+    fn writer(&mut self) -> &mut BufWriter<File> {
+        self.writer
+            .as_mut()
+            .expect("writer present until committed")
+    }
+}
+
+/// Flush the directory entry of `path` so that a rename survives a crash
+#[cfg(all(feature = "cli", unix))]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(parent)?.sync_all()
+}
+
+/// Directories cannot be opened for syncing here; the rename is as durable as the OS makes it
+#[cfg(all(feature = "cli", not(unix)))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(feature = "cli")]
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer().flush()
+    }
+}
+
+#[cfg(feature = "cli")]
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if self.writer.take().is_some() {
+            let _ = fs::remove_file(&self.temp);
+        }
+    }
+}
+
+/// Interval between --progress updates
+#[cfg(feature = "cli")]
+const PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Reports how much of the input has been read, on one stderr line
+#[cfg(feature = "cli")]
+struct ProgressReader<R> {
+    inner: R,
+    /// Input size, if known, for the percentage and time remaining
+    total: Option<u64>,
+    read: u64,
+    start: std::time::Instant,
+    last_report: std::time::Instant,
+    finished: bool,
+}
+
+#[cfg(feature = "cli")]
+impl<R: Read> ProgressReader<R> {
+    fn new(inner: R, total: Option<u64>) -> Self {
+        let now = std::time::Instant::now();
+        Self {
+            inner,
+            total,
+            read: 0,
+            start: now,
+            last_report: now,
+            finished: false,
+        }
+    }
+
+    fn report(&mut self) {
+        let finished = self.finished;
+        self.last_report = std::time::Instant::now();
+        let rate = self.read as f64 / self.start.elapsed().as_secs_f64().max(1e-3);
+
+        let mut line = match self.total {
+            Some(total) if total > 0 => format!(
+                "{} of {} ({:.0}%)",
+                format_size(self.read as f64),
+                format_size(total as f64),
+                self.read as f64 * 100.0 / total as f64
+            ),
+            _ => format_size(self.read as f64),
+        };
+        line.push_str(&format!(", {}/s", format_size(rate)));
+        if let Some(total) = self.total
+            && !finished
+            && rate > 0.0
+        {
+            let remaining = total.saturating_sub(self.read) as f64 / rate;
+            line.push_str(&format!(", ETA {}s", remaining.ceil() as u64));
+        }
+
+        eprint!("\r{}\x1b[K", line);
+        if finished {
+            eprintln!();
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.read += read as u64;
+        if read == 0 && !buf.is_empty() {
+            if !self.finished {
+                self.finished = true;
+                self.report();
+            }
+        } else if self.last_report.elapsed() >= PROGRESS_INTERVAL {
+            self.report();
+        }
+        Ok(read)
+    }
+}
+
+/// Byte count with a binary unit, e.g. "12.3 MiB"
+#[cfg(feature = "cli")]
+fn format_size(bytes: f64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", size as u64)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Byte counts of a finished streamed conversion
+#[cfg(feature = "cli")]
+struct StreamStats {
+    bytes_processed: usize,
+    bytes_written: usize,
+    replacements: usize,
+    detected_encoding: Option<Encoding>,
+    /// Source offsets that do not survive the round trip (`--verify` only)
+    losses: Vec<usize>,
+}
+
+/// Convert everything from `reader` into `writer` chunk by chunk
+#[cfg(feature = "cli")]
+fn stream_convert(
+    stream: &mut StreamingTranslator,
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    mut options: StreamOptions,
+    verbose: bool,
+) -> Result<StreamStats> {
+    let from_encoding = stream.translator().from_encoding();
+    let to_encoding = stream.translator().to_encoding();
+
+    let mut bytes_processed = 0;
+    let mut bytes_written = 0;
+
+    // Handle BOM addition
+    if options.add_bom
+        && let Some(bom) = to_encoding.bom()
+    {
+        writer.write_all(bom).context("Failed to write output")?;
+        bytes_written += bom.len();
+    }
+
+    let mut buffer = vec![0u8; stream.buffer_size()];
+    let mut first_chunk = true;
+    let mut detected_encoding = None;
+    let mut record_carry = Vec::new();
+    // Bytes are compared as they are converted, so the input is never held in memory
+    let mut round_trip = options.verify.then(|| RoundTrip {
+        from: from_encoding,
+        to: to_encoding,
+        policy: options.policy.clone(),
+        pending: Vec::new(),
+        offset: 0,
+        losses: Vec::new(),
+    });
+
+    loop {
+        let bytes_read = read_chunk(reader, &mut buffer).context("Failed to read input")?;
+        if bytes_read == 0 {
+            break;
+        }
+
+        let mut chunk = &buffer[..bytes_read];
+
+        if first_chunk && options.detect {
+            detected_encoding = Some(EncodingDetector::new().detect(chunk).encoding);
+        }
+
+        // Handle BOM stripping
+        if first_chunk {
+            first_chunk = false;
+            if options.strip_bom
+                && let Some(bom) = from_encoding.bom()
+                && chunk.starts_with(bom)
+            {
+                chunk = &chunk[bom.len()..];
+                if verbose {
+                    eprintln!("Stripped BOM ({} bytes)", bom.len());
+                }
+            }
+        }
+
+        let converted = match options.records {
+            Some(ref records) => {
+                // Records may straddle chunks; carry the partial one forward
+                record_carry.extend_from_slice(chunk);
+                let complete = record_carry.len() - record_carry.len() % records.length;
+                let mut converted = Vec::new();
+                for record in record_carry[..complete].chunks(records.length) {
+                    let record = convert_piece(stream, record, &options.policy)?;
+                    records.push_record(&mut converted, &record);
+                }
+                record_carry.drain(..complete);
+                converted
+            }
+            None => {
+                let consumed = stream.bytes_consumed();
+                let converted = convert_piece(stream, chunk, &options.policy)?;
+                if let Some(ref mut round_trip) = round_trip {
+                    round_trip.check(chunk, stream.bytes_consumed() - consumed, &converted)?;
+                }
+                converted
+            }
+        };
+
+        let converted = match options.normalizer {
+            Some(ref mut normalizer) => normalizer.process(&converted),
+            None => converted,
+        };
+
+        writer
+            .write_all(&converted)
+            .context("Failed to write output")?;
+        bytes_processed += chunk.len();
+        bytes_written += converted.len();
+    }
+
+    let mut tail = Vec::new();
+
+    // A short final record still gets its own line
+    if let Some(ref records) = options.records
+        && !record_carry.is_empty()
+    {
+        let mut last = convert_piece(stream, &record_carry, &options.policy)?;
+        last.extend(
+            stream
+                .finish_with_policy(options.policy.clone())
+                .context("Conversion failed")?,
+        );
+        records.push_record(&mut tail, &last);
+    }
+
+    let consumed = stream.bytes_consumed();
+    let last = stream
+        .finish_with_policy(options.policy.clone())
+        .context("Conversion failed")?;
+    if let Some(ref mut round_trip) = round_trip {
+        round_trip.check(&[], stream.bytes_consumed() - consumed, &last)?;
+    }
+    tail.extend(last);
+
+    if let Some(ref mut normalizer) = options.normalizer {
+        tail = normalizer.process(&tail);
+        tail.extend(normalizer.finish());
+    }
+
+    writer.write_all(&tail).context("Failed to write output")?;
+    bytes_written += tail.len();
+
+    writer.flush().context("Failed to write output")?;
+
+    Ok(StreamStats {
+        bytes_processed,
+        bytes_written,
+        replacements: stream.replacements(),
+        detected_encoding,
+        losses: round_trip.map(|check| check.losses).unwrap_or_default(),
+    })
+}
+
+/// Convert one piece of a stream under the given error policy
+#[cfg(feature = "cli")]
+fn convert_piece(
+    stream: &mut StreamingTranslator,
+    data: &[u8],
+    policy: &ErrorPolicy,
+) -> Result<Vec<u8>> {
+    stream
+        .process_chunk_with_policy(data, policy.clone())
+        .context("Conversion failed")
+}
+
+/// Fill `buffer` from `reader`, stopping early only at end of input
+#[cfg(feature = "cli")]
+fn read_chunk(reader: &mut dyn Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Batch manifest: optional `[defaults]` plus one `[[job]]` table per conversion
+#[cfg(feature = "cli")]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchManifest {
+    #[serde(default)]
+    defaults: BatchJobSpec,
+    #[serde(default, rename = "job")]
+    jobs: Vec<BatchJobSpec>,
+}
+
+/// A manifest job; unset fields fall back to `[defaults]`
+#[cfg(feature = "cli")]
+#[derive(Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchJobSpec {
+    input: Option<PathBuf>,
+    output: Option<PathBuf>,
+    from: Option<String>,
+    to: Option<String>,
+    errors: Option<ErrorMode>,
+    replacement: Option<String>,
+    strip_bom: Option<bool>,
+    add_bom: Option<bool>,
+}
+
+/// Error handling mode, named after Python's codec error handlers
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum ErrorMode {
+    /// Fail on the first unmappable byte or character
+    Strict,
+    /// Substitute the replacement character
+    Replace,
+    /// Drop unmappable input
+    #[value(alias = "skip")]
+    #[serde(alias = "skip")]
+    Ignore,
+    /// Substitute \xNN / \uXXXX escapes
+    #[value(name = "backslashreplace")]
+    BackslashReplace,
+    /// Substitute &#NNNN; character references
+    #[value(name = "xmlcharrefreplace")]
+    XmlCharRefReplace,
+    /// Substitute ASCII/Latin approximations ("EUR" for "€"), then replace what is left
+    Translit,
+}
+
+#[cfg(feature = "cli")]
+impl ErrorMode {
+    /// Library policy for this mode; `replacement` only applies to `replace` and `translit`
+    fn policy(self, replacement: Option<&str>) -> ErrorPolicy {
+        match (self, replacement) {
+            (ErrorMode::Strict, _) => ErrorPolicy::Strict,
+            (ErrorMode::Replace, Some(text)) => {
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => ErrorPolicy::ReplaceWithChar(ch),
+                    _ => ErrorPolicy::ReplaceWithStr(text.into()),
+                }
+            }
+            (ErrorMode::Replace, None) => ErrorPolicy::Replace,
+            (ErrorMode::Ignore, _) => ErrorPolicy::Ignore,
+            (ErrorMode::BackslashReplace, _) => ErrorPolicy::BackslashReplace,
+            (ErrorMode::XmlCharRefReplace, _) => ErrorPolicy::XmlCharRefReplace,
+            (ErrorMode::Translit, _) => {
+                let fallback = ErrorMode::Replace.policy(replacement);
+                ErrorPolicy::Transliterate(Arc::new(Transliterator::new().fallback(fallback)))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+impl BatchJobSpec {
+    fn or(self, defaults: &BatchJobSpec) -> Self {
+        let defaults = defaults.clone();
+        Self {
+            input: self.input.or(defaults.input),
+            output: self.output.or(defaults.output),
+            from: self.from.or(defaults.from),
+            to: self.to.or(defaults.to),
+            errors: self.errors.or(defaults.errors),
+            replacement: self.replacement.or(defaults.replacement),
+            strip_bom: self.strip_bom.or(defaults.strip_bom),
+            add_bom: self.add_bom.or(defaults.add_bom),
+        }
+    }
+}
+
+/// Consolidated batch report
+#[cfg(feature = "cli")]
+#[derive(Serialize)]
+struct BatchReport {
+    manifest: String,
+    succeeded: usize,
+    failed: usize,
+    processing_time_ms: u64,
+    jobs: Vec<FileReport>,
+}
+
+#[cfg(feature = "cli")]
+fn batch_command(args: &BatchArgs, cli: &Cli) -> Result<()> {
+    let start_time = std::time::Instant::now();
+
+    let manifest_text = fs::read_to_string(&args.manifest)
+        .with_context(|| format!("Failed to read manifest: {}", args.manifest.display()))?;
+    let manifest: BatchManifest = toml::from_str(&manifest_text)
+        .with_context(|| format!("Invalid manifest: {}", args.manifest.display()))?;
+
+    // Relative paths in the manifest are resolved against its directory
+    let base_dir = args.manifest.parent().unwrap_or(Path::new("."));
+
+    // Translators are built once per encoding pair and shared between jobs
+    let mut translators: HashMap<(Encoding, Encoding), Translator> = HashMap::new();
+    // Policies (and their transliteration tables) likewise once per mode and replacement
+    let mut policies: HashMap<(ErrorMode, String), ErrorPolicy> = HashMap::new();
+    let mut results = Vec::with_capacity(manifest.jobs.len());
+
+    for (index, job) in manifest.jobs.into_iter().enumerate() {
+        let job = job.or(&manifest.defaults);
+        let job_start = std::time::Instant::now();
+
+        let mut result = FileReport::new(
+            display_path(job.input.as_deref()),
+            display_path(job.output.as_deref()),
+            job.from.as_deref().map(encoding_label),
+            job.to.as_deref().map(encoding_label),
+        );
+
+        if cli.verbose {
+            eprintln!("Job {}: {} -> {}", index + 1, result.source, result.output);
+        }
+
+        let outcome = run_batch_job(&job, base_dir, &mut translators, &mut policies, args, cli);
+        result.finish(outcome, job_start.elapsed());
+
+        let failed = !result.success;
+        results.push(result);
+        if failed && args.fail_fast {
+            break;
+        }
+    }
+
+    if let Some(ref report_path) = args.report {
+        write_file_report(report_path, &args.report_format, &results)?;
+    }
+
+    let failed = results.iter().filter(|r| !r.success).count();
+    let report = BatchReport {
+        manifest: args.manifest.display().to_string(),
+        succeeded: results.len() - failed,
+        failed,
+        processing_time_ms: start_time.elapsed().as_millis() as u64,
+        jobs: results,
+    };
+
+    match cli.format {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        OutputFormat::Text => {
+            for job in &report.jobs {
+                match job.error {
+                    None => println!(
+                        "✓ {} -> {} ({} bytes -> {} bytes)",
+                        job.source, job.output, job.bytes_processed, job.bytes_written
+                    ),
+                    Some(ref error) => println!("✗ {}: {}", job.source, error),
+                }
+            }
+            println!(
+                "\nBatch complete: {} succeeded, {} failed in {} ms",
+                report.succeeded, report.failed, report.processing_time_ms
+            );
+        }
+    }
+
+    if report.failed > 0 {
+        return exit_code::status(exit_code::FAILURE);
+    }
+
+    let replacements: usize = report.jobs.iter().map(|job| job.replacements).sum();
+    if cli.strict && replacements > 0 {
+        eprintln!(
+            "error: {} unmappable sequence(s) in lossy output across the batch",
+            replacements
+        );
+        return exit_code::status(exit_code::LOSSY_OUTPUT);
+    }
+
+    Ok(())
+}
+
+/// Execute a single manifest job
+#[cfg(feature = "cli")]
+fn run_batch_job(
+    job: &BatchJobSpec,
+    base_dir: &Path,
+    translators: &mut HashMap<(Encoding, Encoding), Translator>,
+    policies: &mut HashMap<(ErrorMode, String), ErrorPolicy>,
+    args: &BatchArgs,
+    cli: &Cli,
+) -> Result<StreamStats> {
+    let input = job.input.as_ref().context("Job has no input")?;
+    let output = job.output.as_ref().context("Job has no output")?;
+    let EncodingArg::Encoding(from) = job
+        .from
+        .as_deref()
+        .context("Job has no source encoding")?
+        .parse()?;
+    let EncodingArg::Encoding(to) = job
+        .to
+        .as_deref()
+        .context("Job has no target encoding")?
+        .parse()?;
+
+    // TOML strings already support \u escapes, so the value is used as written
+    let mode = job.errors.unwrap_or(ErrorMode::Strict);
+    let replacement = job.replacement.as_deref().unwrap_or("?");
+    let policy = policies
+        .entry((mode, replacement.to_string()))
+        .or_insert_with(|| mode.policy(Some(replacement)))
+        .clone();
+
+    let translator = match translators.entry((from, to)) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => entry.insert(Translator::new(from, to).with_context(|| {
+            format!(
+                "Failed to create translator from {} to {}",
+                from.name(),
+                to.name()
+            )
+        })?),
+    };
+    let mut stream =
+        StreamingTranslator::from_translator(translator.clone(), args.buffer_size.max(1) * 1024);
+
+    let input_path = base_dir.join(input);
+    let output_path = base_dir.join(output);
+
+    let mut reader = File::open(&input_path)
+        .with_context(|| format!("Failed to read input file: {}", input_path.display()))?;
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create output directory: {}", parent.display()))?;
+    }
+    let mut writer = BufWriter::new(
+        File::create(&output_path)
+            .with_context(|| format!("Failed to write output file: {}", output_path.display()))?,
+    );
+
+    let options = StreamOptions {
+        strip_bom: job.strip_bom.unwrap_or(false),
+        add_bom: job.add_bom.unwrap_or(false),
+        policy,
+        detect: args.report.is_some(),
+        records: None,
+        normalizer: None,
+        verify: false,
+    };
+
+    let stats = stream_convert(&mut stream, &mut reader, &mut writer, options, cli.verbose);
+    if stats.is_err() {
+        // Do not leave a half-converted file behind
+        drop(writer);
+        let _ = fs::remove_file(&output_path);
+    }
+    stats
+}
+
+#[cfg(feature = "cli")]
+fn display_path(path: Option<&Path>) -> String {
+    path.map(|p| p.display().to_string()).unwrap_or_default()
+}
+
+/// Per-file entry of a machine-readable run report
+#[cfg(feature = "cli")]
+#[derive(Serialize)]
+struct FileReport {
+    source: String,
+    output: String,
+    declared_encoding: Option<String>,
+    detected_encoding: Option<String>,
+    target_encoding: Option<String>,
+    success: bool,
+    skipped: bool,
+    bytes_processed: usize,
+    bytes_written: usize,
+    replacements: usize,
+    errors: usize,
+    error: Option<String>,
+    processing_time_ms: f64,
+    throughput_mib_s: f64,
+}
+
+#[cfg(feature = "cli")]
+impl FileReport {
+    fn new(
+        source: String,
+        output: String,
+        declared_encoding: Option<String>,
+        target_encoding: Option<String>,
+    ) -> Self {
+        Self {
+            source,
+            output,
+            declared_encoding,
+            detected_encoding: None,
+            target_encoding,
+            success: false,
+            skipped: false,
+            bytes_processed: 0,
+            bytes_written: 0,
+            replacements: 0,
+            errors: 0,
+            error: None,
+            processing_time_ms: 0.0,
+            throughput_mib_s: 0.0,
+        }
+    }
+
+    /// Record the outcome of converting this file
+    fn finish(&mut self, outcome: Result<StreamStats>, elapsed: std::time::Duration) {
+        match outcome {
+            Ok(stats) => {
+                self.success = true;
+                self.bytes_processed = stats.bytes_processed;
+                self.bytes_written = stats.bytes_written;
+                self.replacements = stats.replacements;
+                self.detected_encoding = stats.detected_encoding.map(|e| e.name().to_string());
+            }
+            Err(e) => {
+                self.errors = 1;
+                self.error = Some(format!("{:#}", e));
+            }
+        }
+
+        let seconds = elapsed.as_secs_f64();
+        self.processing_time_ms = seconds * 1000.0;
+        if seconds > 0.0 {
+            self.throughput_mib_s = self.bytes_processed as f64 / seconds / (1024.0 * 1024.0);
+        }
+    }
+}
+
+/// Write per-file report entries as JSON or CSV
+#[cfg(feature = "cli")]
+fn write_file_report(path: &Path, format: &ReportFormat, entries: &[FileReport]) -> Result<()> {
+    let contents = match format {
+        ReportFormat::Json => serde_json::to_string_pretty(entries)? + "\n",
+        ReportFormat::Csv => {
+            let mut csv = String::from(
+                "source,output,declared_encoding,detected_encoding,target_encoding,success,\
+                 skipped,bytes_processed,bytes_written,replacements,errors,error,processing_time_ms,\
+                 throughput_mib_s\n",
+            );
+            for entry in entries {
+                let fields = [
+                    csv_field(&entry.source),
+                    csv_field(&entry.output),
+                    csv_field(entry.declared_encoding.as_deref().unwrap_or("")),
+                    csv_field(entry.detected_encoding.as_deref().unwrap_or("")),
+                    csv_field(entry.target_encoding.as_deref().unwrap_or("")),
+                    entry.success.to_string(),
+                    entry.skipped.to_string(),
+                    entry.bytes_processed.to_string(),
+                    entry.bytes_written.to_string(),
+                    entry.replacements.to_string(),
+                    entry.errors.to_string(),
+                    csv_field(entry.error.as_deref().unwrap_or("")),
+                    format!("{:.3}", entry.processing_time_ms),
+                    format!("{:.3}", entry.throughput_mib_s),
+                ];
+                csv.push_str(&fields.join(","));
+                csv.push('\n');
+            }
+            csv
+        }
+    };
+
+    fs::write(path, contents).with_context(|| format!("Failed to write report: {}", path.display()))
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or line break
+#[cfg(feature = "cli")]
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Canonical name for an encoding label, or the label itself if it is unknown
+#[cfg(feature = "cli")]
+fn encoding_label(label: &str) -> String {
+    match label.parse() {
+        Ok(EncodingArg::Encoding(encoding)) => encoding.name().to_string(),
+        Err(_) => label.to_string(),
+    }
+}
+
+/// Throughput measurement for one encoding pair and backend
+#[cfg(feature = "cli")]
+#[derive(Serialize)]
+struct BenchResult {
+    from: &'static str,
+    to: &'static str,
+    backend: &'static str,
+    input_bytes: usize,
+    iterations: usize,
+    best_mib_s: f64,
+    mean_mib_s: f64,
+}
+
+#[cfg(feature = "cli")]
+fn bench_command(args: &BenchArgs, cli: &Cli) -> Result<()> {
+    let pairs = if args.pairs.is_empty() {
+        [
+            (Encoding::EBCDIC_037, Encoding::UTF8),
+            (Encoding::UTF8, Encoding::EBCDIC_037),
+            (Encoding::EBCDIC_037, Encoding::ISO_8859_1),
+            (Encoding::WINDOWS_1252, Encoding::UTF8),
+            (Encoding::ISO_8859_1, Encoding::WINDOWS_1252),
+            (Encoding::UTF8, Encoding::UTF16LE),
+        ]
+        .iter()
+        .map(|&(from, to)| EncodingPairArg { from, to })
+        .collect()
+    } else {
+        args.pairs.clone()
+    };
+
+    let iterations = args.iterations.max(1);
+    let mut results = Vec::new();
+
+    for pair in &pairs {
+        let translator = Translator::new(pair.from, pair.to).with_context(|| {
+            format!(
+                "Failed to create translator from {} to {}",
+                pair.from.name(),
+                pair.to.name()
+            )
+        })?;
+        let input = bench_input(pair.from, pair.to, args.size)?;
+
+        // Table backends only matter for single-byte pairs
+        let backends: &[Backend] = if pair.from.is_multibyte() || pair.to.is_multibyte() {
+            &[Backend::Scalar]
+        } else {
+            Backend::available()
+        };
+
+        for &backend in backends {
+            if cli.verbose {
+                eprintln!(
+                    "Benchmarking {} -> {} ({}, {} bytes)",
+                    pair.from.name(),
+                    pair.to.name(),
+                    backend.name(),
+                    input.len()
+                );
+            }
+
+            // Warm-up run, also validates the generated input
+            translator
+                .convert_with(&input, backend)
+                .context("Benchmark conversion failed")?;
+
+            let mut throughputs = Vec::with_capacity(iterations);
+            for _ in 0..iterations {
+                let start = std::time::Instant::now();
+                let output = translator.convert_with(&input, backend)?;
+                let seconds = start.elapsed().as_secs_f64().max(f64::EPSILON);
+                std::hint::black_box(output);
+                throughputs.push(input.len() as f64 / seconds / (1024.0 * 1024.0));
+            }
+
+            results.push(BenchResult {
+                from: pair.from.name(),
+                to: pair.to.name(),
+                backend: backend.name(),
+                input_bytes: input.len(),
+                iterations,
+                best_mib_s: throughputs.iter().cloned().fold(0.0, f64::max),
+                mean_mib_s: throughputs.iter().sum::<f64>() / iterations as f64,
+            });
+        }
+    }
+
+    match cli.format {
+        OutputFormat::Json => {
+            let report = serde_json::json!({
+                "host": {
+                    "os": std::env::consts::OS,
+                    "arch": std::env::consts::ARCH,
+                    "threads": std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+                    "simd": Backend::Simd.instruction_set(),
+                },
+                "results": results,
+            });
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        OutputFormat::Text => {
+            println!(
+                "Host: {} {} (SIMD: {}), input size {} bytes, {} iterations",
+                std::env::consts::OS,
+                std::env::consts::ARCH,
+                Backend::Simd.instruction_set().unwrap_or("none"),
+                args.size,
+                iterations
+            );
+            println!();
+            println!(
+                "{:15} {:15} {:8} {:>12} {:>12}",
+                "From", "To", "Backend", "Best MiB/s", "Mean MiB/s"
+            );
+            for result in &results {
+                println!(
+                    "{:15} {:15} {:8} {:>12.1} {:>12.1}",
+                    result.from, result.to, result.backend, result.best_mib_s, result.mean_mib_s
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Build roughly `size` bytes of valid `from` text that is also representable in `to`
+#[cfg(feature = "cli")]
+fn bench_input(from: Encoding, to: Encoding, size: usize) -> Result<Vec<u8>> {
+    let to_source = Translator::new(Encoding::UTF8, from)?;
+    let to_target = Translator::new(Encoding::UTF8, to)?;
+
+    // Mostly printable ASCII with some accented and symbol characters mixed in
+    let candidates = ('\u{20}'..='\u{7E}').chain("äöüéèçñßÄÖÜÉ€£©°".chars());
+    let mut sample = String::new();
+    for ch in candidates {
+        let mut buf = [0u8; 4];
+        let utf8 = ch.encode_utf8(&mut buf).as_bytes();
+        if to_source.convert(utf8).is_ok() && to_target.convert(utf8).is_ok() {
+            sample.push(ch);
+        }
+    }
+
+    if sample.is_empty() {
+        anyhow::bail!(
+            "No common characters between {} and {}",
+            from.name(),
+            to.name()
+        );
+    }
+
+    // Repeat whole sample units so multi-byte sequences are never cut
+    let unit = to_source.convert(sample.as_bytes())?;
+    let input = unit.repeat(size.div_ceil(unit.len()).max(1));
+
+    Ok(input)
+}
+
+#[cfg(feature = "cli")]
+fn map_command(args: &MapArgs, cli: &Cli) -> Result<()> {
+    let EncodingArg::Encoding(encoding) = &args.encoding;
+
+    let table = code_page_table(*encoding)?;
+    let positions = table
+        .iter()
+        .enumerate()
+        .filter(|(_, ch)| !args.mapped_only || ch.is_some());
+
+    match cli.format {
+        OutputFormat::Json => {
+            let entries: Vec<_> = positions
+                .map(|(byte, ch)| {
+                    serde_json::json!({
+                        "byte": format!("0x{:02X}", byte),
+                        "code_point": ch.map(|c| format!("U+{:04X}", c as u32)),
+                        "char": ch.map(String::from),
+                        "utf8": ch.map(utf8_hex),
+                        "name": ch.map(char_name),
+                    })
+                })
+                .collect();
+            let map = serde_json::json!({
+                "encoding": encoding.name(),
+                "mapped": table.iter().filter(|c| c.is_some()).count(),
+                "positions": entries,
+            });
+            println!("{}", serde_json::to_string_pretty(&map)?);
+        }
+        OutputFormat::Text => {
+            println!("Code page map: {}", encoding.name());
+            println!();
+            println!(
+                "{:6} {:10} {:5} {:12} Name",
+                "Byte", "Code point", "Char", "UTF-8"
+            );
+            for (byte, ch) in positions {
+                match ch {
+                    Some(c) => println!(
+                        "0x{:02X}   U+{:04X}     {:5} {:12} {}",
+                        byte,
+                        *c as u32,
+                        printable_char(*c),
+                        utf8_hex(*c),
+                        char_name(*c)
+                    ),
+                    None => println!("0x{:02X}   {:10} {:5} {:12} <unmapped>", byte, "-", "", ""),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "cli")]
+fn compare_command(args: &CompareArgs, cli: &Cli) -> Result<()> {
+    let EncodingArg::Encoding(first) = &args.first;
+    let EncodingArg::Encoding(second) = &args.second;
+
+    let first_table = code_page_table(*first)?;
+    let second_table = code_page_table(*second)?;
+
+    let differences: Vec<(usize, Option<char>, Option<char>)> = first_table
+        .iter()
+        .zip(second_table.iter())
+        .enumerate()
+        .filter(|(_, (a, b))| a != b)
+        .map(|(byte, (a, b))| (byte, *a, *b))
+        .collect();
+
+    match cli.format {
+        OutputFormat::Json => {
+            let describe = |ch: Option<char>| {
+                ch.map(|c| {
+                    serde_json::json!({
+                        "code_point": format!("U+{:04X}", c as u32),
+                        "char": c.to_string(),
+                        "name": char_name(c),
+                    })
+                })
+            };
+            let entries: Vec<_> = differences
+                .iter()
+                .map(|&(byte, a, b)| {
+                    serde_json::json!({
+                        "byte": format!("0x{:02X}", byte),
+                        "first": describe(a),
+                        "second": describe(b),
+                    })
+                })
+                .collect();
+            let comparison = serde_json::json!({
+                "first": first.name(),
+                "second": second.name(),
+                "identical": 256 - differences.len(),
+                "different": differences.len(),
+                "differences": entries,
+            });
+            println!("{}", serde_json::to_string_pretty(&comparison)?);
+        }
+        OutputFormat::Text => {
+            println!("Comparing {} with {}", first.name(), second.name());
+            println!("{} of 256 positions differ\n", differences.len());
+
+            if !differences.is_empty() {
+                println!(
+                    "{:6} {:15} {:15} Names",
+                    "Byte",
+                    first.name(),
+                    second.name()
+                );
+                let short = |ch: Option<char>| match ch {
+                    Some(c) => format!("U+{:04X} {}", c as u32, printable_char(c)),
+                    None => "-".to_string(),
+                };
+                let name = |ch: Option<char>| ch.map(char_name).unwrap_or("<unmapped>".into());
+                for &(byte, a, b) in &differences {
+                    println!(
+                        "0x{:02X}   {:15} {:15} {} → {}",
+                        byte,
+                        short(a),
+                        short(b),
+                        name(a),
+                        name(b)
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "cli")]
+fn export_command(args: &ExportArgs, cli: &Cli) -> Result<()> {
+    let EncodingArg::Encoding(encoding) = &args.encoding;
+    let table = code_page_table(*encoding)?;
+
+    let contents = match cli.format_arg {
+        Some(FormatArg::Text) => {
+            anyhow::bail!("export writes json, csv or ucm tables; --format text is not supported")
+        }
+        None | Some(FormatArg::Json) => {
+            let mappings: Vec<_> = table
+                .iter()
+                .enumerate()
+                .filter_map(|(byte, ch)| ch.map(|c| (byte, c)))
+                .map(|(byte, c)| {
+                    serde_json::json!({
+                        "byte": format!("0x{:02X}", byte),
+                        "code_point": format!("U+{:04X}", c as u32),
+                    })
+                })
+                .collect();
+            let export = serde_json::json!({
+                "encoding": encoding.name(),
+                "mappings": mappings,
+            });
+            serde_json::to_string_pretty(&export)? + "\n"
+        }
+        Some(FormatArg::Csv) => {
+            let mut csv = String::from("byte,code_point,char,name\n");
+            for (byte, ch) in table.iter().enumerate() {
+                if let Some(c) = ch {
+                    csv.push_str(&format!(
+                        "0x{:02X},U+{:04X},{},{}\n",
+                        byte,
+                        *c as u32,
+                        csv_field(&printable_char(*c)),
+                        csv_field(&char_name(*c))
+                    ));
+                }
+            }
+            csv
+        }
+        Some(FormatArg::Ucm) => ucm_table(*encoding, &table),
+    };
+
+    if let Some(ref output_path) = args.output {
+        fs::write(output_path, contents)
+            .with_context(|| format!("Failed to write output file: {}", output_path.display()))?;
+        if cli.verbose {
+            eprintln!(
+                "Wrote {} table to: {}",
+                encoding.name(),
+                output_path.display()
+            );
+        }
+    } else {
+        io::stdout()
+            .write_all(contents.as_bytes())
+            .context("Failed to write to stdout")?;
+    }
+
+    Ok(())
+}
+
+/// Render a single-byte table in ICU `.ucm` format
+///
+/// Entries are sorted by code point. When several bytes decode to the same
+/// character, the lowest byte is the round-trip mapping (`|0`) and the others
+/// are decode-only fallbacks (`|3`).
+#[cfg(feature = "cli")]
+fn ucm_table(encoding: Encoding, table: &[Option<char>; 256]) -> String {
+    let mut entries: Vec<(char, usize)> = table
+        .iter()
+        .enumerate()
+        .filter_map(|(byte, ch)| ch.map(|c| (c, byte)))
+        .collect();
+    entries.sort();
+
+    // EBCDIC pages substitute with SUB at 0x3F, ASCII-based pages at 0x1A
+    let subchar = if encoding.is_ascii_compatible() {
+        0x1A
+    } else {
+        0x3F
+    };
+
+    let mut ucm = String::new();
+    ucm.push_str(&format!(
+        "# {} mapping table exported by fast-encode\n",
+        encoding.name()
+    ));
+    ucm.push_str(&format!(
+        "<code_set_name>               \"{}\"\n",
+        encoding.name()
+    ));
+    ucm.push_str("<mb_cur_max>                  1\n");
+    ucm.push_str("<mb_cur_min>                  1\n");
+    ucm.push_str("<uconv_class>                 \"SBCS\"\n");
+    ucm.push_str(&format!(
+        "<subchar>                     \\x{:02X}\n",
+        subchar
+    ));
+    ucm.push_str("\nCHARMAP\n");
+
+    let mut previous = None;
+    for (ch, byte) in entries {
+        let precision = if previous == Some(ch) { 3 } else { 0 };
+        ucm.push_str(&format!(
+            "<U{:04X}> \\x{:02X} |{}\n",
+            ch as u32, byte, precision
+        ));
+        previous = Some(ch);
+    }
+
+    ucm.push_str("END CHARMAP\n");
+    ucm
+}
+
+/// Decode every byte of a single-byte code page
+#[cfg(feature = "cli")]
+fn code_page_table(encoding: Encoding) -> Result<[Option<char>; 256]> {
+    if encoding.is_multibyte() {
+        anyhow::bail!(
+            "{} is not a single-byte encoding and has no 256-position table",
+            encoding.name()
+        );
+    }
+
+    let translator = Translator::new(encoding, Encoding::UTF8)?;
+    let mut table = [None; 256];
+    for (byte, slot) in table.iter_mut().enumerate() {
+        if let Ok(utf8) = translator.convert(&[byte as u8]) {
+            *slot = std::str::from_utf8(&utf8)
+                .ok()
+                .and_then(|s| s.chars().next());
+        }
+    }
+
+    Ok(table)
+}
+
+/// Bytes dumped by `inspect --length 0`, so a huge file is never read whole
+#[cfg(feature = "cli")]
+const INSPECT_MAX_LENGTH: usize = 1024 * 1024;
+
+/// Read `length` bytes at `offset` of a file, returning the file size and the bytes
+///
+/// A `length` of 0 reads to the end of the file, up to [`INSPECT_MAX_LENGTH`].
+#[cfg(feature = "cli")]
+fn read_window(path: &Path, offset: usize, length: usize) -> io::Result<(usize, Vec<u8>)> {
+    use std::io::{Seek, SeekFrom};
+
+    let mut file = File::open(path)?;
+    let size = usize::try_from(file.metadata()?.len()).unwrap_or(usize::MAX);
+    let start = offset.min(size);
+    let length = match length {
+        0 => INSPECT_MAX_LENGTH,
+        length => length,
+    }
+    .min(size - start);
+
+    file.seek(SeekFrom::Start(start as u64))?;
+    let mut window = vec![0u8; length];
+    let bytes_read = read_chunk(&mut file, &mut window)?;
+    window.truncate(bytes_read);
+    Ok((size, window))
+}
+
+#[cfg(feature = "cli")]
+fn inspect_command(args: &InspectArgs, cli: &Cli) -> Result<()> {
+    if args.width == 0 {
+        anyhow::bail!("--width must be at least 1");
+    }
+
+    let (size, data) = read_window(&args.file, args.offset, args.length)
+        .with_context(|| format!("Failed to read input file: {}", args.file.display()))?;
+    let start = args.offset.min(size);
+    let end = start + data.len();
+
+    let encodings: Vec<Encoding> = args
+        .encodings
+        .iter()
+        .map(|EncodingArg::Encoding(encoding)| *encoding)
+        .collect();
+    // Single-byte pages decode per byte; others decode each row as a whole
+    let tables: Vec<Option<[Option<char>; 256]>> = encodings
+        .iter()
+        .map(|&encoding| code_page_table(encoding).ok())
+        .collect();
+
+    let decode_row = |index: usize, row: &[u8]| match tables[index] {
+        Some(ref table) => row
+            .iter()
+            .map(|&b| match table[b as usize] {
+                Some(ch) if !ch.is_control() => ch,
+                _ => '.',
+            })
+            .collect(),
+        None => decode_lossy(encodings[index], row),
+    };
+
+    match cli.format {
+        OutputFormat::Json => {
+            let rows: Vec<_> = data
+                .chunks(args.width)
+                .enumerate()
+                .map(|(row_index, row)| {
+                    let decodings: serde_json::Map<String, serde_json::Value> = encodings
+                        .iter()
+                        .enumerate()
+                        .map(|(i, encoding)| {
+                            (encoding.name().to_string(), decode_row(i, row).into())
+                        })
+                        .collect();
+                    serde_json::json!({
+                        "offset": start + row_index * args.width,
+                        "hex": hex_bytes(row),
+                        "decodings": decodings,
+                    })
+                })
+                .collect();
+            let dump = serde_json::json!({
+                "file": args.file.display().to_string(),
+                "size": size,
+                "offset": start,
+                "length": end - start,
+                "rows": rows,
+            });
+            println!("{}", serde_json::to_string_pretty(&dump)?);
+        }
+        OutputFormat::Text => {
+            let hex_width = args.width * 3 - 1;
+            let column_width = |i: usize| encodings[i].name().chars().count().max(args.width);
+
+            let mut header = format!(
+                "{:8}  {:hex_width$}",
+                "Offset",
+                "Hex",
+                hex_width = hex_width
+            );
+            for (i, encoding) in encodings.iter().enumerate() {
+                header.push_str(&format!(
+                    "  {:width$}",
+                    encoding.name(),
+                    width = column_width(i)
+                ));
+            }
+            println!("{}", header.trim_end());
+
+            for (row_index, row) in data.chunks(args.width).enumerate() {
+                let mut line = format!(
+                    "{:08X}  {:hex_width$}",
+                    start + row_index * args.width,
+                    hex_bytes(row),
+                    hex_width = hex_width
+                );
+                for i in 0..encodings.len() {
+                    let text: String = decode_row(i, row);
+                    let padding = column_width(i).saturating_sub(text.chars().count());
+                    line.push_str(&format!("  {}{}", text, " ".repeat(padding)));
+                }
+                println!("{}", line.trim_end());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "cli")]
+fn stats_command(args: &StatsArgs, cli: &Cli) -> Result<()> {
+    let mut reader: Box<dyn Read> = match args.file {
+        Some(ref path) => Box::new(
+            File::open(path)
+                .with_context(|| format!("Failed to read input file: {}", path.display()))?,
+        ),
+        None => Box::new(io::stdin().lock()),
+    };
+
+    // Statistics cover the whole input; detection only its start
+    let mut stats = ByteStats::new();
+    let mut sample = Vec::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = read_chunk(&mut reader, &mut buffer).context("Failed to read input")?;
+        if read == 0 {
+            break;
+        }
+        stats.update(&buffer[..read]);
+        let wanted = args.sample_size.saturating_sub(sample.len()).min(read);
+        sample.extend_from_slice(&buffer[..wanted]);
+    }
+
+    let detection = EncodingDetector::with_sample_size(args.sample_size).detect(&sample);
+    let endings = stats.line_endings();
+
+    match cli.format {
+        OutputFormat::Json => {
+            let top: Vec<_> = stats
+                .most_common(args.top)
+                .into_iter()
+                .map(|(byte, count)| serde_json::json!({ "byte": byte, "count": count }))
+                .collect();
+            let candidates: Vec<_> = detection
+                .candidates
+                .iter()
+                .map(|(encoding, confidence)| {
+                    serde_json::json!({ "encoding": encoding.name(), "confidence": confidence })
+                })
+                .collect();
+            let result = serde_json::json!({
+                "bytes": stats.total(),
+                "distinct_bytes": stats.distinct(),
+                "non_ascii": stats.non_ascii(),
+                "non_ascii_ratio": stats.non_ascii_ratio(),
+                "nul": stats.nul(),
+                "c0_controls": stats.c0_controls(),
+                "c1_range": stats.c1_range(),
+                "line_endings": {
+                    "lf": endings.lf,
+                    "crlf": endings.crlf,
+                    "cr": endings.cr,
+                    "mixed": endings.is_mixed(),
+                },
+                "most_common": top,
+                "detected_encoding": detection.encoding.name(),
+                "confidence": detection.confidence,
+                "bom_detected": detection.bom_detected,
+                "candidates": candidates,
+            });
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        }
+        OutputFormat::Text => {
+            println!("Bytes:            {}", stats.total());
+            println!("Distinct bytes:   {}", stats.distinct());
+            println!(
+                "Non-ASCII:        {} ({:.2}%)",
+                stats.non_ascii(),
+                stats.non_ascii_ratio() * 100.0
+            );
+            println!("NUL bytes:        {}", stats.nul());
+            println!(
+                "C0 controls:      {} (excluding tab, CR, LF)",
+                stats.c0_controls()
+            );
+            println!("0x80-0x9F bytes:  {}", stats.c1_range());
+            println!(
+                "Line endings:     {} LF, {} CRLF, {} CR{}",
+                endings.lf,
+                endings.crlf,
+                endings.cr,
+                if endings.is_mixed() { " (mixed)" } else { "" }
+            );
+
+            if args.top > 0 && stats.total() > 0 {
+                println!("\nMost common bytes:");
+                for (byte, count) in stats.most_common(args.top) {
+                    let shown = match byte {
+                        0x20..=0x7E => format!("'{}'", byte as char),
+                        _ => "   ".to_string(),
+                    };
+                    println!(
+                        "  0x{:02X} {}  {:>10}  {:>6.2}%",
+                        byte,
+                        shown,
+                        count,
+                        count as f64 / stats.total() as f64 * 100.0
+                    );
+                }
+            }
+
+            println!(
+                "\nDetected encoding: {} ({:.1}%{})",
+                detection.encoding.name(),
+                detection.confidence * 100.0,
+                if detection.bom_detected { ", BOM" } else { "" }
+            );
+            for (encoding, confidence) in &detection.candidates {
+                println!("  {}: {:.1}%", encoding.name(), confidence * 100.0);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "cli")]
+fn fix_mojibake_command(args: &FixMojibakeArgs, cli: &Cli) -> Result<()> {
+    if args.in_place && args.input.is_none() {
+        anyhow::bail!("Cannot use --in-place without input file");
+    }
+
+    let data = if let Some(ref input_path) = args.input {
+        fs::read(input_path)
+            .with_context(|| format!("Failed to read input file: {}", input_path.display()))?
+    } else {
+        let mut buffer = Vec::new();
+        io::stdin().read_to_end(&mut buffer)?;
+        buffer
+    };
+    let text = String::from_utf8(data).context("Input is not valid UTF-8")?;
+
+    let repair = if args.candidates.is_empty() {
+        mojibake::repair(&text)
+    } else {
+        let candidates: Vec<Encoding> = args
+            .candidates
+            .iter()
+            .map(|EncodingArg::Encoding(encoding)| *encoding)
+            .collect();
+        mojibake::repair_with(&text, &candidates)
+    };
+
+    let layers: Vec<&str> = repair
+        .iter()
+        .flat_map(|r| r.layers.iter().map(|encoding| encoding.name()))
+        .collect();
+    let repaired = repair.as_ref().map_or(0, |r| r.repaired);
+
+    if !args.check {
+        let output = repair.as_ref().map_or(text.as_str(), |r| r.text.as_str());
+        if let Some(input_path) = args.input.as_ref().filter(|_| args.in_place) {
+            if repair.is_some() {
+                fs::write(input_path, output).with_context(|| {
+                    format!("Failed to write to input file: {}", input_path.display())
+                })?;
+            }
+        } else if let Some(ref output_path) = args.output {
+            fs::write(output_path, output).with_context(|| {
+                format!("Failed to write output file: {}", output_path.display())
+            })?;
+        } else {
+            io::stdout().lock().write_all(output.as_bytes())?;
+        }
+    }
+
+    match cli.format {
+        OutputFormat::Json => {
+            let result = serde_json::json!({
+                "damaged": repair.is_some(),
+                "repaired_sequences": repaired,
+                "misread_as": layers,
+            });
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        }
+        OutputFormat::Text => {
+            if repair.is_none() {
+                eprintln!("No double-encoding found");
+            } else {
+                eprintln!(
+                    "{} {} damaged sequence(s) (UTF-8 misread as {})",
+                    if args.check { "Found" } else { "Repaired" },
+                    repaired,
+                    layers.join(", then ")
+                );
+            }
+        }
+    }
+
+    if args.check && repair.is_some() {
+        return exit_code::status(exit_code::FAILURE);
+    }
+
+    Ok(())
+}
+
+/// Unicode name of a character, with a fallback for unnamed controls
+#[cfg(feature = "cli")]
+fn char_name(ch: char) -> String {
+    match unicode_names2::name(ch) {
+        Some(name) => name.to_string(),
+        None if ch.is_control() => "<control>".to_string(),
+        None => "<unnamed>".to_string(),
+    }
+}
+
+/// Character as shown in text output (controls are not printed raw)
+#[cfg(feature = "cli")]
+fn printable_char(ch: char) -> String {
+    if ch.is_control() {
+        String::new()
+    } else {
+        ch.to_string()
+    }
+}
+
+/// UTF-8 bytes of a character as space-separated hex
+#[cfg(feature = "cli")]
+fn utf8_hex(ch: char) -> String {
+    let mut buf = [0u8; 4];
+    ch.encode_utf8(&mut buf)
+        .bytes()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 #[cfg(feature = "cli")]
 fn detect_command(args: &DetectArgs, cli: &Cli) -> Result<()> {
+    if let Some(ref input_path) = args.input
+        && input_path.is_dir()
+    {
+        if args.preview.is_some() {
+            anyhow::bail!("--preview applies to a single file, not a directory");
+        }
+        return detect_directory(args, input_path, cli);
+    }
+
     // Read sample data
     let sample_data = if let Some(ref input_path) = args.input {
-        let mut file = std::fs::File::open(input_path)
-            .with_context(|| format!("Failed to open input file: {}", input_path.display()))?;
-        let mut buffer = vec![0u8; args.sample_size];
-        let bytes_read = file.read(&mut buffer)?;
-        buffer.truncate(bytes_read);
-        buffer
+        read_sample(input_path, args.sample_size, args.sample_strategy)
+            .with_context(|| format!("Failed to open input file: {}", input_path.display()))?
     } else {
-        let mut buffer = vec![0u8; args.sample_size];
-        let bytes_read = io::stdin().read(&mut buffer)?;
-        buffer.truncate(bytes_read);
-        buffer
+        let mut stdin = io::stdin().lock();
+        match args.sample_strategy {
+            SampleStrategy::Head => {
+                let mut buffer = vec![0u8; args.sample_size];
+                let bytes_read = read_chunk(&mut stdin, &mut buffer)?;
+                buffer.truncate(bytes_read);
+                buffer
+            }
+            // A pipe cannot seek, so the whole input is read to find its end
+            strategy => {
+                let mut data = Vec::new();
+                stdin.read_to_end(&mut data)?;
+                sample_windows(data.len() as u64, args.sample_size, strategy)
+                    .into_iter()
+                    .flat_map(|(start, length)| {
+                        let start = start as usize;
+                        let end = start + length;
+                        trim_window(&data[start..end], start > 0, end < data.len()).to_vec()
+                    })
+                    .collect()
+            }
+        }
     };
 
     // Use sophisticated detection algorithm
     let detector = EncodingDetector::with_sample_size(args.sample_size);
-    let detection_result = if let Some(ref language) = args.language {
-        detector.detect_with_hint(&sample_data, language)
-    } else {
-        detector.detect(&sample_data)
+    let detection_result = detect_sample(&detector, args, &sample_data);
+
+    // The detected encoding first, then the next most likely candidates
+    let previews: Vec<(Encoding, String)> = match args.preview {
+        Some(length) => std::iter::once(detection_result.encoding)
+            .chain(
+                detection_result
+                    .candidates
+                    .iter()
+                    .map(|(encoding, _)| *encoding),
+            )
+            .fold(Vec::new(), |mut encodings, encoding| {
+                if !encodings.contains(&encoding) {
+                    encodings.push(encoding);
+                }
+                encodings
+            })
+            .into_iter()
+            .take(PREVIEW_CANDIDATES)
+            .map(|encoding| (encoding, decoded_preview(encoding, &sample_data, length)))
+            .collect(),
+        None => Vec::new(),
     };
 
     match cli.format {
@@ -481,77 +3512,837 @@ fn detect_command(args: &DetectArgs, cli: &Cli) -> Result<()> {
                 }));
             }
 
-            let result = serde_json::json!({
+            let mut result = serde_json::json!({
                 "detected_encoding": detection_result.encoding.name(),
                 "confidence": detection_result.confidence,
                 "bom_detected": detection_result.bom_detected,
+                "source": detection_result.source.name(),
+                "declared": detection_result.declared.map(|encoding| encoding.name()),
                 "sample_size": sample_data.len(),
                 "candidates": candidates_json
             });
+            if args.preview.is_some() {
+                result["previews"] = previews
+                    .iter()
+                    .map(|(encoding, text)| {
+                        serde_json::json!({ "encoding": encoding.name(), "text": text })
+                    })
+                    .collect();
+            }
             println!("{}", serde_json::to_string_pretty(&result)?);
         }
         OutputFormat::Text => {
             println!("Detected encoding: {}", detection_result.encoding.name());
             println!("Confidence: {:.1}%", detection_result.confidence * 100.0);
 
-            if detection_result.bom_detected {
-                println!("BOM detected: Yes");
-            }
+            if detection_result.bom_detected {
+                println!("BOM detected: Yes");
+            }
+            if let Some(declared) = detection_result.declared {
+                let used = if detection_result.source == DetectionSource::Declaration {
+                    "used"
+                } else {
+                    "overruled by the data"
+                };
+                println!("Declared encoding: {} ({})", declared.name(), used);
+            }
+
+            println!("Sample size: {} bytes", sample_data.len());
+
+            if args.confidence && detection_result.candidates.len() > 1 {
+                println!("\nAll candidates:");
+                for (encoding, confidence) in &detection_result.candidates {
+                    println!("  {}: {:.1}%", encoding.name(), confidence * 100.0);
+                }
+            }
+
+            if !previews.is_empty() {
+                println!("\nPreview:");
+                for (encoding, text) in &previews {
+                    println!("  {:15} {}", encoding.name(), text);
+                }
+            }
+        }
+    }
+
+    if let Some(threshold) = args.min_confidence
+        && detection_result.confidence < threshold
+    {
+        eprintln!(
+            "Detection confidence {:.1}% is below the {:.1}% threshold",
+            detection_result.confidence * 100.0,
+            threshold * 100.0
+        );
+        return exit_code::status(exit_code::LOW_CONFIDENCE);
+    }
+
+    Ok(())
+}
+
+/// Detection outcome for one file of a directory scan
+#[cfg(feature = "cli")]
+#[derive(Serialize)]
+struct FileDetection {
+    path: String,
+    encoding: Option<&'static str>,
+    confidence: f64,
+    bom_detected: bool,
+    error: Option<String>,
+}
+
+/// Detect with the hints the detect arguments ask for
+#[cfg(feature = "cli")]
+fn detect_sample(detector: &EncodingDetector, args: &DetectArgs, sample: &[u8]) -> DetectionResult {
+    if args.document_hints {
+        detector.detect_with_document_hints(sample)
+    } else if let Some(ref language) = args.language {
+        detector.detect_with_hint(sample, language)
+    } else {
+        detector.detect(sample)
+    }
+}
+
+/// Detect the encoding of every file below `root`
+#[cfg(feature = "cli")]
+fn detect_directory(args: &DetectArgs, root: &Path, cli: &Cli) -> Result<()> {
+    let files = walk_files(root)?;
+    if cli.verbose {
+        eprintln!("Scanning {} files under {}", files.len(), root.display());
+    }
+
+    let detector = EncodingDetector::with_sample_size(args.sample_size);
+    let detections = parallel_map(&files, args.jobs, |path| {
+        let mut detection = FileDetection {
+            path: path
+                .strip_prefix(root)
+                .unwrap_or(path)
+                .display()
+                .to_string(),
+            encoding: None,
+            confidence: 0.0,
+            bom_detected: false,
+            error: None,
+        };
+
+        match read_sample(path, args.sample_size, args.sample_strategy) {
+            Ok(sample) => {
+                let result = detect_sample(&detector, args, &sample);
+                detection.encoding = Some(result.encoding.name());
+                detection.confidence = result.confidence;
+                detection.bom_detected = result.bom_detected;
+            }
+            Err(e) => detection.error = Some(e.to_string()),
+        }
+
+        detection
+    });
+
+    // Aggregate breakdown, most common encoding first
+    let mut breakdown: Vec<(&str, usize)> = Vec::new();
+    for detection in &detections {
+        let key = detection.encoding.unwrap_or("error");
+        match breakdown.iter_mut().find(|(name, _)| *name == key) {
+            Some((_, count)) => *count += 1,
+            None => breakdown.push((key, 1)),
+        }
+    }
+    breakdown.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    match cli.format {
+        OutputFormat::Json => {
+            let summary: Vec<_> = breakdown
+                .iter()
+                .map(|(name, count)| serde_json::json!({ "encoding": name, "files": count }))
+                .collect();
+            let result = serde_json::json!({
+                "root": root.display().to_string(),
+                "files": detections,
+                "summary": summary,
+            });
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        }
+        OutputFormat::Text => {
+            let width = detections
+                .iter()
+                .map(|d| d.path.chars().count())
+                .max()
+                .unwrap_or(0)
+                .clamp(4, 60);
+
+            println!(
+                "{:width$}  {:15} {:>10}  BOM",
+                "Path",
+                "Encoding",
+                "Confidence",
+                width = width
+            );
+            for detection in &detections {
+                match detection.error {
+                    None => println!(
+                        "{:width$}  {:15} {:>9.1}%  {}",
+                        detection.path,
+                        detection.encoding.unwrap_or("-"),
+                        detection.confidence * 100.0,
+                        if detection.bom_detected { "yes" } else { "no" },
+                        width = width
+                    ),
+                    Some(ref error) => {
+                        println!("{:width$}  error: {}", detection.path, error, width = width)
+                    }
+                }
+            }
+
+            println!("\nSummary ({} files):", detections.len());
+            for (name, count) in &breakdown {
+                println!(
+                    "  {:15} {:>6}  ({:.1}%)",
+                    name,
+                    count,
+                    *count as f64 * 100.0 / detections.len().max(1) as f64
+                );
+            }
+        }
+    }
+
+    if let Some(threshold) = args.min_confidence {
+        let below = detections
+            .iter()
+            .filter(|d| d.error.is_some() || d.confidence < threshold)
+            .count();
+        if below > 0 {
+            eprintln!(
+                "{} file(s) detected below the {:.1}% confidence threshold",
+                below,
+                threshold * 100.0
+            );
+            return exit_code::status(exit_code::LOW_CONFIDENCE);
+        }
+    }
+
+    Ok(())
+}
+
+/// Number of windows read by [`SampleStrategy::Spread`]
+#[cfg(feature = "cli")]
+const SPREAD_WINDOWS: usize = 16;
+
+/// Read up to `sample_size` bytes of a file, chosen by `strategy`
+#[cfg(feature = "cli")]
+fn read_sample(path: &Path, sample_size: usize, strategy: SampleStrategy) -> io::Result<Vec<u8>> {
+    use std::io::{Seek, SeekFrom};
+
+    let mut file = File::open(path)?;
+    if let SampleStrategy::Head = strategy {
+        let mut buffer = vec![0u8; sample_size];
+        let bytes_read = read_chunk(&mut file, &mut buffer)?;
+        buffer.truncate(bytes_read);
+        return Ok(buffer);
+    }
+
+    let length = file.metadata()?.len();
+    let mut sample = Vec::with_capacity(sample_size);
+    let mut window = Vec::new();
+    for (start, size) in sample_windows(length, sample_size, strategy) {
+        window.resize(size, 0);
+        file.seek(SeekFrom::Start(start))?;
+        let bytes_read = read_chunk(&mut file, &mut window)?;
+        // Only edges cut out of the middle of the file are trimmed
+        let cut_end = start + (bytes_read as u64) < length;
+        let trimmed = trim_window(&window[..bytes_read], start > 0, cut_end);
+        sample.extend_from_slice(trimmed);
+    }
+    Ok(sample)
+}
+
+/// `(offset, length)` windows totalling at most `sample_size` bytes of a `length`-byte input
+///
+/// Offsets are kept even so UTF-16 code units stay aligned.
+#[cfg(feature = "cli")]
+fn sample_windows(length: u64, sample_size: usize, strategy: SampleStrategy) -> Vec<(u64, usize)> {
+    let size = sample_size as u64;
+    if length <= size {
+        return vec![(0, length as usize)];
+    }
+
+    match strategy {
+        SampleStrategy::Head => vec![(0, sample_size)],
+        SampleStrategy::Tail => {
+            let start = (length - size).next_multiple_of(2);
+            vec![(start, (length - start) as usize)]
+        }
+        SampleStrategy::Spread => {
+            let count = SPREAD_WINDOWS.min(sample_size / 2).max(1) as u64;
+            let window = size / count;
+            let last = length - window;
+            (0..count)
+                .map(|i| {
+                    let start = if count == 1 {
+                        0
+                    } else {
+                        last * i / (count - 1)
+                    };
+                    (start & !1, window as usize)
+                })
+                .collect()
+        }
+    }
+}
+
+/// Drop UTF-8 characters cut in half by the edges of a sample window
+#[cfg(feature = "cli")]
+fn trim_window(window: &[u8], cut_start: bool, cut_end: bool) -> &[u8] {
+    let leading = if cut_start {
+        leading_partial(window)
+    } else {
+        0
+    };
+    let window = &window[leading..];
+    let trailing = if cut_end { trailing_partial(window) } else { 0 };
+    &window[..window.len() - trailing]
+}
+
+/// Continuation bytes at the start of a window that belong to a cut-off UTF-8 character
+///
+/// Windows containing NUL bytes are most likely UTF-16 and are left whole so
+/// their code units stay aligned; the same applies to [`trailing_partial`].
+#[cfg(feature = "cli")]
+fn leading_partial(window: &[u8]) -> usize {
+    if window.contains(&0) {
+        return 0;
+    }
+    window
+        .iter()
+        .take(3)
+        .take_while(|&&b| (0x80..0xC0).contains(&b))
+        .count()
+}
+
+/// Bytes at the end of a window forming an incomplete UTF-8 character
+#[cfg(feature = "cli")]
+fn trailing_partial(window: &[u8]) -> usize {
+    if window.contains(&0) {
+        return 0;
+    }
+    for back in 1..=window.len().min(3) {
+        let byte = window[window.len() - back];
+        let needed = match byte {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            0x80..=0xBF => continue,
+            _ => return 0,
+        };
+        return if needed > back { back } else { 0 };
+    }
+    0
+}
+
+/// Recursively collect regular files below `root`, sorted by path
+///
+/// Symbolic links to directories are not followed.
+#[cfg(feature = "cli")]
+fn walk_files(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let entries = fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+        for entry in entries {
+            let entry =
+                entry.with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(entry.path());
+            } else if file_type.is_file() || (file_type.is_symlink() && entry.path().is_file()) {
+                files.push(entry.path());
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Apply `f` to every item on `jobs` worker threads, preserving input order
+///
+/// `jobs == 0` uses one worker per available CPU.
+#[cfg(feature = "cli")]
+fn parallel_map<T, R, F>(items: &[T], jobs: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let jobs = match jobs {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+    .min(items.len().max(1));
+
+    if jobs <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let mut indexed: Vec<(usize, R)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            break;
+                        };
+                        results.push((index, f(item)));
+                    }
+                    results
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("worker thread panicked"))
+            .collect()
+    });
+
+    indexed.sort_by_key(|(index, _)| *index);
+    indexed.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Longest request line or header line accepted by `serve`
+#[cfg(feature = "cli")]
+const MAX_HEAD_LINE: u64 = 8192;
+
+#[cfg(feature = "cli")]
+fn serve_command(args: &ServeArgs, cli: &Cli) -> Result<()> {
+    let address = match args.listen.strip_prefix(':') {
+        Some(port) => format!("0.0.0.0:{}", port),
+        None => args.listen.clone(),
+    };
+    let listener = std::net::TcpListener::bind(&address)
+        .with_context(|| format!("Failed to listen on {}", address))?;
+    eprintln!("Listening on http://{}", listener.local_addr()?);
+
+    std::thread::scope(|scope| {
+        for _ in 0..args.workers.max(1) {
+            let listener = &listener;
+            scope.spawn(move || {
+                for connection in listener.incoming() {
+                    let result = connection.and_then(|stream| {
+                        let peer = stream.peer_addr()?;
+                        handle_connection(stream, args, cli.verbose)
+                            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", peer, e)))
+                    });
+                    if let Err(e) = result
+                        && cli.verbose
+                    {
+                        eprintln!("Connection error: {}", e);
+                    }
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+/// Request line and headers of an HTTP request
+#[cfg(feature = "cli")]
+struct HttpRequest {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    /// Header names are lowercased
+    headers: Vec<(String, String)>,
+}
+
+#[cfg(feature = "cli")]
+impl HttpRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Serve one request; every connection is closed after its response
+#[cfg(feature = "cli")]
+fn handle_connection(
+    stream: std::net::TcpStream,
+    args: &ServeArgs,
+    verbose: bool,
+) -> io::Result<()> {
+    // A stalled client must not hold a worker forever
+    let timeout = Some(std::time::Duration::from_secs(args.timeout.max(1)));
+    stream.set_read_timeout(timeout)?;
+    stream.set_write_timeout(timeout)?;
+
+    let mut reader = io::BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    let request = match read_request_head(&mut reader) {
+        Ok(request) => request,
+        Err(e) if is_timeout(&e) => return write_error(&mut writer, 408, "Request timeout"),
+        Err(e) => return write_error(&mut writer, 400, &e.to_string()),
+    };
+    if verbose {
+        eprintln!("{} {}", request.method, request.path);
+    }
+
+    if request
+        .header("expect")
+        .is_some_and(|value| value.eq_ignore_ascii_case("100-continue"))
+    {
+        writer.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+    }
+
+    let mut body: Box<dyn Read + '_> = if request
+        .header("transfer-encoding")
+        .is_some_and(|value| value.eq_ignore_ascii_case("chunked"))
+    {
+        Box::new(ChunkedBody {
+            inner: &mut reader,
+            remaining: 0,
+            done: false,
+        })
+    } else {
+        match request.header("content-length").map(str::parse::<u64>) {
+            Some(Ok(length)) => Box::new((&mut reader).take(length)),
+            Some(Err(_)) => return write_error(&mut writer, 400, "Invalid Content-Length"),
+            None => Box::new(io::empty()),
+        }
+    };
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/convert") => serve_convert(&request, &mut body, &mut writer, args),
+        ("POST", "/detect") => serve_detect(&mut body, &mut writer),
+        ("GET", "/health") => write_response(&mut writer, 200, "text/plain", b"ok\n"),
+        (_, "/convert" | "/detect" | "/health") => {
+            write_error(&mut writer, 405, "Method not allowed")
+        }
+        _ => write_error(&mut writer, 404, "Not found"),
+    }
+}
+
+/// Parse the request line and headers
+#[cfg(feature = "cli")]
+fn read_request_head(reader: &mut impl io::BufRead) -> io::Result<HttpRequest> {
+    let line = read_head_line(reader)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target), Some(_version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Malformed request line",
+        ));
+    };
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect();
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_head_line(reader)?;
+        if line.is_empty() {
+            break;
+        }
+        if headers.len() == 100 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Too many headers",
+            ));
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Malformed header line"))?;
+        headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+
+    Ok(HttpRequest {
+        method: method.to_string(),
+        path: path.to_string(),
+        query,
+        headers,
+    })
+}
+
+/// Read one CRLF-terminated line of at most `MAX_HEAD_LINE` bytes, without the terminator
+#[cfg(feature = "cli")]
+fn read_head_line(reader: &mut impl io::BufRead) -> io::Result<String> {
+    let mut line = Vec::new();
+    io::BufRead::read_until(&mut reader.take(MAX_HEAD_LINE), b'\n', &mut line)?;
+    if line.last() != Some(&b'\n') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Truncated or oversized header line",
+        ));
+    }
+    let line = String::from_utf8_lossy(&line);
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Decode `%XX` escapes and `+` in a query component
+#[cfg(feature = "cli")]
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Request body sent with `Transfer-Encoding: chunked`
+#[cfg(feature = "cli")]
+struct ChunkedBody<R> {
+    inner: R,
+    /// Bytes left in the current chunk
+    remaining: u64,
+    done: bool,
+}
+
+#[cfg(feature = "cli")]
+impl<R: io::BufRead> Read for ChunkedBody<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            let line = read_head_line(&mut self.inner)?;
+            let size = line.split(';').next().unwrap_or("").trim();
+            self.remaining = u64::from_str_radix(size, 16)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid chunk size"))?;
+            if self.remaining == 0 {
+                // Skip any trailers up to the blank line that ends the body
+                while !read_head_line(&mut self.inner)?.is_empty() {}
+                self.done = true;
+                return Ok(0);
+            }
+        }
+
+        let wanted = buf
+            .len()
+            .min(self.remaining.try_into().unwrap_or(usize::MAX));
+        let read = self.inner.read(&mut buf[..wanted])?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.remaining -= read as u64;
+        if self.remaining == 0 && !read_head_line(&mut self.inner)?.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Missing CRLF after chunk",
+            ));
+        }
+        Ok(read)
+    }
+}
+
+/// `POST /convert?from=&to=[&errors=][&replacement=]`: stream the body through a translator
+#[cfg(feature = "cli")]
+fn serve_convert(
+    request: &HttpRequest,
+    body: &mut dyn Read,
+    writer: &mut dyn Write,
+    args: &ServeArgs,
+) -> io::Result<()> {
+    let param = |name: &str| request.query.get(name).map(String::as_str);
+    let (Some(from), Some(to)) = (param("from"), param("to")) else {
+        return write_error(writer, 400, "The from and to query parameters are required");
+    };
+    let (from, to) = match (query_encoding(from), query_encoding(to)) {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(e), _) | (_, Err(e)) => return write_error(writer, 400, &e),
+    };
+
+    let policy = match param("errors").map(|mode| ErrorMode::from_str(mode, true)) {
+        None => ErrorPolicy::Strict,
+        Some(Ok(mode)) => mode.policy(param("replacement")),
+        Some(Err(e)) => return write_error(writer, 400, &format!("Invalid errors mode: {}", e)),
+    };
+
+    let buffer_size = args.buffer_size.max(1) * 1024;
+    let mut stream = match StreamingTranslator::new(from, to, buffer_size) {
+        Ok(stream) => stream,
+        Err(e) => return write_error(writer, 400, &e.to_string()),
+    };
+
+    let mut buffer = vec![0u8; buffer_size];
+    let mut head_sent = false;
+    loop {
+        let read = match read_chunk(body, &mut buffer) {
+            Ok(read) => read,
+            Err(e) if is_timeout(&e) && !head_sent => {
+                return write_error(writer, 408, "Request timeout");
+            }
+            Err(e) => return Err(e),
+        };
+        let converted = if read == 0 {
+            stream.finish_with_policy(policy.clone())
+        } else {
+            stream.process_chunk_with_policy(&buffer[..read], policy.clone())
+        };
+
+        let converted = match converted {
+            Ok(converted) => converted,
+            Err(e) if !head_sent => return write_error(writer, 422, &e.to_string()),
+            // Too late for a status code: end the response without its final chunk
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+        };
+
+        if !head_sent {
+            write!(
+                writer,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset={}\r\n\
+                 Transfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+                to.name()
+            )?;
+            head_sent = true;
+        }
+        if !converted.is_empty() {
+            write!(writer, "{:x}\r\n", converted.len())?;
+            writer.write_all(&converted)?;
+            writer.write_all(b"\r\n")?;
+        }
+        if read == 0 {
+            break;
+        }
+    }
+
+    writer.write_all(b"0\r\n\r\n")?;
+    writer.flush()
+}
+
+/// `POST /detect`: detect the encoding of the start of the body
+#[cfg(feature = "cli")]
+fn serve_detect(body: &mut dyn Read, writer: &mut dyn Write) -> io::Result<()> {
+    let mut sample = vec![0u8; 64 * 1024];
+    let read = read_chunk(body, &mut sample).and_then(|read| {
+        // Drain the rest so the client is not reset mid-upload
+        io::copy(body, &mut io::sink())?;
+        Ok(read)
+    });
+    let read = match read {
+        Ok(read) => read,
+        Err(e) if is_timeout(&e) => return write_error(writer, 408, "Request timeout"),
+        Err(e) => return Err(e),
+    };
+    sample.truncate(read);
+
+    let detection = EncodingDetector::new().detect(&sample);
+    let candidates: Vec<_> = detection
+        .candidates
+        .iter()
+        .map(|(encoding, confidence)| {
+            serde_json::json!({ "encoding": encoding.name(), "confidence": confidence })
+        })
+        .collect();
+    let result = serde_json::json!({
+        "detected_encoding": detection.encoding.name(),
+        "confidence": detection.confidence,
+        "bom_detected": detection.bom_detected,
+        "sample_size": sample.len(),
+        "candidates": candidates,
+    });
+
+    let mut json = serde_json::to_vec_pretty(&result)?;
+    json.push(b'\n');
+    write_response(writer, 200, "application/json", &json)
+}
 
-            println!("Sample size: {} bytes", sample_data.len());
+/// Whether a socket read failed because the read timeout expired
+#[cfg(feature = "cli")]
+fn is_timeout(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
 
-            if args.confidence && detection_result.candidates.len() > 1 {
-                println!("\nAll candidates:");
-                for (encoding, confidence) in &detection_result.candidates {
-                    println!("  {}: {:.1}%", encoding.name(), confidence * 100.0);
-                }
-            }
-        }
+/// Encoding named in a query parameter; server-side code page files are not exposed
+#[cfg(feature = "cli")]
+fn query_encoding(name: &str) -> std::result::Result<Encoding, String> {
+    if name.starts_with("custom:") {
+        return Err("Custom code pages are not available over HTTP".to_string());
     }
+    name.parse::<EncodingArg>()
+        .map(|EncodingArg::Encoding(encoding)| encoding)
+        .map_err(|e| e.to_string())
+}
 
-    Ok(())
+#[cfg(feature = "cli")]
+fn write_response(
+    writer: &mut dyn Write,
+    status: u16,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        422 => "Unprocessable Content",
+        _ => "Error",
+    };
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason,
+        content_type,
+        body.len()
+    )?;
+    writer.write_all(body)?;
+    writer.flush()
 }
 
+/// JSON error response: `{"error": "..."}`
 #[cfg(feature = "cli")]
-fn list_command(args: &ListArgs, cli: &Cli) -> Result<()> {
-    let all_encodings = [
-        (Encoding::UTF8, "unicode", "UTF-8 Unicode"),
-        (Encoding::UTF16LE, "unicode", "UTF-16 Little Endian"),
-        (Encoding::UTF16BE, "unicode", "UTF-16 Big Endian"),
-        (Encoding::ASCII, "ascii", "US-ASCII (7-bit)"),
-        (Encoding::ISO_8859_1, "iso", "ISO-8859-1 (Latin-1)"),
-        (
-            Encoding::ISO_8859_15,
-            "iso",
-            "ISO-8859-15 (Latin-9 with Euro)",
-        ),
-        (
-            Encoding::WINDOWS_1250,
-            "windows",
-            "Windows-1250 (Central European)",
-        ),
-        (
-            Encoding::WINDOWS_1252,
-            "windows",
-            "Windows-1252 (Western European)",
-        ),
-        (Encoding::CP_437, "dos", "DOS CP437 (US OEM)"),
-        (Encoding::CP_850, "dos", "DOS CP850 (Western European OEM)"),
-        (
-            Encoding::EBCDIC_037,
-            "ebcdic",
-            "IBM EBCDIC CP037 (US/Canada)",
-        ),
-        (Encoding::MAC_ROMAN, "mac", "Macintosh Roman"),
-    ];
+fn write_error(writer: &mut dyn Write, status: u16, message: &str) -> io::Result<()> {
+    let body = serde_json::json!({ "error": message }).to_string() + "\n";
+    write_response(writer, status, "application/json", body.as_bytes())
+}
 
-    let filtered_encodings: Vec<_> = all_encodings
+#[cfg(feature = "cli")]
+fn list_command(args: &ListArgs, cli: &Cli) -> Result<()> {
+    let filtered_encodings: Vec<Encoding> = Encoding::all()
         .iter()
-        .filter(|(encoding, category, _)| {
-            if let Some(ref filter_cat) = args.category {
-                if *category != filter_cat.as_str() {
-                    return false;
-                }
+        .copied()
+        .filter(|encoding| {
+            if let Some(category) = args.category
+                && encoding.category() != category.name()
+            {
+                return false;
             }
 
             if args.ascii_compatible && !encoding.is_ascii_compatible() {
@@ -570,11 +4361,12 @@ fn list_command(args: &ListArgs, cli: &Cli) -> Result<()> {
         OutputFormat::Json => {
             let encodings_info: Vec<_> = filtered_encodings
                 .iter()
-                .map(|(encoding, category, description)| {
+                .map(|encoding| {
                     serde_json::json!({
                         "name": encoding.name(),
-                        "category": category,
-                        "description": description,
+                        "category": encoding.category(),
+                        "description": encoding.description(),
+                        "aliases": encoding.aliases(),
                         "ascii_compatible": encoding.is_ascii_compatible(),
                         "multibyte": encoding.is_multibyte(),
                         "has_bom": encoding.bom().is_some()
@@ -587,15 +4379,18 @@ fn list_command(args: &ListArgs, cli: &Cli) -> Result<()> {
             println!("Supported Encodings ({} total):", filtered_encodings.len());
             println!();
 
-            for (encoding, category, description) in filtered_encodings {
+            for encoding in filtered_encodings {
                 println!(
                     "{:15} {:10} {}",
                     encoding.name(),
-                    format!("[{}]", category),
-                    description
+                    format!("[{}]", encoding.category()),
+                    encoding.description()
                 );
 
                 if args.details {
+                    if !encoding.aliases().is_empty() {
+                        println!("                Aliases: {}", encoding.aliases().join(", "));
+                    }
                     println!(
                         "                ASCII Compatible: {}",
                         if encoding.is_ascii_compatible() {
@@ -621,9 +4416,15 @@ fn list_command(args: &ListArgs, cli: &Cli) -> Result<()> {
 }
 
 #[cfg(feature = "cli")]
-fn validate_command(args: &ValidateArgs, _cli: &Cli) -> Result<()> {
+fn validate_command(args: &ValidateArgs, cli: &Cli) -> Result<()> {
     let EncodingArg::Encoding(encoding) = &args.encoding;
 
+    if let Some(ref input_path) = args.input
+        && input_path.is_dir()
+    {
+        return validate_directory(args, *encoding, input_path, cli);
+    }
+
     // Read input
     let input_data = if let Some(ref input_path) = args.input {
         fs::read(input_path)
@@ -634,41 +4435,460 @@ fn validate_command(args: &ValidateArgs, _cli: &Cli) -> Result<()> {
         buffer
     };
 
-    // Try to convert to UTF-8 to validate
-    let translator = Translator::new(*encoding, Encoding::UTF8)?;
+    let report = validator(args, *encoding).validate(&input_data);
+    let errors = &report.invalid;
+
+    match cli.format {
+        OutputFormat::Json => {
+            let errors: Vec<_> = errors
+                .iter()
+                .map(|error| {
+                    let (start, end) = context_window(*encoding, &input_data, error, args.context);
+                    serde_json::json!({
+                        "offset": error.offset,
+                        "length": error.bytes.len(),
+                        "character": error.character,
+                        "reason": error.reason,
+                        "bytes": hex_bytes(&error.bytes),
+                        "context": decode_lossy(*encoding, &input_data[start..end]),
+                    })
+                })
+                .collect();
+            let suggestions: Vec<_> = report
+                .suggestions
+                .iter()
+                .map(|(encoding, confidence)| {
+                    serde_json::json!({ "encoding": encoding.name(), "confidence": confidence })
+                })
+                .collect();
+            let result = serde_json::json!({
+                "encoding": encoding.name(),
+                "target": report.target.map(Encoding::name),
+                "valid": errors.is_empty(),
+                "errors": errors,
+                "suggestions": suggestions,
+            });
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        }
+        OutputFormat::Text => {
+            let target = report
+                .target
+                .map_or(String::new(), |target| format!(" for {}", target.name()));
+            if errors.is_empty() {
+                println!("✓ File is valid {}{}", encoding.name(), target);
+            } else {
+                println!("✗ File is not valid {}{}", encoding.name(), target);
+            }
+
+            if args.all_errors {
+                let color = io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+                for error in errors {
+                    print_error_context(*encoding, &input_data, error, args.context, color);
+                }
+                if !errors.is_empty() {
+                    println!("\n{} invalid sequence(s)", errors.len());
+                }
+            } else if args.show_errors
+                && let Some(error) = errors.first()
+            {
+                println!("  Error at position {}: {}", error.offset, error.reason);
+            }
 
-    match translator.convert(&input_data) {
-        Ok(_) => {
-            println!("✓ File is valid {}", encoding.name());
-            std::process::exit(0);
+            if !report.suggestions.is_empty() {
+                let suggestions: Vec<String> = report
+                    .suggestions
+                    .iter()
+                    .map(|(encoding, confidence)| {
+                        format!("{} ({:.0}%)", encoding.name(), confidence * 100.0)
+                    })
+                    .collect();
+                println!("  Likely encoding: {}", suggestions.join(", "));
+            }
         }
-        Err(e) => {
-            println!("✗ File is not valid {}", encoding.name());
+    }
 
-            if args.show_errors {
-                match e {
-                    EncodeError::UnmappableSource { byte, position } => {
-                        println!(
-                            "  Error at position {}: unmappable byte 0x{:02X}",
-                            position, byte
-                        );
-                    }
-                    EncodeError::UnmappableTarget {
-                        character,
-                        position,
-                    } => {
-                        println!(
-                            "  Error at position {}: unmappable character '{}'",
-                            position, character
-                        );
+    exit_code::status(if errors.is_empty() {
+        exit_code::SUCCESS
+    } else {
+        exit_code::FAILURE
+    })
+}
+
+/// Validator configured from the validate arguments
+#[cfg(feature = "cli")]
+fn validator(args: &ValidateArgs, encoding: Encoding) -> Validator {
+    let limit = match (args.all_errors, args.max_errors) {
+        (false, _) => 1,
+        (true, limit) => limit.unwrap_or(usize::MAX),
+    };
+    let validator = Validator::new(encoding).max_errors(limit);
+    match args.to {
+        Some(EncodingArg::Encoding(target)) => validator.target(target),
+        None => validator,
+    }
+}
+
+/// Validation outcome for one file of a directory scan
+#[cfg(feature = "cli")]
+#[derive(Serialize)]
+struct FileValidation {
+    path: String,
+    valid: bool,
+    invalid_sequences: usize,
+    first_error_offset: Option<usize>,
+    first_error: Option<String>,
+}
+
+/// Validate every file below `root` against `encoding`
+#[cfg(feature = "cli")]
+fn validate_directory(
+    args: &ValidateArgs,
+    encoding: Encoding,
+    root: &Path,
+    cli: &Cli,
+) -> Result<()> {
+    let files = walk_files(root)?;
+    if cli.verbose {
+        eprintln!("Validating {} files under {}", files.len(), root.display());
+    }
+
+    let validator = validator(args, encoding);
+    let results = parallel_map(&files, args.jobs, |path| {
+        let outcome = fs::read(path).map(|data| validator.validate(&data).invalid);
+
+        let (invalid_sequences, first_error_offset, first_error) = match outcome {
+            Ok(errors) => (
+                errors.len(),
+                errors.first().map(|e| e.offset),
+                errors.first().map(|e| e.reason.clone()),
+            ),
+            Err(e) => (0, None, Some(e.to_string())),
+        };
+
+        FileValidation {
+            path: path
+                .strip_prefix(root)
+                .unwrap_or(path)
+                .display()
+                .to_string(),
+            valid: first_error.is_none(),
+            invalid_sequences,
+            first_error_offset,
+            first_error,
+        }
+    });
+
+    let failed = results.iter().filter(|r| !r.valid).count();
+    let passed = results.len() - failed;
+
+    match cli.format {
+        OutputFormat::Json => {
+            let result = serde_json::json!({
+                "root": root.display().to_string(),
+                "encoding": encoding.name(),
+                "passed": passed,
+                "failed": failed,
+                "files": results,
+            });
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        }
+        OutputFormat::Text => {
+            for result in &results {
+                if result.valid {
+                    println!("PASS  {}", result.path);
+                    continue;
+                }
+
+                let error = result.first_error.as_deref().unwrap_or("invalid");
+                match result.first_error_offset {
+                    Some(offset) if args.all_errors => println!(
+                        "FAIL  {}: {} invalid sequence(s), first at offset {}: {}",
+                        result.path, result.invalid_sequences, offset, error
+                    ),
+                    Some(offset) => {
+                        println!("FAIL  {}: offset {}: {}", result.path, offset, error)
                     }
-                    _ => println!("  Error: {}", e),
+                    None => println!("FAIL  {}: {}", result.path, error),
                 }
             }
 
-            std::process::exit(1);
+            println!(
+                "\n{} files checked against {}: {} passed, {} failed",
+                results.len(),
+                encoding.name(),
+                passed,
+                failed
+            );
+        }
+    }
+
+    exit_code::status(if failed == 0 {
+        exit_code::SUCCESS
+    } else {
+        exit_code::FAILURE
+    })
+}
+
+/// A file that failed the audit
+#[cfg(feature = "cli")]
+#[derive(Serialize)]
+struct AuditFinding {
+    path: String,
+    detected_encoding: Option<&'static str>,
+    confidence: f64,
+    first_bad_offset: Option<usize>,
+    reason: String,
+}
+
+/// Audit every file below a directory against the expected encoding
+///
+/// Files are flagged when they contain sequences that are invalid in the expected
+/// encoding, or, for single-byte expectations where every byte decodes, when the
+/// detector confidently identifies a different encoding in which the bytes mean
+/// something else. Pure ASCII therefore passes any ASCII-compatible expectation.
+#[cfg(feature = "cli")]
+fn audit_command(args: &AuditArgs, cli: &Cli) -> Result<()> {
+    let EncodingArg::Encoding(expected) = &args.expect;
+    let expected = *expected;
+    let files = walk_files(&args.dir)?;
+    if cli.verbose {
+        eprintln!(
+            "Auditing {} files under {}",
+            files.len(),
+            args.dir.display()
+        );
+    }
+
+    let detector = EncodingDetector::with_sample_size(args.sample_size);
+
+    let findings = parallel_map(&files, args.jobs, |path| {
+        let display = path
+            .strip_prefix(&args.dir)
+            .unwrap_or(path)
+            .display()
+            .to_string();
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(e) => {
+                return Some(AuditFinding {
+                    path: display,
+                    detected_encoding: None,
+                    confidence: 0.0,
+                    first_bad_offset: None,
+                    reason: e.to_string(),
+                });
+            }
+        };
+
+        let detection = detector.detect(&data);
+        let invalid = Validator::new(expected)
+            .max_errors(1)
+            .validate(&data)
+            .invalid
+            .into_iter()
+            .next()
+            .map(|e| (Some(e.offset), e.reason));
+
+        let (first_bad_offset, reason) = match invalid {
+            Some(invalid) => invalid,
+            None if expected.is_multibyte()
+                || detection.encoding == expected
+                || detection.confidence < args.min_confidence
+                || same_text(detection.encoding, expected, &data) =>
+            {
+                return None;
+            }
+            None => (None, format!("looks like {}", detection.encoding.name())),
+        };
+
+        Some(AuditFinding {
+            path: display,
+            detected_encoding: Some(detection.encoding.name()),
+            confidence: detection.confidence,
+            first_bad_offset,
+            reason,
+        })
+    });
+    let findings: Vec<AuditFinding> = findings.into_iter().flatten().collect();
+
+    match cli.format {
+        OutputFormat::Json => {
+            let result = serde_json::json!({
+                "root": args.dir.display().to_string(),
+                "expected": expected.name(),
+                "scanned": files.len(),
+                "flagged": findings.len(),
+                "files": findings,
+            });
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        }
+        OutputFormat::Text => {
+            if !findings.is_empty() {
+                let width = findings
+                    .iter()
+                    .map(|f| f.path.chars().count())
+                    .max()
+                    .unwrap_or(0)
+                    .clamp(4, 60);
+
+                println!(
+                    "{:width$}  {:15} {:>10}  {:>10}  Reason",
+                    "Path",
+                    "Detected",
+                    "Confidence",
+                    "Bad offset",
+                    width = width
+                );
+                for finding in &findings {
+                    println!(
+                        "{:width$}  {:15} {:>9.1}%  {:>10}  {}",
+                        finding.path,
+                        finding.detected_encoding.unwrap_or("-"),
+                        finding.confidence * 100.0,
+                        finding
+                            .first_bad_offset
+                            .map_or("-".to_string(), |o| o.to_string()),
+                        finding.reason,
+                        width = width
+                    );
+                }
+                println!();
+            }
+            println!(
+                "{} of {} files are not {}",
+                findings.len(),
+                files.len(),
+                expected.name()
+            );
         }
     }
+
+    exit_code::status(if findings.is_empty() {
+        exit_code::SUCCESS
+    } else {
+        exit_code::FAILURE
+    })
+}
+
+/// Whether `data` reads as the same text in both encodings (e.g. pure ASCII)
+#[cfg(feature = "cli")]
+fn same_text(detected: Encoding, expected: Encoding, data: &[u8]) -> bool {
+    Translator::new(detected, expected)
+        .and_then(|translator| translator.convert(data))
+        .is_ok_and(|converted| converted == data)
+}
+
+/// Byte range shown around an invalid sequence, aligned to whole code units
+#[cfg(feature = "cli")]
+fn context_window(
+    encoding: Encoding,
+    data: &[u8],
+    error: &InvalidSequence,
+    context: usize,
+) -> (usize, usize) {
+    let mut start = error.offset.saturating_sub(context);
+    match encoding {
+        Encoding::UTF16LE | Encoding::UTF16BE => start -= (error.offset - start) % 2,
+        Encoding::UTF32LE | Encoding::UTF32BE => start -= (error.offset - start) % 4,
+        _ => {}
+    }
+    let end = (error.offset + error.bytes.len() + context).min(data.len());
+    (start, end)
+}
+
+/// Print the hex and decoded context around an invalid sequence
+#[cfg(feature = "cli")]
+fn print_error_context(
+    encoding: Encoding,
+    data: &[u8],
+    error: &InvalidSequence,
+    context: usize,
+    color: bool,
+) {
+    let (start, end) = context_window(encoding, data, error, context);
+    let bad = error.offset..error.offset + error.bytes.len();
+
+    let hex: Vec<String> = (start..end)
+        .map(|i| match (bad.contains(&i), color) {
+            (true, true) => format!("\x1b[1;31m{:02X}\x1b[0m", data[i]),
+            (true, false) => format!("[{:02X}]", data[i]),
+            (false, _) => format!("{:02X}", data[i]),
+        })
+        .collect();
+
+    println!(
+        "\n  Offset {} (0x{:08X}): {}",
+        error.offset, error.offset, error.reason
+    );
+    println!("    hex:  {}", hex.join(" "));
+    println!("    text: {}", decode_lossy(encoding, &data[start..end]));
+}
+
+/// Decode UTF-32, replacing invalid code points and ignoring a trailing partial unit
+#[cfg(feature = "cli")]
+fn decode_utf32_lossy(encoding: Encoding, data: &[u8]) -> String {
+    data.chunks_exact(4)
+        .map(|unit| {
+            let unit = [unit[0], unit[1], unit[2], unit[3]];
+            let value = match encoding {
+                Encoding::UTF32LE => u32::from_le_bytes(unit),
+                _ => u32::from_be_bytes(unit),
+            };
+            char::from_u32(value).unwrap_or(char::REPLACEMENT_CHARACTER)
+        })
+        .collect()
+}
+
+/// Number of candidates shown by `detect --preview`
+#[cfg(feature = "cli")]
+const PREVIEW_CANDIDATES: usize = 3;
+
+/// The first `length` characters of `data` decoded as `encoding`, on one line
+#[cfg(feature = "cli")]
+fn decoded_preview(encoding: Encoding, data: &[u8], length: usize) -> String {
+    let data = match encoding.bom() {
+        Some(bom) => data.strip_prefix(bom).unwrap_or(data),
+        None => data,
+    };
+    decode_lossy(encoding, data).chars().take(length).collect()
+}
+
+/// Decode bytes for display, replacing invalid input and control characters
+#[cfg(feature = "cli")]
+fn decode_lossy(encoding: Encoding, data: &[u8]) -> String {
+    let text = match encoding {
+        Encoding::UTF8 => String::from_utf8_lossy(data).into_owned(),
+        Encoding::UTF16LE | Encoding::UTF16BE => {
+            let units = data.chunks_exact(2).map(|pair| match encoding {
+                Encoding::UTF16LE => u16::from_le_bytes([pair[0], pair[1]]),
+                _ => u16::from_be_bytes([pair[0], pair[1]]),
+            });
+            char::decode_utf16(units)
+                .map(|ch| ch.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect()
+        }
+        Encoding::UTF32LE | Encoding::UTF32BE => decode_utf32_lossy(encoding, data),
+        _ => match code_page_table(encoding) {
+            Ok(table) => data
+                .iter()
+                .map(|&b| table[b as usize].unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect(),
+            Err(_) => String::from_utf8_lossy(data).into_owned(),
+        },
+    };
+
+    text.chars()
+        .map(|ch| if ch.is_control() { '.' } else { ch })
+        .collect()
+}
+
+/// Bytes as space-separated hex
+#[cfg(feature = "cli")]
+fn hex_bytes(data: &[u8]) -> String {
+    data.iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 #[cfg(feature = "cli")]
@@ -677,13 +4897,22 @@ fn info_command(args: &InfoArgs, cli: &Cli) -> Result<()> {
 
     match cli.format {
         OutputFormat::Json => {
-            let info = serde_json::json!({
+            let mut info = serde_json::json!({
                 "name": encoding.name(),
                 "ascii_compatible": encoding.is_ascii_compatible(),
                 "multibyte": encoding.is_multibyte(),
                 "bom": encoding.bom().map(|b| format!("{:02X?}", b)),
                 "description": get_encoding_description(*encoding)
             });
+            if args.samples {
+                let characters: serde_json::Map<_, _> = (0..=u8::MAX)
+                    .filter_map(|byte| {
+                        let ch = encoding.decode_byte(byte)?;
+                        Some((format!("{:02X}", byte), ch.to_string().into()))
+                    })
+                    .collect();
+                info["characters"] = characters.into();
+            }
             println!("{}", serde_json::to_string_pretty(&info)?);
         }
         OutputFormat::Text => {
@@ -724,6 +4953,8 @@ fn get_encoding_description(encoding: Encoding) -> &'static str {
         Encoding::UTF8 => "Unicode Transformation Format 8-bit, variable-length encoding",
         Encoding::UTF16LE => "Unicode Transformation Format 16-bit, little-endian",
         Encoding::UTF16BE => "Unicode Transformation Format 16-bit, big-endian",
+        Encoding::UTF32LE => "Unicode Transformation Format 32-bit, little-endian",
+        Encoding::UTF32BE => "Unicode Transformation Format 32-bit, big-endian",
         Encoding::ASCII => "American Standard Code for Information Interchange (7-bit)",
         Encoding::ISO_8859_1 => "Latin alphabet No. 1, Western European",
         Encoding::ISO_8859_15 => "Latin alphabet No. 9, Western European with Euro symbol",
@@ -738,18 +4969,331 @@ fn get_encoding_description(encoding: Encoding) -> &'static str {
 
 #[cfg(feature = "cli")]
 fn print_character_samples(encoding: Encoding) {
-    // Print some sample characters from the encoding
-    let samples = match encoding {
-        Encoding::UTF8 | Encoding::ASCII => {
-            vec![(0x41, "A"), (0x61, "a"), (0x30, "0"), (0x21, "!")]
+    if (0..=u8::MAX).all(|byte| encoding.decode_byte(byte).is_none()) {
+        println!("  No character is encoded in a single byte");
+        return;
+    }
+
+    // One row per high nibble; controls show as '·' and unassigned bytes as blanks
+    println!(
+        "     {}",
+        (0..16).map(|low| format!(" {:X}", low)).collect::<String>()
+    );
+    for high in 0..16u8 {
+        let row: String = (0..16u8)
+            .map(|low| match encoding.decode_byte(high << 4 | low) {
+                Some(ch) if ch.is_control() => " ·".to_string(),
+                Some(ch) => format!(" {}", ch),
+                None => "  ".to_string(),
+            })
+            .collect();
+        println!("  {:X}_{}", high, row);
+    }
+    if encoding.is_multibyte() {
+        println!("  Bytes not shown start or continue a multi-byte character");
+    }
+}
+
+#[cfg(all(test, feature = "cli"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_replacement() {
+        for (value, expected) in [
+            ("?", "?"),
+            ("", ""),
+            ("[\\t]", "[\t]"),
+            ("\\uFFFD", "\u{FFFD}"),
+            ("\\xe9\\x3F", "é?"),
+            ("\\u{1F600} \\U0001F600", "\u{1F600} \u{1F600}"),
+            ("a\\\\b", "a\\b"),
+        ] {
+            assert_eq!(
+                parse_replacement(value).as_deref(),
+                Ok(expected),
+                "{}",
+                value
+            );
         }
-        Encoding::WINDOWS_1252 => vec![(0x80, "€"), (0x99, "™"), (0xA9, "©"), (0xAE, "®")],
-        Encoding::CP_437 => vec![(0xC9, "╔"), (0xCD, "═"), (0xBB, "»"), (0xF8, "°")],
-        Encoding::EBCDIC_037 => vec![(0xC1, "A"), (0x81, "a"), (0xF0, "0"), (0x5A, "!")],
-        _ => vec![(0x41, "A"), (0x61, "a")],
-    };
+        for value in ["\\", "\\q", "\\u{110000}", "\\uD800", "\\u{41", "\\xZZ"] {
+            assert!(parse_replacement(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_error_mode_policy() {
+        assert_eq!(ErrorMode::Replace.policy(None), ErrorPolicy::Replace);
+        assert_eq!(
+            ErrorMode::Replace.policy(Some("*")),
+            ErrorPolicy::ReplaceWithChar('*')
+        );
+        assert_eq!(
+            ErrorMode::Replace.policy(Some("<?>")),
+            ErrorPolicy::ReplaceWithStr("<?>".into())
+        );
+        assert_eq!(ErrorMode::Ignore.policy(Some("<?>")), ErrorPolicy::Ignore);
+
+        let ErrorPolicy::Transliterate(translit) = ErrorMode::Translit.policy(Some("<?>")) else {
+            panic!("expected a transliterating policy");
+        };
+        assert_eq!(translit.get('€'), Some("EUR"));
+        assert_eq!(
+            *translit.fallback_policy(),
+            ErrorPolicy::ReplaceWithStr("<?>".into())
+        );
+    }
+
+    #[test]
+    fn test_list_categories() {
+        for &category in Category::value_variants() {
+            let name = category.to_possible_value().unwrap();
+            assert_eq!(name.get_name(), category.name());
+            assert!(
+                Encoding::all()
+                    .iter()
+                    .any(|e| e.category() == category.name()),
+                "{}",
+                category.name()
+            );
+        }
+        for encoding in Encoding::all() {
+            assert!(
+                Category::value_variants()
+                    .iter()
+                    .any(|category| category.name() == encoding.category()),
+                "{}",
+                encoding.name()
+            );
+        }
+    }
+
+    #[test]
+    fn test_batch_jobs_share_policies() {
+        let dir = std::env::temp_dir().join(format!("fast-encode-batch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.txt"), "5 €").unwrap();
+        fs::write(dir.join("b.txt"), "“quoted”").unwrap();
+
+        let cli = Cli::parse_from(["fast-encode", "batch", "jobs.toml"]);
+        let Commands::Batch(ref args) = cli.command else {
+            unreachable!()
+        };
+        let mut translators = HashMap::new();
+        let mut policies = HashMap::new();
+        for (input, output) in [("a.txt", "a.out"), ("b.txt", "b.out")] {
+            let job = BatchJobSpec {
+                input: Some(input.into()),
+                output: Some(output.into()),
+                from: Some("utf-8".into()),
+                to: Some("ascii".into()),
+                errors: Some(ErrorMode::Translit),
+                ..BatchJobSpec::default()
+            };
+            run_batch_job(&job, &dir, &mut translators, &mut policies, args, &cli).unwrap();
+        }
+
+        // One transliterator served both jobs
+        assert_eq!(policies.len(), 1);
+        let ErrorPolicy::Transliterate(ref translit) = policies[&(ErrorMode::Translit, "?".into())]
+        else {
+            panic!("expected a transliterating policy");
+        };
+        assert_eq!(Arc::strong_count(translit), 1);
+        assert_eq!(fs::read(dir.join("a.out")).unwrap(), b"5 EUR");
+        assert_eq!(fs::read(dir.join("b.out")).unwrap(), b"\"quoted\"");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn serve_args() -> ServeArgs {
+        let Commands::Serve(args) = Cli::parse_from(["fast-encode", "serve"]).command else {
+            unreachable!()
+        };
+        args
+    }
+
+    fn head(text: &str) -> io::Result<HttpRequest> {
+        read_request_head(&mut text.as_bytes())
+    }
+
+    /// Reader whose socket timeout has expired
+    struct TimedOut;
+
+    impl Read for TimedOut {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::WouldBlock.into())
+        }
+    }
+
+    #[test]
+    fn test_read_request_head() {
+        let request = head(
+            "POST /convert?from=CP1252&to=utf-8&replacement=%3F+x&flag HTTP/1.1\r\n\
+             Host: localhost\r\nTransfer-Encoding: chunked\r\n\r\nbody",
+        )
+        .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/convert");
+        assert_eq!(request.query["from"], "CP1252");
+        assert_eq!(request.query["replacement"], "? x");
+        assert_eq!(request.query["flag"], "");
+        assert_eq!(request.header("transfer-encoding"), Some("chunked"));
+        assert_eq!(request.header("content-length"), None);
+
+        for bad in [
+            "GET /health\r\n\r\n",
+            "\r\n\r\n",
+            "GET /health HTTP/1.1\r\nno colon\r\n\r\n",
+            "GET /health HTTP/1.1\r\nHost: localhost\r\n",
+        ] {
+            let err = head(bad).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", bad);
+        }
+
+        let many = "X-Header: 1\r\n".repeat(101);
+        let err = head(&format!("GET / HTTP/1.1\r\n{}\r\n", many))
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "Too many headers");
+        let long = "a".repeat(MAX_HEAD_LINE as usize);
+        assert!(head(&format!("GET /{} HTTP/1.1\r\n\r\n", long)).is_err());
+    }
+
+    #[test]
+    fn test_chunked_body() {
+        let read_body = |raw: &str| {
+            let mut body = ChunkedBody {
+                inner: raw.as_bytes(),
+                remaining: 0,
+                done: false,
+            };
+            let mut decoded = Vec::new();
+            body.read_to_end(&mut decoded).map(|_| decoded)
+        };
+
+        assert_eq!(
+            read_body(
+                "5;name=value\r\nhello\r\n1\r\n \r\n5\r\nworld\r\n0\r\nTrailer: x\r\n\r\nnext"
+            )
+            .unwrap(),
+            b"hello world"
+        );
+        assert_eq!(read_body("0\r\n\r\n").unwrap(), b"");
+        for bad in [
+            "zz\r\nhello\r\n0\r\n\r\n",
+            "5\r\nhelloX\r\n0\r\n\r\n",
+            "5\r\nhel",
+        ] {
+            assert!(read_body(bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_serve_convert() {
+        let args = serve_args();
+        let convert = |target: &str, body: &[u8]| {
+            let request = head(&format!("POST {} HTTP/1.1\r\n\r\n", target)).unwrap();
+            let mut response = Vec::new();
+            serve_convert(&request, &mut &body[..], &mut response, &args).unwrap();
+            String::from_utf8_lossy(&response).into_owned()
+        };
+
+        let response = convert("/convert?from=latin1&to=utf-8", b"caf\xE9");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.contains("charset=UTF-8"));
+        assert!(
+            response.ends_with("\r\n\r\n5\r\ncafé\r\n0\r\n\r\n"),
+            "{}",
+            response
+        );
+
+        let response = convert(
+            "/convert?from=utf-8&to=ascii&errors=replace",
+            "é".as_bytes(),
+        );
+        assert!(
+            response.ends_with("\r\n1\r\n?\r\n0\r\n\r\n"),
+            "{}",
+            response
+        );
+
+        for (target, status) in [
+            ("/convert?from=utf-8&to=ascii", "422"),
+            ("/convert?from=nonsense&to=utf-8", "400"),
+            ("/convert?from=utf-8&to=nonsense", "400"),
+            ("/convert?from=utf-8", "400"),
+            ("/convert?from=utf-8&to=ascii&errors=bogus", "400"),
+            ("/convert?from=custom:page.ucm&to=utf-8", "400"),
+        ] {
+            let response = convert(target, "é".as_bytes());
+            assert!(
+                response.starts_with(&format!("HTTP/1.1 {} ", status)),
+                "{}: {}",
+                target,
+                response
+            );
+            assert!(response.contains("{\"error\":"), "{}", response);
+        }
+
+        let request = head("POST /convert?from=utf-8&to=ascii HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = Vec::new();
+        serve_convert(&request, &mut TimedOut, &mut response, &args).unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 408 Request Timeout\r\n"));
+    }
+
+    #[test]
+    fn test_serve_detect() {
+        let mut response = Vec::new();
+        serve_detect(&mut "\u{FEFF}héllo wörld".as_bytes(), &mut response).unwrap();
+        let response = String::from_utf8(response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(head.contains("Content-Type: application/json"));
+        let json: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(json["detected_encoding"], "UTF-8");
+        assert_eq!(json["bom_detected"], true);
+
+        let mut response = Vec::new();
+        serve_detect(&mut TimedOut, &mut response).unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 408 Request Timeout\r\n"));
+    }
+
+    #[test]
+    fn test_atomic_file() {
+        let dir = std::env::temp_dir().join(format!("fast-encode-atomic-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.txt");
+        fs::write(&path, "original").unwrap();
+        let leftovers = || fs::read_dir(&dir).unwrap().count();
+
+        // Dropped without commit: the destination is untouched and the temporary file gone
+        let mut file = AtomicFile::create(&path).unwrap();
+        file.write_all(b"half-converted").unwrap();
+        drop(file);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "original");
+        assert_eq!(leftovers(), 1);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        }
+        let mut file = AtomicFile::create(&path).unwrap();
+        file.write_all(b"converted").unwrap();
+        file.commit().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "converted");
+        assert_eq!(leftovers(), 1);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o640);
+        }
+
+        // A relative path in the current directory syncs "."
+        assert!(sync_parent(Path::new("data.txt")).is_ok());
 
-    for (byte, desc) in samples {
-        println!("  0x{:02X} -> {}", byte, desc);
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
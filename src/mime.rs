@@ -0,0 +1,156 @@
+//! Decoding of MIME encoded-words in mail headers (RFC 2047)
+//!
+//! Header fields such as `Subject:` and `From:` carry non-ASCII text as
+//! `=?charset?B?...?=` (base64) or `=?charset?Q?...?=` (quoted-printable)
+//! words. [`decode_header`] replaces each word with its text, decoded with the
+//! crate's own charset tables, and leaves the rest of the header untouched.
+//!
+//! ```
+//! use fast_encode::{mime, ErrorPolicy};
+//!
+//! let subject = "=?ISO-8859-1?Q?Caf=E9_cr=E8me?= =?UTF-8?B?4oKsMw==?= each";
+//! assert_eq!(mime::decode_header(subject, ErrorPolicy::Strict).unwrap(), "Café crème€3 each");
+//! ```
+//!
+//! As the RFC requires, whitespace between two adjacent encoded-words is
+//! dropped. Adjacent words in the same charset are decoded together, so a
+//! multi-byte character split across two words by a careless mailer still
+//! comes out whole. Words naming a charset this crate does not know, or whose
+//! payload is malformed, are kept verbatim rather than failing the header.
+
+use crate::{Encoding, Error, ErrorPolicy, Result, Translator};
+
+/// Decode every encoded-word in `header` to UTF-8
+///
+/// Characters the named charset cannot decode are handled by `policy`; with
+/// [`ErrorPolicy::Strict`] the error position is a byte offset into the
+/// decoded payload of the run of words that failed.
+pub fn decode_header(header: &str, policy: ErrorPolicy) -> Result<String> {
+    let mut output = String::with_capacity(header.len());
+    // Charset and payload of the run of adjacent words not yet decoded
+    let mut pending: Option<(Encoding, Vec<u8>)> = None;
+    let mut rest = header;
+
+    while let Some(start) = rest.find("=?") {
+        let Some((encoding, payload, length)) = parse_word(&rest[start..]) else {
+            flush(&mut pending, &mut output, &policy)?;
+            output.push_str(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            continue;
+        };
+
+        let between = &rest[..start];
+        let adjacent = pending.is_some() && between.chars().all(char::is_whitespace);
+        if !adjacent {
+            flush(&mut pending, &mut output, &policy)?;
+            output.push_str(between);
+        }
+
+        match &mut pending {
+            Some((current, bytes)) if *current == encoding => bytes.extend_from_slice(&payload),
+            _ => {
+                flush(&mut pending, &mut output, &policy)?;
+                pending = Some((encoding, payload));
+            }
+        }
+        rest = &rest[start + length..];
+    }
+
+    flush(&mut pending, &mut output, &policy)?;
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Decode a pending run of words onto the end of `output`
+fn flush(
+    pending: &mut Option<(Encoding, Vec<u8>)>,
+    output: &mut String,
+    policy: &ErrorPolicy,
+) -> Result<()> {
+    if let Some((encoding, bytes)) = pending.take() {
+        let utf8 = Translator::new(encoding, Encoding::UTF8)?
+            .convert_with_policy(&bytes, policy.clone())?;
+        let text = String::from_utf8(utf8).map_err(|e| Error::InvalidInput(e.to_string()))?;
+        output.push_str(&text);
+    }
+    Ok(())
+}
+
+/// Parse the encoded-word at the start of `input`
+///
+/// Returns its charset, decoded payload and length in `input`, or `None` if
+/// `input` does not start with a well-formed word in a known charset.
+fn parse_word(input: &str) -> Option<(Encoding, Vec<u8>, usize)> {
+    let body = input.strip_prefix("=?")?;
+    let (charset, body) = body.split_once('?')?;
+    let (scheme, body) = body.split_once('?')?;
+    let end = body.find("?=")?;
+    let text = &body[..end];
+
+    if charset.contains(char::is_whitespace) || text.contains(char::is_whitespace) {
+        return None;
+    }
+
+    // RFC 2231 allows a language tag after the charset: =?UTF-8*en?Q?...?=
+    let label = charset.split_once('*').map_or(charset, |(label, _)| label);
+    let encoding = Encoding::for_label(label)?;
+    let payload = match scheme {
+        "B" | "b" => decode_base64(text)?,
+        "Q" | "q" => decode_q(text)?,
+        _ => return None,
+    };
+
+    let length = 2 + charset.len() + 1 + scheme.len() + 1 + end + 2;
+    Some((encoding, payload, length))
+}
+
+/// Base64 with optional padding, as used by the `B` encoding
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for byte in text.trim_end_matches('=').bytes() {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(output)
+}
+
+/// The `Q` encoding: quoted-printable with `_` standing for a space
+fn decode_q(text: &str) -> Option<Vec<u8>> {
+    let bytes = text.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b'_' => output.push(b' '),
+            b'=' => {
+                let hex = bytes.get(index + 1..index + 3)?;
+                if !hex.iter().all(u8::is_ascii_hexdigit) {
+                    return None;
+                }
+                output.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+                index += 2;
+            }
+            byte => output.push(byte),
+        }
+        index += 1;
+    }
+
+    Some(output)
+}
@@ -0,0 +1,129 @@
+//! Repair of double-encoded ("mojibake") text
+//!
+//! The most common damage is UTF-8 bytes decoded as a legacy single-byte code
+//! page and re-encoded as UTF-8, turning `café` into `cafÃ©`. Each run of
+//! non-ASCII characters is mapped back to bytes through the suspected code page;
+//! if those bytes form valid UTF-8, the run was mojibake and is replaced.
+
+use std::collections::HashMap;
+
+use crate::{Encoding, tables};
+
+/// Code pages tried by [`repair`], most common misreading first
+pub const DEFAULT_CANDIDATES: &[Encoding] = &[
+    Encoding::WINDOWS_1252,
+    Encoding::ISO_8859_1,
+    Encoding::WINDOWS_1250,
+    Encoding::CP_437,
+    Encoding::CP_850,
+];
+
+/// Maximum number of nested misreadings undone
+const MAX_LAYERS: usize = 3;
+
+/// Result of a successful mojibake repair
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repair {
+    /// The repaired text
+    pub text: String,
+    /// Code pages the UTF-8 bytes were misread as, outermost first
+    pub layers: Vec<Encoding>,
+    /// Number of damaged runs that were replaced across all layers
+    pub repaired: usize,
+}
+
+/// Repair text damaged by any of the [`DEFAULT_CANDIDATES`] misreadings
+///
+/// Returns `None` if no damage was found.
+pub fn repair(text: &str) -> Option<Repair> {
+    repair_with(text, DEFAULT_CANDIDATES)
+}
+
+/// Repair text, trying only the given single-byte code pages
+///
+/// Up to three nested layers are undone (text that was double-encoded twice).
+/// Within a layer, the candidate that fixes the most runs wins; ties go to the
+/// earlier candidate.
+pub fn repair_with(text: &str, candidates: &[Encoding]) -> Option<Repair> {
+    let reverse_tables: Vec<(Encoding, HashMap<char, u8>)> = candidates
+        .iter()
+        .filter(|encoding| !encoding.is_multibyte())
+        .map(|&encoding| (encoding, reverse_table(encoding)))
+        .collect();
+
+    let mut current = text.to_string();
+    let mut layers = Vec::new();
+    let mut repaired = 0;
+
+    while layers.len() < MAX_LAYERS {
+        let mut best: Option<(Encoding, String, usize)> = None;
+        for (encoding, reverse) in &reverse_tables {
+            let (fixed, count) = undo_layer(&current, reverse);
+            if count > best.as_ref().map_or(0, |b| b.2) {
+                best = Some((*encoding, fixed, count));
+            }
+        }
+
+        let Some((encoding, fixed, count)) = best else {
+            break;
+        };
+        layers.push(encoding);
+        repaired += count;
+        current = fixed;
+    }
+
+    (!layers.is_empty()).then_some(Repair {
+        text: current,
+        layers,
+        repaired,
+    })
+}
+
+/// Character-to-byte table for a single-byte code page
+///
+/// Positions the code page leaves undefined map to the C1 control with the same
+/// value, since lenient decoders pass those bytes through unchanged.
+fn reverse_table(encoding: Encoding) -> HashMap<char, u8> {
+    let chars = tables::get_encoding_chars(encoding);
+    let mut reverse = HashMap::new();
+
+    for (byte, ch) in chars.iter().enumerate() {
+        let ch = ch.or_else(|| char::from_u32(byte as u32).filter(|c| c.is_control()));
+        if let Some(ch) = ch {
+            reverse.entry(ch).or_insert(byte as u8);
+        }
+    }
+
+    reverse
+}
+
+/// Undo one misreading on every non-ASCII run that decodes to valid UTF-8
+///
+/// Returns the new text and the number of runs replaced.
+fn undo_layer(text: &str, reverse: &HashMap<char, u8>) -> (String, usize) {
+    let mut output = String::with_capacity(text.len());
+    let mut repaired = 0;
+    let mut rest = text;
+
+    while let Some(start) = rest.find(|c: char| !c.is_ascii()) {
+        output.push_str(&rest[..start]);
+        let run_len = rest[start..]
+            .find(|c: char| c.is_ascii())
+            .unwrap_or(rest.len() - start);
+        let run = &rest[start..start + run_len];
+
+        let bytes: Option<Vec<u8>> = run.chars().map(|ch| reverse.get(&ch).copied()).collect();
+        match bytes.and_then(|bytes| String::from_utf8(bytes).ok()) {
+            Some(fixed) => {
+                output.push_str(&fixed);
+                repaired += 1;
+            }
+            None => output.push_str(run),
+        }
+
+        rest = &rest[start + run_len..];
+    }
+
+    output.push_str(rest);
+    (output, repaired)
+}
@@ -1,19 +1,45 @@
-//! Multi-byte encoding support for UTF-8, UTF-16, and other variable-length encodings
+//! Multi-byte encoding support for UTF-8, UTF-16, UTF-32, and other variable-length encodings
 //!
 //! This module handles conversions to/from encodings where characters can span multiple bytes.
 
-use crate::{Encoding, Error, Result};
+use crate::{Encoding, Error, Result, SurrogateMode, cjk, vietnamese};
 
 /// Multi-byte translator for handling UTF-8 and other variable-length encodings
+#[derive(Clone)]
 pub struct MultiByte {
     from: Encoding,
     to: Encoding,
+    /// Handling of unpaired surrogates in UTF-16 or UTF-32 input
+    surrogates: SurrogateMode,
+}
+
+/// A code point decoded from UTF-16 or UTF-32
+#[derive(Debug, Clone, Copy)]
+enum CodePoint {
+    Char(char),
+    /// Unpaired surrogate kept by [`SurrogateMode::Preserve`]
+    Surrogate(u16),
 }
 
 impl MultiByte {
     /// Create a new multi-byte translator
     pub fn new(from: Encoding, to: Encoding) -> Self {
-        Self { from, to }
+        Self {
+            from,
+            to,
+            surrogates: SurrogateMode::Reject,
+        }
+    }
+
+    /// Handle unpaired surrogates in the input according to `mode`
+    pub fn with_surrogates(mut self, mode: SurrogateMode) -> Self {
+        self.surrogates = mode;
+        self
+    }
+
+    /// Handling of unpaired surrogates in the input
+    pub fn surrogates(&self) -> SurrogateMode {
+        self.surrogates
     }
 
     /// Convert single-byte encoding to UTF-8
@@ -26,14 +52,23 @@ impl MultiByte {
         }
 
         let from_chars = crate::tables::get_encoding_chars(self.from);
-        let mut output = Vec::new();
+        let composes = vietnamese::composes(self.from);
+        let mut output = String::with_capacity(input.len());
 
         for (pos, &byte) in input.iter().enumerate() {
             if let Some(ch) = from_chars[byte as usize] {
-                // Convert Unicode character to UTF-8 bytes
-                let mut buf = [0u8; 4];
-                let utf8_bytes = ch.encode_utf8(&mut buf).as_bytes();
-                output.extend_from_slice(utf8_bytes);
+                // A tone mark after a vowel becomes part of the precomposed letter
+                if composes
+                    && let Some(composed) = output
+                        .chars()
+                        .next_back()
+                        .and_then(|base| vietnamese::compose(base, ch))
+                {
+                    output.pop();
+                    output.push(composed);
+                } else {
+                    output.push(ch);
+                }
             } else {
                 return Err(Error::UnmappableSource {
                     byte,
@@ -42,10 +77,11 @@ impl MultiByte {
             }
         }
 
-        Ok(output)
+        Ok(output.into_bytes())
     }
 
     /// Convert UTF-8 to single-byte encoding  
+    #[allow(clippy::wrong_self_convention)]
     pub fn from_utf8(&self, input: &[u8]) -> Result<Vec<u8>> {
         if !matches!(self.from, Encoding::UTF8) {
             return Err(Error::UnsupportedConversion {
@@ -64,13 +100,18 @@ impl MultiByte {
             }
         }
 
-        let utf8_str = std::str::from_utf8(input)
-            .map_err(|_| Error::InvalidInput("Invalid UTF-8 sequence".to_string()))?;
+        let utf8_str = validate_utf8(input)?;
 
         let mut output = Vec::new();
         for (char_pos, ch) in utf8_str.char_indices() {
             if let Some(&byte) = char_to_byte.get(&ch) {
                 output.push(byte);
+            } else if let Some((base, mark)) = vietnamese::decompose(ch)
+                && let (Some(&base), Some(&mark)) =
+                    (char_to_byte.get(&base), char_to_byte.get(&mark))
+            {
+                // Letters the target lacks precomposed are written as base letter and tone mark
+                output.extend([base, mark]);
             } else {
                 return Err(Error::UnmappableTarget {
                     character: ch,
@@ -82,116 +123,370 @@ impl MultiByte {
         Ok(output)
     }
 
-    /// Convert UTF-16 to UTF-8
-    pub fn utf16_to_utf8(&self, input: &[u8]) -> Result<Vec<u8>> {
-        if !matches!(self.from, Encoding::UTF16LE | Encoding::UTF16BE) {
+    /// Decode UTF-16 or UTF-32 into code points
+    ///
+    /// Errors name the byte position of the offending code unit.
+    fn decode_wide(&self, input: &[u8]) -> Result<Vec<CodePoint>> {
+        let Some((width, little_endian)) = wide_layout(self.from) else {
             return Err(Error::UnsupportedConversion {
                 from: self.from.name(),
                 to: self.to.name(),
             });
-        }
+        };
+        let keeps_surrogates = wide_layout(self.to).is_some() || self.to == Encoding::UTF8;
+
+        let mut points = Vec::with_capacity(input.len() / width);
+        let mut position = 0;
+        while position + width <= input.len() {
+            let value = read_unit(&input[position..], width, little_endian);
+            let mut length = width;
+            let decoded = match value {
+                0xD800..=0xDBFF if width == 2 => {
+                    match input
+                        .get(position + 2..position + 4)
+                        .map(|low| read_unit(low, 2, little_endian))
+                    {
+                        Some(low @ 0xDC00..=0xDFFF) => {
+                            length = 4;
+                            char::from_u32(0x10000 + ((value - 0xD800) << 10) + (low - 0xDC00))
+                        }
+                        _ => None,
+                    }
+                }
+                value => char::from_u32(value),
+            };
 
-        // Ensure we have an even number of bytes for UTF-16
-        if input.len() % 2 != 0 {
-            return Err(Error::InvalidInput(
-                "UTF-16 data must have even number of bytes".to_string(),
-            ));
+            let point = match decoded {
+                Some(ch) => CodePoint::Char(ch),
+                None if (0xD800..0xE000).contains(&value) => match self.surrogates {
+                    SurrogateMode::Replace => CodePoint::Char(char::REPLACEMENT_CHARACTER),
+                    SurrogateMode::Preserve if keeps_surrogates => {
+                        CodePoint::Surrogate(value as u16)
+                    }
+                    SurrogateMode::Preserve => {
+                        return Err(Error::InvalidInput(format!(
+                            "{} cannot represent unpaired surrogate 0x{:04X} at position {}",
+                            self.to.name(),
+                            value,
+                            position
+                        )));
+                    }
+                    SurrogateMode::Reject => {
+                        return Err(Error::InvalidInput(format!(
+                            "Unpaired surrogate 0x{:04X} in {} at position {}",
+                            value,
+                            self.from.name(),
+                            position
+                        )));
+                    }
+                },
+                None => {
+                    return Err(Error::InvalidInput(format!(
+                        "Invalid {} code point 0x{:X} at position {}",
+                        self.from.name(),
+                        value,
+                        position
+                    )));
+                }
+            };
+            points.push(point);
+            position += length;
         }
 
-        let mut utf16_chars = Vec::new();
+        if position < input.len() {
+            return Err(Error::InvalidInput(format!(
+                "Incomplete {} code unit at position {}",
+                self.from.name(),
+                position
+            )));
+        }
+        Ok(points)
+    }
 
-        // Convert bytes to UTF-16 code units based on endianness
-        for chunk in input.chunks_exact(2) {
-            let code_unit = match self.from {
-                Encoding::UTF16LE => u16::from_le_bytes([chunk[0], chunk[1]]),
-                Encoding::UTF16BE => u16::from_be_bytes([chunk[0], chunk[1]]),
-                _ => unreachable!(),
-            };
-            utf16_chars.push(code_unit);
+    /// Encode decoded UTF-16 or UTF-32 code points into the target encoding
+    fn encode_code_points(&self, points: &[CodePoint]) -> Result<Vec<u8>> {
+        let mut output = Vec::with_capacity(points.len() * 2);
+
+        if let Some((width, little_endian)) = wide_layout(self.to) {
+            for point in points {
+                match *point {
+                    CodePoint::Char(ch) => encode_wide_char(ch, width, little_endian, &mut output),
+                    CodePoint::Surrogate(unit) => {
+                        write_unit(unit as u32, width, little_endian, &mut output)
+                    }
+                }
+            }
+            return Ok(output);
         }
 
-        // Convert UTF-16 code units to UTF-8
-        let utf8_string = String::from_utf16(&utf16_chars)
-            .map_err(|_| Error::InvalidInput("Invalid UTF-16 sequence".to_string()))?;
+        for point in points {
+            match *point {
+                CodePoint::Char(ch) => {
+                    output.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes())
+                }
+                // Generalized UTF-8, as Rust's OsString uses on Windows
+                CodePoint::Surrogate(unit) => output.extend([
+                    0xE0 | (unit >> 12) as u8,
+                    0x80 | ((unit >> 6) & 0x3F) as u8,
+                    0x80 | (unit & 0x3F) as u8,
+                ]),
+            }
+        }
 
-        Ok(utf8_string.into_bytes())
+        match self.to {
+            Encoding::UTF8 => Ok(output),
+            // Surrogates were rejected while decoding, so the text is valid UTF-8
+            to => MultiByte::new(Encoding::UTF8, to).convert(&output),
+        }
     }
 
-    /// Convert UTF-8 to UTF-16
-    pub fn utf8_to_utf16(&self, input: &[u8]) -> Result<Vec<u8>> {
-        if !matches!(self.to, Encoding::UTF16LE | Encoding::UTF16BE) {
+    /// Encode `input` into UTF-16 or UTF-32 via UTF-8
+    fn encode_wide(&self, input: &[u8], output: &mut Vec<u8>) -> Result<()> {
+        let Some((width, little_endian)) = wide_layout(self.to) else {
             return Err(Error::UnsupportedConversion {
                 from: self.from.name(),
                 to: self.to.name(),
             });
+        };
+
+        let converted;
+        let utf8 = match self.from {
+            Encoding::UTF8 => input,
+            from => {
+                converted = MultiByte::new(from, Encoding::UTF8).convert(input)?;
+                &converted
+            }
+        };
+        let text = validate_utf8(utf8)?;
+
+        output.reserve(text.len() * width);
+        for ch in text.chars() {
+            encode_wide_char(ch, width, little_endian, output);
+        }
+        Ok(())
+    }
+
+    /// Convert between any two encodings via UTF-8 intermediate
+    pub fn convert(&self, input: &[u8]) -> Result<Vec<u8>> {
+        if wide_layout(self.from).is_some() {
+            let points = self.decode_wide(input)?;
+            return self.encode_code_points(&points);
+        }
+        if wide_layout(self.to).is_some() {
+            let mut output = Vec::new();
+            self.encode_wide(input, &mut output)?;
+            return Ok(output);
+        }
+        if cjk::is_double_byte(self.from) || cjk::is_double_byte(self.to) {
+            return self.convert_double_byte(input);
         }
 
-        // Parse UTF-8 input
-        let utf8_str = std::str::from_utf8(input)
-            .map_err(|_| Error::InvalidInput("Invalid UTF-8 sequence".to_string()))?;
+        match (self.from, self.to) {
+            // UTF-8 to UTF-8 (validate and copy)
+            (Encoding::UTF8, Encoding::UTF8) => validate_utf8(input).map(|_| input.to_vec()),
 
-        // Convert to UTF-16 code units
-        let utf16_chars: Vec<u16> = utf8_str.encode_utf16().collect();
+            // Direct UTF-8 output
+            (_, Encoding::UTF8) => self.to_utf8(input),
 
-        // Convert code units to bytes based on endianness
-        let mut output = Vec::with_capacity(utf16_chars.len() * 2);
+            // Direct UTF-8 input
+            (Encoding::UTF8, _) => self.from_utf8(input),
 
-        for code_unit in utf16_chars {
-            match self.to {
-                Encoding::UTF16LE => output.extend_from_slice(&code_unit.to_le_bytes()),
-                Encoding::UTF16BE => output.extend_from_slice(&code_unit.to_be_bytes()),
-                _ => unreachable!(),
+            // Single-byte to single-byte via UTF-8
+            _ => {
+                let utf8_intermediate = MultiByte::new(self.from, Encoding::UTF8).to_utf8(input)?;
+                MultiByte::new(Encoding::UTF8, self.to).from_utf8(&utf8_intermediate)
             }
         }
-
-        Ok(output)
     }
 
-    /// Convert between any two encodings via UTF-8 intermediate
-    pub fn convert(&self, input: &[u8]) -> Result<Vec<u8>> {
+    /// Convert, appending to `output`
+    ///
+    /// Re-encoding UTF-8 as UTF-8, UTF-16 or UTF-32, and decoding a
+    /// single-byte encoding that does not compose tone marks to UTF-8, writes
+    /// straight into `output`; everything else is converted with [`convert`](Self::convert) and copied. On error
+    /// `output` may hold part of the conversion.
+    pub fn convert_into(&self, input: &[u8], output: &mut Vec<u8>) -> Result<()> {
         match (self.from, self.to) {
-            // UTF-16 to UTF-8
-            (Encoding::UTF16LE | Encoding::UTF16BE, Encoding::UTF8) => self.utf16_to_utf8(input),
-
-            // UTF-8 to UTF-16
-            (Encoding::UTF8, Encoding::UTF16LE | Encoding::UTF16BE) => self.utf8_to_utf16(input),
-
-            // UTF-16 to UTF-16 (endianness conversion)
-            (Encoding::UTF16LE | Encoding::UTF16BE, Encoding::UTF16LE | Encoding::UTF16BE) => {
-                if self.from == self.to {
-                    // Same encoding, just copy
-                    Ok(input.to_vec())
-                } else {
-                    // Convert via UTF-8 for simplicity
-                    let utf8_intermediate =
-                        MultiByte::new(self.from, Encoding::UTF8).utf16_to_utf8(input)?;
-                    MultiByte::new(Encoding::UTF8, self.to).utf8_to_utf16(&utf8_intermediate)
+            (Encoding::UTF8, Encoding::UTF8) => {
+                validate_utf8(input)?;
+                output.extend_from_slice(input);
+            }
+            (Encoding::UTF8, to) if wide_layout(to).is_some() => self.encode_wide(input, output)?,
+            (from, Encoding::UTF8) if !from.is_multibyte() && !vietnamese::composes(from) => {
+                let from_chars = crate::tables::get_encoding_chars(from);
+                output.reserve(input.len());
+                for (position, &byte) in input.iter().enumerate() {
+                    let Some(ch) = from_chars[byte as usize] else {
+                        return Err(Error::UnmappableSource { byte, position });
+                    };
+                    output.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
                 }
             }
+            _ => output.extend_from_slice(&self.convert(input)?),
+        }
+        Ok(())
+    }
 
-            // UTF-16 to single-byte encoding
-            (Encoding::UTF16LE | Encoding::UTF16BE, _) => {
-                let utf8_intermediate = self.utf16_to_utf8(input)?;
-                MultiByte::new(Encoding::UTF8, self.to).from_utf8(&utf8_intermediate)
+    /// Convert to or from a double-byte encoding via UTF-8
+    fn convert_double_byte(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let utf8 = match self.from {
+            from if cjk::is_double_byte(from) => cjk::to_utf8(from, input)?.into_bytes(),
+            Encoding::UTF8 => MultiByte::new(Encoding::UTF8, Encoding::UTF8).convert(input)?,
+            from => MultiByte::new(from, Encoding::UTF8).convert(input)?,
+        };
+
+        match self.to {
+            to if cjk::is_double_byte(to) => {
+                // The intermediate is valid UTF-8 by construction
+                let text = std::str::from_utf8(&utf8)
+                    .map_err(|_| Error::InvalidInput("Invalid UTF-8 sequence".to_string()))?;
+                cjk::from_utf8(to, text)
             }
+            Encoding::UTF8 => Ok(utf8),
+            to => MultiByte::new(Encoding::UTF8, to).convert(&utf8),
+        }
+    }
+}
 
-            // Single-byte encoding to UTF-16
-            (_, Encoding::UTF16LE | Encoding::UTF16BE) => {
-                let utf8_intermediate = MultiByte::new(self.from, Encoding::UTF8).to_utf8(input)?;
-                self.utf8_to_utf16(&utf8_intermediate)
+/// Number of trailing bytes in `data` that form an incomplete character in `encoding`
+///
+/// Used by the streaming layer to hold back a sequence split across chunk boundaries.
+pub(crate) fn incomplete_suffix_len(encoding: Encoding, data: &[u8]) -> usize {
+    match encoding {
+        Encoding::UTF8 => incomplete_utf8_suffix_len(data),
+        Encoding::UTF32LE | Encoding::UTF32BE => data.len() % 4,
+        Encoding::UTF16LE | Encoding::UTF16BE => {
+            let odd = data.len() % 2;
+            let even = &data[..data.len() - odd];
+            if even.len() < 2 {
+                return odd;
             }
 
-            // Direct UTF-8 output
-            (_, Encoding::UTF8) => self.to_utf8(input),
+            let last = [even[even.len() - 2], even[even.len() - 1]];
+            let unit = match encoding {
+                Encoding::UTF16LE => u16::from_le_bytes(last),
+                _ => u16::from_be_bytes(last),
+            };
 
-            // Direct UTF-8 input
-            (Encoding::UTF8, _) => self.from_utf8(input),
+            // A high surrogate needs the following low surrogate
+            if (0xD800..0xDC00).contains(&unit) {
+                odd + 2
+            } else {
+                odd
+            }
+        }
+        encoding if cjk::is_double_byte(encoding) => cjk::incomplete_suffix_len(encoding, data),
+        // A vowel may still be followed by a tone mark to compose with
+        encoding if vietnamese::composes(encoding) => match data.last() {
+            Some(&byte) => crate::tables::get_encoding_chars(encoding)[byte as usize]
+                .map_or(0, |ch| usize::from(vietnamese::is_base(ch))),
+            None => 0,
+        },
+        _ => 0,
+    }
+}
 
-            // Single-byte to single-byte via UTF-8
-            _ => {
-                let utf8_intermediate = MultiByte::new(self.from, Encoding::UTF8).to_utf8(input)?;
-                MultiByte::new(Encoding::UTF8, self.to).from_utf8(&utf8_intermediate)
+/// Split `input` into ranges of at most `chunk_size` bytes that each end on a
+/// character boundary, so they can be converted independently
+///
+/// A range is only longer than `chunk_size` where a single character is.
+#[cfg(feature = "rayon")]
+pub(crate) fn split_at_boundaries(
+    encoding: Encoding,
+    input: &[u8],
+    chunk_size: usize,
+) -> Vec<std::ops::Range<usize>> {
+    let chunk_size = chunk_size.max(1);
+    let mut ranges = Vec::with_capacity(input.len() / chunk_size + 1);
+    let mut start = 0;
+
+    while start < input.len() {
+        let mut end = start.saturating_add(chunk_size).min(input.len());
+        while end < input.len() {
+            // The suffix is measured from `start`, which is itself a boundary
+            let split = end - incomplete_suffix_len(encoding, &input[start..end]);
+            if split > start {
+                end = split;
+                break;
             }
+            end = end.saturating_add(chunk_size).min(input.len());
         }
+        ranges.push(start..end);
+        start = end;
     }
+
+    ranges
+}
+
+/// Code unit width in bytes and byte order (`true` for little endian) of UTF-16 and UTF-32
+pub(crate) fn wide_layout(encoding: Encoding) -> Option<(usize, bool)> {
+    match encoding {
+        Encoding::UTF16LE => Some((2, true)),
+        Encoding::UTF16BE => Some((2, false)),
+        Encoding::UTF32LE => Some((4, true)),
+        Encoding::UTF32BE => Some((4, false)),
+        _ => None,
+    }
+}
+
+/// Read the `width`-byte code unit at the start of `data`
+pub(crate) fn read_unit(data: &[u8], width: usize, little_endian: bool) -> u32 {
+    match (width, little_endian) {
+        (2, true) => u16::from_le_bytes([data[0], data[1]]) as u32,
+        (2, false) => u16::from_be_bytes([data[0], data[1]]) as u32,
+        (_, true) => u32::from_le_bytes([data[0], data[1], data[2], data[3]]),
+        (_, false) => u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+    }
+}
+
+/// Append `value` as a `width`-byte code unit
+fn write_unit(value: u32, width: usize, little_endian: bool, output: &mut Vec<u8>) {
+    match (width, little_endian) {
+        (2, true) => output.extend((value as u16).to_le_bytes()),
+        (2, false) => output.extend((value as u16).to_be_bytes()),
+        (_, true) => output.extend(value.to_le_bytes()),
+        (_, false) => output.extend(value.to_be_bytes()),
+    }
+}
+
+/// Append `ch` in UTF-16 (`width` 2) or UTF-32 (`width` 4)
+pub(crate) fn encode_wide_char(ch: char, width: usize, little_endian: bool, output: &mut Vec<u8>) {
+    if width == 2 {
+        for unit in ch.encode_utf16(&mut [0; 2]) {
+            write_unit(*unit as u32, width, little_endian, output);
+        }
+    } else {
+        write_unit(ch as u32, width, little_endian, output);
+    }
+}
+
+/// Check that `input` is UTF-8, naming the position of the first invalid byte
+fn validate_utf8(input: &[u8]) -> Result<&str> {
+    std::str::from_utf8(input).map_err(|e| {
+        Error::InvalidInput(format!(
+            "Invalid UTF-8 sequence at position {}",
+            e.valid_up_to()
+        ))
+    })
+}
+
+fn incomplete_utf8_suffix_len(data: &[u8]) -> usize {
+    // Walk back over at most three continuation bytes to find the lead byte
+    for back in 1..=data.len().min(4) {
+        let byte = data[data.len() - back];
+        if byte & 0xC0 == 0x80 {
+            continue;
+        }
+
+        let needed = match byte {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => return 0,
+        };
+
+        return if back < needed { back } else { 0 };
+    }
+
+    0
 }
@@ -0,0 +1,218 @@
+//! Line-ending and control-character normalization applied to converted output
+//!
+//! Line breaks and controls are recognised in the *target* encoding, so the
+//! same logic works whatever the source was: CR, LF, CRLF and NEL are each
+//! rewritten to the newline chosen by [`NewlineMode`], and other control
+//! characters are kept, dropped or replaced according to [`ControlMode`].
+
+use crate::{ControlMode, Encoding, Error, NewlineMode, Result, multibyte, tables};
+
+/// Rewrites line breaks and control characters in encoded output, one chunk at a time
+#[derive(Debug, Clone)]
+pub(crate) struct NewlineNormalizer {
+    /// Requested line endings
+    mode: NewlineMode,
+    /// Requested control character handling
+    controls: ControlMode,
+    /// Target encoding of the output
+    encoding: Encoding,
+    /// Code unit width of the target encoding (2 for UTF-16, 4 for UTF-32, 1 otherwise)
+    unit: usize,
+    /// Encoded line breaks recognised in the output (empty when preserved)
+    breaks: Vec<(char, Vec<u8>)>,
+    /// Encoded newline written in place of each line break
+    newline: Vec<u8>,
+    /// Single-byte values that encode a control character in the target
+    control_bytes: [bool; 256],
+    /// Encoded substitute for each control character
+    replacement: Vec<u8>,
+    /// The substitute is itself a line break and is written as one
+    replacement_break: Option<char>,
+    /// The previous chunk ended with CR, so a leading LF completes a CRLF
+    pending_cr: bool,
+}
+
+impl NewlineNormalizer {
+    /// Create a normalizer for output in `encoding`, or `None` if both modes preserve the output
+    pub(crate) fn new(
+        mode: NewlineMode,
+        controls: ControlMode,
+        encoding: Encoding,
+    ) -> Result<Option<Self>> {
+        let newline: &[char] = match mode {
+            NewlineMode::Preserve if controls == ControlMode::Preserve => return Ok(None),
+            NewlineMode::Preserve => &[],
+            NewlineMode::Lf => &['\n'],
+            NewlineMode::CrLf => &['\r', '\n'],
+            NewlineMode::Cr => &['\r'],
+            NewlineMode::Nel => &['\u{85}'],
+        };
+
+        let mut encoded = Vec::new();
+        for &ch in newline {
+            encoded.extend(encode_required(ch, encoding, "newline")?);
+        }
+
+        let breaks = if mode == NewlineMode::Preserve {
+            Vec::new()
+        } else {
+            LINE_BREAKS
+                .into_iter()
+                .filter_map(|ch| encode_char(ch, encoding).map(|bytes| (ch, bytes)))
+                .collect()
+        };
+
+        let (replacement, replacement_break) = match controls {
+            ControlMode::Replace(ch)
+                if mode != NewlineMode::Preserve && LINE_BREAKS.contains(&ch) =>
+            {
+                (Vec::new(), Some(ch))
+            }
+            ControlMode::Replace(ch) => {
+                (encode_required(ch, encoding, "control replacement")?, None)
+            }
+            ControlMode::Preserve | ControlMode::Strip => (Vec::new(), None),
+        };
+
+        let mut control_bytes = [false; 256];
+        if controls != ControlMode::Preserve {
+            for (byte, ch) in tables::get_encoding_chars(encoding).iter().enumerate() {
+                control_bytes[byte] = ch.is_some_and(is_control);
+            }
+        }
+
+        Ok(Some(Self {
+            mode,
+            controls,
+            encoding,
+            unit: multibyte::wide_layout(encoding).map_or(1, |(width, _)| width),
+            breaks,
+            newline: encoded,
+            control_bytes,
+            replacement,
+            replacement_break,
+            pending_cr: false,
+        }))
+    }
+
+    /// Requested line endings
+    pub(crate) fn mode(&self) -> NewlineMode {
+        self.mode
+    }
+
+    /// Requested control character handling
+    pub(crate) fn controls(&self) -> ControlMode {
+        self.controls
+    }
+
+    /// Rewrite the line breaks and controls in one chunk of complete characters
+    pub(crate) fn normalize(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::with_capacity(data.len());
+        let mut i = 0;
+
+        while i < data.len() {
+            let found = self
+                .breaks
+                .iter()
+                .find(|(_, bytes)| data[i..].starts_with(bytes))
+                .map(|(ch, bytes)| (*ch, bytes.len()));
+            if let Some((ch, length)) = found {
+                i += length;
+                self.line_break(ch, &mut output);
+                continue;
+            }
+
+            if let Some(length) = self.control_len(&data[i..]) {
+                i += length;
+                match (self.controls, self.replacement_break) {
+                    (ControlMode::Replace(_), Some(ch)) => self.line_break(ch, &mut output),
+                    (ControlMode::Replace(_), None) => {
+                        output.extend_from_slice(&self.replacement);
+                        self.pending_cr = false;
+                    }
+                    _ => self.pending_cr = false,
+                }
+                continue;
+            }
+
+            let end = (i + self.unit).min(data.len());
+            output.extend_from_slice(&data[i..end]);
+            self.pending_cr = false;
+            i = end;
+        }
+
+        output
+    }
+
+    /// Write the newline for line break `ch`
+    fn line_break(&mut self, ch: char, output: &mut Vec<u8>) {
+        // LF directly after CR is the second half of a CRLF already written
+        let completes_crlf = ch == '\n' && self.pending_cr;
+        self.pending_cr = ch == '\r';
+        if !completes_crlf {
+            output.extend_from_slice(&self.newline);
+        }
+    }
+
+    /// Length of the encoded control character at the start of `data`, if there is one
+    fn control_len(&self, data: &[u8]) -> Option<usize> {
+        if self.controls == ControlMode::Preserve {
+            return None;
+        }
+
+        let length = match self.encoding {
+            Encoding::UTF8 => match *data {
+                [byte, ..] if byte < 0x80 => is_control(byte as char).then_some(1)?,
+                [0xC2, second, ..] => is_control(char::from(second)).then_some(2)?,
+                _ => return None,
+            },
+            Encoding::UTF16LE | Encoding::UTF16BE | Encoding::UTF32LE | Encoding::UTF32BE => {
+                let (width, little_endian) = multibyte::wide_layout(self.encoding)?;
+                if data.len() < width {
+                    return None;
+                }
+                let ch = char::from_u32(multibyte::read_unit(data, width, little_endian))?;
+                is_control(ch).then_some(width)?
+            }
+            _ => self.control_bytes[*data.first()? as usize].then_some(1)?,
+        };
+        Some(length)
+    }
+}
+
+/// Line breaks rewritten by [`NewlineMode`]
+const LINE_BREAKS: [char; 3] = ['\r', '\n', '\u{85}'];
+
+/// Whether [`ControlMode`] applies to `ch`
+fn is_control(ch: char) -> bool {
+    ch.is_control() && ch != '\t' && !LINE_BREAKS.contains(&ch)
+}
+
+/// Encode a character the caller asked for, failing if `encoding` cannot represent it
+fn encode_required(ch: char, encoding: Encoding, what: &str) -> Result<Vec<u8>> {
+    encode_char(ch, encoding).ok_or_else(|| {
+        Error::InvalidInput(format!(
+            "{} cannot encode {} character U+{:04X}",
+            encoding.name(),
+            what,
+            ch as u32
+        ))
+    })
+}
+
+/// Encode a single character, if `encoding` can represent it
+fn encode_char(ch: char, encoding: Encoding) -> Option<Vec<u8>> {
+    if let Some((width, little_endian)) = multibyte::wide_layout(encoding) {
+        let mut encoded = Vec::with_capacity(width);
+        multibyte::encode_wide_char(ch, width, little_endian, &mut encoded);
+        return Some(encoded);
+    }
+
+    match encoding {
+        Encoding::UTF8 => Some(ch.to_string().into_bytes()),
+        _ => tables::get_encoding_chars(encoding)
+            .iter()
+            .position(|&c| c == Some(ch))
+            .map(|byte| vec![byte as u8]),
+    }
+}
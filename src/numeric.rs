@@ -0,0 +1,405 @@
+//! COBOL packed and zoned decimal codecs
+//!
+//! Mainframe records store numbers as decimal digits rather than binary:
+//!
+//! - [`PackedDecimal`] (`COMP-3`) packs two digits per byte, with the sign in
+//!   the low nibble of the last byte (`C` positive, `D` negative, `F` unsigned).
+//! - [`ZonedDecimal`] (`DISPLAY`) stores one digit per character, with the
+//!   sign carried in the zone of the last one.
+//!
+//! Both decode to and encode from a [`Decimal`], an integer with an implied
+//! number of decimal places (the `V` in a `PIC` clause):
+//!
+//! ```
+//! use fast_encode::numeric::{Decimal, PackedDecimal, ZonedDecimal};
+//!
+//! // PIC S9(5)V99 COMP-3
+//! let amount = PackedDecimal::new(7, 2);
+//! assert_eq!(amount.decode(&[0x00, 0x12, 0x34, 0x5D]).unwrap().to_string(), "-123.45");
+//! assert_eq!(amount.encode("6.5".parse().unwrap()).unwrap(), [0x00, 0x00, 0x65, 0x0C]);
+//!
+//! // PIC S9(3) in EBCDIC: the last digit's zone is the sign
+//! let count = ZonedDecimal::new(3, 0);
+//! assert_eq!(count.decode(&[0xF0, 0xF4, 0xD2]).unwrap(), Decimal::from(-42));
+//! assert!(count.encode(Decimal::from(1000)).is_err());
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Error, Result};
+
+/// Most digits a [`Decimal`] can hold
+pub const MAX_DIGITS: u32 = 38;
+
+/// A decimal number: an unscaled integer and the number of implied decimal places
+///
+/// Two decimals are equal only if both the unscaled value and the scale are;
+/// `1.0` and `1.00` differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Decimal {
+    unscaled: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    /// `unscaled` × 10<sup>-`scale`</sup>
+    pub fn new(unscaled: i128, scale: u32) -> Self {
+        Self { unscaled, scale }
+    }
+
+    /// The value without its decimal point
+    pub fn unscaled(self) -> i128 {
+        self.unscaled
+    }
+
+    /// Number of implied decimal places
+    pub fn scale(self) -> u32 {
+        self.scale
+    }
+
+    /// The same value with `scale` decimal places
+    ///
+    /// `None` if digits would be lost or the result overflows.
+    pub fn rescale(self, scale: u32) -> Option<Self> {
+        let unscaled = if scale >= self.scale {
+            self.unscaled
+                .checked_mul(10i128.checked_pow(scale - self.scale)?)?
+        } else {
+            let divisor = 10i128.checked_pow(self.scale - scale)?;
+            if self.unscaled % divisor != 0 {
+                return None;
+            }
+            self.unscaled / divisor
+        };
+        Some(Self { unscaled, scale })
+    }
+
+    /// The nearest `f64`
+    pub fn to_f64(self) -> f64 {
+        self.unscaled as f64 / 10f64.powi(self.scale as i32)
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Self {
+        Self::new(value as i128, 0)
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.unscaled < 0 { "-" } else { "" };
+        if self.scale == 0 {
+            return write!(f, "{}{}", sign, self.unscaled.unsigned_abs());
+        }
+
+        let magnitude = format!(
+            "{:0width$}",
+            self.unscaled.unsigned_abs(),
+            width = self.scale as usize + 1
+        );
+        let (whole, fraction) = magnitude.split_at(magnitude.len() - self.scale as usize);
+        write!(f, "{}{}.{}", sign, whole, fraction)
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = Error;
+
+    /// Parse an optionally signed number such as `-12.34`, keeping its scale
+    fn from_str(text: &str) -> Result<Self> {
+        let invalid = || Error::InvalidInput(format!("invalid decimal \"{}\"", text));
+        let (negative, unsigned) = match text.as_bytes().first() {
+            Some(b'-') => (true, &text[1..]),
+            Some(b'+') => (false, &text[1..]),
+            _ => (false, text),
+        };
+        let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut unscaled: i128 = 0;
+        for ch in whole.chars().chain(fraction.chars()) {
+            let digit = ch.to_digit(10).ok_or_else(invalid)?;
+            unscaled = unscaled
+                .checked_mul(10)
+                .and_then(|n| n.checked_add(digit as i128))
+                .ok_or_else(invalid)?;
+        }
+        let scale = u32::try_from(fraction.len()).map_err(|_| invalid())?;
+        Ok(Self::new(
+            if negative { -unscaled } else { unscaled },
+            scale,
+        ))
+    }
+}
+
+/// The decimal digits of `value` at `scale`, most significant first, checked to fit `digits`
+fn digits_of(value: Decimal, digits: u32, scale: u32, signed: bool) -> Result<(bool, Vec<u8>)> {
+    let scaled = value.rescale(scale).ok_or_else(|| {
+        Error::InvalidInput(format!(
+            "{} cannot be represented with {} decimal places",
+            value, scale
+        ))
+    })?;
+    if scaled.unscaled < 0 && !signed {
+        return Err(Error::InvalidInput(format!(
+            "{} is negative but the field is unsigned",
+            value
+        )));
+    }
+
+    let magnitude = scaled.unscaled.unsigned_abs().to_string();
+    if magnitude.len() > digits as usize {
+        return Err(Error::InvalidInput(format!(
+            "{} overflows {} digits with {} decimal places",
+            value, digits, scale
+        )));
+    }
+
+    let mut result = vec![0; digits as usize - magnitude.len()];
+    result.extend(magnitude.bytes().map(|b| b - b'0'));
+    Ok((scaled.unscaled < 0, result))
+}
+
+/// Append `digit` to `value`, failing past [`MAX_DIGITS`]
+fn push_digit(value: i128, digit: u8) -> Result<i128> {
+    value
+        .checked_mul(10)
+        .and_then(|n| n.checked_add(digit as i128))
+        .ok_or_else(|| Error::InvalidInput(format!("more than {} digits", MAX_DIGITS)))
+}
+
+fn check_length(bytes: &[u8], expected: usize, what: &str) -> Result<()> {
+    if bytes.len() != expected {
+        return Err(Error::InvalidInput(format!(
+            "{} field is {} bytes, expected {}",
+            what,
+            bytes.len(),
+            expected
+        )));
+    }
+    Ok(())
+}
+
+/// Packed decimal (`COMP-3`) field format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackedDecimal {
+    digits: u32,
+    scale: u32,
+    signed: bool,
+}
+
+impl PackedDecimal {
+    /// A signed field of `digits` digits, `scale` of them after the implied point
+    pub fn new(digits: u32, scale: u32) -> Self {
+        Self {
+            digits,
+            scale,
+            signed: true,
+        }
+    }
+
+    /// Make the field unsigned: encoded with an `F` sign nibble, negative values rejected
+    pub fn unsigned(mut self) -> Self {
+        self.signed = false;
+        self
+    }
+
+    /// Field length in bytes
+    pub fn len(&self) -> usize {
+        self.digits as usize / 2 + 1
+    }
+
+    /// Whether the field holds no digits, only a sign
+    pub fn is_empty(&self) -> bool {
+        self.digits == 0
+    }
+
+    /// Decode a field of exactly [`len`](Self::len) bytes
+    ///
+    /// Sign nibbles `C`, `A`, `E` and `F` are positive and `B` and `D`
+    /// negative; any other nibble, or a digit nibble above 9, is an error.
+    pub fn decode(&self, bytes: &[u8]) -> Result<Decimal> {
+        check_length(bytes, self.len(), "packed decimal")?;
+        let invalid = || Error::InvalidInput(format!("invalid packed decimal {:02X?}", bytes));
+
+        let (&last, body) = bytes.split_last().ok_or_else(invalid)?;
+        let mut value: i128 = 0;
+        let nibbles = body
+            .iter()
+            .flat_map(|&b| [b >> 4, b & 0x0F])
+            .chain([last >> 4]);
+        for nibble in nibbles {
+            if nibble > 9 {
+                return Err(invalid());
+            }
+            value = push_digit(value, nibble)?;
+        }
+
+        let negative = match last & 0x0F {
+            0x0C | 0x0F | 0x0A | 0x0E => false,
+            0x0B | 0x0D => true,
+            _ => return Err(invalid()),
+        };
+        if negative && !self.signed {
+            return Err(invalid());
+        }
+        Ok(Decimal::new(
+            if negative { -value } else { value },
+            self.scale,
+        ))
+    }
+
+    /// Encode `value` at the field's scale
+    ///
+    /// Fails if the value has more decimal places than the field, more
+    /// digits than fit, or is negative for an unsigned field.
+    pub fn encode(&self, value: Decimal) -> Result<Vec<u8>> {
+        let (negative, digits) = digits_of(value, self.digits, self.scale, self.signed)?;
+        let sign = match (self.signed, negative) {
+            (false, _) => 0x0F,
+            (true, false) => 0x0C,
+            (true, true) => 0x0D,
+        };
+
+        // An even digit count leaves a zero pad nibble at the front
+        let mut nibbles = vec![0; 2 * self.len() - 1 - digits.len()];
+        nibbles.extend(digits);
+        nibbles.push(sign);
+        Ok(nibbles
+            .chunks_exact(2)
+            .map(|pair| (pair[0] << 4) | pair[1])
+            .collect())
+    }
+}
+
+/// Zoned decimal (`DISPLAY` numeric) field format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZonedDecimal {
+    digits: u32,
+    scale: u32,
+    signed: bool,
+    ascii: bool,
+}
+
+impl ZonedDecimal {
+    /// A signed EBCDIC field of `digits` digits, `scale` of them after the implied point
+    ///
+    /// Digits are `F0`-`F9`; the zone of the last one is the sign, `C` or
+    /// `F` positive and `D` negative.
+    pub fn new(digits: u32, scale: u32) -> Self {
+        Self {
+            digits,
+            scale,
+            signed: true,
+            ascii: false,
+        }
+    }
+
+    /// Make the field unsigned: encoded with an `F` zone, negative values rejected
+    pub fn unsigned(mut self) -> Self {
+        self.signed = false;
+        self
+    }
+
+    /// Use ASCII digits: a negative last digit is written `p`-`y` (0x70-0x79)
+    ///
+    /// Decoding also accepts the overpunched letters (`{`, `A`-`I`, `}`,
+    /// `J`-`R`) that EBCDIC zoned digits become when converted as text.
+    pub fn ascii(mut self) -> Self {
+        self.ascii = true;
+        self
+    }
+
+    /// Field length in bytes, one per digit
+    pub fn len(&self) -> usize {
+        self.digits as usize
+    }
+
+    /// Whether the field holds no digits
+    pub fn is_empty(&self) -> bool {
+        self.digits == 0
+    }
+
+    /// Decode a field of exactly [`len`](Self::len) bytes
+    pub fn decode(&self, bytes: &[u8]) -> Result<Decimal> {
+        check_length(bytes, self.len(), "zoned decimal")?;
+        let invalid = || Error::InvalidInput(format!("invalid zoned decimal {:02X?}", bytes));
+
+        let (&last, body) = bytes.split_last().ok_or_else(invalid)?;
+        let (zero, sign_digit) = if self.ascii {
+            (b'0', ascii_sign_digit(last))
+        } else {
+            (0xF0, ebcdic_sign_digit(last))
+        };
+        let (digit, negative) = sign_digit.ok_or_else(invalid)?;
+        if negative && !self.signed {
+            return Err(invalid());
+        }
+
+        let mut value: i128 = 0;
+        for &byte in body {
+            let digit = byte.wrapping_sub(zero);
+            if digit > 9 {
+                return Err(invalid());
+            }
+            value = push_digit(value, digit)?;
+        }
+        value = push_digit(value, digit)?;
+        Ok(Decimal::new(
+            if negative { -value } else { value },
+            self.scale,
+        ))
+    }
+
+    /// Encode `value` at the field's scale
+    ///
+    /// Fails if the value has more decimal places than the field, more
+    /// digits than fit, or is negative for an unsigned field.
+    pub fn encode(&self, value: Decimal) -> Result<Vec<u8>> {
+        let (negative, mut digits) = digits_of(value, self.digits, self.scale, self.signed)?;
+        let Some(last) = digits.pop() else {
+            return Ok(Vec::new());
+        };
+
+        let (zero, last) = match (self.ascii, self.signed, negative) {
+            (true, _, false) => (b'0', b'0' + last),
+            (true, _, true) => (b'0', b'p' + last),
+            (false, false, _) => (0xF0, 0xF0 | last),
+            (false, true, false) => (0xF0, 0xC0 | last),
+            (false, true, true) => (0xF0, 0xD0 | last),
+        };
+        let mut bytes: Vec<u8> = digits.into_iter().map(|digit| zero + digit).collect();
+        bytes.push(last);
+        Ok(bytes)
+    }
+}
+
+/// Digit and sign of the last byte of an EBCDIC zoned field
+fn ebcdic_sign_digit(byte: u8) -> Option<(u8, bool)> {
+    let digit = byte & 0x0F;
+    if digit > 9 {
+        return None;
+    }
+    match byte >> 4 {
+        0x0C | 0x0F | 0x0A | 0x0E => Some((digit, false)),
+        0x0B | 0x0D => Some((digit, true)),
+        _ => None,
+    }
+}
+
+/// Digit and sign of the last byte of an ASCII zoned field
+fn ascii_sign_digit(byte: u8) -> Option<(u8, bool)> {
+    match byte {
+        b'0'..=b'9' => Some((byte - b'0', false)),
+        b'{' => Some((0, false)),
+        b'A'..=b'I' => Some((byte - b'A' + 1, false)),
+        b'}' => Some((0, true)),
+        b'J'..=b'R' => Some((byte - b'J' + 1, true)),
+        b'p'..=b'y' => Some((byte - b'p', true)),
+        _ => None,
+    }
+}
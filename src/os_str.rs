@@ -0,0 +1,85 @@
+//! Conversions between encoded bytes, wide strings and `OsStr`
+//!
+//! Windows file names and registry values are UTF-16 that may contain
+//! unpaired surrogates (WTF-16); [`OsStr`] represents them losslessly as
+//! WTF-8. On Unix an `OsStr` is raw bytes, conventionally UTF-8 but not
+//! necessarily valid. These helpers move between either form and any
+//! [`Encoding`], sending surrogates and invalid bytes through the given
+//! [`ErrorPolicy`] instead of requiring manual UTF-16 packing.
+//!
+//! ```
+//! use fast_encode::{os_str, Encoding, ErrorPolicy};
+//!
+//! let name = os_str::decode_to_os_string(b"caf\xE9.txt", Encoding::WINDOWS_1252, ErrorPolicy::Strict).unwrap();
+//! assert_eq!(name, "café.txt");
+//!
+//! let wide = os_str::decode_to_wide(b"caf\xE9", Encoding::WINDOWS_1252, ErrorPolicy::Strict).unwrap();
+//! assert_eq!(wide, [0x63, 0x61, 0x66, 0xE9]);
+//! ```
+
+use std::ffi::{OsStr, OsString};
+
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(windows)]
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+
+use crate::{Encoding, ErrorPolicy, Result, Translator};
+
+/// Decode `data` into UTF-16 code units
+pub fn decode_to_wide(data: &[u8], from: Encoding, policy: ErrorPolicy) -> Result<Vec<u16>> {
+    let bytes = Translator::new(from, Encoding::UTF16LE)?.convert_with_policy(data, policy)?;
+    Ok(bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect())
+}
+
+/// Encode UTF-16 code units into `to`
+///
+/// Unpaired surrogates are invalid input and handled by `policy`. Error
+/// positions are byte offsets, twice the index of the offending unit.
+pub fn encode_from_wide(units: &[u16], to: Encoding, policy: ErrorPolicy) -> Result<Vec<u8>> {
+    let bytes: Vec<u8> = units.iter().flat_map(|unit| unit.to_le_bytes()).collect();
+    Translator::new(Encoding::UTF16LE, to)?.convert_with_policy(&bytes, policy)
+}
+
+/// Decode `data` into an [`OsString`], e.g. a file name read from a legacy archive
+pub fn decode_to_os_string(data: &[u8], from: Encoding, policy: ErrorPolicy) -> Result<OsString> {
+    #[cfg(windows)]
+    {
+        Ok(OsString::from_wide(&decode_to_wide(data, from, policy)?))
+    }
+
+    #[cfg(not(windows))]
+    {
+        let utf8 = Translator::new(from, Encoding::UTF8)?.convert_with_policy(data, policy)?;
+        String::from_utf8(utf8)
+            .map(OsString::from)
+            .map_err(|e| crate::Error::InvalidInput(e.to_string()))
+    }
+}
+
+/// Encode an [`OsStr`], such as a path component, into `to`
+///
+/// On Windows the string's UTF-16 form is encoded, so unpaired surrogates reach
+/// `policy`; on Unix its bytes are read as UTF-8 and invalid sequences do.
+pub fn encode_os_str(value: &OsStr, to: Encoding, policy: ErrorPolicy) -> Result<Vec<u8>> {
+    #[cfg(windows)]
+    {
+        encode_from_wide(&value.encode_wide().collect::<Vec<u16>>(), to, policy)
+    }
+
+    #[cfg(unix)]
+    {
+        Translator::new(Encoding::UTF8, to)?.convert_with_policy(value.as_bytes(), policy)
+    }
+
+    #[cfg(not(any(windows, unix)))]
+    {
+        let text = value.to_str().ok_or_else(|| {
+            crate::Error::InvalidInput(format!("{} is not valid Unicode", value.display()))
+        })?;
+        Translator::new(Encoding::UTF8, to)?.convert_with_policy(text.as_bytes(), policy)
+    }
+}
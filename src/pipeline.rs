@@ -0,0 +1,644 @@
+//! Conversions with text transformations between decoding and encoding
+//!
+//! A [`Pipeline`] decodes its input to Unicode, runs the text through zero or
+//! more [`Transform`] stages and encodes the result, one chunk at a time. Each
+//! chunk passes through every stage before the next is read, so extra
+//! transformations cost no additional pass over the whole buffer.
+//!
+//! ```
+//! use fast_encode::pipeline::{Newlines, Pipeline, Substitute};
+//! use fast_encode::{Encoding, ErrorPolicy, NewlineMode};
+//!
+//! let mut pipeline = Pipeline::new(Encoding::UTF8, Encoding::ASCII)
+//!     .unwrap()
+//!     .stage(Substitute::new().with('€', "EUR"))
+//!     .stage(Newlines::new(NewlineMode::CrLf))
+//!     .policy(ErrorPolicy::Replace);
+//!
+//! let mut output = pipeline.process_chunk("5€\n".as_bytes()).unwrap();
+//! output.extend(pipeline.finish().unwrap());
+//! assert_eq!(output, b"5EUR\r\n");
+//! ```
+
+use std::collections::HashMap;
+
+use crate::newline::NewlineNormalizer;
+use crate::policy::{self, Encoder, Unit};
+use crate::{ControlMode, Encoding, ErrorPolicy, NewlineMode, Result, StreamingTranslator};
+
+/// A text transformation applied between the decode and encode stages
+///
+/// Chunks may end anywhere between two characters. A stage that needs to see
+/// what follows a character, such as a CR that may start a CRLF, keeps it back
+/// and writes it on a later call or from [`finish`](Transform::finish).
+pub trait Transform {
+    /// Transform one chunk of decoded text, appending the result to `output`
+    fn process(&mut self, input: &str, output: &mut String);
+
+    /// Write whatever the stage kept back once the input has ended
+    fn finish(&mut self, _output: &mut String) {}
+}
+
+/// Streaming conversion with [`Transform`] stages between decoding and encoding
+pub struct Pipeline {
+    /// Decode stage: source encoding to UTF-8
+    decoder: StreamingTranslator,
+    /// Transform stages, in the order they run
+    stages: Vec<Box<dyn Transform + Send>>,
+    /// Encode stage
+    encoder: Encoder,
+    to: Encoding,
+    policy: ErrorPolicy,
+    /// UTF-8 length of the transformed text encoded so far (used for error positions)
+    encoded: usize,
+    /// Characters the policy replaced in the encode stage
+    replacements: usize,
+}
+
+impl Pipeline {
+    /// Create a pipeline from `from` to `to` with no transform stages
+    ///
+    /// Errors fail the conversion until another [`policy`](Self::policy) is set.
+    pub fn new(from: Encoding, to: Encoding) -> Result<Self> {
+        Ok(Self {
+            decoder: StreamingTranslator::with_default_buffer(from, Encoding::UTF8)?,
+            stages: Vec::new(),
+            encoder: Encoder::new(to),
+            to,
+            policy: ErrorPolicy::Strict,
+            encoded: 0,
+            replacements: 0,
+        })
+    }
+
+    /// Append a transform stage; stages run in the order they are added
+    pub fn stage<T: Transform + Send + 'static>(mut self, stage: T) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Handle undecodable input and unencodable characters according to `policy`
+    pub fn policy(mut self, policy: ErrorPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Source encoding
+    pub fn from_encoding(&self) -> Encoding {
+        self.decoder.translator().from_encoding()
+    }
+
+    /// Target encoding
+    pub fn to_encoding(&self) -> Encoding {
+        self.to
+    }
+
+    /// Number of errors the policy recovered from so far, in either direction
+    pub fn replacements(&self) -> usize {
+        self.decoder.replacements() + self.replacements
+    }
+
+    /// Convert one chunk of input
+    ///
+    /// Undecodable input is reported at its byte position in the stream.
+    /// Unencodable characters are reported at their byte position in the
+    /// transformed text, as UTF-8, since stages may have moved them.
+    pub fn process_chunk(&mut self, input: &[u8]) -> Result<Vec<u8>> {
+        let decoded = self
+            .decoder
+            .process_chunk_with_policy(input, self.policy.clone())?;
+        let mut text = String::from_utf8(decoded).expect("decode stage produces UTF-8");
+        for stage in &mut self.stages {
+            let mut output = String::with_capacity(text.len());
+            stage.process(&text, &mut output);
+            text = output;
+        }
+        self.encode(&text)
+    }
+
+    /// Signal the end of the input and convert whatever is still held back
+    pub fn finish(&mut self) -> Result<Vec<u8>> {
+        let decoded = self.decoder.finish_with_policy(self.policy.clone())?;
+        let mut text = String::from_utf8(decoded).expect("decode stage produces UTF-8");
+        for stage in &mut self.stages {
+            let mut output = String::with_capacity(text.len());
+            stage.process(&text, &mut output);
+            stage.finish(&mut output);
+            text = output;
+        }
+        self.encode(&text)
+    }
+
+    /// Convert a complete input in one call
+    pub fn convert(&mut self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut output = self.process_chunk(input)?;
+        output.extend(self.finish()?);
+        Ok(output)
+    }
+
+    /// Encode stage
+    fn encode(&mut self, text: &str) -> Result<Vec<u8>> {
+        let base = self.encoded;
+        let units = text
+            .char_indices()
+            .map(|(index, ch)| (base + index, Unit::Char(ch)));
+        let mut output = Vec::with_capacity(text.len());
+        self.replacements += policy::apply(units, &mut self.encoder, &self.policy, &mut output)?;
+        self.encoded += text.len();
+        Ok(output)
+    }
+}
+
+/// Stage that rewrites every CR, LF, CRLF and NEL to one newline
+#[derive(Debug, Clone)]
+pub struct Newlines {
+    normalizer: Option<NewlineNormalizer>,
+}
+
+impl Newlines {
+    /// Rewrite line breaks according to `mode`
+    pub fn new(mode: NewlineMode) -> Self {
+        Self {
+            // Every newline can be written in UTF-8
+            normalizer: NewlineNormalizer::new(mode, ControlMode::Preserve, Encoding::UTF8)
+                .expect("UTF-8 encodes every newline"),
+        }
+    }
+}
+
+impl Transform for Newlines {
+    fn process(&mut self, input: &str, output: &mut String) {
+        match self.normalizer {
+            Some(ref mut normalizer) => {
+                let normalized = normalizer.normalize(input.as_bytes());
+                output.push_str(
+                    std::str::from_utf8(&normalized).expect("line breaks are whole characters"),
+                );
+            }
+            None => output.push_str(input),
+        }
+    }
+}
+
+/// Stage that replaces individual characters with fixed strings
+#[derive(Debug, Clone, Default)]
+pub struct Substitute {
+    replacements: HashMap<char, String>,
+}
+
+impl Substitute {
+    /// Create a stage with no replacements
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Plain-ASCII equivalents for typographic punctuation, spacing and ligatures
+    ///
+    /// Curly quotes become straight ones, en dashes `-` and em dashes `--`,
+    /// an ellipsis `...`, every non-breaking or fixed-width space an ordinary
+    /// space, ligatures their letters (`ﬁ` to `fi`, `Œ` to `OE`), and invisible
+    /// characters such as the soft hyphen and zero-width space are dropped. The
+    /// profile applies to all text, not only to characters the target cannot
+    /// encode, for consumers that only accept ASCII; further replacements can
+    /// be added with [`with`](Self::with).
+    pub fn asciify() -> Self {
+        ASCII_EQUIVALENTS
+            .iter()
+            .fold(Self::new(), |stage, &(ch, replacement)| {
+                stage.with(ch, replacement)
+            })
+    }
+
+    /// Replace every `ch` with `replacement`, which may be empty to drop it
+    pub fn with(mut self, ch: char, replacement: &str) -> Self {
+        self.replacements.insert(ch, replacement.to_string());
+        self
+    }
+}
+
+impl Transform for Substitute {
+    fn process(&mut self, input: &str, output: &mut String) {
+        for ch in input.chars() {
+            match self.replacements.get(&ch) {
+                Some(replacement) => output.push_str(replacement),
+                None => output.push(ch),
+            }
+        }
+    }
+}
+
+/// Replacements made by [`Substitute::asciify`]
+const ASCII_EQUIVALENTS: &[(char, &str)] = &[
+    // Quotation marks and primes
+    ('\u{2018}', "'"),
+    ('\u{2019}', "'"),
+    ('\u{201A}', "'"),
+    ('\u{201B}', "'"),
+    ('\u{2032}', "'"),
+    ('\u{2039}', "<"),
+    ('\u{203A}', ">"),
+    ('\u{201C}', "\""),
+    ('\u{201D}', "\""),
+    ('\u{201E}', "\""),
+    ('\u{201F}', "\""),
+    ('\u{2033}', "\""),
+    ('\u{AB}', "<<"),
+    ('\u{BB}', ">>"),
+    // Hyphens and dashes
+    ('\u{2010}', "-"),
+    ('\u{2011}', "-"),
+    ('\u{2012}', "-"),
+    ('\u{2013}', "-"),
+    ('\u{2014}', "--"),
+    ('\u{2015}', "--"),
+    ('\u{2212}', "-"),
+    // Ellipsis and bullets
+    ('\u{2026}', "..."),
+    ('\u{2022}', "*"),
+    // Spaces
+    ('\u{A0}', " "),
+    ('\u{2002}', " "),
+    ('\u{2003}', " "),
+    ('\u{2004}', " "),
+    ('\u{2005}', " "),
+    ('\u{2006}', " "),
+    ('\u{2007}', " "),
+    ('\u{2008}', " "),
+    ('\u{2009}', " "),
+    ('\u{200A}', " "),
+    ('\u{202F}', " "),
+    ('\u{205F}', " "),
+    ('\u{3000}', " "),
+    // Invisible formatting characters
+    ('\u{AD}', ""),
+    ('\u{200B}', ""),
+    ('\u{200C}', ""),
+    ('\u{200D}', ""),
+    ('\u{2060}', ""),
+    ('\u{FEFF}', ""),
+    // Ligatures
+    ('\u{C6}', "AE"),
+    ('\u{E6}', "ae"),
+    ('\u{132}', "IJ"),
+    ('\u{133}', "ij"),
+    ('\u{152}', "OE"),
+    ('\u{153}', "oe"),
+    ('\u{FB00}', "ff"),
+    ('\u{FB01}', "fi"),
+    ('\u{FB02}', "fl"),
+    ('\u{FB03}', "ffi"),
+    ('\u{FB04}', "ffl"),
+    ('\u{FB05}', "st"),
+    ('\u{FB06}', "st"),
+];
+
+/// Case applied by [`CaseMap`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Case {
+    /// Uppercase, e.g. for mainframe targets that expect it
+    Upper,
+    /// Lowercase
+    Lower,
+}
+
+/// Stage that maps text to upper- or lowercase
+///
+/// Uses the locale-independent Unicode mappings, including the ones that
+/// change length (`ß` uppercases to `SS`). When lowercasing, a capital sigma
+/// becomes `ς` at the end of a word and `σ` elsewhere, which may mean holding
+/// it back until the next chunk shows whether the word continues.
+#[derive(Debug, Clone)]
+pub struct CaseMap {
+    case: Case,
+    /// The last character that was not case-ignorable was a cased letter
+    after_cased: bool,
+    /// A capital sigma and the case-ignorable characters after it, with whether
+    /// it followed a cased letter
+    sigma: Option<(String, bool)>,
+}
+
+impl CaseMap {
+    /// Map text to `case`
+    pub fn new(case: Case) -> Self {
+        Self {
+            case,
+            after_cased: false,
+            sigma: None,
+        }
+    }
+
+    /// Write a held-back sigma, final unless a cased letter follows it
+    fn resolve_sigma(&mut self, followed_by_cased: bool, output: &mut String) {
+        if let Some((held, after_cased)) = self.sigma.take() {
+            output.push(if after_cased && !followed_by_cased {
+                '\u{3C2}'
+            } else {
+                '\u{3C3}'
+            });
+            output.extend(held.chars().skip(1).flat_map(char::to_lowercase));
+        }
+    }
+}
+
+impl Transform for CaseMap {
+    fn process(&mut self, input: &str, output: &mut String) {
+        if self.case == Case::Upper {
+            output.extend(input.chars().flat_map(char::to_uppercase));
+            return;
+        }
+
+        for ch in input.chars() {
+            if is_case_ignorable(ch) {
+                match self.sigma {
+                    Some((ref mut held, _)) => held.push(ch),
+                    None => output.extend(ch.to_lowercase()),
+                }
+                continue;
+            }
+
+            let cased = ch.is_lowercase() || ch.is_uppercase();
+            self.resolve_sigma(cased, output);
+            if ch == '\u{3A3}' {
+                self.sigma = Some((ch.to_string(), self.after_cased));
+            } else {
+                output.extend(ch.to_lowercase());
+            }
+            self.after_cased = cased;
+        }
+    }
+
+    fn finish(&mut self, output: &mut String) {
+        self.resolve_sigma(false, output);
+    }
+}
+
+/// Approximation of Unicode's Case_Ignorable property: apostrophes, word-internal
+/// punctuation and combining marks, which do not end a word for final sigma
+fn is_case_ignorable(ch: char) -> bool {
+    matches!(
+        ch,
+        '\'' | '.'
+            | ':'
+            | '^'
+            | '`'
+            | '\u{AD}'
+            | '\u{B7}'
+            | '\u{2019}'
+            | '\u{300}'..='\u{36F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+/// Paragraph direction assumed by [`BidiReorder`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Lines read left to right; right-to-left runs are embedded in them
+    LeftToRight,
+    /// Lines read right to left; left-to-right runs are embedded in them
+    RightToLeft,
+}
+
+/// Stage that converts between visual and logical order, line by line
+///
+/// Visual-order data such as ISO-8859-8 stores Hebrew as it is displayed, so
+/// decoding it naively yields reversed words. Every right-to-left run (Hebrew
+/// or Arabic letters and whatever lies between them) is reversed, with
+/// brackets mirrored and numbers inside the run kept in reading order; with a
+/// right-to-left paragraph direction the whole line is reversed instead, apart
+/// from its left-to-right runs. Reordering is its own inverse, so the same
+/// stage also turns logical text into visual order.
+///
+/// This is the common heuristic rather than the full Unicode Bidirectional
+/// Algorithm: neutrals and numbers at the edge of a run stay where they are,
+/// and embedding controls are not interpreted. Each line is held back until
+/// its line break arrives.
+#[derive(Debug, Clone)]
+pub struct BidiReorder {
+    direction: Direction,
+    pending: String,
+}
+
+impl BidiReorder {
+    /// Reorder lines whose paragraph direction is `direction`
+    pub fn new(direction: Direction) -> Self {
+        Self {
+            direction,
+            pending: String::new(),
+        }
+    }
+
+    fn reorder_line(&self, line: &str, output: &mut String) {
+        let chars: Vec<char> = line.chars().collect();
+        match self.direction {
+            Direction::RightToLeft => reverse_keeping(
+                &chars,
+                |ch| is_ltr(ch) || ch.is_numeric(),
+                |ch| !is_rtl(ch),
+                output,
+            ),
+            Direction::LeftToRight => {
+                let mut index = 0;
+                while index < chars.len() {
+                    if !is_rtl(chars[index]) {
+                        output.push(chars[index]);
+                        index += 1;
+                        continue;
+                    }
+
+                    let end = run_end(&chars, index, is_rtl, |ch| !is_ltr(ch));
+                    reverse_keeping(
+                        &chars[index..end],
+                        char::is_numeric,
+                        |ch| ch.is_numeric() || matches!(ch, '.' | ',' | ':' | '/'),
+                        output,
+                    );
+                    index = end;
+                }
+            }
+        }
+    }
+}
+
+impl Transform for BidiReorder {
+    fn process(&mut self, input: &str, output: &mut String) {
+        self.pending.push_str(input);
+        let Some(end) = self.pending.rfind(['\n', '\r']) else {
+            return;
+        };
+
+        let ready: String = self.pending.drain(..=end).collect();
+        for line in ready.split_inclusive(['\n', '\r']) {
+            let (text, newline) = line.split_at(line.len() - 1);
+            self.reorder_line(text, output);
+            output.push_str(newline);
+        }
+    }
+
+    fn finish(&mut self, output: &mut String) {
+        let rest = std::mem::take(&mut self.pending);
+        self.reorder_line(&rest, output);
+    }
+}
+
+/// Write `chars` in reverse order, mirrored, except for the runs that start
+/// with a `keep` character, which are written in their original order
+fn reverse_keeping(
+    chars: &[char],
+    keep: impl Fn(char) -> bool + Copy,
+    within: impl Fn(char) -> bool,
+    output: &mut String,
+) {
+    let mut segments = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let end = if keep(chars[index]) {
+            run_end(chars, index, keep, &within)
+        } else {
+            index + 1
+        };
+        segments.push(index..end);
+        index = end;
+    }
+
+    for segment in segments.into_iter().rev() {
+        let kept = keep(chars[segment.start]);
+        for &ch in &chars[segment] {
+            output.push(if kept { ch } else { mirror(ch) });
+        }
+    }
+}
+
+/// End of the run starting at `start`: the last `edge` character reachable
+/// through characters accepted by `within`
+fn run_end(
+    chars: &[char],
+    start: usize,
+    edge: impl Fn(char) -> bool,
+    within: impl Fn(char) -> bool,
+) -> usize {
+    let mut end = start + 1;
+    for (index, &ch) in chars.iter().enumerate().skip(start + 1) {
+        if !within(ch) {
+            break;
+        }
+        if edge(ch) {
+            end = index + 1;
+        }
+    }
+    end
+}
+
+/// Hebrew and Arabic letters and presentation forms
+fn is_rtl(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{5BE}'
+            | '\u{5C0}'
+            | '\u{5C3}'
+            | '\u{5C6}'
+            | '\u{5D0}'..='\u{5F4}'
+            | '\u{608}'
+            | '\u{60B}'
+            | '\u{60D}'
+            | '\u{61B}'..='\u{64A}'
+            | '\u{66D}'..='\u{6D5}'
+            | '\u{FB1D}'..='\u{FDFF}'
+            | '\u{FE70}'..='\u{FEFC}'
+    )
+}
+
+/// Letters that are neither Hebrew nor Arabic
+fn is_ltr(ch: char) -> bool {
+    ch.is_alphabetic() && !is_rtl(ch)
+}
+
+/// The bracket that faces the other way, as right-to-left display draws it
+fn mirror(ch: char) -> char {
+    match ch {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '\u{AB}' => '\u{BB}',
+        '\u{BB}' => '\u{AB}',
+        other => other,
+    }
+}
+
+/// Unicode normalization form applied by [`Normalize`]
+#[cfg(feature = "normalization")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NormalizationForm {
+    /// Canonical composition, as most systems expect
+    Nfc,
+    /// Canonical decomposition, as classic Mac OS and HFS+ store text
+    Nfd,
+}
+
+/// Stage that normalizes text to NFC or NFD
+///
+/// Enabled by the `normalization` feature. A character may compose with the
+/// ones after it, so the text from the last character that cannot is held
+/// back until the next chunk shows how it continues.
+#[cfg(feature = "normalization")]
+#[derive(Debug, Clone)]
+pub struct Normalize {
+    form: NormalizationForm,
+    pending: String,
+}
+
+#[cfg(feature = "normalization")]
+impl Normalize {
+    /// Normalize to `form`
+    pub fn new(form: NormalizationForm) -> Self {
+        Self {
+            form,
+            pending: String::new(),
+        }
+    }
+
+    fn normalize(&self, text: &str, output: &mut String) {
+        use unicode_normalization::UnicodeNormalization;
+
+        match self.form {
+            NormalizationForm::Nfc => output.extend(text.nfc()),
+            NormalizationForm::Nfd => output.extend(text.nfd()),
+        }
+    }
+}
+
+#[cfg(feature = "normalization")]
+impl Transform for Normalize {
+    fn process(&mut self, input: &str, output: &mut String) {
+        use unicode_normalization::{IsNormalized, char::canonical_combining_class, is_nfc_quick};
+
+        self.pending.push_str(input);
+
+        // Nothing before a starter that never composes with what precedes it
+        // (unlike Hangul vowels and a few Indic vowel signs) can change later
+        let split = self
+            .pending
+            .char_indices()
+            .rev()
+            .find(|&(_, ch)| {
+                canonical_combining_class(ch) == 0
+                    && is_nfc_quick(std::iter::once(ch)) == IsNormalized::Yes
+            })
+            .map_or(0, |(index, _)| index);
+
+        let ready: String = self.pending.drain(..split).collect();
+        self.normalize(&ready, output);
+    }
+
+    fn finish(&mut self, output: &mut String) {
+        let rest = std::mem::take(&mut self.pending);
+        self.normalize(&rest, output);
+    }
+}
@@ -0,0 +1,424 @@
+//! Character-by-character conversion used when an [`ErrorPolicy`] has to recover
+//!
+//! The table and multi-byte paths stop at the first problem. When the caller
+//! asked for something other than [`ErrorPolicy::Strict`], the input is instead
+//! decoded unit by unit and re-encoded, with every undecodable byte sequence and
+//! unencodable character handed to the policy.
+
+use std::collections::HashMap;
+
+use crate::{Encoding, Error, ErrorPolicy, Result, Unmappable, cjk, multibyte, tables, vietnamese};
+
+/// One decoded unit of source input
+pub(crate) enum Unit<'a> {
+    /// A successfully decoded character
+    Char(char),
+    /// Bytes that do not form a character in the source encoding
+    Invalid(&'a [u8]),
+}
+
+/// Convert `input`, applying `policy` to every error
+///
+/// Returns the output and the number of errors the policy handled.
+pub(crate) fn convert(
+    from: Encoding,
+    to: Encoding,
+    input: &[u8],
+    policy: &ErrorPolicy,
+) -> Result<(Vec<u8>, usize)> {
+    let mut output = Vec::with_capacity(input.len());
+    let handled = apply(
+        decode(from, input),
+        &mut Encoder::new(to),
+        policy,
+        &mut output,
+    )?;
+    Ok((output, handled))
+}
+
+/// Convert `input`, writing `replacement` for every undecodable byte sequence
+/// and unencodable character
+///
+/// Returns the output and the number of replacements made.
+pub(crate) fn convert_lossy(
+    from: Encoding,
+    to: Encoding,
+    input: &[u8],
+    replacement: u8,
+) -> (Vec<u8>, usize) {
+    let mut output = Vec::with_capacity(input.len());
+    let mut encoder = Encoder::new(to);
+    let mut replacements = 0;
+
+    for (_, unit) in decode(from, input) {
+        if let Unit::Char(ch) = unit
+            && encoder.encode(ch, &mut output)
+        {
+            continue;
+        }
+        output.push(replacement);
+        replacements += 1;
+    }
+
+    (output, replacements)
+}
+
+/// Something that can append the encoding of a single character
+pub(crate) trait CharEncoder {
+    /// Append the encoding of `ch`, returning `false` if it cannot be encoded
+    fn encode(&mut self, ch: char, output: &mut Vec<u8>) -> bool;
+}
+
+/// Encode decoded `units` into `output`, applying `policy` to every error
+///
+/// Returns the number of errors the policy handled.
+pub(crate) fn apply<'a, E: CharEncoder>(
+    units: impl IntoIterator<Item = (usize, Unit<'a>)>,
+    encoder: &mut E,
+    policy: &ErrorPolicy,
+    output: &mut Vec<u8>,
+) -> Result<usize> {
+    let mut handled = 0;
+
+    for (position, unit) in units {
+        match unit {
+            Unit::Char(ch) => {
+                if encoder.encode(ch, output) {
+                    continue;
+                }
+
+                handled += 1;
+                let Some(policy) = transliterate(encoder, ch, policy, output) else {
+                    continue;
+                };
+                let fallback = match policy {
+                    ErrorPolicy::Strict => {
+                        return Err(Error::UnmappableTarget {
+                            character: ch,
+                            position,
+                        });
+                    }
+                    ErrorPolicy::Ignore => continue,
+                    ErrorPolicy::BackslashReplace => backslash_escape(ch),
+                    ErrorPolicy::XmlCharRefReplace => format!("&#{};", ch as u32),
+                    &ErrorPolicy::Custom(handler) => {
+                        let substitute = handler(Unmappable::Target {
+                            character: ch,
+                            position,
+                        });
+                        if substitute.is_empty() {
+                            continue;
+                        }
+                        substitute
+                    }
+                    ErrorPolicy::Replace
+                    | ErrorPolicy::ReplaceWithChar(_)
+                    | ErrorPolicy::ReplaceWithStr(_)
+                    | ErrorPolicy::Transliterate(_) => String::new(),
+                };
+                encode_fallback(encoder, &fallback, policy, ch, position, output)?;
+            }
+            Unit::Invalid(bytes) => {
+                handled += 1;
+                let policy = without_transliteration(policy);
+                let fallback = match policy {
+                    ErrorPolicy::Strict => {
+                        return Err(Error::UnmappableSource {
+                            byte: bytes[0],
+                            position,
+                        });
+                    }
+                    ErrorPolicy::Ignore => continue,
+                    ErrorPolicy::BackslashReplace => {
+                        bytes.iter().map(|b| format!("\\x{:02x}", b)).collect()
+                    }
+                    &ErrorPolicy::Custom(handler) => {
+                        let substitute = handler(Unmappable::Source { bytes, position });
+                        if substitute.is_empty() {
+                            continue;
+                        }
+                        substitute
+                    }
+                    _ => String::new(),
+                };
+                let ch = char::REPLACEMENT_CHARACTER;
+                encode_fallback(encoder, &fallback, policy, ch, position, output)?;
+            }
+        }
+    }
+
+    Ok(handled)
+}
+
+/// Write the transliteration of `ch` if `policy` has one the target can encode
+///
+/// Returns the policy that handles `ch` otherwise, or `None` once it is written.
+fn transliterate<'p, E: CharEncoder>(
+    encoder: &mut E,
+    ch: char,
+    mut policy: &'p ErrorPolicy,
+    output: &mut Vec<u8>,
+) -> Option<&'p ErrorPolicy> {
+    while let ErrorPolicy::Transliterate(translit) = policy {
+        if let Some(approximation) = translit.get(ch) {
+            let mut encoded = Vec::with_capacity(approximation.len());
+            if approximation
+                .chars()
+                .all(|ch| encoder.encode(ch, &mut encoded))
+            {
+                output.extend(encoded);
+                return None;
+            }
+        }
+        policy = translit.fallback_policy();
+    }
+    Some(policy)
+}
+
+/// The policy that ends a chain of transliteration fallbacks
+fn without_transliteration(mut policy: &ErrorPolicy) -> &ErrorPolicy {
+    while let ErrorPolicy::Transliterate(translit) = policy {
+        policy = translit.fallback_policy();
+    }
+    policy
+}
+
+/// Python-style escape for an unencodable character
+fn backslash_escape(ch: char) -> String {
+    match ch as u32 {
+        code @ 0..=0xFF => format!("\\x{:02x}", code),
+        code @ 0x100..=0xFFFF => format!("\\u{:04x}", code),
+        code => format!("\\U{:08x}", code),
+    }
+}
+
+/// Split `input` into decoded characters and invalid byte runs, with their positions
+pub(crate) fn decode(from: Encoding, input: &[u8]) -> Vec<(usize, Unit<'_>)> {
+    let mut units = Vec::with_capacity(input.len());
+
+    match from {
+        Encoding::UTF8 => {
+            let mut offset = 0;
+            for chunk in input.utf8_chunks() {
+                let valid = chunk.valid();
+                units.extend(
+                    valid
+                        .char_indices()
+                        .map(|(index, ch)| (offset + index, Unit::Char(ch))),
+                );
+                offset += valid.len();
+
+                let invalid = chunk.invalid();
+                if !invalid.is_empty() {
+                    units.push((offset, Unit::Invalid(invalid)));
+                    offset += invalid.len();
+                }
+            }
+        }
+        Encoding::UTF32LE | Encoding::UTF32BE => {
+            let little_endian = from == Encoding::UTF32LE;
+            let mut offset = 0;
+            while offset + 3 < input.len() {
+                let value = multibyte::read_unit(&input[offset..], 4, little_endian);
+                units.push(match char::from_u32(value) {
+                    Some(ch) => (offset, Unit::Char(ch)),
+                    None => (offset, Unit::Invalid(&input[offset..offset + 4])),
+                });
+                offset += 4;
+            }
+
+            if offset < input.len() {
+                units.push((offset, Unit::Invalid(&input[offset..])));
+            }
+        }
+        Encoding::UTF16LE | Encoding::UTF16BE => {
+            let little_endian = from == Encoding::UTF16LE;
+            let unit_at = |i: usize| multibyte::read_unit(&input[i..], 2, little_endian) as u16;
+
+            let mut offset = 0;
+            while offset + 1 < input.len() {
+                let high = unit_at(offset);
+                let decoded = if (0xD800..0xDC00).contains(&high) && offset + 3 < input.len() {
+                    let low = unit_at(offset + 2);
+                    char::decode_utf16([high, low])
+                        .next()
+                        .and_then(|r| r.ok())
+                        .map(|ch| (ch, 4))
+                } else {
+                    char::from_u32(high as u32).map(|ch| (ch, 2))
+                };
+
+                match decoded {
+                    Some((ch, len)) => {
+                        units.push((offset, Unit::Char(ch)));
+                        offset += len;
+                    }
+                    None => {
+                        units.push((offset, Unit::Invalid(&input[offset..offset + 2])));
+                        offset += 2;
+                    }
+                }
+            }
+
+            if offset < input.len() {
+                units.push((offset, Unit::Invalid(&input[offset..])));
+            }
+        }
+        from if cjk::is_double_byte(from) => {
+            let mut offset = 0;
+            while offset < input.len() {
+                match cjk::decode_step(from, &input[offset..]) {
+                    cjk::Step::Char(ch, length) => {
+                        units.push((offset, Unit::Char(ch)));
+                        offset += length;
+                    }
+                    cjk::Step::Invalid(length) => {
+                        units.push((offset, Unit::Invalid(&input[offset..offset + length])));
+                        offset += length;
+                    }
+                    cjk::Step::Incomplete => {
+                        units.push((offset, Unit::Invalid(&input[offset..])));
+                        break;
+                    }
+                }
+            }
+        }
+        _ => {
+            let chars = tables::get_encoding_chars(from);
+            let composes = vietnamese::composes(from);
+            for (position, &byte) in input.iter().enumerate() {
+                let Some(ch) = chars[byte as usize] else {
+                    units.push((position, Unit::Invalid(&input[position..position + 1])));
+                    continue;
+                };
+
+                // A tone mark after a vowel becomes part of the precomposed letter
+                if composes
+                    && let Some((_, Unit::Char(base))) = units.last_mut()
+                    && let Some(composed) = vietnamese::compose(*base, ch)
+                {
+                    *base = composed;
+                    continue;
+                }
+                units.push((position, Unit::Char(ch)));
+            }
+        }
+    }
+
+    units
+}
+
+/// Character encoder for the target encoding
+#[derive(Debug, Clone)]
+pub(crate) enum Encoder {
+    Utf8,
+    /// UTF-16 (`width` 2) or UTF-32 (`width` 4)
+    Wide {
+        width: usize,
+        little_endian: bool,
+    },
+    DoubleByte(Encoding),
+    Table(HashMap<char, u8>),
+}
+
+impl Encoder {
+    pub(crate) fn new(to: Encoding) -> Self {
+        if let Some((width, little_endian)) = multibyte::wide_layout(to) {
+            return Encoder::Wide {
+                width,
+                little_endian,
+            };
+        }
+
+        match to {
+            Encoding::UTF8 => Encoder::Utf8,
+            to if cjk::is_double_byte(to) => Encoder::DoubleByte(to),
+            _ => {
+                let mut reverse = HashMap::new();
+                for (byte, ch) in tables::get_encoding_chars(to).iter().enumerate() {
+                    if let Some(ch) = ch {
+                        reverse.entry(*ch).or_insert(byte as u8);
+                    }
+                }
+                Encoder::Table(reverse)
+            }
+        }
+    }
+}
+
+impl CharEncoder for Encoder {
+    fn encode(&mut self, ch: char, output: &mut Vec<u8>) -> bool {
+        match self {
+            Encoder::Utf8 => {
+                output.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+            Encoder::Wide {
+                width,
+                little_endian,
+            } => multibyte::encode_wide_char(ch, *width, *little_endian, output),
+            Encoder::DoubleByte(to) => return cjk::encode(*to, ch, output),
+            Encoder::Table(reverse) => match reverse.get(&ch) {
+                Some(&byte) => output.push(byte),
+                // Letters the target lacks precomposed are written as base letter and tone mark
+                None => match vietnamese::decompose(ch)
+                    .and_then(|(base, mark)| Some([*reverse.get(&base)?, *reverse.get(&mark)?]))
+                {
+                    Some(bytes) => output.extend(bytes),
+                    None => return false,
+                },
+            },
+        }
+        true
+    }
+}
+
+/// Append an escape sequence or substitution, or the policy's replacement if `escape` is empty
+fn encode_fallback<E: CharEncoder>(
+    encoder: &mut E,
+    escape: &str,
+    policy: &ErrorPolicy,
+    original: char,
+    position: usize,
+    output: &mut Vec<u8>,
+) -> Result<()> {
+    let unencodable = |character| Error::UnmappableTarget {
+        character,
+        position,
+    };
+
+    if !escape.is_empty() {
+        let mut encoded = Vec::with_capacity(escape.len());
+        for ch in escape.chars() {
+            if !encoder.encode(ch, &mut encoded) {
+                return Err(unencodable(original));
+            }
+        }
+        output.extend(encoded);
+        return Ok(());
+    }
+
+    match policy {
+        &ErrorPolicy::ReplaceWithChar(replacement) => {
+            if !encoder.encode(replacement, output) {
+                return Err(unencodable(replacement));
+            }
+        }
+        ErrorPolicy::ReplaceWithStr(replacement) => {
+            let mut encoded = Vec::with_capacity(replacement.len());
+            for ch in replacement.chars() {
+                if !encoder.encode(ch, &mut encoded) {
+                    return Err(unencodable(ch));
+                }
+            }
+            output.extend(encoded);
+        }
+        // U+FFFD where the target has it, '?' otherwise
+        _ => {
+            if !encoder.encode(char::REPLACEMENT_CHARACTER, output) && !encoder.encode('?', output)
+            {
+                return Err(unencodable(original));
+            }
+        }
+    }
+    Ok(())
+}
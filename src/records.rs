@@ -0,0 +1,442 @@
+//! Reading and writing mainframe record formats
+//!
+//! z/OS datasets are sequences of records rather than lines:
+//!
+//! - `F`/`FB`: every record is exactly LRECL bytes, with no framing.
+//! - `V`: each record starts with a 4-byte record descriptor word (RDW)
+//!   holding its length, RDW included, as a big-endian `u16`.
+//! - `VB`: RDW-framed records are grouped into blocks, each starting with a
+//!   4-byte block descriptor word (BDW) holding the block length. A BDW with
+//!   the high bit set is an extended BDW with a 31-bit length.
+//!
+//! Records often mix text with packed decimal and binary fields, which must
+//! not go through a character translator. Given a
+//! [`Layout`](crate::fixed_width::Layout), [`RecordReader`] and
+//! [`RecordWriter`] convert only text and zoned decimal fields and copy
+//! everything else, including bytes past the end of the layout, untouched.
+//! Without a layout the whole record is text.
+//!
+//! ```
+//! use fast_encode::fixed_width::{FieldKind, Layout};
+//! use fast_encode::records::{RecordFormat, RecordReader};
+//! use fast_encode::{Encoding, Translator};
+//!
+//! let layout = Layout::new()
+//!     .text("name", 3)
+//!     .field("amount", 2, FieldKind::Packed { scale: 0 });
+//! let translator = Translator::new(Encoding::EBCDIC_037, Encoding::UTF8).unwrap();
+//!
+//! let dataset: &[u8] = &[
+//!     0x00, 0x11, 0x00, 0x00, // BDW: 17-byte block
+//!     0x00, 0x09, 0x00, 0x00, 0xC2, 0xD6, 0xC2, 0x12, 0x3C, // "BOB", +123
+//!     0x00, 0x04, 0x00, 0x00, // empty record
+//! ];
+//! let mut reader = RecordReader::new(dataset, RecordFormat::VariableBlocked, translator)
+//!     .layout(layout);
+//! assert_eq!(reader.read_record().unwrap().unwrap(), b"BOB\x12\x3C");
+//! assert_eq!(reader.read_record().unwrap().unwrap(), b"");
+//! assert!(reader.read_record().unwrap().is_none());
+//! ```
+//!
+//! Converting to a target where characters take more bytes, such as UTF-8,
+//! changes field widths; fixed-length output then needs a target that keeps
+//! one byte per character. Spanned records (`VS`/`VBS`) are not supported.
+
+use std::io::{self, Read, Write};
+
+use crate::fixed_width::{FieldKind, Layout};
+use crate::{Error, Result, Translator};
+
+/// Longest record an RDW can describe, excluding the RDW itself
+pub const MAX_VARIABLE_RECORD: usize = 32756;
+
+/// Largest block size for `VB` output without extended BDWs
+pub const DEFAULT_BLOCK_SIZE: usize = 32760;
+
+/// Length of a record or block descriptor word
+const DESCRIPTOR_LEN: usize = 4;
+
+/// How records are laid out in a dataset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordFormat {
+    /// Records of exactly this many bytes (`F` and `FB`)
+    Fixed(usize),
+    /// Records with an RDW each (`V`)
+    Variable,
+    /// RDW-framed records grouped in BDW-framed blocks (`VB`)
+    VariableBlocked,
+}
+
+/// Convert the text fields of `record`, or all of it without a layout
+fn convert_record(
+    translator: &Translator,
+    layout: Option<&Layout>,
+    record: &[u8],
+) -> Result<Vec<u8>> {
+    let Some(layout) = layout else {
+        return translator.convert(record);
+    };
+
+    let mut output = Vec::with_capacity(translator.max_output_len(record.len()));
+    let mut position = 0;
+    for field in layout.fields() {
+        // Fields are contiguous, but a short variable-length record may end early
+        let start = field.offset.min(record.len());
+        let end = (field.offset + field.length).min(record.len());
+        output.extend_from_slice(&record[position..start]);
+
+        let bytes = &record[start..end];
+        match field.kind {
+            FieldKind::Text | FieldKind::Zoned { .. } => translator
+                .convert_into(bytes, &mut output)
+                .map_err(|e| e.offset_by(start))?,
+            FieldKind::Packed { .. } | FieldKind::Binary { .. } | FieldKind::Filler => {
+                output.extend_from_slice(bytes)
+            }
+        }
+        position = end;
+    }
+    output.extend_from_slice(&record[position..]);
+    Ok(output)
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, Error::InvalidInput(message))
+}
+
+/// Reads records from `R`, converting them with a [`Translator`]
+pub struct RecordReader<R> {
+    inner: R,
+    format: RecordFormat,
+    translator: Translator,
+    layout: Option<Layout>,
+    /// Bytes of the current block not yet read (`VB` only)
+    block_remaining: usize,
+    /// Offset of the next unread byte of input
+    offset: usize,
+}
+
+impl<R: Read> RecordReader<R> {
+    /// Read records in `format` from `inner`, converting them with `translator`
+    pub fn new(inner: R, format: RecordFormat, translator: Translator) -> Self {
+        Self {
+            inner,
+            format,
+            translator,
+            layout: None,
+            block_remaining: 0,
+            offset: 0,
+        }
+    }
+
+    /// Convert only the text and zoned decimal fields of `layout`
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Offset in the input of the next unread byte
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Unwrap the underlying reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Read and convert the next record, or `None` at the end of the input
+    ///
+    /// Conversion errors report positions from the start of the input.
+    pub fn read_record(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let Some(record) = self.read_raw()? else {
+            return Ok(None);
+        };
+
+        let start = self.offset - record.len();
+        convert_record(&self.translator, self.layout.as_ref(), &record)
+            .map(Some)
+            .map_err(|e| e.offset_by(start).into())
+    }
+
+    /// Read the next record without converting it, framing removed
+    pub fn read_raw(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match self.format {
+            RecordFormat::Fixed(0) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                Error::InvalidInput("record length must be at least 1".to_string()),
+            )),
+            RecordFormat::Fixed(length) => {
+                let mut record = vec![0; length];
+                Ok(self.fill(&mut record, "record")?.then_some(record))
+            }
+            RecordFormat::Variable => self.read_variable(),
+            RecordFormat::VariableBlocked => {
+                while self.block_remaining == 0 {
+                    let mut bdw = [0; DESCRIPTOR_LEN];
+                    let at = self.offset;
+                    if !self.fill(&mut bdw, "block descriptor word")? {
+                        return Ok(None);
+                    }
+                    let length = if bdw[0] & 0x80 != 0 {
+                        (u32::from_be_bytes(bdw) & 0x7FFF_FFFF) as usize
+                    } else {
+                        u16::from_be_bytes([bdw[0], bdw[1]]) as usize
+                    };
+                    if length < DESCRIPTOR_LEN {
+                        return Err(invalid_data(format!(
+                            "block length {} at offset {} is shorter than its descriptor",
+                            length, at
+                        )));
+                    }
+                    self.block_remaining = length - DESCRIPTOR_LEN;
+                }
+
+                let at = self.offset;
+                let record = self.read_variable()?.ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        Error::InvalidInput(format!("block truncated at offset {}", at)),
+                    )
+                })?;
+                let used = self.offset - at;
+                if used > self.block_remaining {
+                    return Err(invalid_data(format!(
+                        "record at offset {} overruns its block",
+                        at
+                    )));
+                }
+                self.block_remaining -= used;
+                Ok(Some(record))
+            }
+        }
+    }
+
+    /// Read an RDW and the record it describes
+    fn read_variable(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let at = self.offset;
+        let mut rdw = [0; DESCRIPTOR_LEN];
+        if !self.fill(&mut rdw, "record descriptor word")? {
+            return Ok(None);
+        }
+
+        let length = u16::from_be_bytes([rdw[0], rdw[1]]) as usize;
+        if rdw[2] != 0 {
+            return Err(invalid_data(format!(
+                "spanned record segment at offset {} is not supported",
+                at
+            )));
+        }
+        if length < DESCRIPTOR_LEN {
+            return Err(invalid_data(format!(
+                "record length {} at offset {} is shorter than its descriptor",
+                length, at
+            )));
+        }
+
+        let mut record = vec![0; length - DESCRIPTOR_LEN];
+        if !record.is_empty() && !self.fill(&mut record, "record")? {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                Error::InvalidInput(format!("record at offset {} is truncated", at)),
+            ));
+        }
+        Ok(Some(record))
+    }
+
+    /// Fill `buffer`, returning `false` if the input was already at its end
+    ///
+    /// Input that ends partway through `buffer` is an error.
+    fn fill(&mut self, buffer: &mut [u8], what: &str) -> io::Result<bool> {
+        let mut filled = 0;
+        while filled < buffer.len() {
+            match self.inner.read(&mut buffer[filled..]) {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let at = self.offset;
+        self.offset += filled;
+        match filled {
+            0 => Ok(false),
+            n if n == buffer.len() => Ok(true),
+            n => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                Error::InvalidInput(format!(
+                    "truncated {} at offset {}: {} of {} bytes",
+                    what,
+                    at,
+                    n,
+                    buffer.len()
+                )),
+            )),
+        }
+    }
+}
+
+impl<R: Read> Iterator for RecordReader<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// Converts records with a [`Translator`] and writes them to `W` with framing
+///
+/// Call [`finish`](Self::finish) once all records are written: it writes the
+/// last `VB` block. Dropping the writer finishes it too, ignoring any error.
+pub struct RecordWriter<W: Write> {
+    /// Always `Some` until [`finish`](Self::finish) takes it
+    inner: Option<W>,
+    format: RecordFormat,
+    translator: Translator,
+    layout: Option<Layout>,
+    block_size: usize,
+    /// RDW-framed records of the block being filled (`VB` only)
+    block: Vec<u8>,
+}
+
+impl<W: Write> RecordWriter<W> {
+    /// Write records in `format` to `inner`, converting them with `translator`
+    pub fn new(inner: W, format: RecordFormat, translator: Translator) -> Self {
+        Self {
+            inner: Some(inner),
+            format,
+            translator,
+            layout: None,
+            block_size: DEFAULT_BLOCK_SIZE,
+            block: Vec::new(),
+        }
+    }
+
+    /// Convert only the text and zoned decimal fields of `layout`
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Largest `VB` block to write, BDW included (default [`DEFAULT_BLOCK_SIZE`])
+    ///
+    /// Blocks over 32760 bytes get an extended BDW.
+    pub fn block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size;
+        self
+    }
+
+    /// Get a reference to the underlying writer
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().expect("writer present until finished")
+    }
+
+    /// Convert `record` and write it
+    ///
+    /// `Fixed` records must convert to exactly the record length. Conversion
+    /// errors report positions within `record`.
+    pub fn write_record(&mut self, record: &[u8]) -> io::Result<()> {
+        let converted = convert_record(&self.translator, self.layout.as_ref(), record)?;
+
+        match self.format {
+            RecordFormat::Fixed(length) => {
+                if converted.len() != length {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        Error::InvalidInput(format!(
+                            "record converts to {} bytes, record length is {}",
+                            converted.len(),
+                            length
+                        )),
+                    ));
+                }
+                self.get_mut().write_all(&converted)
+            }
+            RecordFormat::Variable => {
+                let framed = frame(&converted)?;
+                self.get_mut().write_all(&framed)
+            }
+            RecordFormat::VariableBlocked => {
+                let framed = frame(&converted)?;
+                if DESCRIPTOR_LEN + framed.len() > self.block_size {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        Error::InvalidInput(format!(
+                            "record of {} bytes does not fit a {}-byte block",
+                            converted.len(),
+                            self.block_size
+                        )),
+                    ));
+                }
+                if DESCRIPTOR_LEN + self.block.len() + framed.len() > self.block_size {
+                    self.write_block()?;
+                }
+                self.block.extend_from_slice(&framed);
+                Ok(())
+            }
+        }
+    }
+
+    /// Write the last block, flush, and return the underlying writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.finish_records()?;
+        Ok(self.inner.take().expect("writer present until finished"))
+    }
+
+    fn finish_records(&mut self) -> io::Result<()> {
+        self.write_block()?;
+        self.get_mut().flush()
+    }
+
+    /// Write the pending `VB` block, if it holds any records
+    fn write_block(&mut self) -> io::Result<()> {
+        if self.block.is_empty() {
+            return Ok(());
+        }
+
+        let length = DESCRIPTOR_LEN + self.block.len();
+        let bdw = if length > DEFAULT_BLOCK_SIZE {
+            (length as u32 | 0x8000_0000).to_be_bytes()
+        } else {
+            let [high, low] = (length as u16).to_be_bytes();
+            [high, low, 0, 0]
+        };
+        let block = std::mem::take(&mut self.block);
+        let inner = self.get_mut();
+        inner.write_all(&bdw)?;
+        inner.write_all(&block)
+    }
+
+    fn get_mut(&mut self) -> &mut W {
+        self.inner.as_mut().expect("writer present until finished")
+    }
+}
+
+impl<W: Write> Drop for RecordWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.finish_records();
+        }
+    }
+}
+
+/// Prefix `record` with its RDW
+fn frame(record: &[u8]) -> io::Result<Vec<u8>> {
+    if record.len() > MAX_VARIABLE_RECORD {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            Error::InvalidInput(format!(
+                "record of {} bytes is longer than the {}-byte maximum",
+                record.len(),
+                MAX_VARIABLE_RECORD
+            )),
+        ));
+    }
+
+    let [high, low] = ((record.len() + DESCRIPTOR_LEN) as u16).to_be_bytes();
+    let mut framed = Vec::with_capacity(record.len() + DESCRIPTOR_LEN);
+    framed.extend_from_slice(&[high, low, 0, 0]);
+    framed.extend_from_slice(record);
+    Ok(framed)
+}
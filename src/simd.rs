@@ -0,0 +1,305 @@
+//! Vectorized [`TranslationTable`] lookups on stable Rust
+//!
+//! A byte shuffle (`pshufb` on x86, `tbl` on aarch64) looks up a whole vector
+//! of bytes at once in a small table. The 256-byte translation table is
+//! covered by repeating the shuffle over slices of it:
+//!
+//! - SSSE3 and AVX2 shuffle from 16 bytes, so the input is looked up in each
+//!   of the 16 rows in turn, with indices outside the current row pushed out
+//!   of range so they contribute zero.
+//! - NEON `tbl` reads up to 64 bytes and returns zero for out-of-range
+//!   indices, so four lookups cover the table.
+//!
+//! Unmappable bytes are found with the same kind of shuffle over the table's
+//! 32-byte bitmask. The vector code stops at the first vector containing one
+//! and leaves the rest to the scalar loop, which reports its exact position.
+//!
+//! The instruction set is picked at runtime; CPUs without one of them get
+//! no vector prefix and the scalar loop does all the work.
+
+use crate::TranslationTable;
+
+/// Vector instruction set used on this CPU, if any
+pub(crate) fn instruction_set() -> Option<&'static str> {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return Some("avx2");
+        }
+        if is_x86_feature_detected!("ssse3") {
+            return Some("ssse3");
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        return Some("neon");
+    }
+
+    #[allow(unreachable_code)]
+    None
+}
+
+/// Translate the longest prefix of `input` without unmappable bytes that
+/// fills whole vectors, appending it to `output`
+///
+/// Returns the number of input bytes translated.
+pub(crate) fn translate_into(
+    table: &TranslationTable,
+    input: &[u8],
+    output: &mut Vec<u8>,
+) -> usize {
+    output.reserve(input.len());
+    let start = output.len();
+
+    // SAFETY: `output` has room for `input.len()` more bytes, and only the
+    // translated prefix is marked initialized
+    unsafe {
+        let done = translate_raw(
+            table,
+            input.as_ptr(),
+            output.as_mut_ptr().add(start),
+            input.len(),
+        );
+        output.set_len(start + done);
+        done
+    }
+}
+
+/// Translate the longest prefix of `buffer` without unmappable bytes that
+/// fills whole vectors, in place
+///
+/// Returns the number of bytes translated.
+pub(crate) fn translate_in_place(table: &TranslationTable, buffer: &mut [u8]) -> usize {
+    let data = buffer.as_mut_ptr();
+    // SAFETY: each vector is loaded before the translation is stored over it
+    unsafe { translate_raw(table, data, data, buffer.len()) }
+}
+
+/// Translate `len` bytes from `src` to `dst` a vector at a time
+///
+/// # Safety
+///
+/// `src` must be readable and `dst` writable for `len` bytes. They may be the
+/// same pointer but must not otherwise overlap.
+#[allow(unused_variables)]
+unsafe fn translate_raw(
+    table: &TranslationTable,
+    src: *const u8,
+    dst: *mut u8,
+    len: usize,
+) -> usize {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: the CPU supports AVX2; the caller upholds the rest
+            return unsafe { x86::translate_avx2(table, src, dst, len) };
+        }
+        if is_x86_feature_detected!("ssse3") {
+            // SAFETY: the CPU supports SSSE3; the caller upholds the rest
+            return unsafe { x86::translate_ssse3(table, src, dst, len) };
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        // SAFETY: NEON is part of the aarch64 baseline; the caller upholds the rest
+        return unsafe { neon::translate(table, src, dst, len) };
+    }
+
+    #[allow(unreachable_code)]
+    0
+}
+
+/// The unmappable-byte bitmask as bytes: bit `b % 8` of byte `b / 8` is set for byte `b`
+fn unmappable_bytes(table: &TranslationTable) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    for (chunk, word) in bytes.chunks_exact_mut(8).zip(table.unmappable_mask) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    bytes
+}
+
+/// Single-bit masks for bit positions 0 to 7, repeated for a 16-byte shuffle
+const BITS: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod x86 {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    use super::{BITS, unmappable_bytes};
+    use crate::TranslationTable;
+
+    // `pshufb` zeroes a lane whose index has the high bit set and otherwise
+    // reads the row at the index's low nibble. Subtracting `16 * row` and then
+    // adding 0x70 with unsigned saturation leaves the high bit clear exactly
+    // for bytes that fall in `row`.
+
+    /// Translate 16 bytes at a time with SSSE3 shuffles
+    #[target_feature(enable = "ssse3")]
+    pub(super) unsafe fn translate_ssse3(
+        table: &TranslationTable,
+        src: *const u8,
+        dst: *mut u8,
+        len: usize,
+    ) -> usize {
+        let load = |bytes: *const u8| {
+            // SAFETY: only called with pointers to 16 readable bytes
+            unsafe { _mm_loadu_si128(bytes as *const __m128i) }
+        };
+        let rows: [__m128i; 16] = std::array::from_fn(|row| load(table.table[row * 16..].as_ptr()));
+        let bitmask = unmappable_bytes(table);
+        let (bitmask_low, bitmask_high) = (load(bitmask.as_ptr()), load(bitmask[16..].as_ptr()));
+        let bits = load(BITS.as_ptr());
+
+        let mut done = 0;
+        while done + 16 <= len {
+            // SAFETY: the caller provides `len` readable bytes at `src`
+            let input = load(unsafe { src.add(done) });
+
+            // Byte `b / 8` of the bitmask, from whichever half it is in
+            let index = _mm_and_si128(_mm_srli_epi16::<3>(input), _mm_set1_epi8(0x1F));
+            let upper = _mm_cmpeq_epi8(
+                _mm_and_si128(index, _mm_set1_epi8(0x10)),
+                _mm_set1_epi8(0x10),
+            );
+            let words = _mm_or_si128(
+                _mm_and_si128(upper, _mm_shuffle_epi8(bitmask_high, index)),
+                _mm_andnot_si128(upper, _mm_shuffle_epi8(bitmask_low, index)),
+            );
+            let bit = _mm_shuffle_epi8(bits, _mm_and_si128(input, _mm_set1_epi8(0x07)));
+            let clear = _mm_cmpeq_epi8(_mm_and_si128(words, bit), _mm_setzero_si128());
+            if _mm_movemask_epi8(clear) != 0xFFFF {
+                break;
+            }
+
+            let mut output = _mm_setzero_si128();
+            let mut shifted = input;
+            for row in rows {
+                let index = _mm_adds_epu8(shifted, _mm_set1_epi8(0x70));
+                output = _mm_or_si128(output, _mm_shuffle_epi8(row, index));
+                shifted = _mm_sub_epi8(shifted, _mm_set1_epi8(16));
+            }
+            // SAFETY: the caller provides `len` writable bytes at `dst`
+            unsafe { _mm_storeu_si128(dst.add(done) as *mut __m128i, output) };
+            done += 16;
+        }
+        done
+    }
+
+    /// Translate 32 bytes at a time with AVX2 shuffles
+    ///
+    /// `vpshufb` shuffles within each 128-bit lane, so every 16-byte table
+    /// is loaded into both lanes.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn translate_avx2(
+        table: &TranslationTable,
+        src: *const u8,
+        dst: *mut u8,
+        len: usize,
+    ) -> usize {
+        let broadcast = |bytes: &[u8]| {
+            // SAFETY: every caller passes at least 16 bytes
+            _mm256_broadcastsi128_si256(unsafe {
+                _mm_loadu_si128(bytes.as_ptr() as *const __m128i)
+            })
+        };
+        let rows: [__m256i; 16] = std::array::from_fn(|row| broadcast(&table.table[row * 16..]));
+        let bitmask = unmappable_bytes(table);
+        let (bitmask_low, bitmask_high) = (broadcast(&bitmask), broadcast(&bitmask[16..]));
+        let bits = broadcast(&BITS);
+
+        let mut done = 0;
+        while done + 32 <= len {
+            // SAFETY: the caller provides `len` readable bytes at `src`
+            let input = unsafe { _mm256_loadu_si256(src.add(done) as *const __m256i) };
+
+            // Byte `b / 8` of the bitmask, from whichever half it is in
+            let index = _mm256_and_si256(_mm256_srli_epi16::<3>(input), _mm256_set1_epi8(0x1F));
+            let upper = _mm256_cmpeq_epi8(
+                _mm256_and_si256(index, _mm256_set1_epi8(0x10)),
+                _mm256_set1_epi8(0x10),
+            );
+            let words = _mm256_blendv_epi8(
+                _mm256_shuffle_epi8(bitmask_low, index),
+                _mm256_shuffle_epi8(bitmask_high, index),
+                upper,
+            );
+            let bit = _mm256_shuffle_epi8(bits, _mm256_and_si256(input, _mm256_set1_epi8(0x07)));
+            let clear = _mm256_cmpeq_epi8(_mm256_and_si256(words, bit), _mm256_setzero_si256());
+            if _mm256_movemask_epi8(clear) != -1 {
+                break;
+            }
+
+            let mut output = _mm256_setzero_si256();
+            let mut shifted = input;
+            for row in rows {
+                let index = _mm256_adds_epu8(shifted, _mm256_set1_epi8(0x70));
+                output = _mm256_or_si256(output, _mm256_shuffle_epi8(row, index));
+                shifted = _mm256_sub_epi8(shifted, _mm256_set1_epi8(16));
+            }
+            // SAFETY: the caller provides `len` writable bytes at `dst`
+            unsafe { _mm256_storeu_si256(dst.add(done) as *mut __m256i, output) };
+            done += 32;
+        }
+        done
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use std::arch::aarch64::*;
+
+    use super::{BITS, unmappable_bytes};
+    use crate::TranslationTable;
+
+    /// Translate 16 bytes at a time with `tbl` lookups
+    ///
+    /// `vqtbl4q_u8` reads a 64-byte quarter of the table and returns zero for
+    /// indices past its end, so subtracting each quarter's offset selects it.
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn translate(
+        table: &TranslationTable,
+        src: *const u8,
+        dst: *mut u8,
+        len: usize,
+    ) -> usize {
+        // SAFETY: every pointer read stays within `table`, the constants, or
+        // the `len` bytes the caller vouches for
+        unsafe {
+            let bytes = table.table.as_ptr();
+            let quarters = [
+                vld1q_u8_x4(bytes),
+                vld1q_u8_x4(bytes.add(64)),
+                vld1q_u8_x4(bytes.add(128)),
+                vld1q_u8_x4(bytes.add(192)),
+            ];
+            let bitmask = unmappable_bytes(table);
+            let bitmask = vld1q_u8_x2(bitmask.as_ptr());
+            let bits = vld1q_u8(BITS.as_ptr());
+
+            let mut done = 0;
+            while done + 16 <= len {
+                let input = vld1q_u8(src.add(done));
+
+                let words = vqtbl2q_u8(bitmask, vshrq_n_u8::<3>(input));
+                let bit = vqtbl1q_u8(bits, vandq_u8(input, vdupq_n_u8(0x07)));
+                if vmaxvq_u8(vtstq_u8(words, bit)) != 0 {
+                    break;
+                }
+
+                let mut output = vdupq_n_u8(0);
+                for (quarter, offset) in quarters.into_iter().zip([0u8, 64, 128, 192]) {
+                    let index = vsubq_u8(input, vdupq_n_u8(offset));
+                    output = vorrq_u8(output, vqtbl4q_u8(quarter, index));
+                }
+                vst1q_u8(dst.add(done), output);
+                done += 16;
+            }
+            done
+        }
+    }
+}
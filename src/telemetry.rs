@@ -0,0 +1,196 @@
+//! Optional observability hooks around conversions
+//!
+//! With the `metrics` feature, every conversion reports to the [`metrics`]
+//! facade, labelled with its `from` and `to` encodings:
+//!
+//! - `fast_encode_bytes_converted_total`: input bytes converted (counter)
+//! - `fast_encode_replacements_total`: errors an [`ErrorPolicy`](crate::ErrorPolicy) recovered from (counter)
+//! - `fast_encode_errors_total`: failed conversions, with a `kind` label (counter)
+//! - `fast_encode_conversion_duration_seconds`: time per call (histogram)
+//! - `fast_encode_throughput_bytes_per_second`: input bytes per second (histogram)
+//!
+//! With the `tracing` feature, translator construction and failed conversions
+//! emit debug events, conversions of at least [`LARGE_CONVERSION`] bytes and
+//! encoding detection run in debug spans, and streaming chunks in trace spans.
+//! Spans carry the encoding pair and byte counts as fields.
+//!
+//! Without either feature these calls compile to nothing.
+
+use crate::detection::DetectionResult;
+use crate::{Encoding, Error};
+
+#[cfg(feature = "metrics")]
+use std::time::Instant;
+
+/// Input size from which a conversion gets its own tracing span
+#[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+pub(crate) const LARGE_CONVERSION: usize = 64 * 1024;
+
+/// A conversion in progress
+#[cfg_attr(not(any(feature = "metrics", feature = "tracing")), allow(dead_code))]
+pub(crate) struct Conversion {
+    from: Encoding,
+    to: Encoding,
+    bytes: usize,
+    #[cfg(feature = "metrics")]
+    started: Instant,
+    #[cfg(feature = "tracing")]
+    _span: Option<tracing::span::EnteredSpan>,
+}
+
+impl Conversion {
+    /// Start converting `bytes` input bytes from `from` to `to`
+    pub(crate) fn start(from: Encoding, to: Encoding, bytes: usize) -> Self {
+        Self {
+            from,
+            to,
+            bytes,
+            #[cfg(feature = "metrics")]
+            started: Instant::now(),
+            #[cfg(feature = "tracing")]
+            _span: (bytes >= LARGE_CONVERSION).then(|| {
+                tracing::debug_span!("convert", from = from.name(), to = to.name(), bytes).entered()
+            }),
+        }
+    }
+
+    /// Report the outcome: how many errors a policy recovered from, or the error
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    pub(crate) fn finish(self, replacements: usize, error: Option<&Error>) {
+        #[cfg(feature = "tracing")]
+        if let Some(error) = error {
+            tracing::debug!(
+                from = self.from.name(),
+                to = self.to.name(),
+                bytes = self.bytes,
+                %error,
+                "conversion failed"
+            );
+        }
+
+        #[cfg(feature = "metrics")]
+        record_metrics(&self, replacements, error);
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn record_metrics(conversion: &Conversion, replacements: usize, error: Option<&Error>) {
+    describe();
+
+    let (from, to) = (conversion.from.name(), conversion.to.name());
+    let labels = [("from", from), ("to", to)];
+    if let Some(error) = error {
+        let kind = match error {
+            Error::UnmappableSource { .. } => "unmappable_source",
+            Error::UnmappableTarget { .. } => "unmappable_target",
+            Error::InvalidInput(_) => "invalid_input",
+            Error::UnsupportedConversion { .. } => "unsupported_conversion",
+            Error::OutputTooSmall { .. } => "output_too_small",
+        };
+        metrics::counter!("fast_encode_errors_total", "from" => from, "to" => to, "kind" => kind)
+            .increment(1);
+        return;
+    }
+
+    let bytes = conversion.bytes;
+    let elapsed = conversion.started.elapsed().as_secs_f64();
+    metrics::counter!("fast_encode_bytes_converted_total", &labels).increment(bytes as u64);
+    if replacements > 0 {
+        metrics::counter!("fast_encode_replacements_total", &labels).increment(replacements as u64);
+    }
+    metrics::histogram!("fast_encode_conversion_duration_seconds", &labels).record(elapsed);
+    if bytes > 0 && elapsed > 0.0 {
+        metrics::histogram!("fast_encode_throughput_bytes_per_second", &labels)
+            .record(bytes as f64 / elapsed);
+    }
+}
+
+/// Register units and descriptions with the recorder installed at the first conversion
+#[cfg(feature = "metrics")]
+fn describe() {
+    static DESCRIBED: std::sync::Once = std::sync::Once::new();
+
+    DESCRIBED.call_once(|| {
+        use metrics::Unit;
+
+        metrics::describe_counter!(
+            "fast_encode_bytes_converted_total",
+            Unit::Bytes,
+            "Input bytes converted"
+        );
+        metrics::describe_counter!(
+            "fast_encode_replacements_total",
+            Unit::Count,
+            "Conversion errors recovered from by an error policy"
+        );
+        metrics::describe_counter!(
+            "fast_encode_errors_total",
+            Unit::Count,
+            "Conversions that failed"
+        );
+        metrics::describe_histogram!(
+            "fast_encode_conversion_duration_seconds",
+            Unit::Seconds,
+            "Time spent per conversion call"
+        );
+        metrics::describe_histogram!(
+            "fast_encode_throughput_bytes_per_second",
+            "Input bytes converted per second"
+        );
+    });
+}
+
+/// An entered tracing span, exited when dropped
+pub(crate) struct Span {
+    #[cfg(feature = "tracing")]
+    _entered: tracing::span::EnteredSpan,
+}
+
+/// A translator was built, using the byte table or the character-by-character path
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn translator_created(from: Encoding, to: Encoding, path: &'static str) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        from = from.name(),
+        to = to.name(),
+        path,
+        "translator created"
+    );
+}
+
+/// Span around one chunk of a streaming conversion, starting at input offset `offset`
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn stream_chunk(from: Encoding, to: Encoding, bytes: usize, offset: usize) -> Span {
+    Span {
+        #[cfg(feature = "tracing")]
+        _entered: tracing::trace_span!(
+            "stream_chunk",
+            from = from.name(),
+            to = to.name(),
+            bytes,
+            offset
+        )
+        .entered(),
+    }
+}
+
+/// Span around detecting the encoding of `bytes` bytes
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn detection(bytes: usize) -> Span {
+    Span {
+        #[cfg(feature = "tracing")]
+        _entered: tracing::debug_span!("detect", bytes).entered(),
+    }
+}
+
+/// Detection finished with `result`
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn detected(result: &DetectionResult) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        encoding = result.encoding.name(),
+        confidence = result.confidence,
+        bom = result.bom_detected,
+        "encoding detected"
+    );
+}
@@ -0,0 +1,165 @@
+//! Transliteration of characters the target encoding cannot represent
+//!
+//! A [`Transliterator`] maps characters such as "–", "€", curly quotes or "œ"
+//! to ASCII or Latin approximations ("-", "EUR", "\"", "oe"). Pass one to
+//! [`ErrorPolicy::Transliterate`] and every unencodable character is written as
+//! its approximation when the target can encode that, and handed to the
+//! transliterator's fallback policy otherwise:
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use fast_encode::{Encoding, ErrorPolicy, Translator, translit::Transliterator};
+//!
+//! let translator = Translator::builder(Encoding::UTF8, Encoding::ISO_8859_1)
+//!     .policy(ErrorPolicy::Transliterate(Arc::new(Transliterator::new())))
+//!     .build()
+//!     .unwrap();
+//! let output = translator.convert("“Œuvre” – 5 €".as_bytes()).unwrap();
+//! assert_eq!(output, b"\"OEuvre\" - 5 EUR");
+//! ```
+//!
+//! The built-in table covers typographic punctuation, spaces, currency and
+//! other common symbols, ligatures, and the accented letters of the Latin-1
+//! and Latin Extended blocks. Characters the target already encodes are never
+//! transliterated, so "é" stays "é" in ISO-8859-1 and only becomes "e" in ASCII.
+
+use std::collections::BTreeMap;
+
+use crate::ErrorPolicy;
+
+/// The built-in table with a [`Strict`](ErrorPolicy::Strict) fallback
+pub static DEFAULT: Transliterator = Transliterator::new();
+
+/// Maps characters to approximations the target encoding may be able to encode
+///
+/// Substitutions added with [`substitute`](Self::substitute) take precedence
+/// over the built-in table. [`ErrorPolicy::Transliterate`] holds it in an
+/// [`Arc`](std::sync::Arc), so clones of the policy share one table.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Transliterator {
+    /// User-supplied substitutions
+    substitutions: BTreeMap<char, String>,
+    /// Whether the built-in table is consulted
+    builtin: bool,
+    /// Policy for characters with no encodable approximation
+    fallback: ErrorPolicy,
+}
+
+impl Transliterator {
+    /// Create a transliterator with the built-in table
+    pub const fn new() -> Self {
+        Self {
+            substitutions: BTreeMap::new(),
+            builtin: true,
+            fallback: ErrorPolicy::Strict,
+        }
+    }
+
+    /// Create a transliterator without the built-in table
+    pub const fn empty() -> Self {
+        Self {
+            substitutions: BTreeMap::new(),
+            builtin: false,
+            fallback: ErrorPolicy::Strict,
+        }
+    }
+
+    /// Write `replacement` for `ch`; an empty string drops the character
+    pub fn substitute(mut self, ch: char, replacement: impl Into<String>) -> Self {
+        self.substitutions.insert(ch, replacement.into());
+        self
+    }
+
+    /// Handle characters with no encodable approximation according to `policy`
+    /// instead of failing
+    pub fn fallback(mut self, policy: ErrorPolicy) -> Self {
+        self.fallback = policy;
+        self
+    }
+
+    /// Policy for characters with no encodable approximation
+    pub fn fallback_policy(&self) -> &ErrorPolicy {
+        &self.fallback
+    }
+
+    /// Approximation for `ch`, if there is one
+    pub fn get(&self, ch: char) -> Option<&str> {
+        match self.substitutions.get(&ch) {
+            Some(replacement) => Some(replacement),
+            None if self.builtin => builtin(ch),
+            None => None,
+        }
+    }
+}
+
+impl Default for Transliterator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Approximation for `ch` from the built-in table
+pub fn builtin(ch: char) -> Option<&'static str> {
+    BUILTIN
+        .binary_search_by_key(&ch, |&(key, _)| key)
+        .ok()
+        .map(|index| BUILTIN[index].1)
+}
+
+/// Built-in approximations, sorted by character
+#[rustfmt::skip]
+static BUILTIN: &[(char, &str)] = &[
+    ('\u{00A0}', " "), ('¡', "!"), ('¢', "c"), ('£', "GBP"), ('¥', "JPY"), ('¦', "|"),
+    ('§', "S"), ('©', "(C)"), ('«', "<<"), ('¬', "!"), ('\u{00AD}', ""), ('®', "(R)"),
+    ('°', "deg"), ('±', "+/-"), ('²', "2"), ('³', "3"), ('µ', "u"), ('¶', "P"), ('·', "."),
+    ('¹', "1"), ('»', ">>"), ('¼', "1/4"), ('½', "1/2"), ('¾', "3/4"), ('¿', "?"), ('À', "A"),
+    ('Á', "A"), ('Â', "A"), ('Ã', "A"), ('Ä', "A"), ('Å', "A"), ('Æ', "AE"), ('Ç', "C"),
+    ('È', "E"), ('É', "E"), ('Ê', "E"), ('Ë', "E"), ('Ì', "I"), ('Í', "I"), ('Î', "I"),
+    ('Ï', "I"), ('Ð', "D"), ('Ñ', "N"), ('Ò', "O"), ('Ó', "O"), ('Ô', "O"), ('Õ', "O"),
+    ('Ö', "O"), ('×', "x"), ('Ø', "O"), ('Ù', "U"), ('Ú', "U"), ('Û', "U"), ('Ü', "U"),
+    ('Ý', "Y"), ('Þ', "TH"), ('ß', "ss"), ('à', "a"), ('á', "a"), ('â', "a"), ('ã', "a"),
+    ('ä', "a"), ('å', "a"), ('æ', "ae"), ('ç', "c"), ('è', "e"), ('é', "e"), ('ê', "e"),
+    ('ë', "e"), ('ì', "i"), ('í', "i"), ('î', "i"), ('ï', "i"), ('ð', "d"), ('ñ', "n"),
+    ('ò', "o"), ('ó', "o"), ('ô', "o"), ('õ', "o"), ('ö', "o"), ('÷', "/"), ('ø', "o"),
+    ('ù', "u"), ('ú', "u"), ('û', "u"), ('ü', "u"), ('ý', "y"), ('þ', "th"), ('ÿ', "y"),
+    ('Ā', "A"), ('ā', "a"), ('Ă', "A"), ('ă', "a"), ('Ą', "A"), ('ą', "a"), ('Ć', "C"),
+    ('ć', "c"), ('Ĉ', "C"), ('ĉ', "c"), ('Ċ', "C"), ('ċ', "c"), ('Č', "C"), ('č', "c"),
+    ('Ď', "D"), ('ď', "d"), ('Đ', "D"), ('đ', "d"), ('Ē', "E"), ('ē', "e"), ('Ĕ', "E"),
+    ('ĕ', "e"), ('Ė', "E"), ('ė', "e"), ('Ę', "E"), ('ę', "e"), ('Ě', "E"), ('ě', "e"),
+    ('Ĝ', "G"), ('ĝ', "g"), ('Ğ', "G"), ('ğ', "g"), ('Ġ', "G"), ('ġ', "g"), ('Ģ', "G"),
+    ('ģ', "g"), ('Ĥ', "H"), ('ĥ', "h"), ('Ħ', "H"), ('ħ', "h"), ('Ĩ', "I"), ('ĩ', "i"),
+    ('Ī', "I"), ('ī', "i"), ('Ĭ', "I"), ('ĭ', "i"), ('Į', "I"), ('į', "i"), ('İ', "I"),
+    ('ı', "i"), ('Ĳ', "IJ"), ('ĳ', "ij"), ('Ĵ', "J"), ('ĵ', "j"), ('Ķ', "K"), ('ķ', "k"),
+    ('ĸ', "q"), ('Ĺ', "L"), ('ĺ', "l"), ('Ļ', "L"), ('ļ', "l"), ('Ľ', "L"), ('ľ', "l"),
+    ('Ŀ', "L"), ('ŀ', "l"), ('Ł', "L"), ('ł', "l"), ('Ń', "N"), ('ń', "n"), ('Ņ', "N"),
+    ('ņ', "n"), ('Ň', "N"), ('ň', "n"), ('ŉ', "'n"), ('Ŋ', "N"), ('ŋ', "n"), ('Ō', "O"),
+    ('ō', "o"), ('Ŏ', "O"), ('ŏ', "o"), ('Ő', "O"), ('ő', "o"), ('Œ', "OE"), ('œ', "oe"),
+    ('Ŕ', "R"), ('ŕ', "r"), ('Ŗ', "R"), ('ŗ', "r"), ('Ř', "R"), ('ř', "r"), ('Ś', "S"),
+    ('ś', "s"), ('Ŝ', "S"), ('ŝ', "s"), ('Ş', "S"), ('ş', "s"), ('Š', "S"), ('š', "s"),
+    ('Ţ', "T"), ('ţ', "t"), ('Ť', "T"), ('ť', "t"), ('Ŧ', "T"), ('ŧ', "t"), ('Ũ', "U"),
+    ('ũ', "u"), ('Ū', "U"), ('ū', "u"), ('Ŭ', "U"), ('ŭ', "u"), ('Ů', "U"), ('ů', "u"),
+    ('Ű', "U"), ('ű', "u"), ('Ų', "U"), ('ų', "u"), ('Ŵ', "W"), ('ŵ', "w"), ('Ŷ', "Y"),
+    ('ŷ', "y"), ('Ÿ', "Y"), ('Ź', "Z"), ('ź', "z"), ('Ż', "Z"), ('ż', "z"), ('Ž', "Z"),
+    ('ž', "z"), ('ſ', "s"), ('ƒ', "f"), ('Ơ', "O"), ('ơ', "o"), ('Ư', "U"), ('ư', "u"),
+    ('Ǎ', "A"), ('ǎ', "a"), ('Ǐ', "I"), ('ǐ', "i"), ('Ǒ', "O"), ('ǒ', "o"), ('Ǔ', "U"),
+    ('ǔ', "u"), ('Ǖ', "U"), ('ǖ', "u"), ('Ǘ', "U"), ('ǘ', "u"), ('Ǚ', "U"), ('ǚ', "u"),
+    ('Ǜ', "U"), ('ǜ', "u"), ('Ǟ', "A"), ('ǟ', "a"), ('Ǡ', "A"), ('ǡ', "a"), ('Ǧ', "G"),
+    ('ǧ', "g"), ('Ǩ', "K"), ('ǩ', "k"), ('Ǫ', "O"), ('ǫ', "o"), ('Ǭ', "O"), ('ǭ', "o"),
+    ('ǰ', "j"), ('Ǵ', "G"), ('ǵ', "g"), ('Ǹ', "N"), ('ǹ', "n"), ('Ǻ', "A"), ('ǻ', "a"),
+    ('Ȁ', "A"), ('ȁ', "a"), ('Ȃ', "A"), ('ȃ', "a"), ('Ȅ', "E"), ('ȅ', "e"), ('Ȇ', "E"),
+    ('ȇ', "e"), ('Ȉ', "I"), ('ȉ', "i"), ('Ȋ', "I"), ('ȋ', "i"), ('Ȍ', "O"), ('ȍ', "o"),
+    ('Ȏ', "O"), ('ȏ', "o"), ('Ȑ', "R"), ('ȑ', "r"), ('Ȓ', "R"), ('ȓ', "r"), ('Ȕ', "U"),
+    ('ȕ', "u"), ('Ȗ', "U"), ('ȗ', "u"), ('Ș', "S"), ('ș', "s"), ('Ț', "T"), ('ț', "t"),
+    ('Ȟ', "H"), ('ȟ', "h"), ('Ȧ', "A"), ('ȧ', "a"), ('Ȩ', "E"), ('ȩ', "e"), ('Ȫ', "O"),
+    ('ȫ', "o"), ('Ȭ', "O"), ('ȭ', "o"), ('Ȯ', "O"), ('ȯ', "o"), ('Ȱ', "O"), ('ȱ', "o"),
+    ('Ȳ', "Y"), ('ȳ', "y"), ('ẞ', "SS"), ('\u{2002}', " "), ('\u{2003}', " "),
+    ('\u{2009}', " "), ('\u{200A}', " "), ('\u{200B}', ""), ('‐', "-"), ('‑', "-"), ('‒', "-"),
+    ('–', "-"), ('—', "--"), ('―', "--"), ('‘', "'"), ('’', "'"), ('‚', "'"), ('‛', "'"),
+    ('“', "\""), ('”', "\""), ('„', "\""), ('‟', "\""), ('•', "*"), ('‣', ">"), ('…', "..."),
+    ('\u{202F}', " "), ('′', "'"), ('″', "\""), ('‹', "<"), ('›', ">"), ('⁄', "/"),
+    ('\u{2060}', ""), ('₩', "KRW"), ('€', "EUR"), ('₹', "INR"), ('₽', "RUB"), ('™', "TM"),
+    ('←', "<-"), ('→', "->"), ('⇒', "=>"), ('−', "-"), ('≠', "!="), ('≤', "<="), ('≥', ">="),
+    ('ﬀ', "ff"), ('ﬁ', "fi"), ('ﬂ', "fl"), ('ﬃ', "ffi"), ('ﬄ', "ffl"), ('ﬅ', "st"), ('ﬆ', "st"),
+    ('\u{FEFF}', ""),
+];
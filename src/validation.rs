@@ -0,0 +1,467 @@
+//! Checking data for validity in an expected encoding
+//!
+//! [`Validator`] finds every invalid byte sequence in a buffer, or in a stream
+//! fed chunk by chunk, and reports where each one is, what it contains and
+//! which encodings the data more likely is:
+//!
+//! ```
+//! use fast_encode::Encoding;
+//! use fast_encode::validation::Validator;
+//!
+//! let report = Validator::new(Encoding::UTF8).validate(b"caf\xE9 cr\xE8me");
+//! assert!(!report.is_valid());
+//! assert_eq!(report.invalid[0].offset, 3);
+//! assert_eq!(report.invalid[0].bytes, [0xE9]);
+//! ```
+//!
+//! With a [`target`](Validator::target), or from [`Translator::validate`], it
+//! also reports every character the target encoding cannot represent, so one
+//! pass lists everything a conversion would fail on.
+//! [`Translator::compatibility_report`] answers the same question for every
+//! character of the source encoding, without any data.
+
+use std::fmt;
+
+use crate::detection::EncodingDetector;
+use crate::policy::{self, CharEncoder, Encoder, Unit};
+use crate::{Encoding, Translator, cjk, multibyte, tables};
+
+/// Bytes kept from the start of the input for suggesting other encodings
+const SAMPLE_SIZE: usize = 8192;
+
+/// Chunk size used to validate a whole buffer
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Number of alternative encodings suggested for invalid input
+const MAX_SUGGESTIONS: usize = 3;
+
+/// An invalid byte sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidSequence {
+    /// Offset of the sequence from the start of the input
+    pub offset: usize,
+    /// The invalid bytes
+    pub bytes: Vec<u8>,
+    /// The character the target encoding cannot represent (`None` for bytes
+    /// that do not decode)
+    pub character: Option<char>,
+    /// Why the bytes are invalid
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidSequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.reason, self.offset)
+    }
+}
+
+/// Outcome of validating input against an encoding
+#[derive(Debug, Clone)]
+pub struct ValidationReport {
+    /// Encoding the input was checked against
+    pub encoding: Encoding,
+    /// Encoding every character was checked to be representable in, if any
+    pub target: Option<Encoding>,
+    /// Number of bytes checked
+    pub bytes_checked: usize,
+    /// Invalid sequences, in input order
+    pub invalid: Vec<InvalidSequence>,
+    /// Whether checking stopped at the error limit, so `invalid` is incomplete
+    pub truncated: bool,
+    /// Encodings the input decodes in without errors, most likely first, with
+    /// the detector's confidence (empty unless some bytes do not decode)
+    pub suggestions: Vec<(Encoding, f64)>,
+}
+
+impl ValidationReport {
+    /// Whether the input is valid in the encoding
+    pub fn is_valid(&self) -> bool {
+        self.invalid.is_empty()
+    }
+}
+
+/// Characters of one encoding that do not survive conversion to another
+///
+/// Built by [`Translator::compatibility_report`] from the encoding tables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompatibilityReport {
+    /// Source encoding
+    pub from: Encoding,
+    /// Target encoding
+    pub to: Encoding,
+    /// Source bytes whose character the target cannot represent
+    pub unmappable: Vec<(u8, char)>,
+    /// Source bytes that convert, but convert back to something else
+    pub lossy: Vec<(u8, char)>,
+    /// Source bytes with no character assigned in a single-byte source
+    pub undefined: Vec<u8>,
+    /// Whether every source character was checked; for multi-byte sources only
+    /// the characters encoded in a single byte are
+    pub exhaustive: bool,
+}
+
+impl CompatibilityReport {
+    /// Check every single-byte character of `from` against `to`
+    pub(crate) fn new(from: Encoding, to: Encoding) -> Self {
+        let forward = Translator::new(from, to).ok();
+        let backward = Translator::new(to, from).ok();
+        let mut report = CompatibilityReport {
+            from,
+            to,
+            unmappable: Vec::new(),
+            lossy: Vec::new(),
+            undefined: Vec::new(),
+            exhaustive: !from.is_multibyte(),
+        };
+
+        for byte in 0..=u8::MAX {
+            let Some(ch) = from.decode_byte(byte) else {
+                if report.exhaustive {
+                    report.undefined.push(byte);
+                }
+                continue;
+            };
+
+            let converted = forward
+                .as_ref()
+                .and_then(|forward| forward.convert_unrecorded(&[byte]).ok());
+            let Some(converted) = converted else {
+                report.unmappable.push((byte, ch));
+                continue;
+            };
+
+            let restored = backward
+                .as_ref()
+                .and_then(|backward| backward.convert_unrecorded(&converted).ok());
+            if restored.as_deref() != Some(&[byte]) {
+                report.lossy.push((byte, ch));
+            }
+        }
+
+        report
+    }
+
+    /// Whether every checked character round-trips unchanged
+    pub fn is_lossless(&self) -> bool {
+        self.unmappable.is_empty() && self.lossy.is_empty()
+    }
+}
+
+/// Checks input for validity in one encoding
+///
+/// A whole buffer is checked with [`validate`](Self::validate); a stream is
+/// fed to [`update`](Self::update) chunk by chunk, split at arbitrary byte
+/// boundaries, and [`finish`](Self::finish) produces the report.
+#[derive(Debug, Clone)]
+pub struct Validator {
+    encoding: Encoding,
+    target: Option<Encoding>,
+    /// Encoder for a target that cannot represent every character
+    encoder: Option<Encoder>,
+    max_errors: usize,
+    /// Trailing bytes of an incomplete sequence carried over from the previous chunk
+    pending: Vec<u8>,
+    /// Number of input bytes checked so far, not counting `pending`
+    checked: usize,
+    invalid: Vec<InvalidSequence>,
+    /// Start of the input, for suggesting other encodings
+    sample: Vec<u8>,
+}
+
+impl Validator {
+    /// Create a validator for `encoding` that reports every invalid sequence
+    pub fn new(encoding: Encoding) -> Self {
+        Self {
+            encoding,
+            target: None,
+            encoder: None,
+            max_errors: usize::MAX,
+            pending: Vec::new(),
+            checked: 0,
+            invalid: Vec::new(),
+            sample: Vec::new(),
+        }
+    }
+
+    /// Stop checking after `max_errors` invalid sequences
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    /// Also report characters that `target` cannot represent
+    pub fn target(mut self, target: Encoding) -> Self {
+        self.target = Some(target);
+        self.encoder = match target {
+            // Every decoded character has a Unicode encoding
+            Encoding::UTF8
+            | Encoding::UTF16LE
+            | Encoding::UTF16BE
+            | Encoding::UTF32LE
+            | Encoding::UTF32BE => None,
+            target => Some(Encoder::new(target)),
+        };
+        self
+    }
+
+    /// Encoding input is checked against
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Check a complete buffer
+    pub fn validate(&self, data: &[u8]) -> ValidationReport {
+        let mut validator = Validator {
+            pending: Vec::new(),
+            checked: 0,
+            invalid: Vec::new(),
+            sample: Vec::new(),
+            ..self.clone()
+        };
+        for chunk in data.chunks(CHUNK_SIZE) {
+            validator.update(chunk);
+        }
+        validator.finish()
+    }
+
+    /// Check the next chunk of a stream
+    pub fn update(&mut self, chunk: &[u8]) {
+        if self.sample.len() < SAMPLE_SIZE {
+            let wanted = (SAMPLE_SIZE - self.sample.len()).min(chunk.len());
+            self.sample.extend_from_slice(&chunk[..wanted]);
+        }
+        if self.is_full() {
+            return;
+        }
+
+        let mut data = std::mem::take(&mut self.pending);
+        data.extend_from_slice(chunk);
+        let split = data.len() - multibyte::incomplete_suffix_len(self.encoding, &data);
+        self.scan(&data[..split]);
+        self.checked += split;
+        self.pending.extend_from_slice(&data[split..]);
+    }
+
+    /// Finish the stream and report
+    ///
+    /// Input that ends in the middle of a multi-byte sequence is reported as
+    /// truncated.
+    pub fn finish(mut self) -> ValidationReport {
+        if !self.is_full() {
+            let pending = std::mem::take(&mut self.pending);
+            self.scan(&pending);
+            self.checked += pending.len();
+        }
+
+        let suggestions = if self.invalid.iter().all(|e| e.character.is_some()) {
+            Vec::new()
+        } else {
+            suggest(self.encoding, &self.sample)
+        };
+
+        ValidationReport {
+            encoding: self.encoding,
+            target: self.target,
+            bytes_checked: self.checked,
+            truncated: self.is_full(),
+            invalid: self.invalid,
+            suggestions,
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.invalid.len() >= self.max_errors
+    }
+
+    fn report(&mut self, offset: usize, bytes: &[u8], reason: String) {
+        if !self.is_full() {
+            self.invalid.push(InvalidSequence {
+                offset: self.checked + offset,
+                bytes: bytes.to_vec(),
+                character: None,
+                reason,
+            });
+        }
+    }
+
+    /// Record the errors in `data`, which starts at `self.checked`, in input order
+    fn scan(&mut self, data: &[u8]) {
+        let start = self.invalid.len();
+        self.scan_source(data);
+
+        let Some(ref mut encoder) = self.encoder else {
+            return;
+        };
+        let target = self.target.map_or("", |target| target.name());
+        let mut unencodable = Vec::new();
+        let mut scratch = Vec::new();
+        let units = policy::decode(self.encoding, data);
+        for (index, &(offset, ref unit)) in units.iter().enumerate() {
+            let &Unit::Char(ch) = unit else { continue };
+            scratch.clear();
+            if encoder.encode(ch, &mut scratch) {
+                continue;
+            }
+
+            let end = units.get(index + 1).map_or(data.len(), |&(next, _)| next);
+            unencodable.push(InvalidSequence {
+                offset: self.checked + offset,
+                bytes: data[offset..end].to_vec(),
+                character: Some(ch),
+                reason: format!(
+                    "U+{:04X} {:?} cannot be encoded in {}",
+                    ch as u32, ch, target
+                ),
+            });
+        }
+        if unencodable.is_empty() {
+            return;
+        }
+
+        // Both lists are in input order and the source list stopped at the limit
+        let source = self.invalid.split_off(start);
+        let mut merged = Vec::with_capacity(source.len() + unencodable.len());
+        let (mut source, mut unencodable) = (
+            source.into_iter().peekable(),
+            unencodable.into_iter().peekable(),
+        );
+        while let (Some(a), Some(b)) = (source.peek(), unencodable.peek()) {
+            merged.push(
+                if a.offset <= b.offset {
+                    source.next()
+                } else {
+                    unencodable.next()
+                }
+                .unwrap(),
+            );
+        }
+        merged.extend(source.chain(unencodable));
+        merged.truncate(self.max_errors - start);
+        self.invalid.extend(merged);
+    }
+
+    /// Record the bytes in `data` that do not decode
+    fn scan_source(&mut self, data: &[u8]) {
+        match self.encoding {
+            Encoding::UTF8 => {
+                let mut offset = 0;
+                for chunk in data.utf8_chunks() {
+                    offset += chunk.valid().len();
+                    let invalid = chunk.invalid();
+                    if invalid.is_empty() {
+                        continue;
+                    }
+
+                    // Only the final chunk can cut a sequence off
+                    let reason = if offset + invalid.len() == data.len()
+                        && multibyte::incomplete_suffix_len(Encoding::UTF8, invalid)
+                            == invalid.len()
+                    {
+                        "truncated UTF-8 sequence"
+                    } else {
+                        "invalid UTF-8 sequence"
+                    };
+                    self.report(offset, invalid, reason.to_string());
+                    offset += invalid.len();
+                }
+            }
+            encoding @ (Encoding::UTF16LE | Encoding::UTF16BE) => {
+                let unit_at = |i: usize| match encoding {
+                    Encoding::UTF16LE => u16::from_le_bytes([data[i], data[i + 1]]),
+                    _ => u16::from_be_bytes([data[i], data[i + 1]]),
+                };
+
+                let mut offset = 0;
+                while offset + 1 < data.len() && !self.is_full() {
+                    let unit = unit_at(offset);
+                    let paired = (0xD800..0xDC00).contains(&unit)
+                        && offset + 3 < data.len()
+                        && (0xDC00..0xE000).contains(&unit_at(offset + 2));
+
+                    if paired {
+                        offset += 4;
+                        continue;
+                    }
+                    if (0xD800..0xE000).contains(&unit) {
+                        let reason = format!("unpaired surrogate 0x{:04X}", unit);
+                        self.report(offset, &data[offset..offset + 2], reason);
+                    }
+                    offset += 2;
+                }
+
+                if data.len() % 2 == 1 {
+                    let last = data.len() - 1;
+                    let reason = "truncated UTF-16 code unit".to_string();
+                    self.report(last, &data[last..], reason);
+                }
+            }
+            encoding @ (Encoding::UTF32LE | Encoding::UTF32BE) => {
+                let little_endian = encoding == Encoding::UTF32LE;
+                let mut offset = 0;
+                while offset + 3 < data.len() && !self.is_full() {
+                    let value = multibyte::read_unit(&data[offset..], 4, little_endian);
+                    if char::from_u32(value).is_none() {
+                        let reason = if (0xD800..0xE000).contains(&value) {
+                            format!("unpaired surrogate 0x{:04X}", value)
+                        } else {
+                            format!("code point 0x{:X} out of range", value)
+                        };
+                        self.report(offset, &data[offset..offset + 4], reason);
+                    }
+                    offset += 4;
+                }
+
+                if offset < data.len() {
+                    let reason = "truncated UTF-32 code unit".to_string();
+                    self.report(offset, &data[offset..], reason);
+                }
+            }
+            encoding if cjk::is_double_byte(encoding) => {
+                let mut offset = 0;
+                while offset < data.len() && !self.is_full() {
+                    let (length, reason) = match cjk::decode_step(encoding, &data[offset..]) {
+                        cjk::Step::Char(_, length) => {
+                            offset += length;
+                            continue;
+                        }
+                        cjk::Step::Invalid(length) => (length, "invalid"),
+                        cjk::Step::Incomplete => (data.len() - offset, "truncated"),
+                    };
+                    let reason = format!("{} {} sequence", reason, encoding.name());
+                    self.report(offset, &data[offset..offset + length], reason);
+                    offset += length;
+                }
+            }
+            encoding => {
+                let chars = tables::get_encoding_chars(encoding);
+                for (offset, &byte) in data.iter().enumerate() {
+                    if chars[byte as usize].is_none() {
+                        let reason = format!("unmappable byte 0x{:02X}", byte);
+                        self.report(offset, &data[offset..offset + 1], reason);
+                        if self.is_full() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Encodings other than `expected` that `sample` decodes in without errors
+fn suggest(expected: Encoding, sample: &[u8]) -> Vec<(Encoding, f64)> {
+    EncodingDetector::new()
+        .detect(sample)
+        .candidates
+        .into_iter()
+        .filter(|&(candidate, _)| candidate != expected)
+        .filter(|&(candidate, _)| {
+            // The sample may end in the middle of a character
+            let complete = sample.len() - multibyte::incomplete_suffix_len(candidate, sample);
+            Translator::new(candidate, Encoding::UTF8)
+                .and_then(|translator| translator.convert(&sample[..complete]))
+                .is_ok()
+        })
+        .take(MAX_SUGGESTIONS)
+        .collect()
+}
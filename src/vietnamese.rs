@@ -0,0 +1,73 @@
+//! Tone-mark composition for Windows-1258
+//!
+//! Windows-1258 has precomposed forms for only some Vietnamese letters; the
+//! rest are written as a base letter followed by one of five combining tone
+//! marks. Decoding composes such pairs into the precomposed characters most
+//! software expects, and encoding splits a precomposed letter the target lacks
+//! back into base letter and tone mark, so both forms round-trip.
+
+use crate::Encoding;
+
+/// The combining tone marks, in the column order of [`COMPOSITIONS`]
+const TONE_MARKS: [char; 5] = [
+    '\u{0300}', // grave
+    '\u{0301}', // acute
+    '\u{0303}', // tilde
+    '\u{0309}', // hook above
+    '\u{0323}', // dot below
+];
+
+/// Each Vietnamese vowel with every tone mark applied
+const COMPOSITIONS: [(char, [char; 5]); 24] = [
+    ('A', ['À', 'Á', 'Ã', 'Ả', 'Ạ']),
+    ('a', ['à', 'á', 'ã', 'ả', 'ạ']),
+    ('Ă', ['Ằ', 'Ắ', 'Ẵ', 'Ẳ', 'Ặ']),
+    ('ă', ['ằ', 'ắ', 'ẵ', 'ẳ', 'ặ']),
+    ('Â', ['Ầ', 'Ấ', 'Ẫ', 'Ẩ', 'Ậ']),
+    ('â', ['ầ', 'ấ', 'ẫ', 'ẩ', 'ậ']),
+    ('E', ['È', 'É', 'Ẽ', 'Ẻ', 'Ẹ']),
+    ('e', ['è', 'é', 'ẽ', 'ẻ', 'ẹ']),
+    ('Ê', ['Ề', 'Ế', 'Ễ', 'Ể', 'Ệ']),
+    ('ê', ['ề', 'ế', 'ễ', 'ể', 'ệ']),
+    ('I', ['Ì', 'Í', 'Ĩ', 'Ỉ', 'Ị']),
+    ('i', ['ì', 'í', 'ĩ', 'ỉ', 'ị']),
+    ('O', ['Ò', 'Ó', 'Õ', 'Ỏ', 'Ọ']),
+    ('o', ['ò', 'ó', 'õ', 'ỏ', 'ọ']),
+    ('Ô', ['Ồ', 'Ố', 'Ỗ', 'Ổ', 'Ộ']),
+    ('ô', ['ồ', 'ố', 'ỗ', 'ổ', 'ộ']),
+    ('Ơ', ['Ờ', 'Ớ', 'Ỡ', 'Ở', 'Ợ']),
+    ('ơ', ['ờ', 'ớ', 'ỡ', 'ở', 'ợ']),
+    ('U', ['Ù', 'Ú', 'Ũ', 'Ủ', 'Ụ']),
+    ('u', ['ù', 'ú', 'ũ', 'ủ', 'ụ']),
+    ('Ư', ['Ừ', 'Ứ', 'Ữ', 'Ử', 'Ự']),
+    ('ư', ['ừ', 'ứ', 'ữ', 'ử', 'ự']),
+    ('Y', ['Ỳ', 'Ý', 'Ỹ', 'Ỷ', 'Ỵ']),
+    ('y', ['ỳ', 'ý', 'ỹ', 'ỷ', 'ỵ']),
+];
+
+/// Whether decoding `encoding` composes tone marks with the preceding letter
+pub(crate) fn composes(encoding: Encoding) -> bool {
+    encoding == Encoding::WINDOWS_1258
+}
+
+/// The precomposed form of `base` followed by the tone mark `mark`
+pub(crate) fn compose(base: char, mark: char) -> Option<char> {
+    let column = TONE_MARKS.iter().position(|&m| m == mark)?;
+    COMPOSITIONS
+        .iter()
+        .find(|(vowel, _)| *vowel == base)
+        .map(|(_, composed)| composed[column])
+}
+
+/// Whether a tone mark may still follow `base` and compose with it
+pub(crate) fn is_base(base: char) -> bool {
+    COMPOSITIONS.iter().any(|(vowel, _)| *vowel == base)
+}
+
+/// Split a precomposed Vietnamese letter into base letter and tone mark
+pub(crate) fn decompose(ch: char) -> Option<(char, char)> {
+    COMPOSITIONS.iter().find_map(|(vowel, composed)| {
+        let column = composed.iter().position(|&c| c == ch)?;
+        Some((*vowel, TONE_MARKS[column]))
+    })
+}
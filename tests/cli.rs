@@ -0,0 +1,1166 @@
+//! End-to-end tests of the `fast-encode` command line
+#![cfg(feature = "cli")]
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// Scratch directory removed when dropped
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("fast-encode-cli-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Write `contents` to `relative`, creating parent directories
+    fn write(&self, relative: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.0.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Run the binary with `args` in `dir`
+fn fast_encode(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_fast-encode"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Run the binary with whitespace-separated `args` in `dir`
+fn run(dir: &Path, args: &str) -> Output {
+    fast_encode(dir, &args.split_whitespace().collect::<Vec<_>>())
+}
+
+/// Run the binary with whitespace-separated `args` in `dir`, feeding `input` on stdin
+fn run_with_stdin(dir: &Path, args: &str, input: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_fast-encode"))
+        .current_dir(dir)
+        .args(args.split_whitespace())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn directory_include_and_exclude_globs() {
+    let dir = TempDir::new("globs");
+    dir.write("src/a.txt", b"caf\xE9");
+    dir.write("src/b.log", b"log");
+    dir.write("src/sub/c.txt", b"na\xEFve");
+    dir.write("src/sub/skip.txt", b"skip");
+
+    let output = run(
+        dir.path(),
+        "convert -f latin1 -t utf-8 -i src -r --out-dir out --include *.txt --exclude sub/skip.txt",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let out = dir.path().join("out");
+    assert_eq!(fs::read_to_string(out.join("a.txt")).unwrap(), "café");
+    assert_eq!(fs::read_to_string(out.join("sub/c.txt")).unwrap(), "naïve");
+    assert!(!out.join("b.log").exists());
+    assert!(!out.join("sub/skip.txt").exists());
+
+    // A pattern without '/' matches the file name at any depth
+    let output = run(
+        dir.path(),
+        "convert -f latin1 -t utf-8 -i src -r --out-dir out2 --exclude *.txt",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(dir.path().join("out2/b.log").exists());
+    assert!(!dir.path().join("out2/sub").exists());
+
+    // Globs only apply to directory conversions
+    let output = run(
+        dir.path(),
+        "convert -f latin1 -t utf-8 -i src/a.txt --include *.txt",
+    );
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn directory_suffix_writes_next_to_sources() {
+    let dir = TempDir::new("suffix");
+    dir.write("src/a.txt", b"caf\xE9");
+    dir.write("src/sub/b.txt", b"\xE9t\xE9");
+
+    let args = "convert -f latin1 -t utf-8 -i src -r --suffix .utf8";
+    let output = run(dir.path(), args);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("src/a.txt.utf8")).unwrap(),
+        "café"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("src/sub/b.txt.utf8")).unwrap(),
+        "été"
+    );
+    assert_eq!(fs::read(dir.path().join("src/a.txt")).unwrap(), b"caf\xE9");
+
+    // A second run leaves the outputs of the first one alone
+    let output = run(dir.path(), args);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(
+        stdout(&output).contains("Converted 2 files"),
+        "{}",
+        stdout(&output)
+    );
+    assert!(!dir.path().join("src/a.txt.utf8.utf8").exists());
+
+    let output = fast_encode(
+        dir.path(),
+        &[
+            "convert", "-f", "latin1", "-t", "utf-8", "-i", "src", "-r", "--suffix", "",
+        ],
+    );
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("--suffix must not be empty"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn directory_dry_run_writes_nothing() {
+    let dir = TempDir::new("dry-run");
+    dir.write("src/ok.txt", "plain");
+    dir.write("src/bad.txt", "café");
+
+    let output = run(
+        dir.path(),
+        "convert -f utf-8 -t ascii -i src -r --out-dir out --dry-run",
+    );
+    // The unmappable file is reported as a failure, but nothing is written either way
+    assert_eq!(output.status.code(), Some(1));
+    let report = stdout(&output);
+    assert!(
+        report.contains("Dry run over 2 files: 1 would be converted"),
+        "{}",
+        report
+    );
+    assert!(report.contains("✗"), "{}", report);
+    assert!(!dir.path().join("out").exists());
+
+    let output = run(
+        dir.path(),
+        "convert -f utf-8 -t ascii -i src/ok.txt -r --suffix .a --dry-run --include *.txt",
+    );
+    assert!(!output.status.success());
+    assert!(!dir.path().join("src/ok.txt.a").exists());
+
+    let output = run(
+        dir.path(),
+        "convert -f utf-8 -t ascii -i src -r --suffix .a --dry-run",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!dir.path().join("src/ok.txt.a").exists());
+}
+
+#[test]
+fn failed_in_place_conversion_leaves_file_untouched() {
+    let dir = TempDir::new("in-place");
+    let path = dir.write("data.txt", "café");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+    }
+
+    let output = run(
+        dir.path(),
+        "convert -f utf-8 -t ascii -i data.txt --in-place",
+    );
+    assert_eq!(output.status.code(), Some(3), "{}", stderr(&output));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "café");
+    assert_eq!(
+        fs::read_dir(dir.path()).unwrap().count(),
+        1,
+        "temporary file left behind"
+    );
+
+    let output = run(
+        dir.path(),
+        "convert -f utf-8 -t latin1 -i data.txt --in-place",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fs::read(&path).unwrap(), b"caf\xE9");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
+
+#[test]
+fn out_dir_mirrors_input_tree() {
+    let dir = TempDir::new("out-dir");
+    dir.write("src/top.txt", b"caf\xE9");
+    dir.write("src/a/b/deep.txt", b"\xE9t\xE9");
+
+    let output = run(
+        dir.path(),
+        "convert -f latin1 -t utf-8 -i src -r --out-dir out",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let out = dir.path().join("out");
+    assert_eq!(fs::read_to_string(out.join("top.txt")).unwrap(), "café");
+    assert_eq!(fs::read_to_string(out.join("a/b/deep.txt")).unwrap(), "été");
+    // The sources are left as they were
+    assert_eq!(
+        fs::read(dir.path().join("src/top.txt")).unwrap(),
+        b"caf\xE9"
+    );
+
+    // An output directory inside the input tree is not converted again
+    let output = run(
+        dir.path(),
+        "convert -f latin1 -t utf-8 -i src -r --out-dir src/converted",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = run(
+        dir.path(),
+        "convert -f latin1 -t utf-8 -i src -r --out-dir src/converted",
+    );
+    assert!(
+        stdout(&output).contains("Converted 2 files"),
+        "{}",
+        stdout(&output)
+    );
+    assert!(!dir.path().join("src/converted/converted").exists());
+
+    let output = run(
+        dir.path(),
+        "convert -f latin1 -t utf-8 -i src/top.txt --out-dir out",
+    );
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("--out-dir requires a directory input"),
+        "{}",
+        stderr(&output)
+    );
+    let output = run(dir.path(), "convert -f latin1 -t utf-8 -i src -r");
+    assert!(
+        stderr(&output).contains("requires --out-dir, --suffix or --in-place"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn skip_if_target_leaves_converted_files_alone() {
+    let dir = TempDir::new("skip-if-target");
+    dir.write("tree/done.txt", "déjà converti");
+    dir.write("tree/todo.txt", b"pas encore converti \xE0 la main");
+
+    let args = "convert -f latin1 -t utf-8 -i tree -r --in-place --skip-if-target";
+    let output = run(dir.path(), args);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let report = stdout(&output);
+    assert!(
+        report.contains("done.txt (already UTF-8, skipped)"),
+        "{}",
+        report
+    );
+    assert!(report.contains("1 succeeded, 1 skipped"), "{}", report);
+    assert_eq!(
+        fs::read_to_string(dir.path().join("tree/done.txt")).unwrap(),
+        "déjà converti"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("tree/todo.txt")).unwrap(),
+        "pas encore converti à la main"
+    );
+
+    // Running again is a no-op rather than a second, mangling conversion
+    let output = run(dir.path(), args);
+    assert!(
+        stdout(&output).contains("0 succeeded, 2 skipped"),
+        "{}",
+        stdout(&output)
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("tree/todo.txt")).unwrap(),
+        "pas encore converti à la main"
+    );
+
+    let output = run(
+        dir.path(),
+        "convert -f latin1 -t utf-8 -i tree/done.txt --skip-if-target",
+    );
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("--skip-if-target requires --in-place"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn verify_refuses_lossy_in_place_conversion() {
+    let dir = TempDir::new("verify");
+    let path = dir.write("data.txt", "café");
+
+    let output = run(
+        dir.path(),
+        "convert -f utf-8 -t ascii --encoding-errors replace -i data.txt --in-place --verify",
+    );
+    assert_eq!(output.status.code(), Some(5), "{}", stderr(&output));
+    assert!(
+        stderr(&output).contains("1 lossy position(s) at byte offset(s) 3; input left unchanged"),
+        "{}",
+        stderr(&output)
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), "café");
+
+    // Written to another file, the loss is only a warning unless --strict
+    let output = run(
+        dir.path(),
+        "convert -f utf-8 -t ascii --encoding-errors replace -i data.txt -o out.txt --verify",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(
+        stderr(&output).starts_with("warning: round trip"),
+        "{}",
+        stderr(&output)
+    );
+    assert_eq!(fs::read(dir.path().join("out.txt")).unwrap(), b"caf?");
+    let output = run(
+        dir.path(),
+        "--strict convert -f utf-8 -t ascii --encoding-errors replace -i data.txt -o out.txt --verify",
+    );
+    assert_eq!(output.status.code(), Some(5));
+
+    let output = run(
+        dir.path(),
+        "convert -f utf-8 -t latin1 -i data.txt --in-place --verify",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fs::read(&path).unwrap(), b"caf\xE9");
+
+    // Large inputs are checked chunk by chunk, with offsets relative to the whole file
+    let mut large = "é".repeat(3000);
+    large.push('€');
+    large.push_str(&"a".repeat(3000));
+    let large_path = dir.write("large.txt", &large);
+    let output = run(
+        dir.path(),
+        "convert -f utf-8 -t latin1 --encoding-errors replace -i large.txt --in-place --verify --buffer-size 1",
+    );
+    assert_eq!(output.status.code(), Some(5), "{}", stderr(&output));
+    assert!(
+        stderr(&output).contains("1 lossy position(s) at byte offset(s) 6000;"),
+        "{}",
+        stderr(&output)
+    );
+    assert_eq!(fs::read_to_string(&large_path).unwrap(), large);
+
+    // In a directory run the lossy file fails and is left as it was
+    dir.write("tree/ok.txt", "café");
+    dir.write("tree/lossy.txt", "5 €");
+    let output = run(
+        dir.path(),
+        "convert -f utf-8 -t latin1 --encoding-errors replace -i tree -r --in-place --verify",
+    );
+    assert_eq!(output.status.code(), Some(1), "{}", stdout(&output));
+    assert!(
+        stdout(&output).contains("file left unchanged"),
+        "{}",
+        stdout(&output)
+    );
+    assert_eq!(
+        fs::read(dir.path().join("tree/ok.txt")).unwrap(),
+        b"caf\xE9"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("tree/lossy.txt")).unwrap(),
+        "5 €"
+    );
+}
+
+#[test]
+fn files_from_stdin_list() {
+    let dir = TempDir::new("files-from");
+    dir.write("a.txt", b"caf\xE9");
+    dir.write("with space.txt", b"\xE9t\xE9");
+    dir.write("line\nbreak.txt", b"na\xEFve");
+    dir.write("unlisted.txt", b"\xE9");
+
+    // Newline-delimited, with CRLF line endings tolerated
+    let output = run_with_stdin(
+        dir.path(),
+        "convert -f latin1 -t utf-8 --files-from - --out-dir out",
+        b"a.txt\r\nwith space.txt\n\n",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let out = dir.path().join("out");
+    assert_eq!(fs::read_to_string(out.join("a.txt")).unwrap(), "café");
+    assert_eq!(
+        fs::read_to_string(out.join("with space.txt")).unwrap(),
+        "été"
+    );
+    assert!(!out.join("unlisted.txt").exists());
+
+    // NUL-delimited, as written by `find -print0`, allows newlines in names
+    let output = run_with_stdin(
+        dir.path(),
+        "convert -f latin1 -t utf-8 -@ - -0 --suffix .utf8",
+        b"line\nbreak.txt\0a.txt\0",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("line\nbreak.txt.utf8")).unwrap(),
+        "naïve"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("a.txt.utf8")).unwrap(),
+        "café"
+    );
+
+    // A listed file that does not exist fails that entry only
+    let output = run_with_stdin(
+        dir.path(),
+        "convert -f latin1 -t utf-8 --files-from - --out-dir out3",
+        b"missing.txt\na.txt\n",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stdout(&output).contains("1 succeeded, 0 skipped, 1 failed"),
+        "{}",
+        stdout(&output)
+    );
+
+    let output = run(
+        dir.path(),
+        "convert -f latin1 -t utf-8 --files-from nope.lst --out-dir out",
+    );
+    assert_eq!(output.status.code(), Some(6), "{}", stderr(&output));
+    assert!(
+        stderr(&output).contains("Failed to read file list"),
+        "{}",
+        stderr(&output)
+    );
+    let output = run(dir.path(), "convert -f latin1 -t utf-8 -i a.txt -0");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stderr(&output).contains("requires --files-from"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn audit_lists_files_not_in_expected_encoding() {
+    let dir = TempDir::new("audit");
+    dir.write("tree/good.txt", "déjà vu");
+    dir.write("tree/plain.txt", "ascii only");
+    dir.write("tree/sub/bad.txt", b"caf\xE9 cr\xE8me");
+
+    let output = run(dir.path(), "--format json audit tree --expect utf-8");
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["expected"], "UTF-8");
+    assert_eq!(report["scanned"], 3);
+    assert_eq!(report["flagged"], 1);
+    let finding = &report["files"][0];
+    assert_eq!(
+        finding["path"],
+        format!("sub{}bad.txt", std::path::MAIN_SEPARATOR)
+    );
+    assert_eq!(finding["first_bad_offset"], 3);
+
+    let output = run(dir.path(), "audit tree --expect utf-8");
+    assert!(
+        stdout(&output).contains("1 of 3 files are not UTF-8"),
+        "{}",
+        stdout(&output)
+    );
+
+    fs::remove_file(dir.path().join("tree/sub/bad.txt")).unwrap();
+    let output = run(dir.path(), "audit tree --expect utf-8");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert!(
+        stdout(&output).contains("0 of 2 files are not UTF-8"),
+        "{}",
+        stdout(&output)
+    );
+
+    let output = run(dir.path(), "audit missing --expect utf-8");
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("missing"), "{}", stderr(&output));
+    let output = run(dir.path(), "audit tree --expect no-such-encoding");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn batch_runs_manifest_jobs() {
+    let dir = TempDir::new("batch");
+    dir.write("in/a.txt", b"caf\xE9");
+    dir.write("in/b.txt", "5 €");
+    dir.write("in/c.txt", "naïve");
+    dir.write(
+        "jobs.toml",
+        r#"
+[defaults]
+from = "utf-8"
+to = "latin1"
+
+[[job]]
+input = "in/a.txt"
+output = "a.out"
+from = "latin1"
+to = "utf-8"
+
+[[job]]
+input = "in/b.txt"
+output = "b.out"
+errors = "replace"
+replacement = "EUR"
+
+[[job]]
+input = "in/c.txt"
+output = "c.out"
+"#,
+    );
+
+    let output = run(dir.path(), "batch jobs.toml");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(
+        stdout(&output).contains("Batch complete: 3 succeeded, 0 failed"),
+        "{}",
+        stdout(&output)
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("a.out")).unwrap(),
+        "café"
+    );
+    assert_eq!(fs::read(dir.path().join("b.out")).unwrap(), b"5 EUR");
+    assert_eq!(fs::read(dir.path().join("c.out")).unwrap(), b"na\xEFve");
+
+    // Output directories are created as needed
+    dir.write(
+        "nested.toml",
+        "[[job]]\ninput = \"in/a.txt\"\noutput = \"out/deep/a.txt\"\nfrom = \"latin1\"\nto = \"utf-8\"\n",
+    );
+    let output = run(dir.path(), "batch nested.toml");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("out/deep/a.txt")).unwrap(),
+        "café"
+    );
+
+    // A failing job is reported, leaves no output behind and fails the run
+    dir.write(
+        "failing.toml",
+        "[[job]]\ninput = \"in/b.txt\"\noutput = \"fail.out\"\nfrom = \"utf-8\"\nto = \"latin1\"\n\n\
+         [[job]]\ninput = \"in/c.txt\"\noutput = \"c2.out\"\nfrom = \"utf-8\"\nto = \"latin1\"\n",
+    );
+    let output = run(dir.path(), "batch failing.toml");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stdout(&output).contains("✗ in/b.txt"),
+        "{}",
+        stdout(&output)
+    );
+    assert!(!dir.path().join("fail.out").exists());
+    assert!(dir.path().join("c2.out").exists());
+
+    fs::remove_file(dir.path().join("c2.out")).unwrap();
+    let output = run(dir.path(), "batch failing.toml --fail-fast");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!dir.path().join("c2.out").exists());
+
+    dir.write(
+        "bad.toml",
+        "[[job]]\ninput = \"in/a.txt\"\nencoding = \"utf-8\"\n",
+    );
+    let output = run(dir.path(), "batch bad.toml");
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("Invalid manifest"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn per_file_reports() {
+    let dir = TempDir::new("report");
+    dir.write("in/a,b.txt", b"caf\xE9");
+    dir.write("in/c.txt", "5 €");
+    dir.write(
+        "jobs.toml",
+        "[defaults]\nfrom = \"latin1\"\nto = \"utf-8\"\n\n\
+         [[job]]\ninput = \"in/a,b.txt\"\noutput = \"a.out\"\n\n\
+         [[job]]\ninput = \"in/c.txt\"\noutput = \"c.out\"\nfrom = \"utf-8\"\nto = \"ascii\"\n\
+         errors = \"replace\"\n",
+    );
+
+    let output = run(dir.path(), "batch jobs.toml --report report.json");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let report: serde_json::Value =
+        serde_json::from_slice(&fs::read(dir.path().join("report.json")).unwrap()).unwrap();
+    let entries = report.as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["source"], "in/a,b.txt");
+    assert_eq!(entries[0]["declared_encoding"], "ISO-8859-1");
+    assert_eq!(entries[0]["target_encoding"], "UTF-8");
+    assert_eq!(entries[0]["bytes_processed"], 4);
+    assert_eq!(entries[0]["bytes_written"], 5);
+    assert_eq!(entries[0]["success"], true);
+    assert!(entries[0]["detected_encoding"].is_string());
+    assert_eq!(entries[1]["replacements"], 1);
+    assert_eq!(entries[1]["error"], serde_json::Value::Null);
+
+    let output = run(
+        dir.path(),
+        "batch jobs.toml --report report.csv --report-format csv",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let csv = fs::read_to_string(dir.path().join("report.csv")).unwrap();
+    let mut lines = csv.lines();
+    assert!(
+        lines
+            .next()
+            .unwrap()
+            .starts_with("source,output,declared_encoding,")
+    );
+    assert!(
+        lines
+            .next()
+            .unwrap()
+            .starts_with("\"in/a,b.txt\",a.out,ISO-8859-1,"),
+        "{}",
+        csv
+    );
+    assert_eq!(lines.count(), 1);
+
+    // Directory conversions put the same entries in their --format json output
+    let output = run(
+        dir.path(),
+        "--format json convert -f latin1 -t utf-8 -i in -r --out-dir out --include *,*",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["succeeded"], 1);
+    assert_eq!(report["files"][0]["bytes_written"], 5);
+
+    let output = run(dir.path(), "batch jobs.toml --report missing/report.json");
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("Failed to write report"),
+        "{}",
+        stderr(&output)
+    );
+    let output = run(dir.path(), "batch jobs.toml --report-format csv");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn skip_if_target_reruns_single_byte_target() {
+    let dir = TempDir::new("skip-if-target-cp1252");
+    dir.write("d/a.txt", "déjà vu, “quoted”");
+    dir.write("d/b.txt", "crème brûlée");
+
+    let args = "convert -f utf-8 -t WINDOWS-1252 -i d -r --in-place --skip-if-target";
+    let output = run(dir.path(), args);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let converted = fs::read(dir.path().join("d/a.txt")).unwrap();
+    assert_eq!(converted, b"d\xE9j\xE0 vu, \x93quoted\x94");
+
+    // The output may be detected as a related code page, but it is still left alone
+    let output = run(dir.path(), args);
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert!(
+        stdout(&output).contains("0 succeeded, 2 skipped, 0 failed"),
+        "{}",
+        stdout(&output)
+    );
+    assert_eq!(fs::read(dir.path().join("d/a.txt")).unwrap(), converted);
+    assert_eq!(
+        fs::read(dir.path().join("d/b.txt")).unwrap(),
+        b"cr\xE8me br\xFBl\xE9e"
+    );
+}
+
+#[test]
+fn map_prints_full_code_page() {
+    let dir = TempDir::new("map");
+
+    let output = run(dir.path(), "map IBM037");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let table = stdout(&output);
+    assert!(table.starts_with("Code page map: IBM037"), "{}", table);
+    assert!(
+        table.contains("0x4A   U+00A2     ¢     C2 A2        CENT SIGN"),
+        "{}",
+        table
+    );
+    assert!(table.contains("0xC1   U+0041     A     41           LATIN CAPITAL LETTER A"));
+    assert_eq!(
+        table.lines().filter(|line| line.starts_with("0x")).count(),
+        256
+    );
+
+    let output = run(dir.path(), "--format json map CP1252");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let map: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(map["encoding"], "Windows-1252");
+    assert_eq!(map["positions"][0x80]["char"], "€");
+    assert_eq!(map["positions"][0x80]["utf8"], "E2 82 AC");
+
+    let output = run(dir.path(), "map UTF-8");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stderr(&output).contains("not a single-byte encoding"),
+        "{}",
+        stderr(&output)
+    );
+    let output = run(dir.path(), "map no-such-encoding");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn compare_lists_differing_positions() {
+    let dir = TempDir::new("compare");
+
+    let output = run(dir.path(), "compare IBM037 IBM1047");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let diff = stdout(&output);
+    assert!(diff.contains("6 of 256 positions differ"), "{}", diff);
+    assert!(
+        diff.contains("0x5F   U+00AC ¬        U+005E ^        NOT SIGN → CIRCUMFLEX ACCENT"),
+        "{}",
+        diff
+    );
+    assert_eq!(
+        diff.lines().filter(|line| line.starts_with("0x")).count(),
+        6
+    );
+
+    let output = run(dir.path(), "compare CP1252 CP1252");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(
+        stdout(&output).contains("0 of 256 positions differ"),
+        "{}",
+        stdout(&output)
+    );
+
+    let output = run(dir.path(), "compare IBM037 UTF-8");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stderr(&output).contains("not a single-byte encoding"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn export_writes_mapping_tables() {
+    let dir = TempDir::new("export");
+
+    let output = run(dir.path(), "export --encoding CP850");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let table: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(table["encoding"], "CP850");
+    assert_eq!(table["mappings"].as_array().unwrap().len(), 256);
+    assert_eq!(table["mappings"][0x82]["code_point"], "U+00E9");
+
+    let output = run(dir.path(), "export --encoding CP850 --format csv");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let csv = stdout(&output);
+    assert!(csv.starts_with("byte,code_point,char,name\n"), "{}", csv);
+    assert!(
+        csv.contains("0x82,U+00E9,é,LATIN SMALL LETTER E WITH ACUTE\n"),
+        "{}",
+        csv
+    );
+
+    // The global position of --format works too, and -o writes a file
+    let output = run(dir.path(), "--format ucm export -e IBM037 -o ibm037.ucm");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let ucm = fs::read_to_string(dir.path().join("ibm037.ucm")).unwrap();
+    assert!(ucm.contains("<code_set_name>"), "{}", ucm);
+    assert!(ucm.contains("<U00A2> \\x4A |0"), "{}", ucm);
+
+    let output = run(dir.path(), "export --encoding CP850 --format text");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stderr(&output).contains("json, csv or ucm"),
+        "{}",
+        stderr(&output)
+    );
+    let output = run(dir.path(), "export --encoding CP850 --format xml");
+    assert_eq!(output.status.code(), Some(2));
+    let output = run(dir.path(), "map CP850 --format csv");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(
+        stderr(&output).contains("only apply to export"),
+        "{}",
+        stderr(&output)
+    );
+    let output = run(dir.path(), "export --encoding UTF-8");
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn detect_scans_directories() {
+    let dir = TempDir::new("detect-dir");
+    dir.write("tree/a.txt", "café crème brûlée");
+    dir.write("tree/sub/b.txt", "plain ascii text");
+    dir.write("tree/c.txt", "\u{FEFF}bom text");
+
+    let output = run(dir.path(), "detect -i tree");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let table = stdout(&output);
+    assert!(table.starts_with("Path"), "{}", table);
+    let row = table
+        .lines()
+        .find(|line| line.starts_with("c.txt"))
+        .unwrap();
+    assert!(row.contains("UTF-8") && row.ends_with("yes"), "{}", table);
+    assert!(table.contains("Summary (3 files):"), "{}", table);
+    assert!(
+        table.contains("UTF-8                2  (66.7%)"),
+        "{}",
+        table
+    );
+
+    let output = run(dir.path(), "--format json detect -i tree -j 2");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let files = report["files"].as_array().unwrap();
+    assert_eq!(files.len(), 3);
+    assert_eq!(files[0]["path"], "a.txt");
+    assert_eq!(files[0]["encoding"], "UTF-8");
+    assert_eq!(files[1]["bom_detected"], true);
+    assert_eq!(files[2]["encoding"], "US-ASCII");
+    assert_eq!(report["summary"][0]["encoding"], "UTF-8");
+    assert_eq!(report["summary"][0]["files"], 2);
+
+    let output = run(dir.path(), "detect -i missing");
+    assert_eq!(output.status.code(), Some(6));
+    assert!(stderr(&output).contains("missing"), "{}", stderr(&output));
+}
+
+#[test]
+fn validate_all_errors_lists_every_position() {
+    let dir = TempDir::new("validate-all-errors");
+    dir.write("bad.txt", b"ab\xFFcd\xFE\xFEef");
+
+    let output = run(dir.path(), "validate -i bad.txt -e utf-8 --all-errors");
+    assert_eq!(output.status.code(), Some(1));
+    let report = stdout(&output);
+    assert!(
+        report.contains("Offset 2 (0x00000002): invalid UTF-8 sequence"),
+        "{}",
+        report
+    );
+    assert!(
+        report.contains("hex:  61 62 [FF] 63 64 FE FE 65 66"),
+        "{}",
+        report
+    );
+    assert!(
+        report.contains("hex:  61 62 FF 63 64 FE [FE] 65 66"),
+        "{}",
+        report
+    );
+    assert!(report.contains("3 invalid sequence(s)"), "{}", report);
+
+    let output = run(
+        dir.path(),
+        "validate -i bad.txt -e utf-8 --all-errors --max-errors 1 --context 1",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    let report = stdout(&output);
+    assert!(report.contains("hex:  62 [FF] 63"), "{}", report);
+    assert!(report.contains("1 invalid sequence(s)"), "{}", report);
+
+    dir.write("good.txt", "déjà vu");
+    let output = run(dir.path(), "validate -i good.txt -e utf-8 --all-errors");
+    assert!(output.status.success(), "{}", stdout(&output));
+    let output = run(dir.path(), "validate -i bad.txt -e utf-8 --max-errors x");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn validate_checks_directories() {
+    let dir = TempDir::new("validate-dir");
+    dir.write("in/a.txt", "plain");
+    dir.write("in/b.txt", "café");
+    dir.write("in/s/c.txt", "naïve");
+
+    let output = run(dir.path(), "validate -i in -e utf-8");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let report = stdout(&output);
+    assert!(report.contains("PASS  a.txt"), "{}", report);
+    assert!(
+        report.contains("3 files checked against UTF-8: 3 passed, 0 failed"),
+        "{}",
+        report
+    );
+
+    dir.write("in/s/bad.txt", b"ab\xFFcd");
+    let output = run(dir.path(), "validate -i in -e utf-8");
+    assert_eq!(output.status.code(), Some(1));
+    let report = stdout(&output);
+    assert!(
+        report.contains("FAIL  s/bad.txt: offset 2: invalid UTF-8 sequence"),
+        "{}",
+        report
+    );
+    assert!(
+        report.contains("4 files checked against UTF-8: 3 passed, 1 failed"),
+        "{}",
+        report
+    );
+
+    let output = run(dir.path(), "validate -i missing -e utf-8");
+    assert_eq!(output.status.code(), Some(6));
+}
+
+#[test]
+fn inspect_dumps_a_window_side_by_side() {
+    let dir = TempDir::new("inspect");
+    dir.write("data.bin", b"\x83\x81\x86\x51\x40\xC1\xC2\xC3 tail");
+
+    let output = run(dir.path(), "inspect data.bin --as IBM037 --as CP1252");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let dump = stdout(&output);
+    let header = dump.lines().next().unwrap();
+    assert!(header.starts_with("Offset    Hex"), "{}", dump);
+    assert!(
+        header.contains("IBM037") && header.contains("Windows-1252"),
+        "{}",
+        dump
+    );
+    assert!(
+        dump.contains("00000000  83 81 86 51 40 C1 C2 C3"),
+        "{}",
+        dump
+    );
+
+    let output = run(
+        dir.path(),
+        "inspect data.bin --as IBM037 --offset 5 -n 3 --format json",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let dump: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(dump["size"], 13);
+    assert_eq!(dump["offset"], 5);
+    assert_eq!(dump["length"], 3);
+    assert_eq!(dump["rows"][0]["hex"], "C1 C2 C3");
+    assert_eq!(dump["rows"][0]["decodings"]["IBM037"], "ABC");
+
+    let output = run(
+        dir.path(),
+        "inspect data.bin --as IBM037 --offset 9 -n 0 --format json",
+    );
+    let dump: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(dump["length"], 4);
+
+    let output = run(dir.path(), "inspect data.bin --as nope");
+    assert_eq!(output.status.code(), Some(2));
+    let output = run(dir.path(), "inspect missing.bin --as IBM037");
+    assert_eq!(output.status.code(), Some(6));
+}
+
+#[test]
+fn record_length_splits_fixed_width_records() {
+    let dir = TempDir::new("record-length");
+    dir.write("rec.bin", b"\xC1\xC2\x40\x40\xC3\xC4\xC5\xC6\xC7");
+
+    let output = run(
+        dir.path(),
+        "convert -f IBM037 -t utf-8 -i rec.bin --record-length 4 --trim-trailing --line-ending crlf",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "AB\r\nCDEF\r\nG\r\n");
+
+    let output = run(
+        dir.path(),
+        "convert -f IBM037 -t utf-8 -i rec.bin --record-length 4 --line-ending lf",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "AB  \nCDEF\nG\n");
+
+    let output = run(
+        dir.path(),
+        "convert -f IBM037 -t utf-8 -i rec.bin --record-length 0",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("--record-length must be at least 1"));
+    let output = run(
+        dir.path(),
+        "convert -f IBM037 -t utf-8 -i rec.bin --trim-trailing",
+    );
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn detect_previews_top_candidates() {
+    let dir = TempDir::new("detect-preview");
+    dir.write("latin1.txt", b"caf\xE9 cr\xE8me br\xFBl\xE9e");
+
+    let output = run(dir.path(), "detect -i latin1.txt --preview 8");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let report = stdout(&output);
+    assert!(
+        report.contains("Detected encoding: ISO-8859-1"),
+        "{}",
+        report
+    );
+    let preview: Vec<&str> = report
+        .lines()
+        .skip_while(|line| *line != "Preview:")
+        .skip(1)
+        .collect();
+    assert_eq!(preview.len(), 3, "{}", report);
+    assert!(preview[0].starts_with("  ISO-8859-1") && preview[0].ends_with("café crè"));
+
+    let output = run(dir.path(), "detect -i latin1.txt --preview 4 --format json");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let report: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(report["previews"][0]["encoding"], "ISO-8859-1");
+    assert_eq!(report["previews"][0]["text"], "café");
+
+    let output = run(dir.path(), "detect -i latin1.txt --preview x");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn sample_strategy_chooses_where_to_read() {
+    let dir = TempDir::new("sample-strategy");
+    let mut data = vec![b'a'; 20000];
+    for _ in 0..3 {
+        data.extend_from_slice(
+            b"Gr\xFC\xDFe aus M\xFCnchen, sch\xF6ne Gr\xFC\xDFe und \xC4rger.\n",
+        );
+    }
+    dir.write("mixed.txt", &data);
+
+    let detected = |strategy: &str| {
+        let output = run(
+            dir.path(),
+            &format!("detect -i mixed.txt --sample-size 4096{}", strategy),
+        );
+        assert!(output.status.success(), "{}", stderr(&output));
+        stdout(&output)
+    };
+    assert!(detected("").contains("Detected encoding: US-ASCII"));
+    assert!(detected(" --sample-strategy head").contains("Detected encoding: US-ASCII"));
+    assert!(detected(" --sample-strategy tail").contains("Detected encoding: ISO-8859-1"));
+    assert!(detected(" --sample-strategy spread").contains("Detected encoding: ISO-8859-1"));
+
+    let output = run(dir.path(), "detect -i mixed.txt --sample-strategy middle");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn exit_codes_distinguish_failures() {
+    let dir = TempDir::new("exit-codes");
+    dir.write("cafe.txt", "café");
+    dir.write("ascii.txt", "plain ascii text");
+
+    // 3: unmappable input
+    let output = run(dir.path(), "convert -f utf-8 -t ascii -i cafe.txt");
+    assert_eq!(output.status.code(), Some(3), "{}", stderr(&output));
+    assert!(
+        stderr(&output).starts_with("Error: "),
+        "{}",
+        stderr(&output)
+    );
+
+    // 4: detection below --min-confidence
+    let output = run(dir.path(), "detect -i ascii.txt --min-confidence 1.01");
+    assert_eq!(output.status.code(), Some(4), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Detected encoding:"));
+    assert!(
+        stderr(&output).contains("below the 101.0% threshold"),
+        "{}",
+        stderr(&output)
+    );
+
+    // 5: lossy output only fails under --strict
+    let lossy = "convert -f utf-8 -t ascii --encoding-errors replace -i cafe.txt";
+    let output = run(dir.path(), lossy);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "caf?");
+    let output = run(dir.path(), &format!("{} --strict", lossy));
+    assert_eq!(output.status.code(), Some(5), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "caf?");
+    assert!(stderr(&output).contains("error: 1 unmappable sequence(s) in lossy output"));
+
+    // 6: unreadable input
+    let output = run(dir.path(), "convert -f utf-8 -t ascii -i missing.txt");
+    assert_eq!(output.status.code(), Some(6), "{}", stderr(&output));
+
+    // Statuses already reported by the command are not printed again
+    let output = run(dir.path(), "validate -i cafe.txt -e ascii");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!stderr(&output).contains("Error:"), "{}", stderr(&output));
+    let output = run(dir.path(), "validate -i ascii.txt -e ascii");
+    assert!(output.status.success(), "{}", stderr(&output));
+}
+
+#[test]
+fn list_filters_by_category() {
+    let dir = TempDir::new("list-category");
+
+    let output = run(dir.path(), "list --category ascii");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let listing = stdout(&output);
+    assert!(
+        listing.starts_with("Supported Encodings (1 total):"),
+        "{}",
+        listing
+    );
+    assert!(listing.contains("[ascii]"), "{}", listing);
+
+    let output = run(dir.path(), "list --category ebcdic --format json");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let listing: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    let listing = listing.as_array().unwrap();
+    assert!(listing.iter().any(|e| e["name"] == "IBM037"));
+    assert!(listing.iter().all(|e| e["category"] == "ebcdic"));
+
+    let output = run(dir.path(), "list --category klingon");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("ascii"), "{}", stderr(&output));
+    let output = run(dir.path(), "list --help");
+    assert!(stdout(&output).contains("ascii"), "{}", stdout(&output));
+}